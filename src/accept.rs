@@ -0,0 +1,151 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use crate::history::{HistoryAction, HistoryEntry, HistoryLog};
+use crate::ingest::RecordedFileInfo;
+use crate::lookup::{FileInfo, FileInfoLookup};
+use crate::output::WriteableFileInfo;
+use crate::requests::RequestBundle;
+use crate::utility::{prepare_thread_pool, print_err_buf, print_outcome};
+use crate::{Config, DanoResult, DANO_CLEAN_EXIT_CODE};
+
+const ACCEPT_PREFIX: &str = "Accepting new dano hash for: ";
+const NOT_ACCEPT_PREFIX: &str =
+    "WARN: Not accepting new dano hash for (because dry run was specified): ";
+
+// after a deliberate edit, the new hash of a file is accepted, explicitly: each input file is
+// hashed again, in each form recorded, and its records are replaced, while the old records are kept in the history log
+pub struct AcceptChanges;
+
+impl AcceptChanges {
+    pub fn exec(config: &Config, recorded_file_info: RecordedFileInfo) -> DanoResult<i32> {
+        let recorded: Vec<FileInfo> = recorded_file_info
+            .iter()
+            .filter(|file_info| config.paths.contains(&file_info.path))
+            .cloned()
+            .collect();
+
+        let thread_pool = prepare_thread_pool(config)?;
+        let file_info_requests = RequestBundle::new(config, &recorded)?;
+        let (rx_item, _rx_unsupported) =
+            FileInfoLookup::exec(config, file_info_requests, thread_pool)?;
+
+        let mut accepted: Vec<FileInfo> = rx_item
+            .iter()
+            .filter(|file_info| file_info.metadata.is_some())
+            .collect();
+        accepted.sort();
+
+        let history: Vec<HistoryEntry> = accepted
+            .iter()
+            .map(|file_info| {
+                let opt_old = Self::find_old(&recorded, file_info);
+                Self::print_change(
+                    config,
+                    file_info,
+                    opt_old.map(|old| old.hash_value.value.as_ref()),
+                )?;
+
+                Ok(HistoryEntry::new(
+                    HistoryAction::Accepted,
+                    &file_info.path,
+                    opt_old.cloned(),
+                    file_info.metadata.clone(),
+                ))
+            })
+            .collect::<DanoResult<Vec<HistoryEntry>>>()?;
+
+        if accepted.is_empty() {
+            print_err_buf("No file paths to accept.\n")?;
+            return Ok(DANO_CLEAN_EXIT_CODE);
+        }
+
+        let is_dry_run = config.opt_dry_run;
+
+        WriteableFileInfo::from(accepted).replace(config, NOT_ACCEPT_PREFIX, ACCEPT_PREFIX)?;
+
+        if !is_dry_run {
+            HistoryLog::new(config).append(&history)?;
+        }
+
+        Ok(DANO_CLEAN_EXIT_CODE)
+    }
+
+    // the old record is the record of the same path, hashed in the same form
+    fn find_old<'a>(
+        recorded: &'a [FileInfo],
+        file_info: &FileInfo,
+    ) -> Option<&'a crate::lookup::FileMetadata> {
+        let new = file_info.metadata.as_ref()?;
+
+        recorded
+            .iter()
+            .filter(|recorded| recorded.path == file_info.path)
+            .filter_map(|recorded| recorded.metadata.as_ref())
+            .find(|old| {
+                old.hash_algo == new.hash_algo
+                    && old.decoded == new.decoded
+                    && old.selected_streams == new.selected_streams
+            })
+    }
+
+    fn print_change(
+        config: &Config,
+        file_info: &FileInfo,
+        opt_old: Option<&str>,
+    ) -> DanoResult<()> {
+        if config.opt_silent {
+            return Ok(());
+        }
+
+        let Some(new) = file_info
+            .metadata
+            .as_ref()
+            .map(|new| new.hash_value.value.as_ref())
+        else {
+            return Ok(());
+        };
+
+        let (status, msg) = match opt_old {
+            Some(old) if old == new => (
+                "unchanged",
+                format!("{:?}: Hash is unchanged: {}\n", file_info.path, new),
+            ),
+            Some(old) => (
+                "accepted",
+                format!(
+                    "{:?}: Hash has changed: {} -> {}\n",
+                    file_info.path, old, new
+                ),
+            ),
+            None => (
+                "new",
+                format!(
+                    "{:?}: Path was not recorded, new hash: {}\n",
+                    file_info.path, new
+                ),
+            ),
+        };
+
+        print_outcome(
+            config,
+            &file_info.path,
+            status,
+            file_info.metadata.as_ref(),
+            &msg,
+        )
+    }
+}
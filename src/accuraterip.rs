@@ -0,0 +1,781 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    io::Read,
+    path::Path,
+    process::{Command as ExecProcess, Stdio},
+};
+
+use rayon::prelude::*;
+
+use crate::config::Config;
+use crate::ffmpeg::{FFmpegError, FFMPEG_LOCALE, FFMPEG_QUIET_ARGS};
+use crate::flac::FlacImporter;
+use crate::hasher::Crc32;
+use crate::import::Importer;
+use crate::utility::{print_err_buf, print_outcome, shell_quote_command};
+use crate::{DanoError, DanoResult, DANO_CLEAN_EXIT_CODE, DANO_DISORDER_EXIT_CODE};
+
+const ACCURATERIP_DB_URL: &str = "http://www.accuraterip.com/accuraterip";
+const CTDB_LOOKUP_URL: &str = "http://db.cuetools.net/lookup2.php?version=3&ctdb=1&fuzzy=0";
+
+// the timeout, in seconds, of a single request to either database
+const ACCURATERIP_DB_TIMEOUT: &str = "30";
+// curl exits with 22 when the server responds with an HTTP error, as when the disc is not present
+const CURL_HTTP_ERROR_EXIT_CODE: i32 = 22;
+
+// a CD sector holds 1/75th of a second of 16 bit stereo audio, at 44.1kHz
+const CD_SAMPLE_RATE: u32 = 44_100;
+const CD_CHANNELS: u32 = 2;
+const CD_BITS_PER_SAMPLE: u32 = 16;
+const CD_MAX_TRACKS: usize = 99;
+const SAMPLES_PER_SECTOR: u64 = 588;
+const SECTORS_PER_SECOND: u64 = 75;
+// the two second pregap of the first track precedes every track offset in a CDDB disc ID
+const CDDB_PREGAP_SECTORS: u64 = 150;
+
+// the first 5 sectors of the first track, and the last 5 sectors of the last track, are not checksummed,
+// by either database, because a drive may not read so near to either end of the disc
+const SKIPPED_SAMPLES: u64 = 5 * SAMPLES_PER_SECTOR;
+
+// each database entry is a header, a track count and three disc IDs, followed by, for each track,
+// a confidence, a checksum, and the checksum of a single frame, which dano does not use
+const DB_HEADER_LEN: usize = 13;
+const DB_TRACK_LEN: usize = 9;
+
+const DECODE_BUFFER_SIZE: usize = 1024 * 1024;
+
+// the track offsets of a disc, in sectors, inferred from the length of each track, as a rip of an audio CD,
+// without a data track, and without audio hidden before the first track, is just its tracks, end to end
+struct DiscToc {
+    track_offsets: Vec<u64>,
+    leadout_offset: u64,
+}
+
+impl DiscToc {
+    fn new(track_sectors: &[u64]) -> Self {
+        let mut offset = 0u64;
+
+        let track_offsets = track_sectors
+            .iter()
+            .map(|sectors| {
+                let track_offset = offset;
+                offset += sectors;
+                track_offset
+            })
+            .collect();
+
+        Self {
+            track_offsets,
+            leadout_offset: offset,
+        }
+    }
+
+    fn num_tracks(&self) -> usize {
+        self.track_offsets.len()
+    }
+
+    // the lead-out offset is counted as if it were one more track
+    fn accuraterip_ids(&self) -> (u32, u32) {
+        let offsets = self
+            .track_offsets
+            .iter()
+            .chain(std::iter::once(&self.leadout_offset));
+
+        offsets
+            .enumerate()
+            .fold((0u32, 0u32), |(id1, id2), (idx, offset)| {
+                let track_number = idx as u64 + 1;
+
+                (
+                    id1.wrapping_add(*offset as u32),
+                    id2.wrapping_add((*offset).max(1).wrapping_mul(track_number) as u32),
+                )
+            })
+    }
+
+    fn cddb_id(&self) -> u32 {
+        let to_seconds = |offset: u64| (offset + CDDB_PREGAP_SECTORS) / SECTORS_PER_SECOND;
+
+        let digit_sum = |mut num: u64| {
+            let mut sum = 0;
+
+            while num > 0 {
+                sum += num % 10;
+                num /= 10;
+            }
+
+            sum
+        };
+
+        let checksum: u64 = self
+            .track_offsets
+            .iter()
+            .map(|offset| digit_sum(to_seconds(*offset)))
+            .sum();
+
+        let first_offset = self.track_offsets.first().copied().unwrap_or_default();
+        let disc_seconds = to_seconds(self.leadout_offset) - to_seconds(first_offset);
+
+        (((checksum % 0xff) << 24) | (disc_seconds << 8) | self.num_tracks() as u64) as u32
+    }
+
+    fn disc_ids(&self) -> (u32, u32, u32) {
+        let (id1, id2) = self.accuraterip_ids();
+
+        (id1, id2, self.cddb_id())
+    }
+
+    // the CUETools database identifies a disc by the offset of each track, and of the lead-out, in sectors
+    fn ctdb_url(&self) -> String {
+        let toc: Vec<String> = self
+            .track_offsets
+            .iter()
+            .chain(std::iter::once(&self.leadout_offset))
+            .map(|offset| offset.to_string())
+            .collect();
+
+        format!("{}&toc={}", CTDB_LOOKUP_URL, toc.join(":"))
+    }
+
+    fn accuraterip_url(&self) -> String {
+        let (id1, id2, cddb_id) = self.disc_ids();
+
+        format!(
+            "{}/{:x}/{:x}/{:x}/dBAR-{:03}-{:08x}-{:08x}-{:08x}.bin",
+            ACCURATERIP_DB_URL,
+            id1 & 0xf,
+            (id1 >> 4) & 0xf,
+            (id1 >> 8) & 0xf,
+            self.num_tracks(),
+            id1,
+            id2,
+            cddb_id
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DatabaseTrack {
+    confidence: u32,
+    checksum: u32,
+}
+
+// each entry is a distinct pressing of the disc, and each pressing has its own checksums
+struct DatabaseEntries {
+    inner: Vec<Vec<DatabaseTrack>>,
+}
+
+impl DatabaseEntries {
+    fn from_bytes(bytes: &[u8], toc: &DiscToc) -> DanoResult<Self> {
+        let disc_ids = toc.disc_ids();
+        let entry_len = DB_HEADER_LEN + toc.num_tracks() * DB_TRACK_LEN;
+
+        let read_u32 = |bytes: &[u8], offset: usize| -> DanoResult<u32> {
+            Ok(u32::from_le_bytes(bytes[offset..offset + 4].try_into()?))
+        };
+
+        let mut inner = Vec::new();
+        let mut remaining = bytes;
+
+        while !remaining.is_empty() {
+            if remaining.len() < entry_len || remaining[0] as usize != toc.num_tracks() {
+                return Err(DanoError::new(
+                    "The AccurateRip database response could not be parsed.",
+                )
+                .into());
+            }
+
+            let (entry, rest) = remaining.split_at(entry_len);
+            remaining = rest;
+
+            let entry_ids = (
+                read_u32(entry, 1)?,
+                read_u32(entry, 5)?,
+                read_u32(entry, 9)?,
+            );
+
+            if entry_ids != disc_ids {
+                continue;
+            }
+
+            let tracks = entry[DB_HEADER_LEN..]
+                .chunks_exact(DB_TRACK_LEN)
+                .map(|track| {
+                    Ok(DatabaseTrack {
+                        confidence: track[0] as u32,
+                        checksum: read_u32(track, 1)?,
+                    })
+                })
+                .collect::<DanoResult<Vec<DatabaseTrack>>>()?;
+
+            inner.push(tracks);
+        }
+
+        Ok(Self { inner })
+    }
+
+    // the most confident entry which matches either checksum, preferring the newer v2 checksum,
+    // and the sum of the confidence of every entry, the count of submissions of the track
+    fn outcome(&self, track_idx: usize, checksums: &TrackChecksums) -> TrackOutcome {
+        let tracks = || self.inner.iter().filter_map(|entry| entry.get(track_idx));
+
+        let total = tracks().map(|track| track.confidence).sum();
+
+        let best_match = |checksum: u32| {
+            tracks()
+                .filter(|track| track.checksum == checksum)
+                .map(|track| track.confidence)
+                .max()
+        };
+
+        match (best_match(checksums.v2), best_match(checksums.v1)) {
+            (Some(confidence), _) => TrackOutcome::Accurate {
+                database: "AccurateRip v2",
+                confidence,
+                total,
+            },
+            (None, Some(confidence)) => TrackOutcome::Accurate {
+                database: "AccurateRip v1",
+                confidence,
+                total,
+            },
+            (None, None) => TrackOutcome::Inaccurate {
+                database: "AccurateRip",
+                total,
+            },
+        }
+    }
+}
+
+// each CTDB entry is a distinct pressing, or rip offset, of the disc, with its confidence, and the CRC32 of each track
+struct CtdbEntry {
+    confidence: u32,
+    track_crcs: Vec<u32>,
+}
+
+struct CtdbEntries {
+    inner: Vec<CtdbEntry>,
+}
+
+impl CtdbEntries {
+    // the response is XML, of which dano reads only the attributes of each entry element
+    fn from_bytes(bytes: &[u8], toc: &DiscToc) -> DanoResult<Self> {
+        let response = std::str::from_utf8(bytes)
+            .map_err(|_| DanoError::new("The CUETools database response could not be parsed."))?;
+
+        let attribute = |element: &str, name: &str| -> Option<String> {
+            let pattern = format!(" {}=\"", name);
+            let start = element.find(&pattern)? + pattern.len();
+            let len = element[start..].find('"')?;
+
+            Some(element[start..start + len].to_owned())
+        };
+
+        let inner = response
+            .split("<entry ")
+            .skip(1)
+            .filter_map(|rest| {
+                let element = format!(" {}", &rest[..rest.find('>')?]);
+
+                let confidence = attribute(&element, "confidence")?.parse().ok()?;
+                let track_crcs = attribute(&element, "trackcrcs")?
+                    .split_whitespace()
+                    .map(|crc| u32::from_str_radix(crc, 16).ok())
+                    .collect::<Option<Vec<u32>>>()?;
+
+                Some(CtdbEntry {
+                    confidence,
+                    track_crcs,
+                })
+            })
+            // an entry of a disc with other tracks, as a data track, cannot match
+            .filter(|entry| entry.track_crcs.len() == toc.num_tracks())
+            .collect();
+
+        Ok(Self { inner })
+    }
+
+    // the sum of the confidence of every entry which matches, and of every entry
+    fn outcome(&self, track_idx: usize, checksums: &TrackChecksums) -> TrackOutcome {
+        let total = self.inner.iter().map(|entry| entry.confidence).sum();
+
+        let confidence: u32 = self
+            .inner
+            .iter()
+            .filter(|entry| entry.track_crcs.get(track_idx) == Some(&checksums.crc32))
+            .map(|entry| entry.confidence)
+            .sum();
+
+        if confidence > 0 {
+            TrackOutcome::Accurate {
+                database: "CTDB",
+                confidence,
+                total,
+            }
+        } else {
+            TrackOutcome::Inaccurate {
+                database: "CTDB",
+                total,
+            }
+        }
+    }
+}
+
+enum TrackOutcome {
+    Accurate {
+        database: &'static str,
+        confidence: u32,
+        total: u32,
+    },
+    Inaccurate {
+        database: &'static str,
+        total: u32,
+    },
+}
+
+impl TrackOutcome {
+    fn is_accurate(&self) -> bool {
+        matches!(self, TrackOutcome::Accurate { .. })
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            TrackOutcome::Accurate {
+                database,
+                confidence,
+                total,
+            } => format!("{}, confidence {} of {}", database, confidence, total),
+            TrackOutcome::Inaccurate { database, total } => {
+                format!("{}, matches none of {} submissions", database, total)
+            }
+        }
+    }
+}
+
+// both versions of the AccurateRip checksum weight each stereo sample, read as a little endian u32, by its position
+// within the track, v1 keeps only the low 32 bits of each product, which v2 corrects by adding the high 32 bits as well,
+// and the CTDB checksum is the CRC32 of the samples of the track
+#[derive(Debug, Clone, Copy)]
+struct TrackChecksums {
+    v1: u32,
+    v2: u32,
+    crc32: u32,
+}
+
+impl TrackChecksums {
+    fn from_decoded(
+        config: &Config,
+        path: &Path,
+        total_samples: u64,
+        is_first: bool,
+        is_last: bool,
+    ) -> DanoResult<Self> {
+        let ffmpeg_command = config.ffmpeg_command()?;
+
+        let mut process_args: Vec<&OsStr> = FFMPEG_QUIET_ARGS.iter().map(OsStr::new).collect();
+        process_args.push(OsStr::new("-i"));
+        process_args.push(path.as_os_str());
+        process_args.extend(
+            ["-map", "0:a:0", "-f", "s16le", "-c:a", "pcm_s16le", "-"]
+                .iter()
+                .map(OsStr::new),
+        );
+
+        if config.opt_show_commands {
+            print_err_buf(&format!(
+                "Command: {}\n",
+                shell_quote_command(&ffmpeg_command, &process_args)
+            ))?;
+        }
+
+        let mut child = ExecProcess::new(&ffmpeg_command)
+            .envs(FFMPEG_LOCALE)
+            .args(&process_args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // stderr is drained on its own thread, so a chatty ffmpeg cannot block on a full pipe
+        let opt_stderr_handle = child.stderr.take().map(|mut stderr| {
+            std::thread::spawn(move || {
+                let mut buffer = Vec::new();
+                let _ = stderr.read_to_end(&mut buffer);
+                buffer
+            })
+        });
+
+        let first_counted = if is_first { SKIPPED_SAMPLES } else { 1 };
+        // CTDB skips the first 5 sectors whole, where AccurateRip counts the last sample of them
+        let first_crc_counted = if is_first { SKIPPED_SAMPLES + 1 } else { 1 };
+        let last_counted = if is_last {
+            total_samples.saturating_sub(SKIPPED_SAMPLES)
+        } else {
+            total_samples
+        };
+
+        let mut checksums = Self {
+            v1: 0,
+            v2: 0,
+            crc32: 0,
+        };
+        let mut crc32 = Crc32::new();
+        let mut position = 0u64;
+
+        if let Some(mut stdout) = child.stdout.take() {
+            let mut buffer = vec![0u8; DECODE_BUFFER_SIZE];
+            // a read may end within a sample, and the remainder begins the next read
+            let mut carried = 0usize;
+
+            loop {
+                let bytes_read = stdout.read(&mut buffer[carried..])?;
+
+                if bytes_read == 0 {
+                    break;
+                }
+
+                let available = carried + bytes_read;
+                let whole = available - (available % 4);
+
+                // the samples of this read which CTDB counts, skipped just as AccurateRip skips them
+                let first_sample = position + 1;
+                let last_sample = position + (whole / 4) as u64;
+                let crc_first = first_sample.max(first_crc_counted);
+                let crc_last = last_sample.min(last_counted);
+
+                if crc_first <= crc_last {
+                    let start = ((crc_first - first_sample) * 4) as usize;
+                    let end = ((crc_last - first_sample + 1) * 4) as usize;
+                    crc32.update(&buffer[start..end]);
+                }
+
+                buffer[..whole].chunks_exact(4).for_each(|sample| {
+                    position += 1;
+
+                    if position < first_counted || position > last_counted {
+                        return;
+                    }
+
+                    let sample = u32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]);
+                    let product = sample as u64 * position;
+
+                    checksums.v1 = checksums.v1.wrapping_add(product as u32);
+                    checksums.v2 = checksums
+                        .v2
+                        .wrapping_add(product as u32)
+                        .wrapping_add((product >> 32) as u32);
+                });
+
+                buffer.copy_within(whole..available, 0);
+                carried = available - whole;
+            }
+        }
+
+        let status = child.wait()?;
+
+        let stderr = opt_stderr_handle
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default();
+
+        if !status.success() {
+            let stderr = String::from_utf8_lossy(&stderr);
+            return Err(FFmpegError::new(status, stderr.trim(), path, Some("0:a:0")).into());
+        }
+
+        checksums.crc32 = crc32.value();
+
+        if position != total_samples {
+            let msg = format!(
+                "ffmpeg decoded {} samples, but STREAMINFO records {} samples",
+                position, total_samples
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        Ok(checksums)
+    }
+}
+
+// the FLAC files within each directory are the tracks of a single disc, in the order of their file names,
+// and each track is checksummed, and compared to the checksums of other rips in the AccurateRip database,
+// and in the CUETools database, each of which knows of discs the other does not
+pub struct AccurateRipVerification;
+
+impl AccurateRipVerification {
+    pub fn exec(config: &Config) -> DanoResult<i32> {
+        let (flac_paths, other_paths): (Vec<&Path>, Vec<&Path>) = config
+            .paths
+            .iter()
+            .map(|path| path.as_path())
+            .partition(|path| FlacImporter.is_valid_path(path));
+
+        if !config.opt_silent {
+            other_paths.iter().for_each(|path| {
+                eprintln!(
+                    "WARN: Path is not a FLAC file, and has been skipped: {:?}",
+                    path
+                )
+            });
+        }
+
+        let mut discs: BTreeMap<&Path, Vec<&Path>> = BTreeMap::new();
+
+        flac_paths.into_iter().for_each(|path| {
+            discs
+                .entry(path.parent().unwrap_or_else(|| Path::new("")))
+                .or_default()
+                .push(path)
+        });
+
+        let mut num_accurate = 0usize;
+        let mut num_failed = 0usize;
+
+        discs.into_iter().try_for_each(|(dir, mut tracks)| {
+            tracks.sort_unstable();
+            tracks.dedup();
+
+            let outcomes = match Self::verify_disc(config, &tracks) {
+                Ok(Some(outcomes)) => outcomes,
+                Ok(None) => {
+                    if !config.opt_silent {
+                        eprintln!(
+                            "WARN: {:?}: The disc is not present in the AccurateRip database, or in the CUETools database, so its tracks cannot be verified.",
+                            dir
+                        );
+                    }
+
+                    return Ok(());
+                }
+                Err(err) => {
+                    eprintln!("ERROR: {:?}: {}", dir, err);
+                    num_failed += tracks.len();
+                    return Ok(());
+                }
+            };
+
+            tracks
+                .iter()
+                .zip(outcomes)
+                .try_for_each(|(path, outcome)| match outcome {
+                    // a track matched by either database is accurate, as each knows of pressings the other does not
+                    Ok(outcomes) if outcomes.iter().any(TrackOutcome::is_accurate) => {
+                        num_accurate += 1;
+
+                        if config.opt_silent {
+                            return Ok(());
+                        }
+
+                        print_outcome(
+                            config,
+                            path,
+                            "ok",
+                            None,
+                            &format!(
+                                "{:?}: Accurately ripped ({})\n",
+                                path,
+                                Self::describe(&outcomes)
+                            ),
+                        )
+                    }
+                    Ok(outcomes) => {
+                        num_failed += 1;
+
+                        print_outcome(
+                            config,
+                            path,
+                            "mismatch",
+                            None,
+                            &format!(
+                                "WARN: {:?}: Not accurately ripped ({})\n",
+                                path,
+                                Self::describe(&outcomes)
+                            ),
+                        )
+                    }
+                    Err(err) => {
+                        num_failed += 1;
+                        eprintln!("ERROR: {:?}: {}", path, err);
+
+                        print_outcome(
+                            config,
+                            path,
+                            "error",
+                            None,
+                            &format!("WARN: {:?}: The file could not be decoded.\n", path),
+                        )
+                    }
+                })
+        })?;
+
+        if num_failed > 0 {
+            print_err_buf(&format!(
+                "FAILED: {} tracks were accurately ripped, but {} tracks matched neither the AccurateRip database, nor the CUETools database, or could not be decoded.\n",
+                num_accurate, num_failed
+            ))?;
+
+            return Ok(DANO_DISORDER_EXIT_CODE);
+        }
+
+        print_err_buf(&format!(
+            "PASSED: {} tracks were accurately ripped, according to the AccurateRip database, or the CUETools database.\n",
+            num_accurate
+        ))?;
+
+        Ok(DANO_CLEAN_EXIT_CODE)
+    }
+
+    fn describe(outcomes: &[TrackOutcome]) -> String {
+        outcomes
+            .iter()
+            .map(TrackOutcome::describe)
+            .collect::<Vec<String>>()
+            .join("; ")
+    }
+
+    // a disc which is present in neither database cannot be verified, and its tracks are not decoded,
+    // else each track has an outcome in each database in which the disc is present
+    #[allow(clippy::type_complexity)]
+    fn verify_disc(
+        config: &Config,
+        tracks: &[&Path],
+    ) -> DanoResult<Option<Vec<DanoResult<Vec<TrackOutcome>>>>> {
+        if tracks.len() > CD_MAX_TRACKS {
+            let msg = format!(
+                "A disc may have at most {} tracks, but {} FLAC files were found.",
+                CD_MAX_TRACKS,
+                tracks.len()
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        let total_samples = tracks
+            .iter()
+            .map(|path| {
+                let stream_info = FlacImporter::import_stream_info(path)?;
+
+                let is_cd_audio = stream_info.sample_rate == CD_SAMPLE_RATE
+                    && stream_info.channels == CD_CHANNELS
+                    && stream_info.bits_per_sample == CD_BITS_PER_SAMPLE
+                    && stream_info.total_samples > 0
+                    && stream_info.total_samples % SAMPLES_PER_SECTOR == 0;
+
+                if !is_cd_audio {
+                    let msg = format!(
+                        "Path is not a track ripped from an audio CD, 16 bit stereo audio at 44.1kHz, in whole sectors: {:?}",
+                        path
+                    );
+                    return Err(DanoError::new(&msg).into());
+                }
+
+                Ok(stream_info.total_samples)
+            })
+            .collect::<DanoResult<Vec<u64>>>()?;
+
+        let track_sectors: Vec<u64> = total_samples
+            .iter()
+            .map(|samples| samples / SAMPLES_PER_SECTOR)
+            .collect();
+
+        let toc = DiscToc::new(&track_sectors);
+
+        let opt_entries = match Self::fetch(config, "AccurateRip", &toc.accuraterip_url())? {
+            Some(bytes) => Some(DatabaseEntries::from_bytes(&bytes, &toc)?),
+            None => None,
+        }
+        .filter(|entries| !entries.inner.is_empty());
+
+        let opt_ctdb_entries = match Self::fetch(config, "CUETools", &toc.ctdb_url())? {
+            Some(bytes) => Some(CtdbEntries::from_bytes(&bytes, &toc)?),
+            None => None,
+        }
+        .filter(|entries| !entries.inner.is_empty());
+
+        if opt_entries.is_none() && opt_ctdb_entries.is_none() {
+            return Ok(None);
+        }
+
+        let outcomes = tracks
+            .par_iter()
+            .zip(total_samples.par_iter())
+            .enumerate()
+            .map(|(idx, (path, samples))| {
+                let is_first = idx == 0;
+                let is_last = idx == tracks.len() - 1;
+
+                let checksums =
+                    TrackChecksums::from_decoded(config, path, *samples, is_first, is_last)?;
+
+                let outcomes = opt_entries
+                    .iter()
+                    .map(|entries| entries.outcome(idx, &checksums))
+                    .chain(
+                        opt_ctdb_entries
+                            .iter()
+                            .map(|entries| entries.outcome(idx, &checksums)),
+                    )
+                    .collect();
+
+                Ok(outcomes)
+            })
+            .collect();
+
+        Ok(Some(outcomes))
+    }
+
+    fn fetch(config: &Config, database: &str, url: &str) -> DanoResult<Option<Vec<u8>>> {
+        let curl_command = config.curl_command()?;
+
+        let process_args: Vec<&OsStr> = [
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--location",
+            "--max-time",
+            ACCURATERIP_DB_TIMEOUT,
+            url,
+        ]
+        .into_iter()
+        .map(OsStr::new)
+        .collect();
+
+        if config.opt_show_commands {
+            print_err_buf(&format!(
+                "Command: {}\n",
+                shell_quote_command(&curl_command, &process_args)
+            ))?;
+        }
+
+        let process_output = ExecProcess::new(&curl_command)
+            .args(&process_args)
+            .output()?;
+
+        match process_output.status.code() {
+            Some(0) => Ok(Some(process_output.stdout)),
+            Some(CURL_HTTP_ERROR_EXIT_CODE) => Ok(None),
+            _ => {
+                let msg = format!(
+                    "Could not fetch from the {} database: {}",
+                    database,
+                    String::from_utf8_lossy(&process_output.stderr).trim()
+                );
+                Err(DanoError::new(&msg).into())
+            }
+        }
+    }
+}
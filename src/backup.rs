@@ -0,0 +1,99 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    fs::OpenOptions,
+    path::{Path, PathBuf},
+};
+
+use crate::{Config, DanoResult};
+
+const BACKUP_EXTENSION: &str = "bak";
+
+// numbered backups of the hash file, such as dano_hashes.txt.bak.1, where the highest number is the latest backup
+pub struct HashFileBackup;
+
+impl HashFileBackup {
+    fn backup_path(path: &Path, number: usize) -> PathBuf {
+        let mut backup_path = path.as_os_str().to_owned();
+        backup_path.push(format!(".{}.{}", BACKUP_EXTENSION, number));
+
+        PathBuf::from(backup_path)
+    }
+
+    // the backups of the hash file, oldest first
+    pub fn list(path: &Path) -> DanoResult<Vec<(usize, PathBuf)>> {
+        let Some(file_name) = path.file_name() else {
+            return Ok(Vec::new());
+        };
+
+        let prefix = format!("{}.{}.", file_name.to_string_lossy(), BACKUP_EXTENSION);
+
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        let mut backups: Vec<(usize, PathBuf)> = std::fs::read_dir(dir)?
+            .flatten()
+            .filter_map(|entry| {
+                let number = entry
+                    .file_name()
+                    .to_string_lossy()
+                    .strip_prefix(&prefix)?
+                    .parse::<usize>()
+                    .ok()?;
+
+                Some((number, Self::backup_path(path, number)))
+            })
+            .collect();
+
+        backups.sort();
+
+        Ok(backups)
+    }
+
+    // the hash file, as it was, is copied, rather than linked, because the rewrite may be copied over the hash file,
+    // and the copy keeps the modification time of the hash file, so each backup is timestamped
+    pub fn exec(config: &Config, path: &Path) -> DanoResult<()> {
+        if config.num_backups == 0 || !path.exists() {
+            return Ok(());
+        }
+
+        let backups = Self::list(path)?;
+
+        let next = backups.last().map_or(1, |(number, _)| number + 1);
+        let backup_path = Self::backup_path(path, next);
+
+        std::fs::copy(path, &backup_path)?;
+
+        if let Ok(modified) = path.metadata().and_then(|metadata| metadata.modified()) {
+            OpenOptions::new()
+                .write(true)
+                .open(&backup_path)?
+                .set_modified(modified)?;
+        }
+
+        // only the latest backups are kept, including the backup just made
+        let num_stale = (backups.len() + 1).saturating_sub(config.num_backups);
+
+        backups
+            .iter()
+            .take(num_stale)
+            .try_for_each(|(_, stale_path)| std::fs::remove_file(stale_path))
+            .map_err(|err| err.into())
+    }
+}
@@ -0,0 +1,383 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::compress::HashFileCompression;
+use crate::footer::{HashFileFooter, FOOTER_HASH_ALGO};
+use crate::hasher::NativeHasher;
+use crate::lookup::{FileInfo, FileMetadata};
+use crate::utility::{deserialize, deserialize_version, is_newer_version, serialize};
+use crate::{Config, DanoError, DanoResult, DANO_FILE_INFO_VERSION};
+
+// a binary hash file begins with the magic bytes, and the format version of its records
+const BINARY_MAGIC: &[u8; 8] = b"DANO\0BIN";
+const BINARY_EXTENSION: &str = "bin";
+const LEN_PREFIX_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashFileFormat {
+    Text,
+    Binary,
+}
+
+impl HashFileFormat {
+    fn sniff(path: &Path) -> Option<Self> {
+        let mut file = HashFileCompression::open(path).ok()?;
+        let mut magic = [0u8; BINARY_MAGIC.len()];
+
+        match file.read_exact(&mut magic) {
+            Ok(_) if &magic == BINARY_MAGIC => Some(HashFileFormat::Binary),
+            _ => Some(HashFileFormat::Text),
+        }
+    }
+
+    // a compressed hash file is named for its format, and then for its compression, such as dano_hashes.bin.zst
+    fn from_extension(path: &Path) -> Self {
+        let path = if HashFileCompression::has_extension(path) {
+            path.with_extension("")
+        } else {
+            path.to_owned()
+        };
+
+        match path.extension() {
+            Some(ext) if ext == BINARY_EXTENSION => HashFileFormat::Binary,
+            _ => HashFileFormat::Text,
+        }
+    }
+
+    // a rewrite converts the hash file to the format specified, else it keeps the format of the hash file
+    pub fn for_rewrite(config: &Config, path: &Path) -> Self {
+        config
+            .opt_hash_file_format
+            .or_else(|| Self::sniff(path))
+            .unwrap_or_else(|| Self::from_extension(path))
+    }
+
+    // but an append must be in the format of the hash file appended to, until it is next rewritten
+    pub fn for_append(config: &Config, path: &Path) -> Self {
+        Self::sniff(path)
+            .or(config.opt_hash_file_format)
+            .unwrap_or_else(|| Self::from_extension(path))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryRecord {
+    path: String,
+    metadata: FileMetadata,
+}
+
+// the count and checksum of the encoded entries which precede the footer, as the footer of the text format
+// is of its lines, and so a binary hash file is checked without converting its records to text
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct BinaryFooter {
+    num_entries: usize,
+    checksum: String,
+}
+
+impl BinaryFooter {
+    fn new(num_entries: usize, bytes: &[u8]) -> DanoResult<Self> {
+        let mut hasher = NativeHasher::new(FOOTER_HASH_ALGO)?;
+        hasher.update(bytes);
+
+        Ok(Self {
+            num_entries,
+            checksum: hasher.finalize_hex(),
+        })
+    }
+}
+
+// a line of the text format which is not a record in the latest format version, such as the header, a tree hash,
+// or a line written by a newer version of dano, is kept as it is
+#[derive(Serialize, Deserialize)]
+enum BinaryEntry {
+    Record(Box<BinaryRecord>),
+    Line(String),
+    Sealed,
+    Footer(BinaryFooter),
+}
+
+impl BinaryEntry {
+    fn from_line(line: &str) -> Self {
+        let opt_record = Some(line)
+            .filter(|line| deserialize_version(line) == Some(DANO_FILE_INFO_VERSION))
+            .and_then(|line| deserialize(line).ok())
+            .and_then(|file_info| Self::record(&file_info));
+
+        match opt_record {
+            Some(record) => record,
+            None => BinaryEntry::Line(line.to_owned()),
+        }
+    }
+
+    fn from_file_info(file_info: &FileInfo) -> DanoResult<Self> {
+        match Self::record(file_info) {
+            Some(record) => Ok(record),
+            None => Ok(BinaryEntry::Line(
+                serialize(file_info)?.trim_end().to_owned(),
+            )),
+        }
+    }
+
+    fn record(file_info: &FileInfo) -> Option<Self> {
+        if file_info.version != DANO_FILE_INFO_VERSION {
+            return None;
+        }
+
+        Some(BinaryEntry::Record(Box::new(BinaryRecord {
+            path: file_info.path.to_str()?.to_owned(),
+            metadata: file_info.metadata.clone()?,
+        })))
+    }
+
+    fn into_file_info(self) -> Option<FileInfo> {
+        match self {
+            BinaryEntry::Record(record) => Some(FileInfo {
+                version: DANO_FILE_INFO_VERSION,
+                path: PathBuf::from(record.path),
+                metadata: Some(record.metadata),
+            }),
+            BinaryEntry::Line(line) => deserialize(&line).ok(),
+            BinaryEntry::Sealed | BinaryEntry::Footer(_) => None,
+        }
+    }
+
+    // the seal and the footer are of the encoded entries, and have no text
+    fn to_line(&self) -> DanoResult<String> {
+        match self {
+            BinaryEntry::Record(record) => {
+                let file_info = FileInfo {
+                    version: DANO_FILE_INFO_VERSION,
+                    path: PathBuf::from(&record.path),
+                    metadata: Some(record.metadata.clone()),
+                };
+
+                serialize(&file_info)
+            }
+            BinaryEntry::Line(line) => Ok(format!("{}\n", line)),
+            BinaryEntry::Sealed | BinaryEntry::Footer(_) => Ok(String::new()),
+        }
+    }
+
+    fn encode(&self, buffer: &mut Vec<u8>) -> DanoResult<()> {
+        let bytes = bincode::serialize(self)?;
+
+        buffer.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        buffer.extend_from_slice(&bytes);
+
+        Ok(())
+    }
+}
+
+// each entry is prefixed by its length, so records may be appended, as they are to the text format
+pub struct BinaryHashFile;
+
+impl BinaryHashFile {
+    pub fn is_binary(bytes: &[u8]) -> bool {
+        bytes.starts_with(BINARY_MAGIC)
+    }
+
+    fn file_header() -> Vec<u8> {
+        let mut buffer = BINARY_MAGIC.to_vec();
+        buffer.extend_from_slice(&(DANO_FILE_INFO_VERSION as u32).to_le_bytes());
+        buffer
+    }
+
+    // records are encoded in the layout of the format version which wrote them, which no other version can decode,
+    // and each entry is returned with the offset at which it begins, which the footer is checked against
+    fn decode(source: &Path, bytes: &[u8]) -> DanoResult<Vec<(usize, BinaryEntry)>> {
+        let truncated = || {
+            let msg = format!(
+                "{:?} is a binary hash file which was truncated, or only partially written.",
+                source
+            );
+            DanoError::new(&msg)
+        };
+
+        let header_len = BINARY_MAGIC.len() + LEN_PREFIX_SIZE;
+        let version_bytes = bytes
+            .get(BINARY_MAGIC.len()..header_len)
+            .ok_or_else(truncated)?;
+        let version = u32::from_le_bytes(version_bytes.try_into()?) as usize;
+
+        if version != DANO_FILE_INFO_VERSION {
+            let msg = format!(
+                "{:?} is a binary hash file, in format version {}, but this version of dano reads binary hash files only in format version {}.  \
+                Use the version of dano which wrote it to convert it with --format=text.",
+                source, version, DANO_FILE_INFO_VERSION
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        let mut entries = Vec::new();
+        let mut offset = header_len;
+
+        while offset < bytes.len() {
+            let rest = &bytes[offset..];
+            let len_bytes = rest.get(..LEN_PREFIX_SIZE).ok_or_else(truncated)?;
+            let len = u32::from_le_bytes(len_bytes.try_into()?) as usize;
+            let entry_bytes = rest
+                .get(LEN_PREFIX_SIZE..LEN_PREFIX_SIZE + len)
+                .ok_or_else(truncated)?;
+
+            entries.push((offset, bincode::deserialize(entry_bytes)?));
+            offset += LEN_PREFIX_SIZE + len;
+        }
+
+        Ok(entries)
+    }
+
+    // a binary hash file which does not match its footer, or which is sealed, but has lost its footer, is not read,
+    // just as a text hash file is not
+    fn check_footer(
+        source: &Path,
+        bytes: &[u8],
+        entries: &[(usize, BinaryEntry)],
+    ) -> DanoResult<()> {
+        let opt_footer =
+            entries
+                .iter()
+                .enumerate()
+                .find_map(|(idx, (offset, entry))| match entry {
+                    BinaryEntry::Footer(footer) => Some((idx, *offset, footer)),
+                    _ => None,
+                });
+
+        let msg = match opt_footer {
+            Some((idx, offset, footer)) => {
+                let preceding = BinaryFooter::new(idx, &bytes[..offset])?;
+
+                if &preceding == footer {
+                    return Ok(());
+                }
+
+                format!(
+                    "{:?} does not match its footer, which records {} entries, but {} entries precede it, or the checksum of those entries differs, \
+                    so the hash file was likely damaged, or only partially written.",
+                    source, footer.num_entries, preceding.num_entries
+                )
+            }
+            None if entries
+                .iter()
+                .any(|(_, entry)| matches!(entry, BinaryEntry::Sealed)) =>
+            {
+                format!(
+                    "{:?} has lost its footer, so the hash file was likely truncated, or only partially written.",
+                    source
+                )
+            }
+            None => return Ok(()),
+        };
+
+        let msg = format!(
+            "{}  Use --compact, or --fsck --repair, to rewrite it from the records which remain.",
+            msg
+        );
+
+        Err(DanoError::new(&msg).into())
+    }
+
+    pub fn check(source: &Path, bytes: &[u8]) -> DanoResult<()> {
+        let entries = Self::decode(source, bytes)?;
+        Self::check_footer(source, bytes, &entries)
+    }
+
+    // the text a binary hash file was encoded from, such that it may be read like any other hash file
+    pub fn read_text(source: &Path, bytes: &[u8]) -> DanoResult<String> {
+        Self::decode(source, bytes)?
+            .iter()
+            .map(|(_, entry)| entry.to_line())
+            .collect()
+    }
+
+    // records are decoded directly, and not parsed from their text, which is the point of the binary format,
+    // and are returned with the number of lines written by a newer version of dano, which were skipped
+    pub fn read_records(source: &Path, bytes: &[u8]) -> DanoResult<(Vec<FileInfo>, usize)> {
+        let entries = Self::decode(source, bytes)?;
+
+        Self::check_footer(source, bytes, &entries)?;
+
+        let num_newer = entries
+            .iter()
+            .filter(|(_, entry)| matches!(entry, BinaryEntry::Line(line) if is_newer_version(line)))
+            .count();
+
+        let records = entries
+            .into_iter()
+            .filter_map(|(_, entry)| entry.into_file_info())
+            .collect();
+
+        Ok((records, num_newer))
+    }
+
+    // the rewritten hash file, still a tmp file, is converted in place, and sealed with a footer of its entries,
+    // and any footer carried over from the old hash file is dropped
+    pub fn encode(tmp_file: &Path) -> DanoResult<()> {
+        let contents = std::fs::read_to_string(tmp_file)?;
+
+        let lines: Vec<&str> = contents
+            .lines()
+            .filter(|line| !HashFileFooter::is_footer_line(line))
+            .collect();
+
+        let num_header_lines = lines
+            .iter()
+            .take_while(|line| line.starts_with("//"))
+            .count();
+
+        let mut buffer = Self::file_header();
+
+        lines[..num_header_lines]
+            .iter()
+            .map(|line| BinaryEntry::Line((*line).to_owned()))
+            .chain(std::iter::once(BinaryEntry::Sealed))
+            .chain(
+                lines[num_header_lines..]
+                    .iter()
+                    .map(|line| BinaryEntry::from_line(line)),
+            )
+            .try_for_each(|entry| entry.encode(&mut buffer))?;
+
+        let footer = BinaryFooter::new(lines.len() + 1, &buffer)?;
+        BinaryEntry::Footer(footer).encode(&mut buffer)?;
+
+        std::fs::write(tmp_file, buffer).map_err(|err| err.into())
+    }
+
+    pub fn append(path: &Path, header: &str, file_infos: &[FileInfo]) -> DanoResult<()> {
+        let mut buffer = Vec::new();
+
+        if !path.exists() {
+            buffer = Self::file_header();
+
+            header
+                .lines()
+                .try_for_each(|line| BinaryEntry::Line(line.to_owned()).encode(&mut buffer))?;
+        }
+
+        file_infos.iter().try_for_each(|file_info| {
+            BinaryEntry::from_file_info(file_info)?.encode(&mut buffer)
+        })?;
+
+        HashFileCompression::append(path, &buffer)
+    }
+}
@@ -0,0 +1,151 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::ingest::RecordedFileInfo;
+use crate::lookup::FileInfoLookup;
+use crate::process::ProcessedFiles;
+use crate::requests::RequestBundle;
+use crate::schedule::Schedule;
+use crate::utility::{prepare_thread_pool, print_err_buf};
+use crate::{Config, DanoResult, DANO_CLEAN_EXIT_CODE, DANO_DEFAULT_HASH_FILE_NAME};
+
+// when the input files span multiple drives, each with a drive local catalog, a hash file
+// at the root of the mount, each input file is tested against the catalog of its own drive
+pub struct MultiCatalog {
+    groups: BTreeMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl MultiCatalog {
+    // returns none when every input file belongs to the default hash file
+    pub fn discover(config: &Config) -> Option<Self> {
+        if !config.opt_multi_catalog {
+            return None;
+        }
+
+        let default_catalog = config
+            .hash_file
+            .canonicalize()
+            .unwrap_or_else(|_| config.hash_file.clone());
+
+        let mut groups: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+
+        config.paths.iter().for_each(|path| {
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+
+            let catalog = match Self::mount_root(&canonical) {
+                Some(mount_root) if mount_root.join(DANO_DEFAULT_HASH_FILE_NAME).exists() => {
+                    mount_root.join(DANO_DEFAULT_HASH_FILE_NAME)
+                }
+                _ => default_catalog.clone(),
+            };
+
+            groups.entry(catalog).or_default().push(canonical);
+        });
+
+        if groups.keys().all(|catalog| catalog == &default_catalog) {
+            return None;
+        }
+
+        Some(Self { groups })
+    }
+
+    // the topmost ancestor directory on the same device as the path
+    #[cfg(unix)]
+    fn mount_root(path: &Path) -> Option<PathBuf> {
+        use std::os::unix::fs::MetadataExt;
+
+        let device = path.metadata().ok()?.dev();
+
+        path.ancestors()
+            .skip(1)
+            .take_while(
+                |ancestor| matches!(ancestor.metadata(), Ok(metadata) if metadata.dev() == device),
+            )
+            .last()
+            .map(|ancestor| ancestor.to_owned())
+    }
+
+    #[cfg(not(unix))]
+    fn mount_root(_path: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    pub fn exec(self, config: &Config) -> DanoResult<i32> {
+        let mut exit_code = DANO_CLEAN_EXIT_CODE;
+        let mut num_tested = 0usize;
+        let num_catalogs = self.groups.len();
+
+        for (catalog, paths) in self.groups {
+            if !config.opt_silent {
+                print_err_buf(&format!(
+                    "Testing {} files against catalog: {:?}\n",
+                    paths.len(),
+                    catalog
+                ))?;
+            }
+
+            let catalog_config = Config {
+                hash_file: catalog.clone(),
+                output_file: catalog,
+                is_single_path: false,
+                paths,
+                ..config.clone()
+            };
+
+            let recorded_file_info = RecordedFileInfo::from_catalog(&catalog_config)?;
+
+            let thread_pool = prepare_thread_pool(&catalog_config)?;
+            let file_info_requests = RequestBundle::new(&catalog_config, &recorded_file_info)?;
+            let (rx_item, _rx_unsupported) =
+                FileInfoLookup::exec(&catalog_config, file_info_requests, thread_pool)?;
+            let processed_files =
+                ProcessedFiles::new(&catalog_config, recorded_file_info, rx_item)?;
+
+            num_tested += processed_files.test_results.len();
+
+            if !catalog_config.opt_read_only {
+                if let Err(err) =
+                    Schedule::new(&catalog_config).and_then(|schedule| schedule.record_verified())
+                {
+                    eprintln!(
+                        "WARN: Could not record the time of this verification: {}",
+                        err
+                    );
+                }
+            }
+
+            let catalog_exit_code = processed_files.write_out(&catalog_config)?;
+
+            if catalog_exit_code != DANO_CLEAN_EXIT_CODE {
+                exit_code = catalog_exit_code;
+            }
+        }
+
+        if !config.opt_silent {
+            print_err_buf(&format!(
+                "Tested {} files against {} catalogs.\n",
+                num_tested, num_catalogs
+            ))?;
+        }
+
+        Ok(exit_code)
+    }
+}
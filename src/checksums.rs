@@ -0,0 +1,175 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::config::{Config, SelectedStreams};
+use crate::import::Importer;
+use crate::lookup::{FileInfo, FileMetadata, HashValue};
+use crate::{DanoError, DanoResult, DANO_FILE_INFO_VERSION, HEXADECIMAL_RADIX};
+
+const CHECKSUM_FILE_EXTENSIONS: [&str; 4] = ["md5", "sha1", "sha256", "sha512"];
+
+// md5sum and friends hash the whole file, so these records can only be verified by a whole file hash
+pub struct ChecksumFileImporter;
+
+impl Importer for ChecksumFileImporter {
+    fn name(&self) -> &'static str {
+        "md5sum"
+    }
+
+    fn is_valid_path(&self, path: &Path) -> bool {
+        let has_extension = matches!(path.extension(), Some(extension) if CHECKSUM_FILE_EXTENSIONS
+            .iter()
+            .any(|valid| extension.eq_ignore_ascii_case(valid)));
+
+        // also accept the conventional names, like "MD5SUMS" or "SHA256SUMS"
+        let has_name = matches!(path.file_name(), Some(file_name) if file_name
+            .to_string_lossy()
+            .to_ascii_uppercase()
+            .ends_with("SUMS"));
+
+        has_extension || has_name
+    }
+
+    fn import(&self, _config: &Config, path: &Path) -> DanoResult<Vec<FileInfo>> {
+        let contents = std::fs::read_to_string(path)?;
+
+        // paths within a checksum file are relative to the checksum file's directory
+        let parent = path.parent().unwrap_or_else(|| Path::new(""));
+
+        let file_info = contents
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with(';'))
+            .filter_map(|line| match Self::parse_line(line) {
+                Some(parsed) => Some(parsed),
+                None => {
+                    eprintln!("ERROR: Could not parse line from {:?}: {}", path, line);
+                    None
+                }
+            })
+            .map(|(hash_algo, hash_string, entry_path)| {
+                let entry_path = if entry_path.is_absolute() {
+                    entry_path
+                } else {
+                    parent.join(entry_path)
+                };
+
+                Self::generate_file_info(&entry_path, hash_algo, hash_string)
+            })
+            .filter_map(|res| match res {
+                Ok(file_info) => Some(file_info),
+                Err(err) => {
+                    eprintln!("ERROR: {}", err);
+                    None
+                }
+            })
+            .collect();
+
+        Ok(file_info)
+    }
+}
+
+impl ChecksumFileImporter {
+    // GNU style is "<hex>  <path>" or "<hex> *<path>", BSD style is "ALGO (<path>) = <hex>"
+    fn parse_line(line: &str) -> Option<(&'static str, &str, PathBuf)> {
+        if let Some((tag_and_path, hash_string)) = line.rsplit_once(") = ") {
+            let (tag, path) = tag_and_path.split_once(" (")?;
+            let hash_algo = Self::algo_from_tag(tag)?;
+
+            return Some((hash_algo, hash_string.trim(), PathBuf::from(path)));
+        }
+
+        let (hash_string, path) = line.split_once(' ')?;
+        let path = path.strip_prefix(['*', ' ']).unwrap_or(path);
+        let hash_algo = Self::algo_from_len(hash_string.len())?;
+
+        Some((hash_algo, hash_string, PathBuf::from(path)))
+    }
+
+    // use the names ffmpeg uses for these algorithms, so records may be compared
+    fn algo_from_tag(tag: &str) -> Option<&'static str> {
+        match tag.to_ascii_uppercase().as_str() {
+            "MD5" => Some("MD5"),
+            "SHA1" => Some("SHA160"),
+            "SHA256" => Some("SHA256"),
+            "SHA512" => Some("SHA512"),
+            _ => None,
+        }
+    }
+
+    fn algo_from_len(len: usize) -> Option<&'static str> {
+        match len {
+            32 => Some("MD5"),
+            40 => Some("SHA160"),
+            64 => Some("SHA256"),
+            128 => Some("SHA512"),
+            _ => None,
+        }
+    }
+
+    fn generate_file_info(path: &Path, hash_algo: &str, hash_string: &str) -> DanoResult<FileInfo> {
+        if !path.exists() {
+            let msg = format!("Path listed in checksum file does not exist: {:?}", path);
+            return Err(DanoError::new(&msg).into());
+        }
+
+        let hash_value = if hash_string.chars().all(|c| c.is_ascii_hexdigit()) {
+            HashValue {
+                radix: HEXADECIMAL_RADIX,
+                value: hash_string
+                    .to_ascii_lowercase()
+                    .trim_start_matches('0')
+                    .into(),
+            }
+        } else {
+            let msg = format!("Could not parse integer from checksum file for: {:?}", path);
+            return Err(DanoError::new(&msg).into());
+        };
+
+        Ok(FileInfo {
+            path: path.to_owned(),
+            version: DANO_FILE_INFO_VERSION,
+            metadata: Some(FileMetadata {
+                last_written: SystemTime::now(),
+                hash_algo: hash_algo.into(),
+                hash_value,
+                modify_time: path.metadata()?.modified()?,
+                selected_streams: SelectedStreams::WholeFile,
+                decoded: false,
+                opt_bits_per_second: None,
+                opt_media_info: None,
+                hardlinked: false,
+                fallback: false,
+                opt_video_format: None,
+                native: false,
+                opt_frame_digests: None,
+                opt_stream_hashes: None,
+                opt_size: None,
+                opt_last_checked: None,
+                opt_tags_hash: None,
+                cover_art_excluded: false,
+                opt_decode_format: None,
+                opt_track_hashes: None,
+            }),
+        })
+    }
+}
@@ -0,0 +1,203 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use crate::config::CompactModeConfig;
+use crate::lookup::FileInfo;
+use crate::tree::TreeHashes;
+use crate::utility::{
+    check_free_space, deserialize, deserialize_version, get_tmp_file, hash_file_exists,
+    invoked_from, persist_hash_file, print_err_buf, read_hash_file, resolve_path, serialize,
+};
+use crate::{Config, DanoError, DanoResult, DANO_FILE_INFO_VERSION};
+
+const HEADER_PREFIX: &str = "//";
+
+// a record is superseded by a more recently written record of the same path, in the same form
+type RecordKey = (PathBuf, Option<bool>);
+
+#[derive(Debug, Default)]
+struct CompactStats {
+    records_before: usize,
+    records_after: usize,
+    superseded: usize,
+    upgraded: usize,
+    missing: usize,
+    unparseable: usize,
+    tree_hashes: usize,
+    bytes_before: usize,
+    bytes_after: usize,
+}
+
+impl CompactStats {
+    fn summary(&self, is_dry_run: bool) -> String {
+        let verb = if is_dry_run {
+            "Would compact"
+        } else {
+            "Compacted"
+        };
+
+        format!(
+            "{} hash file from {} to {} records ({} superseded, {} missing paths dropped, {} legacy records upgraded, {} unparseable lines kept), \
+            and {} tree hashes kept, {} bytes reclaimed.\n",
+            verb,
+            self.records_before,
+            self.records_after,
+            self.superseded,
+            self.missing,
+            self.upgraded,
+            self.unparseable,
+            self.tree_hashes,
+            self.bytes_before.saturating_sub(self.bytes_after)
+        )
+    }
+}
+
+pub struct HashFileCompaction;
+
+impl HashFileCompaction {
+    pub fn exec(config: &Config, compact_config: &CompactModeConfig) -> DanoResult<()> {
+        if !hash_file_exists(&config.hash_file) {
+            return Err(DanoError::new("No hash file exists to compact.").into());
+        }
+
+        let contents = read_hash_file(&config.hash_file)?;
+
+        let mut stats = CompactStats {
+            bytes_before: contents.len(),
+            ..Default::default()
+        };
+
+        let header: Vec<&str> = contents
+            .lines()
+            .take_while(|line| line.starts_with(HEADER_PREFIX))
+            .collect();
+
+        // recorded paths are relative to the directory from which dano was invoked, when written
+        let base_dir = invoked_from(&header).unwrap_or_else(|| config.pwd.clone());
+
+        let mut latest: BTreeMap<RecordKey, FileInfo> = BTreeMap::new();
+        let mut unparseable: Vec<&str> = Vec::new();
+        // tree hashes are not records of any file, and are kept as they are
+        let mut tree_hashes: Vec<&str> = Vec::new();
+
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with(HEADER_PREFIX))
+            .for_each(|line| {
+                if TreeHashes::is_tree_hash_line(line) {
+                    tree_hashes.push(line);
+                    return;
+                }
+
+                stats.records_before += 1;
+
+                let Ok(file_info) = deserialize(line) else {
+                    // never silently discard a line we do not understand
+                    unparseable.push(line);
+                    return;
+                };
+
+                if deserialize_version(line) != Some(DANO_FILE_INFO_VERSION) {
+                    stats.upgraded += 1;
+                }
+
+                let key: RecordKey = (
+                    file_info.path.clone(),
+                    file_info.metadata.as_ref().map(|metadata| metadata.decoded),
+                );
+
+                match latest.get(&key) {
+                    Some(existing)
+                        if Self::last_written(existing) > Self::last_written(&file_info) =>
+                    {
+                        stats.superseded += 1;
+                    }
+                    Some(_) => {
+                        stats.superseded += 1;
+                        latest.insert(key, file_info);
+                    }
+                    None => {
+                        latest.insert(key, file_info);
+                    }
+                }
+            });
+
+        let mut kept: Vec<FileInfo> = latest.into_values().collect();
+
+        if compact_config.opt_drop_missing {
+            let before = kept.len();
+            kept.retain(|file_info| resolve_path(&base_dir, &file_info.path).exists());
+            stats.missing = before - kept.len();
+        }
+
+        stats.unparseable = unparseable.len();
+        stats.tree_hashes = tree_hashes.len();
+
+        let mut buffer = String::new();
+
+        header.iter().for_each(|line| {
+            buffer.push_str(line);
+            buffer.push('\n');
+        });
+
+        kept.iter().try_for_each(|file_info| -> DanoResult<()> {
+            buffer.push_str(&serialize(file_info)?);
+            Ok(())
+        })?;
+
+        unparseable
+            .iter()
+            .chain(tree_hashes.iter())
+            .for_each(|line| {
+                buffer.push_str(line);
+                buffer.push('\n');
+            });
+
+        stats.records_after = kept.len() + unparseable.len();
+        stats.bytes_after = buffer.len();
+
+        if !config.opt_dry_run {
+            let tmp_file = get_tmp_file(config, &config.hash_file);
+            check_free_space(&tmp_file, buffer.len() as u64)?;
+
+            let res = std::fs::write(&tmp_file, &buffer)
+                .map_err(|err| err.into())
+                .and_then(|_| persist_hash_file(config, &tmp_file, &config.hash_file));
+
+            if res.is_err() {
+                let _ = std::fs::remove_file(&tmp_file);
+            }
+
+            res?;
+        }
+
+        if config.opt_silent {
+            return Ok(());
+        }
+
+        print_err_buf(&stats.summary(config.opt_dry_run))
+    }
+
+    fn last_written(file_info: &FileInfo) -> Option<std::time::SystemTime> {
+        file_info
+            .metadata
+            .as_ref()
+            .map(|metadata| metadata.last_written)
+    }
+}
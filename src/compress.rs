@@ -0,0 +1,111 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    path::Path,
+};
+
+use crate::utility::{is_streamed_hash_file, read_streamed_hash_file};
+use crate::DanoResult;
+
+// the magic bytes which begin each zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+const ZSTD_EXTENSION: &str = "zst";
+// zero selects the default compression level of zstd
+const ZSTD_LEVEL: i32 = 0;
+
+// a hash file, in either format, may be compressed, and, because concatenated zstd frames decompress
+// to the concatenation of their contents, records are appended to a compressed hash file as a frame of their own
+pub struct HashFileCompression;
+
+impl HashFileCompression {
+    pub fn has_extension(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == ZSTD_EXTENSION)
+    }
+
+    fn has_magic(path: &Path) -> bool {
+        if is_streamed_hash_file(path) {
+            return read_streamed_hash_file(path).is_ok_and(|bytes| bytes.starts_with(&ZSTD_MAGIC));
+        }
+
+        let mut magic = [0u8; ZSTD_MAGIC.len()];
+
+        File::open(path)
+            .and_then(|mut file| file.read_exact(&mut magic))
+            .is_ok_and(|_| magic == ZSTD_MAGIC)
+    }
+
+    // an existing hash file is compressed if it was written compressed, whatever its name,
+    // and a new hash file is compressed if its name ends in '.zst'
+    pub fn is_compressed(path: &Path) -> bool {
+        if path.exists() {
+            return Self::has_magic(path);
+        }
+
+        Self::has_extension(path)
+    }
+
+    // the hash file is decompressed as it is read, rather than all at once
+    pub fn open(path: &Path) -> DanoResult<Box<dyn Read>> {
+        if is_streamed_hash_file(path) {
+            let bytes = read_streamed_hash_file(path)?;
+
+            if bytes.starts_with(&ZSTD_MAGIC) {
+                return Ok(Box::new(zstd::stream::read::Decoder::new(bytes)?));
+            }
+
+            return Ok(Box::new(bytes));
+        }
+
+        let file = File::open(path)?;
+
+        if Self::has_magic(path) {
+            return Ok(Box::new(zstd::stream::read::Decoder::new(file)?));
+        }
+
+        Ok(Box::new(file))
+    }
+
+    pub fn read(path: &Path) -> DanoResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        Self::open(path)?.read_to_end(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    // the rewritten hash file, still a tmp file, and already sealed, is compressed in place
+    pub fn compress(tmp_file: &Path) -> DanoResult<()> {
+        let bytes = std::fs::read(tmp_file)?;
+        let compressed = zstd::stream::encode_all(bytes.as_slice(), ZSTD_LEVEL)?;
+
+        std::fs::write(tmp_file, compressed).map_err(|err| err.into())
+    }
+
+    pub fn append(path: &Path, bytes: &[u8]) -> DanoResult<()> {
+        let is_compressed = Self::is_compressed(path);
+
+        let mut output_file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        if is_compressed {
+            let compressed = zstd::stream::encode_all(bytes, ZSTD_LEVEL)?;
+            return output_file.write_all(&compressed).map_err(|err| err.into());
+        }
+
+        output_file.write_all(bytes).map_err(|err| err.into())
+    }
+}
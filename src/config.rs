@@ -16,20 +16,46 @@
 // that was distributed with this source code.
 
 use std::{
-    collections::HashSet,
-    ffi::OsStr,
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    ffi::{OsStr, OsString},
+    io::IsTerminal,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
-use clap::{crate_name, crate_version, Arg, ArgMatches};
-use itertools::Either;
+use clap::{crate_name, crate_version, Arg, ArgGroup, ArgMatches};
+use glob::Pattern;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use which::which;
 
-use crate::utility::read_stdin;
+use crate::binary::HashFileFormat;
+use crate::ffmpeg::FFmpegCapabilities;
+use crate::hasher::NativeHasher;
+use crate::history::HISTORY_EXTENSION;
+use crate::import::{detect_checksum_importer, importer_names};
+use crate::lock::{LockWait, LOCK_EXTENSION};
+use crate::mediainfo::MediaQuery;
+use crate::policy::HashPolicy;
+use crate::probe::FFProbe;
+use crate::remote::{RemoteHashFile, RemoteMedia};
+use crate::rules::{PathRule, PathRules};
+use crate::sidecar::Sidecar;
+use crate::signature::SigningTool;
+use crate::sniff::{ContentSniffer, SniffedContent};
+use crate::undo::UNDO_EXTENSION;
+use crate::utility::{
+    canonicalize, hash_file_exists, is_stdin_path, is_streamed_hash_file, read_files_from,
+    read_stdin,
+};
 use crate::{DanoError, DanoResult, DANO_DEFAULT_HASH_FILE_NAME};
 
 const XATTR_ENV_KEY: &str = "DANO_XATTR_WRITES";
+const CONFIG_FILE_NAME: &str = "config.toml";
+const SYSTEM_CONFIG_DIR: &str = "/etc/dano";
+const DEFAULT_NUM_BACKUPS: usize = 3;
+const DEFAULT_DAEMON_INTERVAL: Duration = Duration::from_secs(7 * 86_400);
 
 fn parse_args() -> ArgMatches {
     clap::Command::new(crate_name!())
@@ -41,7 +67,9 @@ fn parse_args() -> ArgMatches {
             Arg::new("INPUT_FILES")
                 .help("select the input files to be hashed or verified, etc.  INPUT_FILES can also be read from stdin for NULL or NEWLINE delimited inputs.  \
                 By default, files which don't appear to be valid extensions for ffmpeg are filtered with a WARN message, unless the SILENT flag is enabled.  \
-                Hidden files (so-called dot files), files with no name, or no extension are silently ignored.  The default behavior can be disabled with the DISABLE_FILTER flag.")
+                A file whose contents are recognized as media is accepted whatever its extension, and a file with a media extension, whose contents are recognized as something else, such as an archive, is skipped.  \
+                Files with no name, or no extension are skipped, and counted in a summary at the end of the run.  The default behavior can be disabled with the DISABLE_FILTER flag.  \
+                Hidden files (so-called dot files) are always skipped, unless the INCLUDE_HIDDEN flag is enabled.")
                 .takes_value(true)
                 .multiple_values(true)
                 .value_parser(clap::builder::ValueParser::os_string())
@@ -49,7 +77,11 @@ fn parse_args() -> ArgMatches {
         )
         .arg(
             Arg::new("OUTPUT_FILE")
-                .help("select the output file to record the file information. If not specified, 'dano_hashes.txt' in the current working directory will be used.")
+                .help("select the output file to record the file information. If not specified, 'dano_hashes.txt' in the current working directory will be used.  \
+                In WRITE and DUMP modes, '-' streams each record to stdout, as a line of a hash file, for piping into another tool, or over ssh, \
+                in which case records are written to no other store, and all other output is printed to stderr.  \
+                A new output file whose name ends in '.zst', such as 'dano_hashes.txt.zst', is compressed with zstd, as is each rewrite of an output file which was compressed.  \
+                A compressed hash file is decompressed as it is read, whatever its name.")
                 .short('o')
                 .long("output-file")
                 .takes_value(true)
@@ -60,7 +92,11 @@ fn parse_args() -> ArgMatches {
         )
         .arg(
             Arg::new("HASH_FILE")
-                .help("select the file from which to read recorded file information.  If not specified, the output file will be used (or if not specified, 'dano_hashes.txt' in the current working directory will be used).")
+                .help("select the file from which to read recorded file information.  If not specified, the output file will be used (or if not specified, 'dano_hashes.txt' in the current working directory will be used).  \
+                '-' reads the hash file from stdin, such as a hash file piped in over ssh, in which case input files cannot also be read from stdin, and the hash file cannot be rewritten.  \
+                An http://, https:// or s3:// URL fetches the hash file with curl, for instance, to test a mirror against a hash file kept on a web server, or in object storage, \
+                which likewise cannot be rewritten.  \
+                An s3:// URL, such as 's3://bucket/dano_hashes.txt', requires the AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY environment variables, and respects AWS_SESSION_TOKEN, AWS_REGION and AWS_ENDPOINT_URL.")
                 .short('k')
                 .long("hash-file")
                 .takes_value(true)
@@ -74,7 +110,6 @@ fn parse_args() -> ArgMatches {
                 .help("write the new input files' hash information.  If no other flags are specified, dano will ignore files which already have file hashes.")
                 .short('w')
                 .long("write")
-                .conflicts_with_all(&["PRINT", "DUMP", "DUPLICATES", "CLEAN", "TEST"])
                 .display_order(4))
         .arg(
             Arg::new("TEST")
@@ -83,39 +118,595 @@ fn parse_args() -> ArgMatches {
                 .long("test")
                 .alias("compare")
                 .short_alias('c')
-                .conflicts_with_all(&["PRINT", "DUMP", "DUPLICATES", "CLEAN", "WRITE"])
                 .display_order(5))
         .arg(
             Arg::new("PRINT")
                 .help("pretty print all recorded file information (discovered within both the hash file and any xattrs).")
                 .short('p')
                 .long("print")
-                .conflicts_with_all(&["DUMP", "DUPLICATES", "CLEAN", "WRITE", "TEST"])
                 .display_order(6))
         .arg(
             Arg::new("DUMP")
                 .help("dump the recorded file information (in hash file and xattrs) to the output file (don't test/compare).")
                 .long("dump")
-                .conflicts_with_all(&["DUPLICATES", "CLEAN", "WRITE", "PRINT", "TEST"])
                 .display_order(7))
         .arg(
             Arg::new("DUPLICATES")
                 .help("show any hash value duplicates discovered when reading back recorded file information (in hash file and xattrs).")
                 .long("duplicates")
                 .aliases(&["dupes"])
-                .conflicts_with_all(&["DUMP", "CLEAN", "WRITE", "PRINT", "TEST"])
                 .display_order(8))
         .arg(
             Arg::new("CLEAN")
                 .help("remove any hash files, given as input files, and remove any extended attributes, given as input files.")
                 .long("clean")
                 .display_order(9))
+        .arg(
+            Arg::new("COMPACT")
+                .help("rewrite the hash file, removing records superseded by a more recently written record of the same path, \
+                and upgrading records written by earlier versions of dano.  Reports how many records and bytes were reclaimed.  \
+                Extended attributes are not modified.")
+                .long("compact")
+                .display_order(9))
+        .arg(
+            Arg::new("INIT")
+                .help("a guided initial hashing run for a new library.  Walks the given directory, estimates the total work from file sizes, asks for confirmation, \
+                and then hashes the library in batches, reporting progress, and writing both extended attributes and a hash file (in the given directory, \
+                unless OUTPUT_FILE is specified), with canonical paths.  Because each batch is written when complete, an interrupted run may be resumed \
+                by simply running the same command again.")
+                .long("init")
+                .takes_value(true)
+                .require_equals(true)
+                .value_parser(clap::builder::ValueParser::os_string())
+                .conflicts_with("INPUT_FILES")
+                .display_order(9))
+        .arg(
+            Arg::new("VERIFY_COPY")
+                .help("verify a copy, such as after a large migration, by hashing the media files beneath both the source and destination directories, \
+                and comparing files by their path relative to each directory.  Reports files which are missing from, or only present in, the destination, \
+                and files whose streams do not match.  Dano extended attributes are reused for source files which have not been modified since being recorded, \
+                but destination files are always hashed, because a copied extended attribute proves nothing about the copy.")
+                .long("verify-copy")
+                .takes_value(true)
+                .number_of_values(2)
+                .value_names(&["SRC_DIR", "DST_DIR"])
+                .value_parser(clap::builder::ValueParser::os_string())
+                .conflicts_with("INPUT_FILES")
+                .display_order(9))
+        .arg(
+            Arg::new("IMPORT")
+                .help("import checksums recorded by another tool, and write such information as dano recorded file information.  \
+                'flac' and 'wavpack' import the MD5 sum of the decoded audio stream embedded in such files.  \
+                'cshatag' imports the SHA256 whole file hash from a file's cshatag extended attributes.  \
+                'md5sum' imports whole file hashes from the input checksum files (md5sum, sha1sum, sha256sum, or sha512sum format).  \
+                'sfv' imports CRC32 whole file hashes from the input .sfv files.  \
+                'ffp' imports FLAC fingerprints, the MD5 sum of the decoded audio stream, from the input .ffp files.")
+                .long("import")
+                .takes_value(true)
+                .require_equals(true)
+                .possible_values(importer_names())
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(10))
         .arg(
             Arg::new("IMPORT_FLAC")
-                .help("import flac checksums and write such information as dano recorded file information.")
+                .help("import flac checksums and write such information as dano recorded file information.  Same as --import=flac.")
                 .long("import-flac")
-                .conflicts_with_all(&["TEST", "PRINT", "DUMP", "DUPLICATES"])
                 .display_order(10))
+        .arg(
+            Arg::new("IMPORT_WAVPACK")
+                .help("import WavPack checksums, the MD5 sum of the decoded audio stream, embedded in WavPack files, \
+                and write such information as dano recorded file information.  Same as --import=wavpack.")
+                .long("import-wavpack")
+                .display_order(10))
+        .arg(
+            Arg::new("IMPORT_CHECKSUMS")
+                .help("import an existing checksum file, and write its entries as dano recorded file information.  \
+                The format is detected from the file name: .sfv files hold CRC32 whole file hashes, .ffp files hold FLAC fingerprints, \
+                and .md5, .sha1, .sha256 and .sha512 files, or md5sum style files, hold whole file hashes.  \
+                Paths within the checksum file are relative to the checksum file's directory.  When specified, INPUT_FILES are not required.")
+                .long("import-checksums")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("FILE")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .conflicts_with("INPUT_FILES")
+                .display_order(63))
+        .arg(
+            Arg::new("RULES")
+                .help("read a rules file which maps path globs to the settings used to hash new files, such that, for example, \
+                files under /music are hashed as decoded MD5, while files under /video are hashed as murmur3 stream copies.  \
+                The rules file is TOML, and each [[rule]] has a glob, and any of: hash_algo, decode (true or false), and only (audio, video, all, or a stream specifier).  \
+                The first rule which matches a path applies.  A setting a rule does not specify is taken from the command line, \
+                and an extension override in the config file takes precedence over a rule's decode setting.  \
+                Recorded files are always verified with their recorded settings.")
+                .long("rules")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("FILE")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(64))
+        .arg(
+            Arg::new("EXPORT")
+                .help("export the recorded file information to stdout, as a standard checksum file, so that other tools, and those without dano, may verify the files.  \
+                'md5sum' and 'sha256sum' export whole file hashes in coreutils format, and 'sfv' exports CRC32 whole file hashes.  \
+                Where a path has no recorded whole file hash of that algorithm, which is usual, because dano records hashes of the streams, \
+                the file is hashed again, so such files should be verified with TEST before export.  \
+                'ffp' exports FLAC fingerprints, which are only available for records of the decoded audio stream, hashed with MD5 at the file's bit depth, \
+                such as those imported with --import-flac.  A path which is not valid UTF-8 is skipped, with a warning.")
+                .long("export")
+                .takes_value(true)
+                .require_equals(true)
+                .possible_values(["md5sum", "sha256sum", "sfv", "ffp"])
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(65))
+        .arg(
+            Arg::new("WATCH")
+                .help("watch the given directory, and its subdirectories, for new files, and hash each new media file once it has settled, \
+                that is, once its size and modify time have not changed for a few seconds, writing the new records to the hash file and xattrs as they arrive.  \
+                Files already in the directory, which have no record, are hashed when the watch begins.  Useful for download or rip landing folders.  \
+                dano watches until it is interrupted.")
+                .long("watch")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("DIR")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .conflicts_with("INPUT_FILES")
+                .display_order(66))
+        .arg(
+            Arg::new("DAEMON")
+                .help("in TEST mode, keep running, and re-verify the recorded entries, such that each entry is verified once per INTERVAL.  \
+                The stalest entries, those never verified, or verified longest ago, are verified first, in batches, and, after each batch, \
+                when each entry was verified, and its outcome, is recorded in the dano state file, so a daemon which is stopped and started again \
+                resumes where it left off.  The hash file is read again each cycle, so new entries are verified in turn.  \
+                When specified, INPUT_FILES are not required.")
+                .long("daemon")
+                .requires("TEST")
+                .conflicts_with_all(&["INPUT_FILES", "BUDGET", "REFERENCE", "WRITE_NEW", "OVERWRITE_OLD", "STATUS_FILE"])
+                .display_order(67))
+        .arg(
+            Arg::new("INTERVAL")
+                .help("in DAEMON mode, the interval within which each entry should be verified again, such as: --interval=7d, or --interval=12h.  Default is 7d.")
+                .long("interval")
+                .takes_value(true)
+                .require_equals(true)
+                .requires("DAEMON")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(68))
+        .arg(
+            Arg::new("STRICT_SCHEMA")
+                .help("fail, rather than warn and continue, when the hash file, or an extended attribute, contains records written by a newer version of dano, \
+                which this version cannot read.  By default, such records are skipped, with a warning and a count, and are preserved when the hash file is rewritten.")
+                .long("strict-schema")
+                .display_order(69))
+        .arg(
+            Arg::new("CONFIG")
+                .help("read defaults from the given config file, rather than from the per user config file, $XDG_CONFIG_HOME/dano/config.toml.  \
+                A config file may set: hash_algo, xattr, threads, decode, canonical_paths, disable_filter, probe_filter, probe_unknown, include_hidden, backups, and stable_output, \
+                as well as per extension overrides, such that options need not be repeated on every invocation.  \
+                Options given on the command line take precedence, and a flag set in a config file may be turned off with its --no- form, such as --no-decode.  The system wide config file, /etc/dano/config.toml, is always read.  \
+                Use --config=/dev/null to ignore the per user config file for a single run.")
+                .long("config")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("FILE")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(70))
+        .arg(
+            Arg::new("COVERAGE")
+                .help("report the media files beneath the given directory which have no record at all, in the hash file or in extended attributes, \
+                grouped by directory, with counts, that is, the files which are not yet protected.  The directory is walked, and filtered, as for WRITE.  \
+                Nothing is hashed.  With --porcelain, each unrecorded file is printed as a result, so the report may be exported.  \
+                Exits with a non-zero status when any file is unrecorded.")
+                .long("coverage")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("DIR")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .conflicts_with("INPUT_FILES")
+                .display_order(71))
+        .arg(
+            Arg::new("STRICT")
+                .help("exit with a non-zero status for any path which was not verified, not only for a hash mismatch, or a missing file.  \
+                In TEST mode, a path which is a new file, not yet recorded, fails, and in any mode, a file which ffmpeg could not hash, \
+                because the codec or container is unsupported, fails.")
+                .long("strict")
+                .conflicts_with("LENIENT")
+                .display_order(72))
+        .arg(
+            Arg::new("LENIENT")
+                .help("exit with a non-zero status only for a hash mismatch.  A missing file, or a file which could not be read, \
+                is still reported, but does not fail the run.  By default, a hash mismatch or a missing file fails the run, \
+                and a file which could not be read exits with status 4.")
+                .long("lenient")
+                .display_order(73))
+        .arg(
+            Arg::new("QUICK")
+                .help("in TEST mode, skip hashing any recorded file whose modify time, and size, if recorded, are unchanged, and report it as OK.  \
+                Only a file whose metadata has changed is hashed again.  A quick test cannot detect silent corruption, such as bit rot, \
+                which leaves a file's metadata unchanged, but can make a frequent scrub of a large library take seconds, rather than hours.")
+                .long("quick")
+                .requires("TEST")
+                .display_order(74))
+        .arg(
+            Arg::new("PRUNE")
+                .help("rewrite the hash file, removing only the records of paths which no longer exist on disk, and leaving every other record as it was.  \
+                Relative paths are resolved against the directory from which dano was invoked when the hash file was first written.  \
+                With --dry-run, only print the records which would be removed.  Extended attributes are never removed, \
+                but the extended attributes of any INPUT_FILES which record a moved or renamed path are reported.")
+                .long("prune")
+                .display_order(75))
+        .arg(
+            Arg::new("DIFF")
+                .help("compare two hash files, such as archival snapshots of a library, and report paths which were added, removed, \
+                renamed (the same hash under a new path), or whose hash has changed.  Only the records are compared, so neither ffmpeg, \
+                nor the files themselves, are required.  Paths are compared as recorded.  \
+                Exits with a non-zero status when the two hash files differ.")
+                .long("diff")
+                .takes_value(true)
+                .number_of_values(2)
+                .value_names(&["OLD_HASH_FILE", "NEW_HASH_FILE"])
+                .value_parser(clap::builder::ValueParser::os_string())
+                .conflicts_with("INPUT_FILES")
+                .display_order(76))
+        .arg(
+            Arg::new("SIDECAR")
+                .help("write each input file's hash to a sidecar file beside it, named as the file, with a .dano extension, such as: movie.mkv.dano, \
+                rather than to the hash file, or to extended attributes.  Useful on file systems without extended attributes, \
+                and for collections whose files are moved one by one.  Sidecars are always read, when present, whether or not this option is given.")
+                .long("sidecar")
+                .conflicts_with_all(&["XATTR", "XATTR_IF_MISSING", "BOTH_STORES"])
+                .display_order(77))
+        .arg(
+            Arg::new("PROBE_FILTER")
+                .help("rather than filtering input files by the list of extensions which ffmpeg lists as \"common\", run ffprobe on each input file, \
+                and accept any file which ffprobe recognizes as containing an audio or video stream, whatever its extension.  \
+                Slower than the extension filter, because each file must be opened, but no file ffmpeg can demux is skipped merely because of its extension.")
+                .long("probe-filter")
+                .conflicts_with("DISABLE_FILTER")
+                .display_order(78))
+        .arg(
+            Arg::new("INCLUDE")
+                .help("only include input files whose paths match the given glob pattern, such as: --include='*.mkv'.  \
+                May be given more than once, and a file is included if its path matches any of the patterns.  \
+                Applies to INPUT_FILES, as well as to files found when walking a directory, such as for INIT or STATUS.")
+                .long("include")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(79))
+        .arg(
+            Arg::new("EXCLUDE")
+                .help("exclude input files whose paths match the given glob pattern, such as: --exclude='*sample*'.  \
+                May be given more than once, and takes precedence over INCLUDE.  When walking a directory, \
+                a directory whose path matches is not entered at all.")
+                .long("exclude")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(80))
+        .arg(
+            Arg::new("FILES_FROM")
+                .help("read INPUT_FILES from the given file, one path per line, rather than from the command line or stdin, \
+                such that a long list of paths need not fit on the command line, and a run may be replayed from the same list.  \
+                Use --files-from=- to read the list from stdin.")
+                .long("files-from")
+                .takes_value(true)
+                .require_equals(true)
+                .conflicts_with("INPUT_FILES")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(81))
+        .arg(
+            Arg::new("NULL")
+                .help("paths in the FILES_FROM list are delimited by NUL characters, rather than newlines, such as the output of find -print0.")
+                .short('0')
+                .long("null")
+                .requires("FILES_FROM")
+                .display_order(82))
+        .arg(
+            Arg::new("MAX_DEPTH")
+                .help("when walking a directory, such as for INIT, VERIFY_COPY or STATUS, descend at most the given number of levels, \
+                such as: --max-depth=1, which only includes the files immediately within the directory, and no subdirectories.")
+                .long("max-depth")
+                .takes_value(true)
+                .require_equals(true)
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .display_order(83))
+        .arg(
+            Arg::new("FFMPEG_PATH")
+                .help("run the given ffmpeg, such as a static build of a newer ffmpeg, rather than the first ffmpeg found in the PATH.  \
+                May also be given by the DANO_FFMPEG environment variable, though this option takes precedence.")
+                .long("ffmpeg-path")
+                .takes_value(true)
+                .require_equals(true)
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(84))
+        .arg(
+            Arg::new("FFMPEG_ARGS")
+                .help("append the given arguments, separated by whitespace, to each ffmpeg command, before the hash muxer options, \
+                such as: --ffmpeg-args=\"-map 0:a:0\".  Note that arguments which change what is hashed will change the hash, \
+                and so must be given again whenever such files are tested.  Files are always hashed by ffmpeg, and never natively, when this option is given.")
+                .long("ffmpeg-args")
+                .takes_value(true)
+                .require_equals(true)
+                .allow_hyphen_values(true)
+                .display_order(86))
+        .arg(
+            Arg::new("PIPE_HASH")
+                .help("ask ffmpeg to write the stream data to stdout, and hash it within dano, rather than with ffmpeg's hash muxer, \
+                for any hash algorithm dano implements (crc32, murmur3, md5, sha1, sha256, sha512, blake3).  The bytes hashed are the same, so the hashes are the same, \
+                and records written either way may be tested either way.  A hash algorithm which dano implements, but the installed ffmpeg does not, is always hashed this way.")
+                .long("pipe-hash")
+                .display_order(87))
+        .arg(
+            Arg::new("MIGRATE_ALGO")
+                .help("migrate recorded hashes to the given hash algorithm.  In a single pass, each recorded file is hashed twice, \
+                once with its recorded hash algorithm, to verify it, and once with the new hash algorithm, in the same form, decoded or copied.  \
+                Only a record which is verified is replaced, in each store which holds it, and the hash file is rewritten once, atomically.  \
+                A record which cannot be verified is reported, and is kept, unchanged.  When input files are given, only their records are migrated.  \
+                Each old record is appended to the history log, next to the hash file, with the new record.")
+                .long("migrate-algo")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("ALGO")
+                .possible_values(["murmur3", "md5", "crc32", "adler32", "sha1", "sha160", "sha256", "sha384", "sha512", "blake3"])
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(88))
+        .arg(
+            Arg::new("WHOLE_FILE")
+                .help("hash the raw bytes of each new input file, with a hash algorithm dano implements (crc32, murmur3, md5, sha1, sha256, sha512, blake3), \
+                without ffmpeg.  Such records are marked as whole file records, and are always verified the same way.  \
+                This is useful for files which are not media, such as cue sheets, rip logs, and cover art, which live alongside media in the same hash file.  \
+                The default filtering of file extensions is disabled when this option is given, so such files may be tested, as well as written, with this option.")
+                .long("whole-file")
+                .conflicts_with_all(&["DECODE", "ONLY", "IMAGE_PIXELS", "FRAME_HASH", "STREAM_HASH", "PIPE_HASH"])
+                .display_order(89))
+        .arg(
+            Arg::new("TAGS_HASH")
+                .help("record a second hash, of the container's and each stream's tags, as ffprobe reports them, apart from the hash of the streams.  \
+                When a TEST finds the streams intact, but the tags changed, such as after retagging, the path is reported as such, which is not a failure.  \
+                ACCEPT records the new tags hash.")
+                .long("tags-hash")
+                .conflicts_with("WHOLE_FILE")
+                .display_order(90))
+        .arg(
+            Arg::new("INCLUDE_COVER_ART")
+                .help("include attached pictures, such as embedded cover art, when hashing all streams, or the video streams, of new input files.  \
+                By default, attached pictures are excluded, so retagging a file with new cover art does not change its hash.  \
+                Records written by earlier versions of dano, which always included attached pictures, are still verified as they were recorded.")
+                .long("include-cover-art")
+                .conflicts_with("WHOLE_FILE")
+                .display_order(91))
+        .arg(
+            Arg::new("DECODE_FORMAT")
+                .help("when decoding the audio streams of new input files, decode to the given sample format: \
+                's32' or 's24', signed integer samples of 32 or 24 bits, or 'f32' or 'f64', floating point samples of 32 or 64 bits, \
+                rather than at the bit depth, or the floating point format, dano detects.  The sample format is recorded, so the file is always verified the same way.  \
+                Decoded audio is never resampled or remixed: ffmpeg's PCM encoders accept every sample rate and channel layout, \
+                and dano refuses to hash decoded audio when FFMPEG_ARGS would change the sample rate or channel layout, \
+                because any such conversion makes a decoded hash depend upon the version of ffmpeg.")
+                .long("decode-format")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("FORMAT")
+                .possible_values(["s32", "s24", "f32", "f64"])
+                .conflicts_with("WHOLE_FILE")
+                .display_order(92))
+        .arg(
+            Arg::new("TEST_FLAC")
+                .help("verify FLAC files against the MD5 sum embedded in each file's STREAMINFO block, like \"flac -t\", but on many threads.  \
+                Each input FLAC file is decoded by ffmpeg, at the bit depth recorded in its STREAMINFO block, and the MD5 sum of the decoded audio is compared to the embedded MD5 sum.  \
+                A FLAC file without an embedded MD5 sum is reported, but cannot be verified.  No records are required, or written, \
+                but, where a record of the same decoded audio, with the same MD5 sum, exists, the time of its last check is updated, given --record-checked.")
+                .long("test-flac")
+                .display_order(93))
+        .arg(
+            Arg::new("ACCURATERIP")
+                .help("verify FLAC files ripped from audio CDs against the AccurateRip database, and the CUETools database (CTDB), \
+                each of which records the checksums of other rips of the same disc.  The AccurateRip v1 and v2 checksums, and the CTDB CRC32, \
+                of each track are computed, and checked against each database in which the disc is present.  \
+                The FLAC files within each directory are taken to be the tracks of a single disc, in the order of their file names, \
+                and the disc is identified by the length of each track, so every track of the disc must be present.  \
+                A rip must be corrected for the read offset of its drive, and a disc with a data track, or with audio hidden before its first track, cannot be identified.  \
+                Each track is reported as accurately ripped, when it matches either database, with the confidence of the matching submissions, \
+                and of all submissions, in each database, or not.  A disc which is present in neither database cannot be verified.  Requires curl, and access to the network.  No records are required, or written.")
+                .long("accuraterip")
+                .display_order(94))
+        .arg(
+            Arg::new("CUE_TRACKS")
+                .help("when a new input file is a single file album, with a CUE sheet beside it, such as \"album.cue\", or \"album.flac.cue\", \
+                record a hash of each track's decoded audio, as well as the hash of the album.  Each track begins at its INDEX 01, and ends where the next track begins.  \
+                Tracks are only hashed when the album's audio is decoded.  A TEST of a changed album reports which tracks have changed, \
+                and DUPLICATES reports recorded files which match a track, such as the tracks of a later split, or of a re-rip.")
+                .long("cue-tracks")
+                .conflicts_with("WHOLE_FILE")
+                .display_order(95))
+        .arg(
+            Arg::new("TREE_HASH")
+                .help("compute an aggregate hash of each directory, from the recorded hashes of the files beneath it, and record it in the hash file.  \
+                A directory's tree hash covers its files, and the tree hashes of its subdirectories, so a single changed record changes the tree hash of \
+                each directory above it.  File names are not hashed, so copies of an album, or of a season, with the same streams, have the same tree hash, \
+                and such directories are reported as identical.  A recorded tree hash which has changed is reported, but is only replaced given OVERWRITE_OLD.  \
+                Without input files, every record in the hash file is included.  The tree hash is computed with the selected hash algorithm, \
+                which must be one dano implements (crc32, murmur3, md5, sha1, sha256, sha512, blake3).")
+                .long("tree-hash")
+                .display_order(96))
+        .arg(
+            Arg::new("SIGN")
+                .help("sign the hash file, with a detached signature, written next to the hash file, whenever dano writes the hash file, \
+                or whenever the hash file is not yet signed, so that a hash file which has been tampered with cannot vouch for media which has been tampered with.  \
+                By default, the hash file is signed with minisign, as dano_hashes.txt.minisig, or else with gpg, as dano_hashes.txt.asc.  \
+                Whenever a signature exists next to the hash file, it is verified before the hash file is read, and dano refuses to read a hash file whose signature does not verify.  \
+                Once a hash file is signed, dano refuses to write it without this option, as its signature would no longer verify, \
+                and refuses to read it when its signature is missing, unless given --remove-signature.  \
+                With --record-checked, the time of each file's last check is not recorded in a signed hash file, unless it is signed again.")
+                .long("sign")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .default_missing_value("minisign")
+                .possible_values(["minisign", "gpg"])
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(97))
+        .arg(
+            Arg::new("SIGN_KEY")
+                .help("the key with which to sign the hash file: for minisign, the path of the secret key, by default, ~/.minisign/minisign.key, \
+                and, for gpg, the user ID of the key, by default, the default key of the keyring.")
+                .long("sign-key")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("KEY")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .requires("SIGN")
+                .display_order(98))
+        .arg(
+            Arg::new("VERIFY_KEY")
+                .help("the path of the minisign public key with which to verify a minisign signature of the hash file, by default, ./minisign.pub.  \
+                A gpg signature is verified with the keys of the keyring.")
+                .long("verify-key")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("PATH")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(99))
+        .arg(
+            Arg::new("REQUIRE_SIGNATURE")
+                .help("refuse to read a hash file which is not signed, such that removing the signature of a hash file which has been tampered with does not suffice.")
+                .long("require-signature")
+                .display_order(100))
+        .arg(
+            Arg::new("REMOVE_SIGNATURE")
+                .help("write a signed hash file without its signature, and remove the signature, which would no longer verify, \
+                or read a hash file whose signature is missing.  Either way, dano no longer expects the hash file to be signed.")
+                .long("remove-signature")
+                .conflicts_with_all(&["SIGN", "REQUIRE_SIGNATURE"])
+                .display_order(121))
+        .arg(
+            Arg::new("WAIT")
+                .help("when another dano holds the lock on the hash file, wait for it to be released, for at most the given duration, such as: --wait=30s, if given, \
+                else indefinitely, which is the default.  Each run holds an advisory lock on a lock file next to the hash file, such as dano_hashes.txt.lock, \
+                exclusively, when it may write the hash file, or shared, when it only reads the hash file, so that two runs cannot interleave their writes, \
+                or overwrite one another's records.  A run in WATCH mode holds the lock only for each batch of files, and a run in DAEMON mode only while it writes the results of each batch.")
+                .long("wait")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .value_name("DURATION")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(101))
+        .arg(
+            Arg::new("NO_WAIT")
+                .help("when another dano holds the lock on the hash file, quit with an error, rather than wait for the lock to be released.")
+                .long("no-wait")
+                .conflicts_with("WAIT")
+                .display_order(102))
+        .arg(
+            Arg::new("BACKUPS")
+                .help("the number of backups of the hash file to keep.  Each time the hash file is rewritten, as by --rewrite, --overwrite or --compact, \
+                the hash file, as it was, is first copied, with its modification time, to a numbered backup next to it, such as dano_hashes.txt.bak.4, \
+                where the highest number is the latest backup, and only the latest backups are kept, so that a bad rewrite may be rolled back.  \
+                The default is 3, and 0 disables backups.  May also be set as 'backups' in the config file.")
+                .long("backups")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("NUM")
+                .value_parser(clap::value_parser!(usize))
+                .display_order(103))
+        .arg(
+            Arg::new("UNDO")
+                .help("undo the last write operation, such as an accidental --overwrite, by restoring the hash file, and the extended attributes, \
+                to their prior state.  Each run which changes the records of the hash file, or of the extended attributes of its input files, \
+                records those changes, with the prior records, in a file next to the hash file, such as dano_hashes.txt.undo, \
+                replacing the record of the last such run.  A record, or an extended attribute, which has changed since the write operation is left as it is, \
+                with a warning.  An undo is itself recorded, so undoing twice redoes the write operation.  \
+                Changes to the time of each file's last check are not recorded, and are not undone.")
+                .long("undo")
+                .conflicts_with("INPUT_FILES")
+                .display_order(104))
+        .arg(
+            Arg::new("HISTORY")
+                .help("print the history of the records of the given path, or, if no path is given, of every path, from the history log.  \
+                Each run which changes the records of the hash file appends an entry to the history log, next to the hash file, such as dano_hashes.txt.history, \
+                for each record which was added, overwritten, removed, pruned, compacted, cleaned, repaired, undone, accepted or migrated, \
+                with the old and the new record, the time and the user, so that the provenance of each recorded hash may be traced.  \
+                The history log is only ever appended to.")
+                .long("history")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .value_name("PATH")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .conflicts_with("INPUT_FILES")
+                .display_order(105))
+        .arg(
+            Arg::new("STABLE_OUTPUT")
+                .help("rewrite the hash file such that the same records always produce the same hash file, byte for byte, \
+                so that, stored in git, the hash file changes only where a record changes, whatever the order in which records were written.  \
+                Each time the hash file is rewritten, records are sorted by path, each header line is written only once, and each record is written in the same form, \
+                so the same files, hashed on another machine, or from another directory, produce the same hash file.  \
+                The directory dano was invoked from is not recorded, so recorded relative paths are relative to the directory of the hash file.  \
+                Neither the time each record was written, or last checked, nor the modify time of each file, is recorded, \
+                so STATUS and --quick treat every file as possibly changed, and --record-checked has no lasting effect.  \
+                May also be set as 'stable_output' in the config file.")
+                .long("stable-output")
+                .display_order(106))
+        .arg(
+            Arg::new("FSCK")
+                .help("check every line of the hash file, and report, by line number, each line which could not be parsed, which is missing fields, \
+                which was written by an unknown, or a newer, version of dano, or which is a record of the same path, in the same form, \
+                as a more recently written record, whether a duplicate, or with a conflicting hash, as well as a hash file which does not match its footer.  \
+                Input files are not hashed.  Use --repair to also repair the hash file.")
+                .long("fsck")
+                .conflicts_with("INPUT_FILES")
+                .display_order(107))
+        .arg(
+            Arg::new("REPAIR")
+                .help("in FSCK mode, repair the hash file, by dropping each line which cannot be read, and each duplicate, or conflicting, record, \
+                keeping only the most recently written record of each path, in each form, and by rewriting each record written by an earlier version of dano, \
+                or written otherwise than dano would write it.  Lines written by a newer version of dano are kept as they are.")
+                .long("repair")
+                .requires("FSCK")
+                .conflicts_with("READ_ONLY")
+                .display_order(108))
+        .arg(
+            Arg::new("FORMAT")
+                .help("the format in which to write the hash file: 'text', one JSON record per line, or 'binary', a compact binary encoding of the same records, \
+                which is smaller, and much faster to read, for a library of many files.  A hash file is always read in whichever format it was written.  \
+                By default, a new hash file is written in the binary format if its name ends in '.bin', such as dano_hashes.bin, and otherwise in the text format, \
+                and an existing hash file is rewritten in its own format.  An existing hash file is converted to the format specified when it is next rewritten, \
+                as by --compact.  A binary hash file may only be read by a version of dano which writes records in the same format version.")
+                .long("format")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("FORMAT")
+                .possible_values(["text", "binary"])
+                .display_order(109))
+        .arg(
+            Arg::new("REMOTE")
+                .help("in WRITE and TEST modes, accept URLs as input files, such as 'https://', 'smb://' or 'sftp://' URLs, which are read by ffmpeg, over any protocol it supports, \
+                rather than from the local filesystem.  A URL is not checked to exist, and is recorded by the URL itself, unless mapped to a local path, with --remote-map.  \
+                Remote media has neither a modify time, nor a size, and so its records may only be written to the hash file, and it is never hashed natively, nor as a whole file.  \
+                A URL whose last path component has no known media extension, as when followed by a query string, requires --disable-filter.")
+                .long("remote")
+                .display_order(110))
+        .arg(
+            Arg::new("REMOTE_MAP")
+                .help("in REMOTE mode, record each URL which begins with the URL prefix by the local path it mirrors, beneath the local directory, \
+                and read each recorded path beneath the local directory from the URL it mirrors, such as '--remote-map=sftp://nas/media=/srv/media', \
+                so a mirror may be tested against records of the local files, and the local files against records of the mirror.")
+                .long("remote-map")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("URL_PREFIX=DIR")
+                .requires("REMOTE")
+                .display_order(111))
+        .arg(
+            Arg::new("RECORD_CHECKED")
+                .help("in TEST and TEST_FLAC modes, record the time at which each file which matched its record was checked, in each store which holds the record, \
+                so that --status may report how stale the last check is, even without the dano state file.  \
+                A test otherwise never rewrites the hash file, nor any extended attribute, or sidecar, merely to record the time of the check.")
+                .long("record-checked")
+                .conflicts_with("READ_ONLY")
+                .display_order(112))
         .arg(
             Arg::new("NUM_THREADS")
                 .help("requested number of threads to use for file processing.  Default is the number of logical cores.")
@@ -144,9 +735,10 @@ fn parse_args() -> ArgMatches {
         .arg(
             Arg::new("OVERWRITE_OLD")
                 .help("in TEST mode, when not writing to an extended attribute, if a file's hash matches a recorded hash, but that file now has a different file name, \
-                overwrite file info with the most current to the hash file.")
+                overwrite file info with the most current to the hash file.  In TREE_HASH mode, replace each recorded tree hash which has changed, \
+                and remove each recorded tree hash with no records left beneath it.")
                 .long("overwrite")
-                .requires("TEST")
+                .requires("OVERWRITABLE")
                 .conflicts_with_all(&["PRINT", "DUMP", "DUPLICATES", "WRITE"])
                 .display_order(14),
         )
@@ -166,24 +758,30 @@ fn parse_args() -> ArgMatches {
             Arg::new("XATTR")
                 .help("try to write (dano will always try to read) hash to any input file's extended attributes.  \
                 Can also be enabled by setting environment variable DANO_XATTR_WRITES to any value (such as: export DANO_XATTR_WRITES=enabled).  \
-                When XATTR is enabled, if a write is requested, dano will always overwrite extended attributes previously written.")
+                When XATTR is enabled, if a write is requested, dano will always overwrite extended attributes previously written.  \
+                On Windows, the hash is written to an NTFS alternate data stream, such as: file.mkv:dano.checksum.")
                 .short('x')
                 .long("xattr")
                 .display_order(17),
         )
         .arg(
             Arg::new("HASH_ALGO")
-                .help("specify the algorithm to use for hashing.  Default is 'murmur3'.")
+                .help("specify the algorithm to use for hashing.  Default is 'murmur3'.  \
+                'blake3' is hashed by dano itself, on many threads at once, as ffmpeg does not implement it, and is both fast and cryptographically strong.")
                 .long("hash-algo")
                 .takes_value(true)
                 .min_values(1)
                 .require_equals(true)
-                .possible_values(["murmur3", "md5", "crc32", "adler32", "sha1", "sha160", "sha256", "sha384", "sha512"])
+                .possible_values(["murmur3", "md5", "crc32", "adler32", "sha1", "sha160", "sha256", "sha384", "sha512", "blake3"])
                 .value_parser(clap::builder::ValueParser::os_string())
                 .display_order(18))
         .arg(
             Arg::new("DECODE")
-                .help("decode internal bitstream before hashing.  This option makes testing and writes much slower, but this option is potentially useful for lossless formats.")
+                .help("decode internal bitstream before hashing.  This option makes testing and writes much slower, but this option is potentially useful for lossless formats.  \
+                When decoding video, dano records and requests the pixel format and frame rate of the first video stream, \
+                so the hash does not depend on the format ffmpeg would otherwise choose.  \
+                Likewise, when decoding lossless audio, dano records and requests the bit depth of the first audio stream, 16, 24 or 32 bits, \
+                so samples are never truncated to 16 bits.")
                 .long("decode")
                 .conflicts_with_all(&["PRINT", "DUMP", "DUPLICATES"])
                 .display_order(19))
@@ -198,13 +796,17 @@ fn parse_args() -> ArgMatches {
         .arg(
             Arg::new("ONLY")
                 .help("hash the an input file container's first audio or video stream only, if available.  \
-                dano will fall back to default behavior, if no stream is available.")
+                dano verifies the stream is available with ffprobe.  If no such stream is available, dano will warn, fall back to hashing all streams, \
+                and mark the record as a fallback, so the record represents the streams actually hashed.  \
+                An explicit ffmpeg stream specifier may also be given, such as: --only=a:1, for the second audio stream, or --only=0:v:0, \
+                so a file with many audio streams, such as many language tracks, may be pinned to a single stream.  \
+                An explicit stream is never a fallback: if it is not available, hashing the file fails.")
                 .long("only")
                 .takes_value(true)
                 .require_equals(true)
-                .possible_values(["audio", "video"])
+                .value_name("audio|video|SPECIFIER")
                 .value_parser(clap::builder::ValueParser::os_string())
-                .requires("WRITE")
+                .requires("WRITE_MODE")
                 .display_order(21))
         .arg(
             Arg::new("DRY_RUN")
@@ -212,38 +814,1134 @@ fn parse_args() -> ArgMatches {
             .long("dry-run")
             .conflicts_with_all(&["PRINT", "DUPLICATES"])
             .display_order(22))
+        .arg(
+            Arg::new("MEDIA_INFO")
+                .help("when hashing, also record a compact summary of technical metadata (resolution, bitrate, channels, sample rate), as reported by ffprobe.")
+                .long("media-info")
+                .conflicts_with_all(&["PRINT", "DUMP", "DUPLICATES", "CLEAN"])
+                .display_order(23))
+        .arg(
+            Arg::new("LONG")
+                .help("in PRINT mode, also print any recorded technical metadata.")
+                .long("long")
+                .requires("PRINT")
+                .display_order(24))
+        .arg(
+            Arg::new("QUERY")
+                .help("in PRINT mode, only print recorded file information whose technical metadata matches the query.  \
+                A query is a comma separated list of comparisons, all of which must match, such as: --query=\"height>=1080,channels=2\".  \
+                Valid fields are: width, height, bit_rate, channels, and sample_rate.  Valid operators are: =, !=, <, <=, >, and >=.")
+                .long("query")
+                .takes_value(true)
+                .require_equals(true)
+                .requires("PRINT")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(25))
+        .arg(
+            Arg::new("IMAGE_PIXELS")
+                .help("for image files, hash only the decoded pixel data of the image, so that edits to EXIF or XMP metadata, including orientation flags, \
+                do not change the image's hash.  Image file extensions are accepted by the extension filter when this option is enabled.")
+                .long("image-pixels")
+                .conflicts_with_all(&["PRINT", "DUMP", "DUPLICATES", "CLEAN"])
+                .display_order(26))
+        .arg(
+            Arg::new("SHOW_COMMANDS")
+                .help("print the full ffmpeg command, as it may be entered at a shell, which dano executes for each file.  \
+                Useful in combination with DRY_RUN, when debugging why a file hashes differently than expected.")
+                .long("show-commands")
+                .display_order(27))
+        .arg(
+            Arg::new("PROBE_UNKNOWN")
+                .help("files without an extension are skipped by default.  When this option is enabled, dano will instead run ffprobe on such files, \
+                and accept any file which ffprobe recognizes as containing an audio or video stream.")
+                .long("probe-unknown")
+                .display_order(28))
+        .arg(
+            Arg::new("INCLUDE_HIDDEN")
+                .help("include hidden files (so-called dot files), which are otherwise skipped, even when the DISABLE_FILTER flag is enabled.  \
+                Hidden files are still subject to the extension filter, unless the DISABLE_FILTER flag is also enabled.")
+                .long("include-hidden")
+                .display_order(29))
+        .arg(
+            Arg::new("MATCH")
+                .help("in TEST mode, select recorded file information to test, from the hash file, whose paths match the given glob pattern, \
+                such as: --match='**/Artist/**'.  When specified, INPUT_FILES are not required.")
+                .long("match")
+                .takes_value(true)
+                .require_equals(true)
+                .requires("TEST")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(30))
+        .arg(
+            Arg::new("XATTR_IF_MISSING")
+                .help("in WRITE mode, write extended attributes only to input files which lack a dano extended attribute.  \
+                If the hash file contains a record for such a file, that record is written to the extended attribute, without hashing the file again.  \
+                Input files which already have a dano extended attribute are left untouched.  Implies XATTR.")
+                .long("xattr-if-missing")
+                .requires("WRITE_MODE")
+                .conflicts_with_all(&["REWRITE_ALL", "IMPORT", "IMPORT_FLAC", "IMPORT_WAVPACK"])
+                .display_order(31))
+        .arg(
+            Arg::new("RESTORE_XATTRS")
+                .help("in TEST mode, when a file verifies OK, but has no dano extended attribute, or its extended attribute does not match the verified record, \
+                rewrite the extended attribute from the verified record.  This keeps the hash file and extended attributes convergent as part of routine verification.")
+                .long("restore-xattrs")
+                .requires("TEST")
+                .display_order(32))
+        .arg(
+            Arg::new("STABLE_ORDER")
+                .help("print results in input path order, instead of the order in which threads complete.  \
+                Results are still emitted as soon as all prior paths are complete, so this makes logs and reports reproducible \
+                between runs, at the cost of holding back results which complete early.")
+                .long("stable-order")
+                .display_order(33))
+        .arg(
+            Arg::new("BUDGET")
+                .help("in TEST mode, verify only as many recorded entries as can be verified within the given time budget, such as: --budget=2h, or --budget=1h30m.  \
+                The stalest entries, those never verified, or verified longest ago, are selected first.  When each entry was last selected, and its outcome, \
+                is recorded, per hash file, in the dano state file (at $XDG_STATE_HOME/dano/state.json), so that repeated runs, for instance nightly from cron, \
+                will eventually verify every entry.  When specified, INPUT_FILES are not required.")
+                .long("budget")
+                .takes_value(true)
+                .require_equals(true)
+                .requires("TEST")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(34))
+        .arg(
+            Arg::new("CACHE_UNSUPPORTED")
+                .help("in WRITE mode, remember files which ffmpeg could not hash, because their codec or container is unsupported, in the dano state file \
+                (at $XDG_STATE_HOME/dano/state.json), and skip such files on later runs, until the file's size or modify time changes.")
+                .long("cache-unsupported")
+                .requires("WRITE_MODE")
+                .display_order(35))
+        .arg(
+            Arg::new("RETRY_UNSUPPORTED")
+                .help("in WRITE mode, retry files which were previously remembered as unsupported.  Implies CACHE_UNSUPPORTED.")
+                .long("retry-unsupported")
+                .requires("WRITE_MODE")
+                .display_order(36))
+        .arg(
+            Arg::new("DROP_MISSING")
+                .help("in COMPACT mode, also remove records for paths which no longer exist.  \
+                Relative paths are resolved against the directory from which dano was invoked when the hash file was first written.")
+                .long("drop-missing")
+                .requires("COMPACT")
+                .display_order(37))
+        .arg(
+            Arg::new("YES")
+                .help("in INIT mode, proceed without asking for confirmation.  Required when stdin is not a terminal.")
+                .long("yes")
+                .requires("INIT")
+                .display_order(38))
+        .arg(
+            Arg::new("MAX_RATE")
+                .help("in INIT mode, limit the average rate at which files are hashed to the given number of megabytes per second, \
+                such as: --max-rate=100, so that an initial run does not monopolize a shared disk or NAS.")
+                .long("max-rate")
+                .takes_value(true)
+                .require_equals(true)
+                .requires("INIT")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .display_order(39))
+        .arg(
+            Arg::new("REFERENCE")
+                .help("in TEST mode, verify files against the given reference hash file, such as one shipped by a publisher, or generated on a source NAS, \
+                instead of the local hash file and extended attributes.  The reference is only ever read, never written.  When a file disagrees with the reference, \
+                the local record is consulted, to indicate which side disagrees.  When INPUT_FILES are specified, only those entries of the reference are verified.")
+                .long("reference")
+                .takes_value(true)
+                .require_equals(true)
+                .requires("TEST")
+                .conflicts_with_all(&["WRITE_NEW", "OVERWRITE_OLD", "RESTORE_XATTRS", "BUDGET"])
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(40))
+        .arg(
+            Arg::new("REPORT_HTML")
+                .help("after a TEST or WRITE run, write a self-contained HTML report to the given directory, as index.html.  \
+                The report contains sortable tables of every entry, of failures and of duplicates, as well as summary statistics, \
+                and requires no network access to view, so it may be shared as verification evidence.")
+                .long("report-html")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("DIR")
+                .conflicts_with_all(&["PRINT", "DUMP", "DUPLICATES", "CLEAN", "COMPACT", "VERIFY_COPY", "INIT", "REFERENCE", "REWRITE_ALL", "IMPORT", "IMPORT_FLAC", "IMPORT_WAVPACK"])
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(41))
+        .arg(
+            Arg::new("TMP_DIR")
+                .help("place the temporary copy of the hash file, written whenever the hash file is rewritten, in the given directory, \
+                for instance, on another filesystem, when the filesystem of the hash file has little free space.  \
+                By default, the temporary copy is written next to the hash file.")
+                .long("tmp-dir")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("DIR")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(42))
+        .arg(
+            Arg::new("READ_ONLY")
+                .help("guarantee dano will not modify anything: no hash file, extended attribute, temporary file or state file is ever written, \
+                even if other options, such as --write-new, --overwrite or --restore-xattrs, are specified, as such options are disabled.  \
+                Modes which must write, such as WRITE, DUMP, CLEAN and COMPACT, are not permitted.  Implies --dry-run.")
+                .long("read-only")
+                .conflicts_with_all(&["WRITE", "REWRITE_ALL", "IMPORT", "IMPORT_FLAC", "IMPORT_WAVPACK", "IMPORT_CHECKSUMS", "WATCH", "INIT", "DUMP", "CLEAN", "COMPACT", "PRUNE", "ACCEPT", "MIGRATE_ALGO", "TEST_FLAC", "TREE_HASH", "UNDO"])
+                .display_order(43))
+        .arg(
+            Arg::new("ALLOW_WEAK_HASH")
+                .help("write records with a hash algorithm weaker than the minimum required by policy.  \
+                A minimum hash algorithm, such as 'sha256', may be set as 'min_hash_algo' in the system wide config file, /etc/dano/config.toml, \
+                or in the per user config file.  A user may raise, but not lower, the system wide minimum.")
+                .long("allow-weak-hash")
+                .display_order(44))
+        .arg(
+            Arg::new("SCHEMA_REPORT")
+                .help("in PRINT mode, instead of printing recorded file information, count the records of each format version, \
+                from the hash file and from the extended attributes of any input files, and list those records still stored in a legacy format version.  \
+                When INPUT_FILES are specified, only their records are counted.")
+                .long("schema-report")
+                .requires("PRINT")
+                .conflicts_with_all(&["LONG", "QUERY"])
+                .display_order(45))
+        .arg(
+            Arg::new("PORCELAIN")
+                .help("print machine readable results, one line per path, to stdout, and all other messages to stderr, in every mode, \
+                so that scripts may rely upon stdout.  Results are formatted as newline delimited JSON, by default, or as tab separated values, \
+                with the fields: status, path, hash algorithm and hash value.")
+                .long("porcelain")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .default_missing_value("ndjson")
+                .possible_values(["ndjson", "tsv"])
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(46))
+        .arg(
+            Arg::new("STATUS")
+                .help("quickly summarize the state of the given directory: how many media files beneath it are recorded, how many of those have been modified \
+                since being recorded, how many are new, how many recorded files are missing, and when the hash file was last verified.  Nothing is hashed, \
+                only the hash file, extended attributes and file metadata are read, so this is fast enough for a shell prompt or a pre-backup check.  \
+                Exits with a non-zero status when any file is new, modified or missing.")
+                .long("status")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("DIR")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .conflicts_with("INPUT_FILES")
+                .display_order(47))
+        .arg(
+            Arg::new("RECEIPT")
+                .help("after a TEST run, write a receipt of the verification to the given file, so an archive may later prove that a fixity check was performed, \
+                at a given time, with given results.  The receipt records the time, the hash file and the SHA256 checksum of the very bytes read, or no catalog, when only xattrs were verified, and a summary of the results, \
+                and is signed with gpg, as a detached, armored signature, written next to the receipt, with the extension \".asc\".")
+                .long("receipt")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("FILE")
+                .requires("TEST")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(48))
+        .arg(
+            Arg::new("RECEIPT_KEY")
+                .help("sign the receipt with the given gpg key, instead of the default gpg key.")
+                .long("receipt-key")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("KEY_ID")
+                .requires("RECEIPT")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(49))
+        .arg(
+            Arg::new("RECEIPT_TSA")
+                .help("also request an RFC 3161 timestamp of the receipt from the timestamp authority at the given URL, \
+                which is written next to the receipt, with the extension \".tsr\".  Requires the 'openssl' and 'curl' commands.")
+                .long("receipt-tsa")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("URL")
+                .requires("RECEIPT")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(50))
+        .arg(
+            Arg::new("ALLOW_EMPTY_STREAMS")
+                .help("record suspicious hashes, which are otherwise refused when writing.  A hash is suspicious when its value is zero, \
+                when it is the hash of no data at all, as when the selected streams are empty, or when the file has a duration of zero.  \
+                Suspicious hashes are reported, whether or not they are recorded.")
+                .long("allow-empty-streams")
+                .display_order(51))
+        .arg(
+            Arg::new("BOTH_STORES")
+                .help("write each record to both the input file's extended attributes and the hash file, in a single run.  \
+                Otherwise, when XATTR is enabled, records are written only to extended attributes.  Implies XATTR.")
+                .long("both-stores")
+                .conflicts_with_all(&["DUMP", "PRINT", "DUPLICATES", "CLEAN", "COMPACT", "VERIFY_COPY", "STATUS"])
+                .display_order(52))
+        .arg(
+            Arg::new("UPGRADE_XATTRS")
+                .help("upgrade, in place, any dano extended attribute read which is stored in a legacy format version.  \
+                Legacy and unparseable extended attributes are always reported, and counted in a summary, but are only upgraded when this option is specified.")
+                .long("upgrade-xattrs")
+                .conflicts_with_all(&["READ_ONLY", "CLEAN"])
+                .display_order(53))
+        .arg(
+            Arg::new("ELEVATE_WITH")
+                .help("hash input files which dano does not have permission to read by running ffmpeg with the given helper command, such as \"sudo -n\".  \
+                Otherwise, such input files are skipped, reported, and dano exits with status 4, when no other error or inconsistency was found.")
+                .long("elevate-with")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("CMD")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(54))
+        .arg(
+            Arg::new("NO_NATIVE")
+                .help("always hash with ffmpeg.  By default, the sample data of uncompressed audio files, WAV and AIFF, is hashed by dano itself, \
+                without ffmpeg, when the streams are copied, not decoded, and the hash algorithm is one which dano implements (crc32, murmur3, md5, sha1, sha256, sha512).  \
+                Such records are marked, so they will always be verified the same way.")
+                .long("no-native")
+                .display_order(55))
+        .arg(
+            Arg::new("NO_XATTR")
+                .help("do not write hashes to extended attributes, as set by 'xattr' in the config file, or by the DANO_XATTR environment variable.  The last of --xattr and --no-xattr given takes precedence.")
+                .long("no-xattr")
+                .overrides_with("XATTR")
+                .display_order(113))
+        .arg(
+            Arg::new("NO_DISABLE_FILTER")
+                .help("do not disable the default filtering of file extensions, as set by 'disable_filter' in the config file.  The last of --disable-filter and --no-disable-filter given takes precedence.")
+                .long("no-disable-filter")
+                .overrides_with("DISABLE_FILTER")
+                .display_order(114))
+        .arg(
+            Arg::new("NO_CANONICAL_PATHS")
+                .help("do not use canonical paths, as set by 'canonical_paths' in the config file.  The last of --canonical-paths and --no-canonical-paths given takes precedence.")
+                .long("no-canonical-paths")
+                .overrides_with("CANONICAL_PATHS")
+                .display_order(115))
+        .arg(
+            Arg::new("NO_DECODE")
+                .help("do not decode internal bitstreams before hashing, as set by 'decode' in the config file.  The last of --decode and --no-decode given takes precedence.")
+                .long("no-decode")
+                .overrides_with("DECODE")
+                .display_order(116))
+        .arg(
+            Arg::new("NO_PROBE_FILTER")
+                .help("do not filter input files with ffprobe, as set by 'probe_filter' in the config file.  The last of --probe-filter and --no-probe-filter given takes precedence.")
+                .long("no-probe-filter")
+                .overrides_with("PROBE_FILTER")
+                .display_order(117))
+        .arg(
+            Arg::new("NO_PROBE_UNKNOWN")
+                .help("do not probe files without an extension, as set by 'probe_unknown' in the config file.  The last of --probe-unknown and --no-probe-unknown given takes precedence.")
+                .long("no-probe-unknown")
+                .overrides_with("PROBE_UNKNOWN")
+                .display_order(118))
+        .arg(
+            Arg::new("NO_INCLUDE_HIDDEN")
+                .help("do not include hidden files, as set by 'include_hidden' in the config file.  The last of --include-hidden and --no-include-hidden given takes precedence.")
+                .long("no-include-hidden")
+                .overrides_with("INCLUDE_HIDDEN")
+                .display_order(119))
+        .arg(
+            Arg::new("NO_STABLE_OUTPUT")
+                .help("do not write stable output, as set by 'stable_output' in the config file.  The last of --stable-output and --no-stable-output given takes precedence.")
+                .long("no-stable-output")
+                .overrides_with("STABLE_OUTPUT")
+                .display_order(120))
+        .arg(
+            Arg::new("ACCEPT")
+                .help("accept the current contents of the given input files, after a deliberate edit.  Each input file is hashed again, \
+                in each form in which it was recorded, and its records, in extended attributes and/or the hash file, are replaced.  \
+                Unlike TEST with OVERWRITE_OLD, which overwrites every mismatched record, only the given input files are touched, \
+                and each old record is appended to the history log, next to the hash file, with the new record, the time and the user.")
+                .long("accept")
+                .requires("INPUT_FILES")
+                .display_order(56))
+        .arg(
+            Arg::new("PROGRESS")
+                .help("while hashing, show a status line on stderr, with the number of files completed, the file most recently started, \
+                the throughput in MB/s, and an estimate of the time remaining.  The status line is disabled when stdout is not a terminal, \
+                such as when output is piped to another program, or when SILENT is specified.")
+                .long("progress")
+                .display_order(57))
+        .arg(
+            Arg::new("ONE_FILE_SYSTEM")
+                .help("when walking a directory, such as for INIT, VERIFY_COPY or STATUS, do not descend into directories on other file systems, \
+                such as nested mounts on a NAS.  Symlinks are always followed, but a directory is only ever entered once, \
+                so a symlink loop is skipped, and reported, rather than followed forever.")
+                .long("one-file-system")
+                .display_order(58))
+        .arg(
+            Arg::new("STATUS_FILE")
+                .help("while a TEST runs, rewrite the given file every few seconds with a small JSON summary of the run so far: \
+                files done and total, the file most recently tested, and any failures, so dashboards and scripts may follow a long scrub.  \
+                Each rewrite is atomic.  When the run is complete, the file is written a final time, marked finished.  \
+                This is independent of any final report.")
+                .long("status-file")
+                .takes_value(true)
+                .require_equals(true)
+                .value_name("FILE")
+                .requires("TEST")
+                .value_parser(clap::builder::ValueParser::os_string())
+                .display_order(59))
+        .arg(
+            Arg::new("FRAME_HASH")
+                .help("hash each frame, with ffmpeg's framehash muxer, rather than the stream as a whole.  The record holds a digest of every frame hash, \
+                which is verified like any other hash, and a digest of each range of 1000 frames, per stream, so, when a TEST finds a mismatch, \
+                the corruption is localized to a range of frames.  Frame hashing is slower, and requires a hash algorithm dano implements: \
+                crc32, murmur3, md5, sha1, sha256, or sha512.")
+                .long("frame-hash")
+                .conflicts_with("STREAM_HASH")
+                .display_order(60))
+        .arg(
+            Arg::new("STREAM_HASH")
+                .help("hash each stream, with ffmpeg's streamhash muxer, rather than the container as a whole.  The record holds a digest of every stream hash, \
+                which is verified like any other hash, and the hash of each stream, so, when a TEST finds a mismatch, dano reports which streams, \
+                audio, video or subtitle, have changed.  Stream hashing requires a hash algorithm dano implements: crc32, murmur3, md5, sha1, sha256, or sha512.")
+                .long("stream-hash")
+                .display_order(62))
+        .arg(
+            Arg::new("CROSS_ALGO")
+                .help("in DUPLICATES mode, find duplicate content even when it was recorded with different hash algorithms.  \
+                Where the same form of content, such as decoded audio, was recorded with more than one algorithm, one file of each group of identical records \
+                is hashed again, in its recorded form, with SHA256, and groups are compared by that hash.  Such files must still exist.")
+                .long("cross-algo")
+                .requires("DUPLICATES")
+                .display_order(61))
+        // the modes whose records OVERWRITE_OLD may replace
+        .group(ArgGroup::new("OVERWRITABLE").args(&["TEST", "TREE_HASH"]))
+        // the modes which write new records, to which the options of WRITE mode also apply
+        .group(
+            ArgGroup::new("WRITE_MODE")
+                .args(&["WRITE", "IMPORT", "IMPORT_FLAC", "IMPORT_WAVPACK", "IMPORT_CHECKSUMS", "WATCH"])
+                .multiple(true),
+        )
+        // only one execution mode may be specified
+        .group(ArgGroup::new("MODE").args(&[
+            "WRITE",
+            "TEST",
+            "PRINT",
+            "DUMP",
+            "DUPLICATES",
+            "CLEAN",
+            "COMPACT",
+            "INIT",
+            "VERIFY_COPY",
+            "IMPORT",
+            "IMPORT_FLAC",
+            "IMPORT_WAVPACK",
+            "IMPORT_CHECKSUMS",
+            "EXPORT",
+            "WATCH",
+            "COVERAGE",
+            "PRUNE",
+            "DIFF",
+            "MIGRATE_ALGO",
+            "TEST_FLAC",
+            "ACCURATERIP",
+            "TREE_HASH",
+            "UNDO",
+            "HISTORY",
+            "FSCK",
+            "STATUS",
+            "ACCEPT",
+        ]))
         .get_matches()
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct WriteModeConfig {
-    pub opt_rewrite: bool,
-    pub opt_import_flac: bool,
+fn is_hash_file_side_file(path: &Path, hash_file: &Path) -> bool {
+    let is_side_extension = path.extension().is_some_and(|ext| {
+        [LOCK_EXTENSION, UNDO_EXTENSION, HISTORY_EXTENSION]
+            .iter()
+            .any(|side_ext| ext == *side_ext)
+    });
+
+    is_side_extension
+        && path.file_stem().is_some_and(|stem| {
+            stem == DANO_DEFAULT_HASH_FILE_NAME || Some(stem) == hash_file.file_name()
+        })
+}
+
+pub fn is_permission_denied(path: &Path) -> bool {
+    matches!(std::fs::File::open(path), Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied)
+}
+
+// directories which traversal declined to enter
+#[derive(Debug, Clone, Copy, Default)]
+struct SkippedDirs {
+    symlink_loop: usize,
+    other_file_system: usize,
+}
+
+// the include and exclude glob patterns, which scope a run to some paths
+#[derive(Debug, Clone, Default)]
+pub struct PathGlobs {
+    includes: Vec<Pattern>,
+    excludes: Vec<Pattern>,
+}
+
+impl PathGlobs {
+    fn new(matches: &ArgMatches) -> DanoResult<Self> {
+        let parse = |name: &str| -> DanoResult<Vec<Pattern>> {
+            matches
+                .values_of_os(name)
+                .into_iter()
+                .flatten()
+                .map(|glob| Pattern::new(&glob.to_string_lossy()).map_err(|err| err.into()))
+                .collect()
+        };
+
+        Ok(Self {
+            includes: parse("INCLUDE")?,
+            excludes: parse("EXCLUDE")?,
+        })
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        self.excludes
+            .iter()
+            .any(|pattern| pattern.matches_path(path))
+    }
+
+    fn is_selected(&self, path: &Path) -> bool {
+        if self.is_excluded(path) {
+            return false;
+        }
+
+        self.includes.is_empty()
+            || self
+                .includes
+                .iter()
+                .any(|pattern| pattern.matches_path(path))
+    }
+}
+
+// how a directory is walked
+#[derive(Debug, Clone, Default)]
+struct WalkOpts {
+    opt_one_file_system: bool,
+    opt_max_depth: Option<usize>,
+    path_globs: PathGlobs,
+}
+
+// every file beneath the directory.  symlinks are followed, but each directory is entered only once,
+// by its canonical path, so a symlink back to a parent directory cannot loop
+fn walk_dir(dir: &Path, walk_opts: &WalkOpts) -> DanoResult<(Vec<PathBuf>, SkippedDirs)> {
+    if !dir.is_dir() {
+        let msg = format!("Path is not a directory: {:?}", dir);
+        return Err(DanoError::new(&msg).into());
+    }
+
+    let root_device = get_device_id(dir);
+
+    let mut files = Vec::new();
+    let mut skipped = SkippedDirs::default();
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+    // the depth of a directory is the depth of the files within it, the given directory's files are at depth 1
+    let mut pending = vec![(dir.to_owned(), 1usize)];
+
+    while let Some((next_dir, depth)) = pending.pop() {
+        let canonical = next_dir.canonicalize().unwrap_or_else(|_| next_dir.clone());
+
+        if !visited.insert(canonical) {
+            eprintln!(
+                "WARN: Skipping directory already visited, a symlink loop or alias: {:?}",
+                next_dir
+            );
+            skipped.symlink_loop += 1;
+            continue;
+        }
+
+        if walk_opts.opt_one_file_system && get_device_id(&next_dir) != root_device {
+            eprintln!(
+                "WARN: Skipping directory on another file system: {:?}",
+                next_dir
+            );
+            skipped.other_file_system += 1;
+            continue;
+        }
+
+        let entries = match std::fs::read_dir(&next_dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                eprintln!("WARN: Could not read directory {:?}: {}", next_dir, err);
+                continue;
+            }
+        };
+
+        entries.flatten().for_each(|entry| {
+            let path = entry.path();
+
+            // a symlink is resolved to what it points to, a broken symlink is ignored
+            let opt_file_type = match entry.file_type() {
+                Ok(file_type) if file_type.is_symlink() => {
+                    path.metadata().ok().map(|metadata| metadata.file_type())
+                }
+                res => res.ok(),
+            };
+
+            match opt_file_type {
+                // an excluded directory is never entered
+                Some(file_type)
+                    if file_type.is_dir() && walk_opts.path_globs.is_excluded(&path) => {}
+                // a directory is only entered when its files are within the maximum depth
+                Some(file_type)
+                    if file_type.is_dir()
+                        && walk_opts
+                            .opt_max_depth
+                            .is_none_or(|max_depth| depth < max_depth) =>
+                {
+                    pending.push((path, depth + 1))
+                }
+                Some(file_type) if file_type.is_file() => files.push(path),
+                _ => (),
+            }
+        });
+    }
+
+    files.sort_unstable();
+
+    Ok((files, skipped))
+}
+
+#[cfg(unix)]
+fn get_device_id(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    path.metadata().ok().map(|metadata| metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn get_device_id(_path: &Path) -> Option<u64> {
+    None
+}
+
+// a flag given on the command line, as --flag or --no-flag, takes precedence over the config file
+fn flag_or_config(matches: &ArgMatches, name: &str, opt_config_value: Option<bool>) -> bool {
+    if matches.is_present(name) {
+        return true;
+    }
+
+    if matches.is_present(format!("NO_{}", name)) {
+        return false;
+    }
+
+    opt_config_value == Some(true)
+}
+
+// parse a duration such as "2h", "1h30m", "90s", or a number of seconds, such as "5400"
+fn parse_duration(input: &str) -> DanoResult<Duration> {
+    let invalid = || {
+        let msg = format!(
+            "Could not parse duration: {:?}.  Durations look like: 2h, 1h30m, 45m, or 90s.",
+            input
+        );
+        DanoError::new(&msg)
+    };
+
+    let input = input.trim();
+
+    // a zero duration would make every pass immediately due
+    if let Ok(secs) = input.parse::<u64>() {
+        if secs == 0 {
+            return Err(invalid().into());
+        }
+
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut total_secs: u64 = 0;
+    let mut digits = String::new();
+
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        let multiplier = match c {
+            'd' => 86_400,
+            'h' => 3_600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid().into()),
+        };
+
+        let value: u64 = digits.parse().map_err(|_| invalid())?;
+        total_secs = value
+            .checked_mul(multiplier)
+            .and_then(|secs| total_secs.checked_add(secs))
+            .ok_or_else(invalid)?;
+        digits.clear();
+    }
+
+    if !digits.is_empty() || total_secs == 0 {
+        return Err(invalid().into());
+    }
+
+    Ok(Duration::from_secs(total_secs))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteModeConfig {
+    pub opt_rewrite: bool,
+    pub opt_import: Option<Box<str>>,
+    pub opt_xattr_if_missing: bool,
+    pub opt_cache_unsupported: bool,
+    pub opt_retry_unsupported: bool,
+    pub opt_init: Option<InitConfig>,
+    pub opt_watch: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptConfig {
+    pub path: PathBuf,
+    pub opt_key: Option<String>,
+    pub opt_tsa: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitConfig {
+    pub dir: PathBuf,
+    pub opt_yes: bool,
+    pub opt_max_rate: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestModeConfig {
+    pub opt_overwrite_old: bool,
+    pub opt_write_new: bool,
+    pub opt_match: Option<Pattern>,
+    pub opt_restore_xattrs: bool,
+    pub opt_budget: Option<Duration>,
+    pub opt_reference: Option<PathBuf>,
+    pub opt_daemon: Option<Duration>,
+    pub opt_quick: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactModeConfig {
+    pub opt_drop_missing: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeHashModeConfig {
+    pub opt_overwrite_old: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsckModeConfig {
+    pub opt_repair: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyCopyConfig {
+    pub src_dir: PathBuf,
+    pub dst_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusConfig {
+    pub dir: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageConfig {
+    pub dir: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffConfig {
+    pub old: PathBuf,
+    pub new: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrateAlgoConfig {
+    pub hash_algo: Box<str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecMode {
+    Test(TestModeConfig),
+    Write(WriteModeConfig),
+    Print,
+    Dump,
+    Duplicates,
+    Clean,
+    Compact(CompactModeConfig),
+    Fsck(FsckModeConfig),
+    Prune,
+    Diff(DiffConfig),
+    VerifyCopy(VerifyCopyConfig),
+    Status(StatusConfig),
+    Coverage(CoverageConfig),
+    Accept,
+    Export(ExportFormat),
+    MigrateAlgo(MigrateAlgoConfig),
+    TestFlac,
+    AccurateRip,
+    TreeHash(TreeHashModeConfig),
+    Undo,
+    History(Option<PathBuf>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Md5sum,
+    Sha256sum,
+    Sfv,
+    Ffp,
+}
+
+pub type OptFlacBitsPerSecond = Option<u32>;
+
+// per extension overrides, from the config file, such as:
+//
+// [ext.ts]
+// ffmpeg_args = ["-f", "mpegts"]
+//
+// [ext.dsf]
+// decode = true
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExtOverride {
+    // input options, placed before ffmpeg's "-i"
+    pub ffmpeg_args: Option<Vec<String>>,
+    pub decode: Option<bool>,
+}
+
+// defaults for every invocation, from the config file, such as:
+//
+// hash_algo = "sha256"
+// xattr = true
+// threads = 4
+// decode = false
+// canonical_paths = true
+// disable_filter = false
+//
+// a default which is enabled cannot be disabled by a command line option, but may be skipped
+// for a single run with --config=/dev/null
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    ext: BTreeMap<String, ExtOverride>,
+    #[serde(default)]
+    hash_algo: Option<String>,
+    #[serde(default)]
+    min_hash_algo: Option<String>,
+    #[serde(default)]
+    xattr: Option<bool>,
+    #[serde(default)]
+    threads: Option<usize>,
+    #[serde(default)]
+    decode: Option<bool>,
+    #[serde(default)]
+    canonical_paths: Option<bool>,
+    #[serde(default)]
+    disable_filter: Option<bool>,
+    #[serde(default)]
+    probe_filter: Option<bool>,
+    #[serde(default)]
+    probe_unknown: Option<bool>,
+    #[serde(default)]
+    include_hidden: Option<bool>,
+    #[serde(default)]
+    backups: Option<usize>,
+    #[serde(default)]
+    stable_output: Option<bool>,
+}
+
+impl ConfigFile {
+    fn default_path() -> Option<PathBuf> {
+        let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(config_home) if !config_home.is_empty() => PathBuf::from(config_home),
+            #[cfg(windows)]
+            _ => PathBuf::from(std::env::var_os("APPDATA")?),
+            #[cfg(not(windows))]
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+
+        Some(config_home.join("dano").join(CONFIG_FILE_NAME))
+    }
+
+    fn read(opt_path: Option<PathBuf>) -> DanoResult<Self> {
+        match opt_path {
+            Some(path) if path.exists() => {
+                let contents = std::fs::read_to_string(&path)?;
+
+                toml::from_str(&contents).map_err(|err| {
+                    let msg = format!("Could not parse config file {:?}: {}", path, err);
+                    DanoError::new(&msg).into()
+                })
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    // the per user config file, or the config file specified, takes precedence over the system wide config file,
+    // except a user may only raise, never lower, the system wide minimum hash algorithm
+    fn new(opt_config_path: Option<&Path>) -> DanoResult<Self> {
+        let system = Self::read(Some(
+            PathBuf::from(SYSTEM_CONFIG_DIR).join(CONFIG_FILE_NAME),
+        ))?;
+
+        let mut user = match opt_config_path {
+            Some(path) if !path.exists() => {
+                let msg = format!("Config file does not exist: {:?}", path);
+                return Err(DanoError::new(&msg).into());
+            }
+            Some(path) => Self::read(Some(path.to_owned()))?,
+            None => Self::read(Self::default_path())?,
+        };
+
+        let mut ext = system.ext;
+        ext.append(&mut user.ext);
+
+        let min_hash_algo = match (system.min_hash_algo, user.min_hash_algo) {
+            (Some(system_min), Some(user_min)) => {
+                Some(HashPolicy::stronger(&system_min, &user_min).to_owned())
+            }
+            (system_min, user_min) => system_min.or(user_min),
+        };
+
+        Ok(Self {
+            ext,
+            hash_algo: user.hash_algo.or(system.hash_algo),
+            min_hash_algo,
+            xattr: user.xattr.or(system.xattr),
+            threads: user.threads.or(system.threads),
+            decode: user.decode.or(system.decode),
+            canonical_paths: user.canonical_paths.or(system.canonical_paths),
+            disable_filter: user.disable_filter.or(system.disable_filter),
+            probe_filter: user.probe_filter.or(system.probe_filter),
+            probe_unknown: user.probe_unknown.or(system.probe_unknown),
+            include_hidden: user.include_hidden.or(system.include_hidden),
+            backups: user.backups.or(system.backups),
+            stable_output: user.stable_output.or(system.stable_output),
+        })
+    }
+}
+
+const IMAGE_EXTENSIONS: [&str; 7] = ["jpg", "jpeg", "png", "tif", "tiff", "webp", "bmp"];
+
+pub fn is_image_path(path: &Path) -> bool {
+    matches!(path.extension(), Some(extension) if IMAGE_EXTENSIONS
+        .iter()
+        .any(|image_ext| extension.eq_ignore_ascii_case(image_ext)))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PorcelainFormat {
+    Ndjson,
+    Tsv,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SelectedStreams {
+    All,
+    AudioOnly,
+    VideoOnly,
+    WholeFile,
+    ImageOnly,
+    // an explicit ffmpeg stream specifier, of the input file, such as "0:a:1"
+    Stream(Box<str>),
+}
+
+// the sample format of decoded audio, when given explicitly, rather than detected
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DecodeFormat {
+    S24,
+    S32,
+    F32,
+    F64,
+}
+
+impl DecodeFormat {
+    pub fn codec(&self) -> &'static str {
+        match self {
+            DecodeFormat::S24 => "pcm_s24le",
+            DecodeFormat::S32 => "pcm_s32le",
+            DecodeFormat::F32 => "pcm_f32le",
+            DecodeFormat::F64 => "pcm_f64le",
+        }
+    }
+}
+
+impl SelectedStreams {
+    // "audio", "video" or "all", or a stream specifier, such as "a:1", "0:v:0", or a stream index, such as "2"
+    pub fn from_only(only: &str) -> Option<Self> {
+        match only {
+            "audio" => return Some(SelectedStreams::AudioOnly),
+            "video" => return Some(SelectedStreams::VideoOnly),
+            "all" => return Some(SelectedStreams::All),
+            _ => (),
+        }
+
+        let is_valid = !only.is_empty()
+            && only
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '_' | '-' | '.'));
+
+        if !is_valid {
+            return None;
+        }
+
+        // there is only ever a single input file, so the file index, if any, must be 0
+        let specifier = match only.split_once(':') {
+            Some(("0", rest)) if !rest.is_empty() => only.to_owned(),
+            Some((file_index, _)) if file_index.chars().all(|c| c.is_ascii_digit()) => return None,
+            _ => format!("0:{}", only),
+        };
+
+        Some(SelectedStreams::Stream(specifier.into()))
+    }
+}
+
+// which outcomes of a run produce a non-zero exit, such that a CI job, or a scrub run from cron,
+// may choose whether only a mismatch fails, or whether any path left unverified fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strictness {
+    Lenient,
+    Default,
+    Strict,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct TestModeConfig {
-    pub opt_overwrite_old: bool,
-    pub opt_write_new: bool,
+impl Strictness {
+    // a mismatch always fails
+    pub fn fails_on_missing(&self) -> bool {
+        !matches!(self, Strictness::Lenient)
+    }
+
+    pub fn fails_on_new(&self) -> bool {
+        matches!(self, Strictness::Strict)
+    }
+
+    pub fn fails_on_unsupported(&self) -> bool {
+        matches!(self, Strictness::Strict)
+    }
+
+    pub fn fails_on_permission_denied(&self) -> bool {
+        !matches!(self, Strictness::Lenient)
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum ExecMode {
-    Test(TestModeConfig),
-    Write(WriteModeConfig),
-    Print,
-    Dump,
-    Duplicates,
-    Clean,
+// inputs which were not rejected with an error, but were skipped, and which
+// we summarize at the end of a run, so users aren't left wondering where their inputs went
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SkippedPaths {
+    pub excluded: usize,
+    pub hidden: usize,
+    pub no_name: usize,
+    pub no_extension: usize,
+    pub not_media: usize,
+    pub not_media_content: usize,
+    pub permission_denied: usize,
+    pub symlink_loop: usize,
+    pub other_file_system: usize,
 }
 
-pub type OptFlacBitsPerSecond = Option<u32>;
+impl SkippedPaths {
+    pub fn summary(&self) -> Option<String> {
+        let mut parts: Vec<String> = Vec::new();
+        let mut hints: Vec<&str> = Vec::new();
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
-pub enum SelectedStreams {
-    All,
-    AudioOnly,
-    VideoOnly,
+        if self.excluded > 0 {
+            parts.push(format!(
+                "{} excluded by --include or --exclude",
+                self.excluded
+            ));
+        }
+
+        if self.hidden > 0 {
+            parts.push(format!("{} hidden files", self.hidden));
+            hints.push("--include-hidden");
+        }
+
+        if self.no_name > 0 {
+            parts.push(format!("{} without a file name", self.no_name));
+        }
+
+        if self.no_extension > 0 {
+            parts.push(format!("{} without extension", self.no_extension));
+            hints.push("--probe-unknown");
+        }
+
+        if self.not_media > 0 {
+            parts.push(format!(
+                "{} not recognized as media by ffprobe",
+                self.not_media
+            ));
+        }
+
+        if self.not_media_content > 0 {
+            parts.push(format!(
+                "{} with a media extension, but contents which are not media",
+                self.not_media_content
+            ));
+            hints.push("--disable-filter");
+        }
+
+        if self.permission_denied > 0 {
+            parts.push(format!(
+                "{} which could not be read, permission denied",
+                self.permission_denied
+            ));
+            hints.push("--elevate-with");
+        }
+
+        if self.symlink_loop > 0 {
+            parts.push(format!(
+                "{} directories already visited, by a symlink loop or alias",
+                self.symlink_loop
+            ));
+        }
+
+        if self.other_file_system > 0 {
+            parts.push(format!(
+                "{} directories on other file systems",
+                self.other_file_system
+            ));
+        }
+
+        if parts.is_empty() {
+            return None;
+        }
+
+        let mut summary = format!("Skipped {}", parts.join(", "));
+
+        if !hints.is_empty() {
+            summary += &format!("; use {} to include", hints.join(" / "));
+        }
+
+        Some(summary + ".")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PathFilterOpts {
+    opt_disable_filter: bool,
+    opt_canonical_paths: bool,
+    opt_silent: bool,
+    opt_image_pixels: bool,
+    opt_probe_filter: bool,
+    opt_probe_unknown: bool,
+    opt_include_hidden: bool,
+    opt_check_readable: bool,
+    opt_elevate: bool,
+    opt_remote_media: Option<RemoteMedia>,
+    path_globs: PathGlobs,
+}
+
+enum FilteredPath {
+    Valid(PathBuf),
+    UnknownExtension(String),
+    Excluded,
+    Hidden,
+    NoName,
+    NoExtension,
+    NotMedia,
+    NotMediaContent,
+    PermissionDenied,
 }
 
 #[derive(Debug, Clone)]
@@ -251,19 +1949,140 @@ pub struct Config {
     pub exec_mode: ExecMode,
     pub opt_silent: bool,
     pub opt_decode: bool,
+    pub opt_decode_format: Option<DecodeFormat>,
     pub opt_xattr: bool,
+    pub opt_sidecar: bool,
     pub opt_dry_run: bool,
+    pub opt_read_only: bool,
+    pub opt_allow_empty_streams: bool,
+    pub opt_upgrade_xattrs: bool,
+    pub opt_elevate_with: Option<Vec<String>>,
+    pub opt_ffmpeg_path: Option<PathBuf>,
+    pub opt_ffmpeg_args: Option<Vec<String>>,
+    pub opt_pipe_hash: bool,
+    pub opt_no_native: bool,
+    pub opt_frame_hash: bool,
+    pub opt_stream_hash: bool,
+    pub opt_tags_hash: bool,
+    pub opt_cue_tracks: bool,
+    pub opt_include_cover_art: bool,
+    pub opt_cross_algo: bool,
+    pub opt_strict_schema: bool,
+    pub strictness: Strictness,
+    pub opt_both_stores: bool,
+    pub opt_media_info: bool,
+    pub opt_image_pixels: bool,
+    pub opt_show_commands: bool,
+    pub opt_stable_order: bool,
+    pub opt_progress: bool,
+    pub opt_long: bool,
+    pub opt_schema_report: bool,
+    pub opt_multi_catalog: bool,
+    pub opt_porcelain: Option<PorcelainFormat>,
+    pub opt_query: Option<MediaQuery>,
+    pub opt_report_html: Option<PathBuf>,
+    pub opt_receipt: Option<ReceiptConfig>,
+    pub opt_status_file: Option<PathBuf>,
+    pub opt_tmp_dir: Option<PathBuf>,
+    pub opt_hash_policy: Option<HashPolicy>,
+    pub opt_sign: Option<SigningTool>,
+    pub opt_sign_key: Option<OsString>,
+    pub opt_verify_key: Option<PathBuf>,
+    pub opt_require_signature: bool,
+    pub opt_remove_signature: bool,
+    pub opt_lock_wait: LockWait,
+    pub num_backups: usize,
+    pub opt_stable_output: bool,
+    pub opt_record_checked: bool,
+    pub opt_hash_file_format: Option<HashFileFormat>,
+    pub opt_output_stdout: bool,
+    pub opt_remote_media: Option<RemoteMedia>,
+    pub skipped_paths: SkippedPaths,
     pub is_single_path: bool,
     pub opt_num_threads: Option<usize>,
     pub selected_streams: SelectedStreams,
     pub selected_hash_algo: Box<str>,
+    pub ext_overrides: BTreeMap<String, ExtOverride>,
+    pub opt_path_rules: Option<PathRules>,
     pub pwd: PathBuf,
     pub output_file: PathBuf,
     pub hash_file: PathBuf,
     pub paths: Vec<PathBuf>,
+    pub path_filter_opts: PathFilterOpts,
 }
 
 impl Config {
+    pub fn get_ext_override(&self, path: &Path) -> Option<&ExtOverride> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        self.ext_overrides.get(&ext)
+    }
+
+    pub fn get_path_rule(&self, path: &Path) -> Option<&PathRule> {
+        self.opt_path_rules.as_ref()?.get(path, &self.pwd)
+    }
+
+    // a command given on the command line is preferred to whichever is first in the PATH
+    fn find_command(name: &str, opt_path: Option<&Path>) -> DanoResult<PathBuf> {
+        match opt_path {
+            Some(path) => which(path).map_err(|_| {
+                let msg = format!("'{}' command not found at the given path: {:?}", name, path);
+                DanoError::new(&msg).into()
+            }),
+            None => which(name).map_err(|_| {
+                let msg = format!(
+                    "'{}' command not found. Make sure the command '{}' is in your path.",
+                    name, name
+                );
+                DanoError::new(&msg).into()
+            }),
+        }
+    }
+
+    pub fn ffmpeg_command(&self) -> DanoResult<PathBuf> {
+        Self::find_command("ffmpeg", self.opt_ffmpeg_path.as_deref())
+    }
+
+    pub fn curl_command(&self) -> DanoResult<PathBuf> {
+        Self::find_command("curl", None)
+    }
+
+    // the input ffmpeg reads, which, for a local path which mirrors remote media, is its URL
+    pub fn media_input<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        match &self.opt_remote_media {
+            Some(remote_media) => remote_media.media_input(path),
+            None => Cow::Borrowed(path),
+        }
+    }
+
+    pub fn signing_command(&self, tool: SigningTool) -> DanoResult<PathBuf> {
+        Self::find_command(tool.name(), None)
+    }
+
+    // a hash algorithm which dano implements, but ffmpeg does not, is always piped
+    pub fn is_piped_hash_algo(&self, hash_algo: &str) -> bool {
+        NativeHasher::new(hash_algo).is_ok()
+            && (self.opt_pipe_hash || !FFmpegCapabilities::is_ffmpeg_hash_algo(hash_algo))
+    }
+
+    // settings for new hashes, from the first rule which matches the path, else from the command line
+    pub fn new_hash_algo(&self, path: &Path) -> &str {
+        self.get_path_rule(path)
+            .and_then(|rule| rule.opt_hash_algo.as_deref())
+            .unwrap_or(&self.selected_hash_algo)
+    }
+
+    pub fn new_decoded(&self, path: &Path) -> bool {
+        self.get_path_rule(path)
+            .and_then(|rule| rule.opt_decode)
+            .unwrap_or(self.opt_decode)
+    }
+
+    pub fn new_selected_streams(&self, path: &Path) -> &SelectedStreams {
+        self.get_path_rule(path)
+            .and_then(|rule| rule.opt_selected_streams.as_ref())
+            .unwrap_or(&self.selected_streams)
+    }
+
     pub fn new() -> DanoResult<Self> {
         let arg_matches = parse_args();
         Config::from_matches(arg_matches)
@@ -272,7 +2091,7 @@ impl Config {
     fn from_matches(matches: ArgMatches) -> DanoResult<Self> {
         // current working directory will be helpful in a number of places
         let pwd = if let Ok(pwd) = std::env::current_dir() {
-            if let Ok(path) = PathBuf::from(&pwd).canonicalize() {
+            if let Ok(path) = canonicalize(&pwd) {
                 PathBuf::from(path.as_path())
             } else {
                 return Err(DanoError::new(
@@ -287,34 +2106,188 @@ impl Config {
             .into());
         };
 
-        let opt_xattr = matches.is_present("XATTR") || std::env::var_os(XATTR_ENV_KEY).is_some();
+        let config_file = ConfigFile::new(matches.value_of_os("CONFIG").map(Path::new))?;
+
+        // extensions are compared in lowercase
+        let ext_overrides: BTreeMap<String, ExtOverride> = config_file
+            .ext
+            .into_iter()
+            .map(|(ext, ext_override)| (ext.to_lowercase(), ext_override))
+            .collect();
+
+        let opt_xattr_if_missing = matches.is_present("XATTR_IF_MISSING");
+        let opt_xattr = matches.is_present("BOTH_STORES")
+            || opt_xattr_if_missing
+            || matches.is_present("INIT")
+            || flag_or_config(
+                &matches,
+                "XATTR",
+                Some(config_file.xattr == Some(true) || std::env::var_os(XATTR_ENV_KEY).is_some()),
+            );
+        // every option which would write is disabled here, when read only, so no write path may be reached
+        let opt_read_only = matches.is_present("READ_ONLY");
+        let opt_allow_empty_streams = matches.is_present("ALLOW_EMPTY_STREAMS");
+        let opt_upgrade_xattrs = matches.is_present("UPGRADE_XATTRS");
+        let opt_no_native = matches.is_present("NO_NATIVE");
+        let opt_elevate_with: Option<Vec<String>> =
+            matches.value_of_os("ELEVATE_WITH").and_then(|helper| {
+                let helper: Vec<String> = helper
+                    .to_string_lossy()
+                    .split_whitespace()
+                    .map(|arg| arg.to_owned())
+                    .collect();
+
+                (!helper.is_empty()).then_some(helper)
+            });
         let opt_dry_run = matches.is_present("DRY_RUN")
-            || (matches.is_present("PRINT") && matches.is_present("WRITE"));
+            || (matches.is_present("PRINT") && matches.is_present("WRITE"))
+            || opt_read_only;
         let opt_num_threads = matches
             .value_of_lossy("NUM_THREADS")
-            .and_then(|num_threads_str| num_threads_str.parse::<usize>().ok());
+            .and_then(|num_threads_str| num_threads_str.parse::<usize>().ok())
+            .or(config_file.threads);
         let opt_silent = matches.is_present("SILENT");
-        let opt_disable_filter = matches.is_present("DISABLE_FILTER");
-        let opt_canonical_paths = matches.is_present("CANONICAL_PATHS");
-        let opt_decode = matches.is_present("DECODE");
-        let opt_import_flac = matches.is_present("IMPORT_FLAC");
+        let opt_disable_filter =
+            flag_or_config(&matches, "DISABLE_FILTER", config_file.disable_filter);
+        let opt_init = matches.value_of_os("INIT").map(|dir| InitConfig {
+            dir: PathBuf::from(dir),
+            opt_yes: matches.is_present("YES"),
+            opt_max_rate: matches.get_one::<u64>("MAX_RATE").copied(),
+        });
+        // the initial run defaults to writing both extended attributes and a hash file, with canonical paths
+        let opt_both_stores = matches.is_present("BOTH_STORES") || opt_init.is_some();
+        let opt_canonical_paths = opt_init.is_some()
+            || flag_or_config(&matches, "CANONICAL_PATHS", config_file.canonical_paths);
+        let opt_decode = flag_or_config(&matches, "DECODE", config_file.decode);
+        let opt_import: Option<Box<str>> = if matches.is_present("IMPORT_FLAC") {
+            Some("flac".into())
+        } else if matches.is_present("IMPORT_WAVPACK") {
+            Some("wavpack".into())
+        } else if let Some(checksum_file) = matches.value_of_os("IMPORT_CHECKSUMS") {
+            match detect_checksum_importer(Path::new(checksum_file)) {
+                Some(importer) => Some(importer.name().into()),
+                None => {
+                    let msg = format!(
+                        "Could not detect the format of checksum file: {:?}",
+                        checksum_file
+                    );
+                    return Err(DanoError::new(&msg).into());
+                }
+            }
+        } else {
+            matches
+                .value_of_os("IMPORT")
+                .map(|name| name.to_string_lossy().into())
+        };
         let opt_rewrite = matches.is_present("REWRITE_ALL");
-        let opt_overwrite_old = matches.is_present("OVERWRITE_OLD");
-        let opt_write_new = matches.is_present("WRITE_NEW");
+        let opt_overwrite_old = matches.is_present("OVERWRITE_OLD") && !opt_read_only;
+        let opt_write_new = matches.is_present("WRITE_NEW") && !opt_read_only;
+
+        if opt_read_only
+            && !opt_silent
+            && ["WRITE_NEW", "OVERWRITE_OLD", "RESTORE_XATTRS"]
+                .iter()
+                .any(|arg| matches.is_present(arg))
+        {
+            eprintln!(
+                "WARN: Options which would write are disabled, because --read-only was specified."
+            );
+        }
+        let opt_media_info = matches.is_present("MEDIA_INFO");
+        let opt_image_pixels = matches.is_present("IMAGE_PIXELS");
+        let opt_show_commands = matches.is_present("SHOW_COMMANDS");
+        let opt_stable_order = matches.is_present("STABLE_ORDER");
+        let opt_progress =
+            matches.is_present("PROGRESS") && !opt_silent && std::io::stdout().is_terminal();
+        let opt_probe_filter = flag_or_config(&matches, "PROBE_FILTER", config_file.probe_filter);
+        let opt_probe_unknown =
+            flag_or_config(&matches, "PROBE_UNKNOWN", config_file.probe_unknown);
+        let opt_include_hidden =
+            flag_or_config(&matches, "INCLUDE_HIDDEN", config_file.include_hidden);
+        let opt_long = matches.is_present("LONG");
+        let opt_schema_report = matches.is_present("SCHEMA_REPORT");
+        let opt_porcelain = matches.value_of_os("PORCELAIN").map(|format| {
+            if format == OsStr::new("tsv") {
+                PorcelainFormat::Tsv
+            } else {
+                PorcelainFormat::Ndjson
+            }
+        });
+        let opt_report_html = matches.value_of_os("REPORT_HTML").map(PathBuf::from);
+        let opt_receipt = matches.value_of_os("RECEIPT").map(|receipt| ReceiptConfig {
+            path: PathBuf::from(receipt),
+            opt_key: matches
+                .value_of_os("RECEIPT_KEY")
+                .map(|key| key.to_string_lossy().into_owned()),
+            opt_tsa: matches
+                .value_of_os("RECEIPT_TSA")
+                .map(|tsa| tsa.to_string_lossy().into_owned()),
+        });
+        // fail before verifying, rather than after, when the receipt cannot be signed or timestamped
+        if let Some(receipt_config) = &opt_receipt {
+            receipt_config.check_commands()?;
+        }
+        let opt_tmp_dir = match matches.value_of_os("TMP_DIR").map(PathBuf::from) {
+            Some(tmp_dir) if !tmp_dir.is_dir() => {
+                let msg = format!("Temporary directory does not exist: {:?}", tmp_dir);
+                return Err(DanoError::new(&msg).into());
+            }
+            opt_tmp_dir => opt_tmp_dir,
+        };
+        let opt_query = match matches.value_of_os("QUERY") {
+            Some(query) => Some(MediaQuery::new(&query.to_string_lossy())?),
+            None => None,
+        };
 
         let exec_mode = if matches.is_present("CLEAN") {
             ExecMode::Clean
         } else if matches.is_present("TEST") {
+            let opt_match = match matches.value_of_os("MATCH") {
+                Some(glob) => Some(Pattern::new(&glob.to_string_lossy())?),
+                None => None,
+            };
+
+            let opt_daemon = if matches.is_present("DAEMON") {
+                match matches.value_of_os("INTERVAL") {
+                    Some(interval) => Some(parse_duration(&interval.to_string_lossy())?),
+                    None => Some(DEFAULT_DAEMON_INTERVAL),
+                }
+            } else {
+                None
+            };
+
+            let opt_budget = match matches.value_of_os("BUDGET") {
+                Some(budget) => Some(parse_duration(&budget.to_string_lossy())?),
+                None => None,
+            };
+
             let test_mode_config = TestModeConfig {
                 opt_overwrite_old,
                 opt_write_new,
+                opt_match,
+                opt_restore_xattrs: matches.is_present("RESTORE_XATTRS") && !opt_read_only,
+                opt_budget,
+                opt_reference: matches.value_of_os("REFERENCE").map(PathBuf::from),
+                opt_daemon,
+                opt_quick: matches.is_present("QUICK"),
             };
 
             ExecMode::Test(test_mode_config)
-        } else if matches.is_present("WRITE") || opt_rewrite || opt_import_flac {
+        } else if matches.is_present("WRITE")
+            || opt_rewrite
+            || opt_import.is_some()
+            || opt_init.is_some()
+            || matches.is_present("WATCH")
+        {
             ExecMode::Write(WriteModeConfig {
                 opt_rewrite,
-                opt_import_flac,
+                opt_import,
+                opt_xattr_if_missing,
+                opt_cache_unsupported: matches.is_present("CACHE_UNSUPPORTED")
+                    || matches.is_present("RETRY_UNSUPPORTED"),
+                opt_retry_unsupported: matches.is_present("RETRY_UNSUPPORTED"),
+                opt_init: opt_init.clone(),
+                opt_watch: matches.value_of_os("WATCH").map(PathBuf::from),
             })
         } else if matches.is_present("DUMP") {
             ExecMode::Dump
@@ -322,20 +2295,95 @@ impl Config {
             ExecMode::Print
         } else if matches.is_present("DUPLICATES") {
             ExecMode::Duplicates
+        } else if let Some(mut dirs) = matches.values_of_os("VERIFY_COPY") {
+            match (dirs.next(), dirs.next()) {
+                (Some(src_dir), Some(dst_dir)) => ExecMode::VerifyCopy(VerifyCopyConfig {
+                    src_dir: PathBuf::from(src_dir),
+                    dst_dir: PathBuf::from(dst_dir),
+                }),
+                _ => {
+                    return Err(DanoError::new(
+                        "VERIFY_COPY requires both a source and a destination directory.",
+                    )
+                    .into())
+                }
+            }
+        } else if let Some(dir) = matches.value_of_os("STATUS") {
+            ExecMode::Status(StatusConfig {
+                dir: PathBuf::from(dir),
+            })
+        } else if let Some(dir) = matches.value_of_os("COVERAGE") {
+            ExecMode::Coverage(CoverageConfig {
+                dir: PathBuf::from(dir),
+            })
+        } else if let Some(mut hash_files) = matches.values_of_os("DIFF") {
+            match (hash_files.next(), hash_files.next()) {
+                (Some(old), Some(new)) => ExecMode::Diff(DiffConfig {
+                    old: PathBuf::from(old),
+                    new: PathBuf::from(new),
+                }),
+                _ => {
+                    return Err(
+                        DanoError::new("DIFF requires both an old and a new hash file.").into(),
+                    )
+                }
+            }
+        } else if matches.is_present("PRUNE") {
+            ExecMode::Prune
+        } else if matches.is_present("COMPACT") {
+            ExecMode::Compact(CompactModeConfig {
+                opt_drop_missing: matches.is_present("DROP_MISSING"),
+            })
+        } else if matches.is_present("ACCEPT") {
+            ExecMode::Accept
+        } else if let Some(hash_algo) = matches.value_of_os("MIGRATE_ALGO") {
+            let hash_algo = match hash_algo.to_string_lossy().as_ref() {
+                "sha1" => "sha160".into(),
+                hash_algo => hash_algo.into(),
+            };
+
+            ExecMode::MigrateAlgo(MigrateAlgoConfig { hash_algo })
+        } else if matches.is_present("TEST_FLAC") {
+            ExecMode::TestFlac
+        } else if matches.is_present("ACCURATERIP") {
+            ExecMode::AccurateRip
+        } else if matches.is_present("TREE_HASH") {
+            ExecMode::TreeHash(TreeHashModeConfig { opt_overwrite_old })
+        } else if matches.is_present("UNDO") {
+            ExecMode::Undo
+        } else if matches.is_present("HISTORY") {
+            ExecMode::History(matches.value_of_os("HISTORY").map(PathBuf::from))
+        } else if matches.is_present("FSCK") {
+            ExecMode::Fsck(FsckModeConfig {
+                opt_repair: matches.is_present("REPAIR"),
+            })
+        } else if let Some(format) = matches.value_of_os("EXPORT") {
+            let format = match format.to_string_lossy().as_ref() {
+                "md5sum" => ExportFormat::Md5sum,
+                "sha256sum" => ExportFormat::Sha256sum,
+                "sfv" => ExportFormat::Sfv,
+                _ => ExportFormat::Ffp,
+            };
+
+            ExecMode::Export(format)
         } else {
             return Err(DanoError::new(
-                "You must specify an execution mode: TEST, WRITE, DUPLICATES, CLEAN, PRINT or DUMP",
+                "You must specify an execution mode: TEST, WRITE, DUPLICATES, CLEAN, PRINT, DUMP, COMPACT, PRUNE, DIFF, VERIFY_COPY, STATUS, COVERAGE, ACCEPT, EXPORT, MIGRATE_ALGO, TEST_FLAC, ACCURATERIP, TREE_HASH, UNDO, HISTORY or FSCK",
             )
             .into());
         };
 
-        let selected_streams = if let Some(only_stream) = matches.value_of_os("ONLY") {
-            if only_stream == OsStr::new("video") {
-                SelectedStreams::VideoOnly
-            } else if only_stream == OsStr::new("audio") {
-                SelectedStreams::AudioOnly
-            } else {
-                SelectedStreams::All
+        let selected_streams = if matches.is_present("WHOLE_FILE") {
+            SelectedStreams::WholeFile
+        } else if let Some(only_stream) = matches.value_of_os("ONLY") {
+            let only_stream = only_stream.to_string_lossy();
+
+            match SelectedStreams::from_only(&only_stream) {
+                Some(selected_streams) => selected_streams,
+                None => {
+                    let msg = format!("Invalid stream selection: {}", only_stream);
+                    return Err(DanoError::new(&msg).into());
+                }
             }
         } else {
             SelectedStreams::All
@@ -343,81 +2391,502 @@ impl Config {
 
         let output_file = if let Some(output_file) = matches.value_of_os("OUTPUT_FILE") {
             PathBuf::from(output_file)
+        } else if let Some(init_config) = &opt_init {
+            init_config.dir.join(DANO_DEFAULT_HASH_FILE_NAME)
         } else {
             pwd.join(DANO_DEFAULT_HASH_FILE_NAME)
         };
 
-        let selected_hash_algo = if let Some(hash_algo) = matches.value_of_os("HASH_ALGO") {
-            if hash_algo == OsStr::new("sha1") {
-                "sha160".into()
-            } else {
-                hash_algo.to_string_lossy().into()
+        // records may be streamed to stdout, rather than written to a hash file
+        let opt_output_stdout = output_file.as_os_str() == "-";
+
+        if opt_output_stdout && !matches!(exec_mode, ExecMode::Write(_) | ExecMode::Dump) {
+            return Err(
+                DanoError::new("Only WRITE and DUMP modes may write records to stdout.").into(),
+            );
+        }
+
+        if opt_output_stdout && matches.is_present("SIGN") {
+            return Err(DanoError::new("Records written to stdout cannot be signed.").into());
+        }
+
+        // remote media is only ever read by ffmpeg, and so is recorded only in the hash file
+        let opt_remote_media = if matches.is_present("REMOTE") {
+            if !matches!(exec_mode, ExecMode::Write(_) | ExecMode::Test(_)) {
+                return Err(
+                    DanoError::new("Only WRITE and TEST modes may hash remote media.").into(),
+                );
+            }
+
+            if selected_streams == SelectedStreams::WholeFile {
+                return Err(DanoError::new(
+                    "Remote media is read by ffmpeg, and so cannot be hashed as a whole file.",
+                )
+                .into());
+            }
+
+            if opt_xattr || matches.is_present("SIDECAR") {
+                return Err(DanoError::new(
+                    "Records of remote media may only be written to the hash file, and not to extended attributes, or sidecars.",
+                )
+                .into());
             }
+
+            Some(RemoteMedia::new(matches.value_of("REMOTE_MAP"))?)
         } else {
-            "murmur3".into()
+            None
         };
 
+        // a hash algo specified on the command line takes precedence over the config file default
+        let selected_hash_algo: Box<str> = match matches
+            .value_of_os("HASH_ALGO")
+            .map(|hash_algo| hash_algo.to_string_lossy())
+            .or_else(|| config_file.hash_algo.as_deref().map(Cow::Borrowed))
+        {
+            Some(hash_algo) if hash_algo == "sha1" => "sha160".into(),
+            Some(hash_algo) => hash_algo.into(),
+            None => "murmur3".into(),
+        };
+
+        let opt_lock_wait = if matches.is_present("NO_WAIT") {
+            LockWait::NoWait
+        } else {
+            match matches.value_of_os("WAIT") {
+                Some(duration) if !duration.is_empty() => {
+                    LockWait::AtMost(parse_duration(&duration.to_string_lossy())?)
+                }
+                _ => LockWait::Indefinitely,
+            }
+        };
+
+        let opt_hash_policy = match &config_file.min_hash_algo {
+            Some(min_hash_algo) => Some(HashPolicy::new(
+                min_hash_algo,
+                matches.is_present("ALLOW_WEAK_HASH"),
+            )?),
+            None => None,
+        };
+
+        // fail before hashing, when new hashes would be refused at write time
+        let is_hashing_new = match &exec_mode {
+            ExecMode::Write(write_config) => {
+                !write_config.opt_rewrite && write_config.opt_import.is_none()
+            }
+            ExecMode::Test(test_mode_config) => test_mode_config.opt_write_new,
+            _ => false,
+        };
+
+        let opt_path_rules = match matches.value_of_os("RULES") {
+            Some(rules_file) => Some(PathRules::read(Path::new(rules_file))?),
+            None => None,
+        };
+
+        if let Some(hash_policy) = &opt_hash_policy {
+            if let ExecMode::MigrateAlgo(migrate_config) = &exec_mode {
+                if !opt_dry_run {
+                    hash_policy.check_selected(&migrate_config.hash_algo)?;
+                }
+            }
+
+            if is_hashing_new && !opt_dry_run {
+                hash_policy.check_selected(&selected_hash_algo)?;
+
+                opt_path_rules
+                    .iter()
+                    .flat_map(|path_rules| path_rules.hash_algos())
+                    .try_for_each(|hash_algo| hash_policy.check_selected(hash_algo))?;
+            }
+        }
+
         let hash_file = if let Some(hash_file) = matches.value_of_os("HASH_FILE") {
             PathBuf::from(hash_file)
+        } else if opt_output_stdout {
+            pwd.join(DANO_DEFAULT_HASH_FILE_NAME)
         } else {
             output_file.clone()
         };
 
-        let paths: Vec<PathBuf> = {
-            let res: Vec<PathBuf> = if let Some(input_files) = matches.values_of_os("INPUT_FILES") {
-                input_files.par_bridge().map(PathBuf::from).collect()
+        // a hash file piped in, or fetched from a remote URL, is only ever read
+        if is_streamed_hash_file(&hash_file) {
+            let is_rewrite = matches!(
+                exec_mode,
+                ExecMode::Compact(_)
+                    | ExecMode::Prune
+                    | ExecMode::Fsck(FsckModeConfig { opt_repair: true })
+            );
+
+            if is_rewrite {
+                return Err(DanoError::new(
+                    "A hash file read from stdin, or from a remote URL, cannot be rewritten.",
+                )
+                .into());
+            }
+        }
+
+        if is_stdin_path(&hash_file)
+            && matches
+                .value_of_os("FILES_FROM")
+                .is_some_and(|files_from| is_stdin_path(Path::new(files_from)))
+        {
+            return Err(DanoError::new(
+                "Input files cannot be read from stdin when the hash file is read from stdin.",
+            )
+            .into());
+        }
+
+        if RemoteHashFile::is_remote(&hash_file) {
+            RemoteHashFile::fetch(
+                &Self::find_command("curl", None)?,
+                &hash_file,
+                opt_show_commands,
+            )?;
+        }
+
+        let walk_opts = WalkOpts {
+            opt_one_file_system: matches.is_present("ONE_FILE_SYSTEM"),
+            opt_max_depth: matches
+                .get_one::<u64>("MAX_DEPTH")
+                .map(|depth| *depth as usize),
+            path_globs: PathGlobs::new(&matches)?,
+        };
+
+        let (paths, skipped_paths, path_filter_opts): (Vec<PathBuf>, SkippedPaths, PathFilterOpts) = {
+            let (res, skipped_dirs): (Vec<PathBuf>, SkippedDirs) = if let Some(init_config) =
+                &opt_init
+            {
+                walk_dir(&init_config.dir, &walk_opts)?
+            } else if let ExecMode::VerifyCopy(verify_copy_config) = &exec_mode {
+                let (mut res, src_skipped) = walk_dir(&verify_copy_config.src_dir, &walk_opts)?;
+                let (dst_res, dst_skipped) = walk_dir(&verify_copy_config.dst_dir, &walk_opts)?;
+                res.extend(dst_res);
+
+                let skipped_dirs = SkippedDirs {
+                    symlink_loop: src_skipped.symlink_loop + dst_skipped.symlink_loop,
+                    other_file_system: src_skipped.other_file_system
+                        + dst_skipped.other_file_system,
+                };
+
+                (res, skipped_dirs)
+            } else if let ExecMode::Status(status_config) = &exec_mode {
+                walk_dir(&status_config.dir, &walk_opts)?
+            } else if let ExecMode::Coverage(coverage_config) = &exec_mode {
+                walk_dir(&coverage_config.dir, &walk_opts)?
+            } else if let ExecMode::Write(WriteModeConfig {
+                opt_watch: Some(dir),
+                ..
+            }) = &exec_mode
+            {
+                walk_dir(dir, &walk_opts)?
+            } else if let Some(checksum_file) = matches.value_of_os("IMPORT_CHECKSUMS") {
+                (vec![PathBuf::from(checksum_file)], SkippedDirs::default())
+            } else if let Some(files_from) = matches.value_of_os("FILES_FROM") {
+                (
+                    read_files_from(Path::new(files_from), matches.is_present("NULL"))?,
+                    SkippedDirs::default(),
+                )
+            } else if let Some(input_files) = matches.values_of_os("INPUT_FILES") {
+                (
+                    input_files.par_bridge().map(PathBuf::from).collect(),
+                    SkippedDirs::default(),
+                )
             } else {
-                match &exec_mode {
-                    ExecMode::Test(_) if hash_file.exists() => Vec::new(),
-                    _ => read_stdin()?,
-                }
+                let res = match &exec_mode {
+                        ExecMode::Test(_) if hash_file_exists(&hash_file) => Vec::new(),
+                        // compaction only concerns the hash file
+                        ExecMode::Compact(_) | ExecMode::Prune => Vec::new(),
+                        // a diff only concerns the two hash files
+                        ExecMode::Diff(_) => Vec::new(),
+                        ExecMode::Print if opt_schema_report => Vec::new(),
+                        ExecMode::Export(_) if hash_file_exists(&hash_file) => Vec::new(),
+                        // a migration, without input files, migrates every record in the hash file
+                        ExecMode::MigrateAlgo(_) => Vec::new(),
+                        // a tree hash, without input files, includes every record in the hash file
+                        ExecMode::TreeHash(_) => Vec::new(),
+                        // an undo concerns only the paths of the last write
+                        ExecMode::Undo => Vec::new(),
+                        // the history is queried by the path given, if any, and not by input files
+                        ExecMode::History(_) => Vec::new(),
+                        // a check only concerns the hash file
+                        ExecMode::Fsck(_) => Vec::new(),
+                        _ if is_stdin_path(&hash_file) => {
+                            return Err(DanoError::new(
+                                "Input files must be given as arguments when the hash file is read from stdin.",
+                            )
+                            .into())
+                        }
+                        _ => read_stdin()?,
+                    };
+
+                (res, SkippedDirs::default())
             };
 
-            Self::parse_paths(
-                &res,
+            // importers validate their own input paths, which may not be media files
+            let is_import = matches!(&exec_mode, ExecMode::Write(write_config) if write_config.opt_import.is_some());
+
+            // only modes which read the contents of input files require permission to read them
+            let opt_check_readable = matches!(
                 &exec_mode,
-                opt_disable_filter,
+                ExecMode::Test(_)
+                    | ExecMode::Write(_)
+                    | ExecMode::VerifyCopy(_)
+                    | ExecMode::Accept
+                    | ExecMode::MigrateAlgo(_)
+                    | ExecMode::TestFlac
+                    | ExecMode::AccurateRip
+            );
+
+            let filter_opts = PathFilterOpts {
+                opt_disable_filter: opt_disable_filter
+                    || is_import
+                    || selected_streams == SelectedStreams::WholeFile,
                 opt_canonical_paths,
                 opt_silent,
-                &hash_file,
-            )
+                opt_image_pixels,
+                opt_probe_filter,
+                opt_probe_unknown,
+                opt_include_hidden,
+                opt_check_readable,
+                opt_elevate: opt_elevate_with.is_some(),
+                opt_remote_media: opt_remote_media.clone(),
+                path_globs: walk_opts.path_globs.clone(),
+            };
+
+            let (paths, mut skipped_paths) =
+                Self::parse_paths(&res, &exec_mode, &hash_file, &filter_opts);
+            skipped_paths.symlink_loop = skipped_dirs.symlink_loop;
+            skipped_paths.other_file_system = skipped_dirs.other_file_system;
+
+            (paths, skipped_paths, filter_opts)
         };
 
-        if paths.is_empty() {
-            return Err(DanoError::new("No valid paths given.  Exiting.").into());
+        // when selecting from recorded file info, input files are not required
+        let is_match = matches!(&exec_mode, ExecMode::Test(test_mode_config)
+            if test_mode_config.opt_match.is_some()
+                || test_mode_config.opt_budget.is_some()
+                || test_mode_config.opt_reference.is_some()
+                || test_mode_config.opt_daemon.is_some());
+
+        // when no hash file is specified, input files are tested against any drive local catalog,
+        // but only when testing, because writing into a drive local catalog would change its path form
+        let opt_multi_catalog = matches!(&exec_mode, ExecMode::Test(test_mode_config)
+            if !test_mode_config.opt_write_new
+                && !test_mode_config.opt_overwrite_old
+                && test_mode_config.opt_match.is_none()
+                && test_mode_config.opt_budget.is_none()
+                && test_mode_config.opt_reference.is_none()
+                && test_mode_config.opt_daemon.is_none())
+            && !matches.is_present("HASH_FILE")
+            && !matches.is_present("OUTPUT_FILE")
+            // a receipt vouches for a single hash file, and a status file follows a single run
+            && !matches.is_present("RECEIPT")
+            && !matches.is_present("STATUS_FILE");
+
+        let is_compact = matches!(
+            &exec_mode,
+            ExecMode::Compact(_)
+                | ExecMode::Prune
+                | ExecMode::Diff(_)
+                | ExecMode::MigrateAlgo(_)
+                | ExecMode::TreeHash(_)
+                | ExecMode::Undo
+                | ExecMode::History(_)
+                | ExecMode::Fsck(_)
+        );
+        let is_verify_copy = matches!(&exec_mode, ExecMode::VerifyCopy(_));
+        // a directory without media files still has a status, such as missing files
+        let is_status = matches!(&exec_mode, ExecMode::Status(_) | ExecMode::Coverage(_));
+        // an export, without input files, exports every record in the hash file
+        let is_export = matches!(&exec_mode, ExecMode::Export(_)) && hash_file_exists(&hash_file);
+        // a watched directory may be empty, until new files arrive
+        let is_watch =
+            matches!(&exec_mode, ExecMode::Write(write_config) if write_config.opt_watch.is_some());
+
+        if paths.is_empty()
+            && !is_match
+            && !is_compact
+            && !is_verify_copy
+            && !is_status
+            && !is_export
+            && !is_watch
+            && !opt_schema_report
+        {
+            let msg = match skipped_paths.summary() {
+                Some(summary) => format!("No valid paths given.  {}  Exiting.", summary),
+                None => "No valid paths given.  Exiting.".to_owned(),
+            };
+            return Err(DanoError::new(&msg).into());
         }
 
-        Ok(Config {
+        let strictness = if matches.is_present("STRICT") {
+            Strictness::Strict
+        } else if matches.is_present("LENIENT") {
+            Strictness::Lenient
+        } else {
+            Strictness::Default
+        };
+
+        let config = Config {
             exec_mode,
             opt_silent,
             opt_num_threads,
             opt_decode,
+            opt_decode_format: matches
+                .value_of("DECODE_FORMAT")
+                .map(|format| match format {
+                    "s24" => DecodeFormat::S24,
+                    "s32" => DecodeFormat::S32,
+                    "f64" => DecodeFormat::F64,
+                    _ => DecodeFormat::F32,
+                }),
             opt_xattr,
+            opt_sidecar: matches.is_present("SIDECAR"),
             opt_dry_run,
-            is_single_path: { paths.len() <= 1 },
+            opt_read_only,
+            opt_allow_empty_streams,
+            opt_upgrade_xattrs,
+            opt_elevate_with,
+            opt_ffmpeg_path: matches
+                .value_of_os("FFMPEG_PATH")
+                .map(|path| path.to_owned())
+                .or_else(|| std::env::var_os("DANO_FFMPEG").filter(|path| !path.is_empty()))
+                .map(PathBuf::from),
+            opt_ffmpeg_args: matches
+                .value_of("FFMPEG_ARGS")
+                .map(|args| {
+                    args.split_whitespace()
+                        .map(|arg| arg.to_owned())
+                        .collect::<Vec<String>>()
+                })
+                .filter(|args| !args.is_empty()),
+            opt_pipe_hash: matches.is_present("PIPE_HASH"),
+            opt_no_native,
+            opt_frame_hash: matches.is_present("FRAME_HASH"),
+            opt_stream_hash: matches.is_present("STREAM_HASH"),
+            opt_tags_hash: matches.is_present("TAGS_HASH"),
+            opt_cue_tracks: matches.is_present("CUE_TRACKS"),
+            opt_include_cover_art: matches.is_present("INCLUDE_COVER_ART"),
+            opt_cross_algo: matches.is_present("CROSS_ALGO"),
+            opt_strict_schema: matches.is_present("STRICT_SCHEMA"),
+            strictness,
+            opt_both_stores,
+            opt_media_info,
+            opt_image_pixels,
+            opt_show_commands,
+            opt_stable_order,
+            opt_progress,
+            opt_long,
+            opt_schema_report,
+            opt_multi_catalog,
+            opt_porcelain,
+            opt_query,
+            opt_report_html,
+            opt_receipt,
+            opt_status_file: matches.value_of_os("STATUS_FILE").map(PathBuf::from),
+            opt_tmp_dir,
+            opt_hash_policy,
+            opt_sign: matches.value_of_os("SIGN").map(|tool| {
+                if tool == "gpg" {
+                    SigningTool::Gpg
+                } else {
+                    SigningTool::Minisign
+                }
+            }),
+            opt_sign_key: matches.value_of_os("SIGN_KEY").map(|key| key.to_owned()),
+            opt_verify_key: matches.value_of_os("VERIFY_KEY").map(PathBuf::from),
+            opt_require_signature: matches.is_present("REQUIRE_SIGNATURE"),
+            opt_remove_signature: matches.is_present("REMOVE_SIGNATURE"),
+            opt_lock_wait,
+            num_backups: matches
+                .get_one::<usize>("BACKUPS")
+                .copied()
+                .or(config_file.backups)
+                .unwrap_or(DEFAULT_NUM_BACKUPS),
+            opt_stable_output: flag_or_config(&matches, "STABLE_OUTPUT", config_file.stable_output),
+            opt_record_checked: matches.is_present("RECORD_CHECKED"),
+            opt_output_stdout,
+            opt_remote_media,
+            opt_hash_file_format: matches.value_of("FORMAT").map(|format| {
+                if format == "binary" {
+                    HashFileFormat::Binary
+                } else {
+                    HashFileFormat::Text
+                }
+            }),
+            skipped_paths,
+            is_single_path: { paths.len() <= 1 && !is_match },
             selected_streams,
             selected_hash_algo,
+            ext_overrides,
+            opt_path_rules,
             pwd,
             output_file,
             hash_file,
             paths,
-        })
+            path_filter_opts,
+        };
+
+        // fail before hashing, rather than once per file, when this ffmpeg cannot produce the requested hashes
+        if is_hashing_new && !config.paths.is_empty() {
+            if let Ok(ffmpeg_command) = config.ffmpeg_command() {
+                // a piped hash algorithm, or a whole file hash, is hashed by dano, whether or not ffmpeg supports it
+                let hash_algos = std::iter::once(config.selected_hash_algo.as_ref())
+                    .filter(|_| config.selected_streams != SelectedStreams::WholeFile)
+                    .chain(
+                        config
+                            .opt_path_rules
+                            .iter()
+                            .flat_map(|path_rules| path_rules.hash_algos()),
+                    )
+                    .filter(|hash_algo| !config.is_piped_hash_algo(hash_algo));
+
+                FFmpegCapabilities::check_hash_algos(&ffmpeg_command, hash_algos)?;
+            }
+        }
+
+        // a whole file hash algorithm must be one dano implements
+        if is_hashing_new && config.selected_streams == SelectedStreams::WholeFile {
+            NativeHasher::new(&config.selected_hash_algo)?;
+        }
+
+        if let ExecMode::MigrateAlgo(migrate_config) = &config.exec_mode {
+            if let Ok(ffmpeg_command) = config.ffmpeg_command() {
+                let hash_algos = std::iter::once(migrate_config.hash_algo.as_ref())
+                    .filter(|hash_algo| !config.is_piped_hash_algo(hash_algo));
+
+                FFmpegCapabilities::check_hash_algos(&ffmpeg_command, hash_algos)?;
+            }
+        }
+
+        Ok(config)
+    }
+
+    // filter paths which arrive after startup, such as in a watched directory, just as the input paths were filtered
+    pub fn filter_paths(&self, raw_paths: &[PathBuf]) -> Vec<PathBuf> {
+        Self::parse_paths(
+            raw_paths,
+            &self.exec_mode,
+            &self.hash_file,
+            &self.path_filter_opts,
+        )
+        .0
     }
 
     fn parse_paths(
         raw_paths: &[PathBuf],
         exec_mode: &ExecMode,
-        opt_disable_filter: bool,
-        opt_canonical_paths: bool,
-        opt_silent: bool,
         hash_file: &Path,
-    ) -> Vec<PathBuf> {
+        filter_opts: &PathFilterOpts,
+    ) -> (Vec<PathBuf>, SkippedPaths) {
         let auto_extension_filter = include_str!("../data/ffmpeg_extensions_list.txt");
 
-        let (bad_extensions, valid_paths): (Vec<_>, Vec<_>) = raw_paths
+        // a URL, in REMOTE mode, is read by ffmpeg, and so is not checked against the local filesystem
+        let is_remote_url =
+            |path: &Path| filter_opts.opt_remote_media.is_some() && RemoteMedia::is_url(path);
+
+        let filtered: Vec<FilteredPath> = raw_paths
             .into_par_iter()
             .filter(|path| {
-                if path.exists() {
+                if is_remote_url(path) || path.exists() {
                     return true;
                 }
 
@@ -425,24 +2894,16 @@ impl Config {
                 false
             })
             .filter(|path| {
-                if path.is_file() {
+                if is_remote_url(path) || path.is_file() {
                     return true;
                 }
 
                 eprintln!("ERROR: Path is not a regular file: {:?}", path);
                 false
             })
-            .filter(|path| {
-                if path.to_str().is_some() {
-                    return true;
-                }
-
-                eprintln!("ERROR: Path cannot be serialized to string: {:?}", path);
-                false
-            })
             .map(|path| {
-                if opt_canonical_paths {
-                    if let Ok(canonical) = path.canonicalize() {
+                if filter_opts.opt_canonical_paths && !is_remote_url(path) {
+                    if let Ok(canonical) = canonicalize(path) {
                         return canonical;
                     }
 
@@ -459,9 +2920,10 @@ impl Config {
                     if path.file_name() == Some(OsStr::new(DANO_DEFAULT_HASH_FILE_NAME)) {
                         match std::fs::remove_file(path) {
                             Ok(_) => {
+                                // reported on stderr, so stdout, given --porcelain, carries only machine readable lines
                                 let msg =
                                     format!("dano hash file successfully removed: {:?}", path);
-                                println!("{}", &msg);
+                                eprintln!("{}", &msg);
                             }
                             Err(err) => {
                                 let msg = format!(
@@ -475,6 +2937,16 @@ impl Config {
                     }
                 }
 
+                // the lock file, undo journal and history which dano keeps next to a hash file are not media
+                if is_hash_file_side_file(path, hash_file) {
+                    return false;
+                }
+
+                // a sidecar is a record, not media, and, when cleaning, is removed with the record of its media file
+                if Sidecar::is_sidecar(path) {
+                    return false;
+                }
+
                 if path.file_name() == Some(hash_file.as_os_str()) {
                     eprintln!(
                         "ERROR: File name is the name of a dano hash file: {:?}",
@@ -486,40 +2958,118 @@ impl Config {
 
                 true
             })
-            .filter_map(|path| {
-                if !opt_disable_filter {
-                    let path_ref = &path;
+            .map(|path| {
+                if !filter_opts.path_globs.is_selected(&path) {
+                    return FilteredPath::Excluded;
+                }
+
+                // hidden files are excluded, whether or not the extension filter is disabled
+                let is_hidden = match path.file_name() {
+                    Some(file_name) => file_name.to_string_lossy().starts_with('.'),
+                    None => return FilteredPath::NoName,
+                };
+
+                if is_hidden && !filter_opts.opt_include_hidden {
+                    return FilteredPath::Hidden;
+                }
 
-                    let opt_extension = path_ref.extension();
+                if filter_opts.opt_disable_filter {
+                    return FilteredPath::Valid(path);
+                }
 
-                    if auto_extension_filter
-                        .lines()
-                        .any(|extension| opt_extension == Some(OsStr::new(extension)))
-                    {
-                        return Some(Either::Right(path));
+                // whatever the extension, a file is accepted if ffprobe can demux it
+                if filter_opts.opt_probe_filter {
+                    if FFProbe::is_media(&path) {
+                        return FilteredPath::Valid(path);
                     }
 
-                    if let Some(ext) = opt_extension {
-                        return Some(Either::Left(ext.to_string_lossy().to_string()));
+                    return FilteredPath::NotMedia;
+                }
+
+                let opt_extension = path.extension();
+
+                let is_media_extension = auto_extension_filter
+                    .lines()
+                    .any(|extension| opt_extension == Some(OsStr::new(extension)))
+                    || (filter_opts.opt_image_pixels && is_image_path(&path));
+
+                // the contents of a file, when recognized, overrule its extension
+                match ContentSniffer::sniff(&path) {
+                    SniffedContent::Media => return FilteredPath::Valid(path),
+                    SniffedContent::NotMedia(mime_type) if is_media_extension => {
+                        if !filter_opts.opt_silent {
+                            eprintln!(
+                                "WARN: Path has a media extension, but its contents are {}, and it has been skipped: {:?}",
+                                mime_type, path
+                            );
+                        }
+
+                        return FilteredPath::NotMediaContent;
                     }
+                    _ if is_media_extension => return FilteredPath::Valid(path),
+                    _ => (),
+                }
 
-                    // what are these None cases: hidden files (dot files),
-                    // no file name, no extension
-                    return None;
+                match opt_extension {
+                    Some(ext) => FilteredPath::UnknownExtension(ext.to_string_lossy().to_string()),
+                    None if filter_opts.opt_probe_unknown => {
+                        if FFProbe::is_media(&path) {
+                            FilteredPath::Valid(path)
+                        } else {
+                            FilteredPath::NotMedia
+                        }
+                    }
+                    None => FilteredPath::NoExtension,
                 }
+            })
+            .map(|filtered_path| match filtered_path {
+                FilteredPath::Valid(path)
+                    if filter_opts.opt_check_readable && is_permission_denied(&path) =>
+                {
+                    if filter_opts.opt_elevate {
+                        return FilteredPath::Valid(path);
+                    }
 
-                Some(Either::Right(path))
+                    eprintln!("ERROR: Permission denied, path cannot be read: {:?}", path);
+                    FilteredPath::PermissionDenied
+                }
+                filtered_path => filtered_path,
             })
-            .partition_map(|item| item);
+            .collect();
 
-        if !opt_silent && !bad_extensions.is_empty() {
-            let unique: HashSet<String> = bad_extensions.into_iter().collect();
+        let mut valid_paths = Vec::new();
+        let mut bad_extensions = HashSet::new();
+        let mut skipped = SkippedPaths::default();
+
+        filtered
+            .into_iter()
+            .for_each(|filtered_path| match filtered_path {
+                // a URL which a local path mirrors is recorded by the local path
+                FilteredPath::Valid(path) => match &filter_opts.opt_remote_media {
+                    Some(remote_media) => valid_paths.push(remote_media.local_path(&path)),
+                    None => valid_paths.push(path),
+                },
+                FilteredPath::UnknownExtension(ext) => {
+                    bad_extensions.insert(ext);
+                }
+                FilteredPath::Excluded => skipped.excluded += 1,
+                FilteredPath::Hidden => skipped.hidden += 1,
+                FilteredPath::NoName => skipped.no_name += 1,
+                FilteredPath::NoExtension => skipped.no_extension += 1,
+                FilteredPath::NotMedia => skipped.not_media += 1,
+                FilteredPath::NotMediaContent => skipped.not_media_content += 1,
+                FilteredPath::PermissionDenied => skipped.permission_denied += 1,
+            });
 
-            let buffer: String = unique.iter().map(|ext| format!("{} ", ext)).collect();
+        if !filter_opts.opt_silent && !bad_extensions.is_empty() {
+            let buffer: String = bad_extensions
+                .iter()
+                .map(|ext| format!("{} ", ext))
+                .collect();
 
             eprintln!("WARN: The following are extensions which are unknown to dano: {:?}.  dano has excluded all files with these extensions.  If you know these file types are acceptable to ffmpeg, you may use --disable-filter to force dano to accept their use.", buffer.trim());
         }
 
-        valid_paths
+        (valid_paths, skipped)
     }
 }
@@ -0,0 +1,210 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use crate::config::VerifyCopyConfig;
+use crate::ingest::RecordedFileInfo;
+use crate::lookup::{FileInfo, FileInfoLookup, FileMetadata};
+use crate::requests::RequestBundle;
+use crate::utility::{prepare_thread_pool, print_err_buf, print_outcome};
+use crate::{
+    Config, DanoResult, DANO_CLEAN_EXIT_CODE, DANO_DISORDER_EXIT_CODE, DANO_FILE_INFO_VERSION,
+};
+
+#[derive(Debug, Default)]
+struct CopyStats {
+    ok: usize,
+    mismatched: usize,
+    missing_from_dst: usize,
+    only_in_dst: usize,
+    not_hashed: usize,
+}
+
+pub struct CopyVerification;
+
+impl CopyVerification {
+    pub fn exec(config: &Config, verify_copy_config: &VerifyCopyConfig) -> DanoResult<i32> {
+        let src_dir = &verify_copy_config.src_dir;
+        let dst_dir = &verify_copy_config.dst_dir;
+
+        // paths are compared relative to their directory, if one directory is nested
+        // in the other, the more specific directory claims the path
+        let (src_nested, dst_nested) = (src_dir.starts_with(dst_dir), dst_dir.starts_with(src_dir));
+
+        let mut src_files: BTreeMap<PathBuf, PathBuf> = BTreeMap::new();
+        let mut dst_files: BTreeMap<PathBuf, PathBuf> = BTreeMap::new();
+
+        config.paths.iter().for_each(|path| {
+            let in_src = path.strip_prefix(src_dir).ok();
+            let in_dst = path.strip_prefix(dst_dir).ok();
+
+            match (in_src, in_dst) {
+                (Some(relative), None) => {
+                    src_files.insert(relative.to_owned(), path.clone());
+                }
+                (None, Some(relative)) => {
+                    dst_files.insert(relative.to_owned(), path.clone());
+                }
+                (Some(relative), Some(_)) if src_nested || !dst_nested => {
+                    src_files.insert(relative.to_owned(), path.clone());
+                }
+                (Some(_), Some(relative)) => {
+                    dst_files.insert(relative.to_owned(), path.clone());
+                }
+                (None, None) => (),
+            }
+        });
+
+        // source records may be reused, but only when the file has not been modified since it was recorded
+        let src_recorded: BTreeMap<&Path, FileMetadata> = src_files
+            .iter()
+            .filter_map(|(relative, path)| {
+                let file_info = RecordedFileInfo::read_file_info_from_xattr(path)?;
+                let metadata = file_info.metadata?;
+                let modify_time = path.metadata().ok()?.modified().ok()?;
+
+                (metadata.modify_time == modify_time).then_some((relative.as_path(), metadata))
+            })
+            .collect();
+
+        // destination files are hashed the same way as their source was recorded, so the two are comparable
+        let dst_as_recorded: Vec<FileInfo> = dst_files
+            .iter()
+            .filter_map(|(relative, path)| {
+                src_recorded
+                    .get(relative.as_path())
+                    .map(|metadata| FileInfo {
+                        version: DANO_FILE_INFO_VERSION,
+                        path: path.clone(),
+                        metadata: Some(metadata.clone()),
+                    })
+            })
+            .collect();
+
+        // files present on only one side need not be hashed at all
+        let new_paths: Vec<PathBuf> = src_files
+            .iter()
+            .filter(|(relative, _path)| dst_files.contains_key(*relative))
+            .chain(
+                dst_files
+                    .iter()
+                    .filter(|(relative, _path)| src_files.contains_key(*relative)),
+            )
+            .filter(|(relative, _path)| !src_recorded.contains_key(relative.as_path()))
+            .map(|(_relative, path)| path.clone())
+            .collect();
+
+        let request_config = Config {
+            paths: new_paths,
+            ..config.clone()
+        };
+
+        let thread_pool = prepare_thread_pool(config)?;
+        let file_info_requests = RequestBundle::new(&request_config, &dst_as_recorded)?;
+        let (rx_item, _rx_unsupported) =
+            FileInfoLookup::exec(config, file_info_requests, thread_pool)?;
+
+        let hashed: BTreeMap<PathBuf, FileMetadata> = rx_item
+            .iter()
+            .filter_map(|file_info| Some((file_info.path, file_info.metadata?)))
+            .collect();
+
+        let relative_paths: BTreeSet<&PathBuf> = src_files.keys().chain(dst_files.keys()).collect();
+        let mut stats = CopyStats::default();
+
+        relative_paths.into_iter().try_for_each(|relative| {
+            let opt_src = src_files.get(relative).and_then(|path| {
+                src_recorded
+                    .get(relative.as_path())
+                    .or_else(|| hashed.get(path))
+            });
+            let opt_dst = dst_files.get(relative).and_then(|path| hashed.get(path));
+
+            let (status, msg) = match (src_files.get(relative), dst_files.get(relative)) {
+                (Some(_), None) => {
+                    stats.missing_from_dst += 1;
+                    (
+                        "missing",
+                        format!("WARN: {:?}: Missing from destination.\n", relative),
+                    )
+                }
+                (None, Some(_)) => {
+                    stats.only_in_dst += 1;
+                    (
+                        "only_in_destination",
+                        format!("WARN: {:?}: Only present in destination.\n", relative),
+                    )
+                }
+                _ => match (opt_src, opt_dst) {
+                    (Some(src), Some(dst)) if src.hash_value == dst.hash_value => {
+                        stats.ok += 1;
+
+                        if config.opt_silent {
+                            return Ok(());
+                        }
+
+                        ("ok", format!("{:?}: OK\n", relative))
+                    }
+                    (Some(_), Some(_)) => {
+                        stats.mismatched += 1;
+                        (
+                            "mismatch",
+                            format!("WARN: {:?}: Destination does not match source.\n", relative),
+                        )
+                    }
+                    (None, _) => {
+                        stats.not_hashed += 1;
+                        (
+                            "not_hashed",
+                            format!("WARN: {:?}: Source could not be hashed.\n", relative),
+                        )
+                    }
+                    (_, None) => {
+                        stats.not_hashed += 1;
+                        (
+                            "not_hashed",
+                            format!("WARN: {:?}: Destination could not be hashed.\n", relative),
+                        )
+                    }
+                },
+            };
+
+            print_outcome(config, relative, status, opt_dst.or(opt_src), &msg)
+        })?;
+
+        print_err_buf(&format!(
+            "Verified copy of {:?} to {:?}: {} OK, {} mismatched, {} missing from destination, {} only in destination, {} could not be hashed.\n",
+            src_dir,
+            dst_dir,
+            stats.ok,
+            stats.mismatched,
+            stats.missing_from_dst,
+            stats.only_in_dst,
+            stats.not_hashed
+        ))?;
+
+        if stats.mismatched + stats.missing_from_dst + stats.only_in_dst + stats.not_hashed == 0 {
+            Ok(DANO_CLEAN_EXIT_CODE)
+        } else {
+            Ok(DANO_DISORDER_EXIT_CODE)
+        }
+    }
+}
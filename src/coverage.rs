@@ -0,0 +1,116 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+
+use crate::config::CoverageConfig;
+use crate::status::DirStatus;
+use crate::utility::{canonicalize, print_commentary, print_outcome};
+use crate::{Config, DanoResult, DANO_CLEAN_EXIT_CODE, DANO_DISORDER_EXIT_CODE};
+
+// the inverse of a TEST, the media files beneath a directory which have no record anywhere, and so are not yet
+// protected at all, grouped by directory.  like a status, nothing is hashed
+pub struct DirCoverage {
+    dir: PathBuf,
+    num_files: usize,
+    unrecorded: BTreeMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl DirCoverage {
+    pub fn exec(config: &Config, coverage_config: &CoverageConfig) -> DanoResult<i32> {
+        let coverage = Self::new(config, coverage_config)?;
+
+        coverage.print(config)?;
+
+        if coverage.unrecorded.is_empty() {
+            Ok(DANO_CLEAN_EXIT_CODE)
+        } else {
+            Ok(DANO_DISORDER_EXIT_CODE)
+        }
+    }
+
+    fn new(config: &Config, coverage_config: &CoverageConfig) -> DanoResult<Self> {
+        let dir = canonicalize(&coverage_config.dir)?;
+
+        let recorded = DirStatus::from_hash_file(config, &dir)?;
+
+        let unrecorded_paths: Vec<PathBuf> = config
+            .paths
+            .par_iter()
+            .filter(|path| {
+                let relative = path.strip_prefix(&coverage_config.dir).unwrap_or(path);
+                let absolute = dir.join(relative);
+
+                !recorded.contains_key(&DirStatus::normalize(&absolute))
+                    && DirStatus::from_non_file(&absolute).is_none()
+            })
+            .cloned()
+            .collect();
+
+        let mut unrecorded: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+
+        unrecorded_paths.into_iter().for_each(|path| {
+            let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            unrecorded.entry(parent).or_default().push(path);
+        });
+
+        unrecorded.values_mut().for_each(|paths| paths.sort());
+
+        Ok(Self {
+            dir,
+            num_files: config.paths.len(),
+            unrecorded,
+        })
+    }
+
+    fn print(&self, config: &Config) -> DanoResult<()> {
+        print_commentary(
+            config,
+            &format!(
+                "Coverage of {:?}, against hash file {:?}:\n",
+                self.dir, config.hash_file
+            ),
+        )?;
+
+        self.unrecorded.iter().try_for_each(|(parent, paths)| {
+            print_commentary(
+                config,
+                &format!("{:?}: {} unrecorded files\n", parent, paths.len()),
+            )?;
+
+            paths.iter().try_for_each(|path| {
+                print_outcome(config, path, "unrecorded", None, &format!("  {:?}\n", path))
+            })
+        })?;
+
+        let num_unrecorded: usize = self.unrecorded.values().map(|paths| paths.len()).sum();
+
+        print_commentary(
+            config,
+            &format!(
+                "Unrecorded: {} of {} media files, in {} directories\n",
+                num_unrecorded,
+                self.num_files,
+                self.unrecorded.len()
+            ),
+        )
+    }
+}
@@ -0,0 +1,122 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::config::{Config, SelectedStreams};
+use crate::import::Importer;
+use crate::lookup::{FileInfo, FileMetadata, HashValue};
+use crate::{DanoError, DanoResult, DANO_FILE_INFO_VERSION, HEXADECIMAL_RADIX};
+
+const CSHATAG_HASH_KEY_NAME: &str = "user.shatag.sha256";
+const CSHATAG_TIMESTAMP_KEY_NAME: &str = "user.shatag.ts";
+const CSHATAG_HASH_ALGO: &str = "SHA256";
+
+// cshatag hashes the whole file, so these records can only be verified by a whole file hash
+pub struct CshatagImporter;
+
+impl Importer for CshatagImporter {
+    fn name(&self) -> &'static str {
+        "cshatag"
+    }
+
+    fn is_valid_path(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn import(&self, _config: &Config, path: &Path) -> DanoResult<Vec<FileInfo>> {
+        let hash_string = Self::read_xattr_string(path, CSHATAG_HASH_KEY_NAME)?;
+        let timestamp_string = Self::read_xattr_string(path, CSHATAG_TIMESTAMP_KEY_NAME)?;
+
+        let modify_time = path.metadata()?.modified()?;
+
+        // cshatag records the mtime of the file when hashed, a hash for an older mtime is stale
+        if Self::parse_timestamp(&timestamp_string)? != modify_time {
+            let msg = format!(
+                "cshatag hash is stale, file has been modified since it was recorded: {:?}",
+                path
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        let hash_value =
+            if hash_string.chars().all(|c| c.is_ascii_hexdigit()) && hash_string.len() <= 128 {
+                HashValue {
+                    radix: HEXADECIMAL_RADIX,
+                    value: hash_string
+                        .to_ascii_lowercase()
+                        .trim_start_matches('0')
+                        .into(),
+                }
+            } else {
+                return Err(DanoError::new("Could not parse integer from cshatag xattr.").into());
+            };
+
+        let file_info = FileInfo {
+            path: path.to_owned(),
+            version: DANO_FILE_INFO_VERSION,
+            metadata: Some(FileMetadata {
+                last_written: SystemTime::now(),
+                hash_algo: CSHATAG_HASH_ALGO.into(),
+                hash_value,
+                modify_time,
+                selected_streams: SelectedStreams::WholeFile,
+                decoded: false,
+                opt_bits_per_second: None,
+                opt_media_info: None,
+                hardlinked: false,
+                fallback: false,
+                opt_video_format: None,
+                native: false,
+                opt_frame_digests: None,
+                opt_stream_hashes: None,
+                opt_size: None,
+                opt_last_checked: None,
+                opt_tags_hash: None,
+                cover_art_excluded: false,
+                opt_decode_format: None,
+                opt_track_hashes: None,
+            }),
+        };
+
+        Ok(vec![file_info])
+    }
+}
+
+impl CshatagImporter {
+    fn read_xattr_string(path: &Path, key: &str) -> DanoResult<String> {
+        match xattr::get(path, key)? {
+            Some(bytes) => Ok(std::str::from_utf8(&bytes)?.trim().to_owned()),
+            None => {
+                let msg = format!("No cshatag extended attribute {} for path: {:?}", key, path);
+                Err(DanoError::new(&msg).into())
+            }
+        }
+    }
+
+    // cshatag writes timestamps as "seconds.nanoseconds"
+    fn parse_timestamp(timestamp: &str) -> DanoResult<SystemTime> {
+        let (secs, nanos) = timestamp.split_once('.').unwrap_or((timestamp, "0"));
+
+        let duration = Duration::new(secs.parse::<u64>()?, nanos.parse::<u32>()?);
+
+        Ok(UNIX_EPOCH + duration)
+    }
+}
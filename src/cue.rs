@@ -0,0 +1,286 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::lookup::FileInfo;
+use crate::{DanoError, DanoResult};
+
+// positions within a CUE sheet are in CD sectors, 75 per second
+const SECTORS_PER_SECOND: u64 = 75;
+
+// the hash of the decoded audio of a single track of a CUE sheet, from the start of the track,
+// its INDEX 01, to the start of the next track, or the end of the file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TrackHash {
+    pub number: u32,
+    pub opt_title: Option<Box<str>>,
+    pub start: u64,
+    pub opt_end: Option<u64>,
+    pub hash_value: Box<str>,
+}
+
+impl TrackHash {
+    // which tracks of a mismatched file differ from the record, like "track 3 has changed"
+    pub fn describe_mismatch(current: &[TrackHash], recorded: &[TrackHash]) -> Vec<String> {
+        let changed = current.iter().filter_map(|track| {
+            match recorded.iter().find(|other| other.number == track.number) {
+                Some(other) if other == track => None,
+                Some(other) if other.start != track.start || other.opt_end != track.opt_end => {
+                    Some(format!(
+                        "track {} has moved, the CUE sheet has changed",
+                        track.number
+                    ))
+                }
+                Some(_) => Some(format!("track {} has changed", track.number)),
+                None => Some(format!("track {} is new", track.number)),
+            }
+        });
+
+        let missing = recorded
+            .iter()
+            .filter(|track| !current.iter().any(|other| other.number == track.number))
+            .map(|track| format!("track {} is missing", track.number));
+
+        changed.chain(missing).collect()
+    }
+
+    // files whose decoded audio matches a recorded track of another file, such as a track split from
+    // a single file album, or a re-rip of the same track
+    pub fn find_matches(recorded_file_info: &[FileInfo]) -> Vec<String> {
+        let albums = recorded_file_info.iter().filter_map(|album| {
+            let metadata = album.metadata.as_ref()?;
+            let tracks = metadata.opt_track_hashes.as_ref()?;
+
+            Some((album, metadata, tracks))
+        });
+
+        albums
+            .flat_map(|(album, album_metadata, tracks)| {
+                tracks.iter().flat_map(move |track| {
+                    recorded_file_info.iter().filter_map(move |file_info| {
+                        let metadata = file_info.metadata.as_ref()?;
+
+                        let is_match = file_info.path != album.path
+                            && metadata.decoded
+                            && metadata
+                                .hash_algo
+                                .eq_ignore_ascii_case(&album_metadata.hash_algo)
+                            && metadata.hash_value.value == track.hash_value;
+
+                        is_match.then(|| {
+                            format!(
+                                "{:?} matches track {} of {:?}",
+                                file_info.path, track.number, album.path
+                            )
+                        })
+                    })
+                })
+            })
+            .collect()
+    }
+}
+
+struct CueTrack {
+    number: u32,
+    opt_title: Option<Box<str>>,
+    start: u64,
+}
+
+// a CUE sheet which describes a single file album, such as "album.cue", or "album.flac.cue", beside "album.flac"
+pub struct CueSheet {
+    tracks: Vec<CueTrack>,
+}
+
+impl CueSheet {
+    pub fn find(path: &Path) -> Option<PathBuf> {
+        let mut appended = path.as_os_str().to_owned();
+        appended.push(".cue");
+
+        [path.with_extension("cue"), PathBuf::from(appended)]
+            .into_iter()
+            .find(|cue_path| cue_path.is_file())
+    }
+
+    pub fn read(cue_path: &Path) -> DanoResult<Self> {
+        let bytes = std::fs::read(cue_path)?;
+        // CUE sheets are often written in a legacy code page, but only the titles may be other than ASCII
+        let contents = String::from_utf8_lossy(&bytes);
+
+        let parse_err = |line: &str| {
+            let msg = format!("Could not parse line of CUE sheet {:?}: {}", cue_path, line);
+            DanoError::new(&msg)
+        };
+
+        let mut num_files = 0usize;
+        let mut tracks: Vec<CueTrack> = Vec::new();
+        // the most recent TRACK is an audio track, whose TITLE and INDEX lines follow
+        let mut opt_current: Option<(u32, Option<Box<str>>, Option<u64>)> = None;
+
+        let mut finish_track = |opt_current: Option<(u32, Option<Box<str>>, Option<u64>)>| {
+            let Some((number, opt_title, opt_start)) = opt_current else {
+                return Ok(());
+            };
+
+            let Some(start) = opt_start else {
+                let msg = format!(
+                    "Track {} of CUE sheet {:?} has no INDEX 01",
+                    number, cue_path
+                );
+                return Err(DanoError::new(&msg));
+            };
+
+            tracks.push(CueTrack {
+                number,
+                opt_title,
+                start,
+            });
+
+            Ok(())
+        };
+
+        for line in contents.lines().map(|line| line.trim()) {
+            let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+
+            match command.to_ascii_uppercase().as_str() {
+                "FILE" => num_files += 1,
+                "TRACK" => {
+                    finish_track(opt_current.take())?;
+
+                    let (number, track_type) =
+                        rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                    let number = number.parse::<u32>().map_err(|_| parse_err(line))?;
+
+                    // data tracks have no audio to hash
+                    if track_type.trim().eq_ignore_ascii_case("AUDIO") {
+                        opt_current = Some((number, None, None));
+                    }
+                }
+                "TITLE" => {
+                    if let Some((_, opt_title, _)) = opt_current.as_mut() {
+                        *opt_title = Some(rest.trim_matches('"').into());
+                    }
+                }
+                "INDEX" => {
+                    let (index, timestamp) =
+                        rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+                    if let Some((_, _, opt_start)) = opt_current.as_mut() {
+                        if index.parse::<u32>() == Ok(1) {
+                            *opt_start = Some(
+                                Self::parse_timestamp(timestamp.trim())
+                                    .ok_or_else(|| parse_err(line))?,
+                            );
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        finish_track(opt_current.take())?;
+
+        if num_files != 1 {
+            let msg = format!(
+                "CUE sheet does not describe a single file album: {:?}",
+                cue_path
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        if tracks.is_empty() {
+            let msg = format!("CUE sheet contains no audio tracks: {:?}", cue_path);
+            return Err(DanoError::new(&msg).into());
+        }
+
+        if tracks.windows(2).any(|pair| pair[0].start >= pair[1].start) {
+            let msg = format!("CUE sheet tracks are out of order: {:?}", cue_path);
+            return Err(DanoError::new(&msg).into());
+        }
+
+        Ok(Self { tracks })
+    }
+
+    // "mm:ss:ff", minutes, seconds, and frames, which are CD sectors
+    fn parse_timestamp(timestamp: &str) -> Option<u64> {
+        let mut fields = timestamp.split(':').map(|field| field.parse::<u64>().ok());
+
+        let (Some(Some(minutes)), Some(Some(seconds)), Some(Some(frames)), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return None;
+        };
+
+        if seconds >= 60 || frames >= SECTORS_PER_SECOND {
+            return None;
+        }
+
+        Some((minutes * 60 + seconds) * SECTORS_PER_SECOND + frames)
+    }
+
+    // each track ends where the next begins, so any pregap belongs to the track before it,
+    // as most tools split a single file album
+    pub fn regions(&self) -> impl Iterator<Item = TrackRegion> + '_ {
+        self.tracks
+            .iter()
+            .enumerate()
+            .map(|(idx, track)| TrackRegion {
+                number: track.number,
+                opt_title: track.opt_title.clone(),
+                start: track.start,
+                opt_end: self.tracks.get(idx + 1).map(|next| next.start),
+            })
+    }
+}
+
+pub struct TrackRegion {
+    pub number: u32,
+    pub opt_title: Option<Box<str>>,
+    pub start: u64,
+    pub opt_end: Option<u64>,
+}
+
+impl TrackRegion {
+    // ffmpeg's input options, "-ss" and "-t", which limit decoding to the track, as decimal seconds,
+    // which every version of ffmpeg accepts
+    pub fn as_input_args(&self) -> Vec<String> {
+        let as_seconds = |sectors: u64| {
+            let micros = (sectors * 1_000_000 + SECTORS_PER_SECOND / 2) / SECTORS_PER_SECOND;
+            format!("{}.{:06}", micros / 1_000_000, micros % 1_000_000)
+        };
+
+        let mut input_args = vec!["-ss".to_owned(), as_seconds(self.start)];
+
+        if let Some(end) = self.opt_end {
+            input_args.extend(["-t".to_owned(), as_seconds(end - self.start)]);
+        }
+
+        input_args
+    }
+
+    pub fn into_track_hash(self, hash_value: &str) -> TrackHash {
+        TrackHash {
+            number: self.number,
+            opt_title: self.opt_title,
+            start: self.start,
+            opt_end: self.opt_end,
+            hash_value: hash_value.trim_start_matches('0').into(),
+        }
+    }
+}
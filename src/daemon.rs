@@ -0,0 +1,108 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::time::{Duration, SystemTime};
+
+use crate::ingest::RecordedFileInfo;
+use crate::lock::{HashFileLock, LockKind};
+use crate::schedule::Schedule;
+use crate::utility::print_commentary;
+use crate::{Config, DanoResult};
+
+// entries are verified, and their verification recorded, in batches, so a daemon which is stopped
+// loses at most one batch of progress
+const BATCH_SIZE: usize = 100;
+
+// the daemon wakes at least this often, to find new entries in the hash file, and at most this often,
+// so a clock change cannot make it spin
+const MAX_SLEEP: Duration = Duration::from_secs(3600);
+const MIN_SLEEP: Duration = Duration::from_secs(60);
+
+pub struct ScrubDaemon;
+
+impl ScrubDaemon {
+    pub fn exec<F>(config: &Config, interval: Duration, verify_batch: F) -> DanoResult<i32>
+    where
+        F: Fn(&Config, RecordedFileInfo) -> DanoResult<i32>,
+    {
+        print_commentary(
+            config,
+            &format!(
+                "Starting scrub daemon, verifying each entry every {}.\n",
+                Self::format_duration(interval)
+            ),
+        )?;
+
+        loop {
+            // the hash file is read again each cycle, so new entries are verified in turn
+            let recorded_file_info = {
+                let _hash_file_lock = HashFileLock::acquire(config, LockKind::Shared)?;
+                RecordedFileInfo::new(config)?.into_inner()
+            };
+            let due = Schedule::new(config)?.due(recorded_file_info.clone(), interval);
+
+            if !due.is_empty() {
+                print_commentary(
+                    config,
+                    &format!("{} entries are due for verification.\n", due.len()),
+                )?;
+            }
+
+            for batch in due.chunks(BATCH_SIZE) {
+                let batch_config = Config {
+                    paths: batch
+                        .iter()
+                        .map(|file_info| file_info.path.clone())
+                        .collect(),
+                    ..config.clone()
+                };
+
+                // the batch takes the lock itself, only once it is hashed, to write back its results
+                verify_batch(&batch_config, RecordedFileInfo::from(batch.to_vec()))?;
+            }
+
+            let sleep = match Schedule::new(config)?.next_due(&recorded_file_info, interval) {
+                Some(next_due) => next_due
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default()
+                    .clamp(MIN_SLEEP, MAX_SLEEP),
+                None => MAX_SLEEP,
+            };
+
+            print_commentary(
+                config,
+                &format!(
+                    "Next check for entries due in {}.\n",
+                    Self::format_duration(sleep)
+                ),
+            )?;
+
+            std::thread::sleep(sleep);
+        }
+    }
+
+    fn format_duration(duration: Duration) -> String {
+        let secs = duration.as_secs();
+
+        match secs {
+            secs if secs >= 86_400 && secs % 86_400 == 0 => format!("{}d", secs / 86_400),
+            secs if secs >= 3600 && secs % 3600 < 60 => format!("{}h", secs / 3600),
+            secs if secs >= 3600 => format!("{}h{}m", secs / 3600, (secs % 3600) / 60),
+            secs if secs >= 60 => format!("{}m", secs / 60),
+            secs => format!("{}s", secs),
+        }
+    }
+}
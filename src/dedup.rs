@@ -0,0 +1,200 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::config::SelectedStreams;
+use crate::ingest::RecordedFileInfo;
+use crate::lookup::{FileInfo, FileInfoLookup, FileMetadata};
+use crate::mediainfo::VideoFormat;
+use crate::requests::RequestBundle;
+use crate::utility::{prepare_thread_pool, print_commentary, print_file_info};
+use crate::{Config, DanoResult, DANO_CLEAN_EXIT_CODE, DANO_DISORDER_EXIT_CODE};
+
+// a strong algorithm, which every version of ffmpeg, and dano itself, implements
+const COMMON_HASH_ALGO: &str = "SHA256";
+
+// records may only be compared when the same content was hashed the same way, decoded or copied,
+// and with the same streams, whatever the algorithm
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ContentForm {
+    decoded: bool,
+    selected_streams: SelectedStreams,
+    opt_bits_per_second: Option<u32>,
+    opt_video_format: Option<VideoFormat>,
+}
+
+impl From<&FileMetadata> for ContentForm {
+    fn from(metadata: &FileMetadata) -> Self {
+        Self {
+            decoded: metadata.decoded,
+            selected_streams: metadata.selected_streams.clone(),
+            opt_bits_per_second: metadata.opt_bits_per_second,
+            opt_video_format: metadata.opt_video_format.clone(),
+        }
+    }
+}
+
+// records keyed by algorithm and hash value, so each group is already known to share content
+type AlgoGroups = BTreeMap<(Box<str>, Box<str>), Vec<FileInfo>>;
+
+// a catalog built over years may record the same content with different algorithms, so
+// duplicates are found by hashing one candidate of each group again, with a common algorithm
+pub struct CrossAlgoDuplicates;
+
+impl CrossAlgoDuplicates {
+    pub fn exec(config: &Config, recorded_file_info: RecordedFileInfo) -> DanoResult<i32> {
+        let mut forms: Vec<(ContentForm, AlgoGroups)> = Vec::new();
+
+        recorded_file_info
+            .into_inner()
+            .into_iter()
+            .for_each(|file_info| {
+                let Some(metadata) = &file_info.metadata else {
+                    return;
+                };
+
+                let form = ContentForm::from(metadata);
+                let key = (
+                    metadata.hash_algo.to_ascii_uppercase().into_boxed_str(),
+                    metadata.hash_value.value.clone(),
+                );
+
+                let groups = match forms.iter_mut().position(|(other, _)| other == &form) {
+                    Some(idx) => &mut forms[idx].1,
+                    None => {
+                        forms.push((form, BTreeMap::new()));
+                        &mut forms.last_mut().unwrap().1
+                    }
+                };
+
+                groups.entry(key).or_default().push(file_info);
+            });
+
+        // only a form recorded with more than one algorithm requires any hashing
+        let candidates: Vec<FileInfo> = forms
+            .iter()
+            .filter(|(_form, groups)| {
+                groups
+                    .keys()
+                    .map(|(algo, _value)| algo)
+                    .collect::<BTreeSet<_>>()
+                    .len()
+                    > 1
+            })
+            .flat_map(|(_form, groups)| {
+                groups
+                    .iter()
+                    .filter(|((algo, _value), _group)| algo.as_ref() != COMMON_HASH_ALGO)
+                    .map(|(_key, group)| group[0].clone())
+            })
+            .collect();
+
+        let common_values = Self::hash_candidates(config, &candidates)?;
+
+        let duplicates: Vec<Vec<FileInfo>> = forms
+            .into_iter()
+            .flat_map(|(form, groups)| {
+                let mut merged: BTreeMap<Box<str>, Vec<FileInfo>> = BTreeMap::new();
+
+                groups.into_iter().for_each(|((algo, value), group)| {
+                    let opt_common_value = if algo.as_ref() == COMMON_HASH_ALGO {
+                        Some(value.clone())
+                    } else {
+                        common_values
+                            .iter()
+                            .find(|(file_info, _)| {
+                                file_info.path == group[0].path
+                                    && file_info
+                                        .metadata
+                                        .as_ref()
+                                        .is_some_and(|metadata| ContentForm::from(metadata) == form)
+                            })
+                            .map(|(_, common_value)| common_value.clone())
+                    };
+
+                    // a group which was not hashed again can only be a duplicate of itself
+                    let merge_key = match opt_common_value {
+                        Some(common_value) => common_value,
+                        None => format!("{}={}", algo, value).into_boxed_str(),
+                    };
+
+                    merged.entry(merge_key).or_default().extend(group);
+                });
+
+                merged.into_values().filter(|group| group.len() > 1)
+            })
+            .collect();
+
+        if duplicates.is_empty() {
+            if !config.opt_silent {
+                eprintln!("No duplicates found.");
+            }
+            return Ok(DANO_CLEAN_EXIT_CODE);
+        }
+
+        duplicates.iter().try_for_each(|group| {
+            print_commentary(
+                config,
+                &format!("Duplicate content, {} files:\n", group.len()),
+            )?;
+            group
+                .iter()
+                .try_for_each(|file_info| print_file_info(config, file_info))
+        })?;
+
+        if !config.opt_silent {
+            eprintln!("WARN: Duplicates found.");
+        }
+
+        Ok(DANO_DISORDER_EXIT_CODE)
+    }
+
+    // each candidate is hashed in its recorded form, but with the common algorithm
+    fn hash_candidates(
+        config: &Config,
+        candidates: &[FileInfo],
+    ) -> DanoResult<Vec<(FileInfo, Box<str>)>> {
+        if candidates.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (present, missing): (Vec<FileInfo>, Vec<FileInfo>) = candidates
+            .iter()
+            .cloned()
+            .partition(|file_info| file_info.path.exists());
+
+        missing.iter().for_each(|file_info| {
+            eprintln!(
+                "WARN: {:?}: Path does not exist, and cannot be compared across hash algorithms.",
+                file_info.path
+            )
+        });
+
+        let thread_pool = prepare_thread_pool(config)?;
+        let file_info_requests = RequestBundle::with_hash_algo(config, &present, COMMON_HASH_ALGO);
+        let (rx_item, _rx_unsupported) =
+            FileInfoLookup::exec(config, file_info_requests, thread_pool)?;
+
+        Ok(rx_item
+            .iter()
+            .filter_map(|file_info| {
+                let common_value = file_info.metadata.as_ref()?.hash_value.value.clone();
+                Some((file_info, common_value))
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,200 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use crate::config::DiffConfig;
+use crate::lookup::{FileInfo, FileMetadata};
+use crate::utility::{hash_file_exists, print_commentary, print_outcome, read_file_info_from_path};
+use crate::{Config, DanoError, DanoResult, DANO_CLEAN_EXIT_CODE, DANO_DISORDER_EXIT_CODE};
+
+// a hash value is only comparable to another of the same algorithm
+type HashKey = (Box<str>, Box<str>);
+
+// compares two sets of recorded file info, such as archival snapshots of a library, only by their records,
+// so neither ffmpeg nor the files themselves are required
+pub struct HashFileDiff {
+    added: Vec<PathBuf>,
+    removed: Vec<PathBuf>,
+    renamed: Vec<(PathBuf, PathBuf)>,
+    changed: Vec<PathBuf>,
+}
+
+impl HashFileDiff {
+    pub fn exec(config: &Config, diff_config: &DiffConfig) -> DanoResult<i32> {
+        let old = Self::read(&diff_config.old)?;
+        let new = Self::read(&diff_config.new)?;
+
+        let diff = Self::new(&old, &new);
+
+        diff.print(config)?;
+
+        if diff.added.len() + diff.removed.len() + diff.renamed.len() + diff.changed.len() == 0 {
+            Ok(DANO_CLEAN_EXIT_CODE)
+        } else {
+            Ok(DANO_DISORDER_EXIT_CODE)
+        }
+    }
+
+    // a path may be recorded in more than one form, such as both decoded and copied
+    fn read(hash_file: &Path) -> DanoResult<BTreeMap<PathBuf, Vec<FileMetadata>>> {
+        if !hash_file_exists(hash_file) {
+            let msg = format!("Hash file to compare does not exist: {:?}", hash_file);
+            return Err(DanoError::new(&msg).into());
+        }
+
+        let mut recorded: BTreeMap<PathBuf, Vec<FileMetadata>> = BTreeMap::new();
+
+        read_file_info_from_path(hash_file)?
+            .into_iter()
+            .filter_map(|file_info: FileInfo| Some((file_info.path, file_info.metadata?)))
+            .for_each(|(path, metadata)| recorded.entry(path).or_default().push(metadata));
+
+        Ok(recorded)
+    }
+
+    fn hash_key(metadata: &FileMetadata) -> HashKey {
+        (
+            metadata.hash_algo.to_ascii_lowercase().into(),
+            metadata.hash_value.value.clone(),
+        )
+    }
+
+    // forms of the same path are comparable when hashed the same way, and a path has changed
+    // when any comparable forms disagree
+    fn is_changed(old_forms: &[FileMetadata], new_forms: &[FileMetadata]) -> bool {
+        old_forms.iter().any(|old| {
+            new_forms.iter().any(|new| {
+                old.hash_algo.eq_ignore_ascii_case(&new.hash_algo)
+                    && old.decoded == new.decoded
+                    && old.selected_streams == new.selected_streams
+                    && old.hash_value != new.hash_value
+            })
+        })
+    }
+
+    fn new(
+        old: &BTreeMap<PathBuf, Vec<FileMetadata>>,
+        new: &BTreeMap<PathBuf, Vec<FileMetadata>>,
+    ) -> Self {
+        let changed: Vec<PathBuf> = old
+            .iter()
+            .filter_map(|(path, old_forms)| {
+                let new_forms = new.get(path)?;
+                Self::is_changed(old_forms, new_forms).then(|| path.clone())
+            })
+            .collect();
+
+        // a path only in the old set, whose hash reappears under a path only in the new set, was renamed
+        let mut removed_by_hash: BTreeMap<HashKey, &Path> = BTreeMap::new();
+
+        old.iter()
+            .filter(|(path, _old_forms)| !new.contains_key(*path))
+            .for_each(|(path, old_forms)| {
+                old_forms.iter().for_each(|metadata| {
+                    removed_by_hash
+                        .entry(Self::hash_key(metadata))
+                        .or_insert(path.as_path());
+                })
+            });
+
+        let mut renamed: Vec<(PathBuf, PathBuf)> = Vec::new();
+        let mut renamed_from: BTreeSet<&Path> = BTreeSet::new();
+        let mut added: Vec<PathBuf> = Vec::new();
+
+        new.iter()
+            .filter(|(path, _new_forms)| !old.contains_key(*path))
+            .for_each(|(path, new_forms)| {
+                let opt_old_path = new_forms.iter().find_map(|metadata| {
+                    removed_by_hash
+                        .get(&Self::hash_key(metadata))
+                        .filter(|old_path| !renamed_from.contains(*old_path))
+                        .copied()
+                });
+
+                match opt_old_path {
+                    Some(old_path) => {
+                        renamed_from.insert(old_path);
+                        renamed.push((old_path.to_path_buf(), path.clone()));
+                    }
+                    None => added.push(path.clone()),
+                }
+            });
+
+        let removed: Vec<PathBuf> = old
+            .keys()
+            .filter(|path| !new.contains_key(*path) && !renamed_from.contains(path.as_path()))
+            .cloned()
+            .collect();
+
+        Self {
+            added,
+            removed,
+            renamed,
+            changed,
+        }
+    }
+
+    fn print(&self, config: &Config) -> DanoResult<()> {
+        self.added.iter().try_for_each(|path| {
+            print_outcome(config, path, "added", None, &format!("Added: {:?}\n", path))
+        })?;
+
+        self.removed.iter().try_for_each(|path| {
+            print_outcome(
+                config,
+                path,
+                "removed",
+                None,
+                &format!("Removed: {:?}\n", path),
+            )
+        })?;
+
+        self.renamed.iter().try_for_each(|(old_path, new_path)| {
+            print_outcome(
+                config,
+                new_path,
+                "renamed",
+                None,
+                &format!("Renamed: {:?} -> {:?}\n", old_path, new_path),
+            )
+        })?;
+
+        self.changed.iter().try_for_each(|path| {
+            print_outcome(
+                config,
+                path,
+                "changed",
+                None,
+                &format!("WARN: Changed hash: {:?}\n", path),
+            )
+        })?;
+
+        print_commentary(
+            config,
+            &format!(
+                "Added: {} files, Removed: {} files, Renamed: {} files, Changed hash: {} files\n",
+                self.added.len(),
+                self.removed.len(),
+                self.renamed.len(),
+                self.changed.len()
+            ),
+        )
+    }
+}
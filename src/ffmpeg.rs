@@ -0,0 +1,238 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fmt,
+    io::ErrorKind,
+    path::Path,
+    process::{Command as ExecProcess, ExitStatus},
+};
+
+use rayon::prelude::*;
+
+use crate::probe::FFProbe;
+use crate::{DanoError, DanoResult};
+
+// ffmpeg's own messages are not translated, but messages which describe an OS error, like
+// "Permission denied", come from strerror, and so ffmpeg is always run in the C locale, so its messages read the same everywhere
+pub const FFMPEG_LOCALE: [(&str, &str); 2] = [("LC_ALL", "C"), ("LANG", "C")];
+
+// quiet ffmpeg, so stderr contains only errors, and never wait on stdin
+pub const FFMPEG_QUIET_ARGS: [&str; 4] = ["-nostdin", "-hide_banner", "-loglevel", "error"];
+
+// the hash algorithms of the hash muxer, as of ffmpeg 7, though an older ffmpeg may support fewer
+const FFMPEG_HASH_ALGOS: [&str; 15] = [
+    "MD5",
+    "murmur3",
+    "RIPEMD128",
+    "RIPEMD160",
+    "RIPEMD256",
+    "RIPEMD320",
+    "SHA160",
+    "SHA224",
+    "SHA256",
+    "SHA512/224",
+    "SHA512/256",
+    "SHA384",
+    "SHA512",
+    "CRC32",
+    "adler32",
+];
+
+// a tiny generated sample, so a hash algorithm may be tried without any input file
+const FFMPEG_SAMPLE_ARGS: [&str; 4] = ["-f", "lavfi", "-i", "anullsrc=d=0.01"];
+
+// ffmpeg exits with 255 when interrupted by a signal
+const FFMPEG_INTERRUPTED_EXIT_CODE: i32 = 255;
+
+// ffmpeg's own error codes, AVERROR, are negated four character tags
+const fn fferrtag(tag: [u8; 4]) -> i64 {
+    -(u32::from_le_bytes(tag) as i64)
+}
+
+// an input without the selected streams fails as ffmpeg would fail to find them
+pub const AVERROR_STREAM_NOT_FOUND: i64 = fferrtag([0xF8, b'S', b'T', b'R']);
+
+// errors of an input which cannot be demuxed or decoded, and so will recur on every attempt
+const UNSUPPORTED_ERROR_CODES: [i64; 5] = [
+    fferrtag(*b"INDA"),
+    fferrtag([0xF8, b'D', b'E', b'C']),
+    fferrtag([0xF8, b'D', b'E', b'M']),
+    AVERROR_STREAM_NOT_FOUND,
+    fferrtag(*b"PAWE"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FFmpegErrorKind {
+    // will recur on every attempt, until the file itself changes
+    Unsupported,
+    InvalidHashAlgo,
+    NotFound,
+    PermissionDenied,
+    Interrupted,
+    Other,
+}
+
+// an ffmpeg failure, classified once, when ffmpeg exits, so callers match on the kind,
+// rather than on the text of the message
+#[derive(Debug, Clone)]
+pub struct FFmpegError {
+    pub kind: FFmpegErrorKind,
+    pub details: String,
+}
+
+impl FFmpegError {
+    // ffmpeg's messages are for the user, and are never parsed.  instead, a failure is classified by its exit status,
+    // and then by the error code ffprobe reports when it opens the same input and selects the same streams
+    pub fn new(
+        status: ExitStatus,
+        stderr: &str,
+        input: &Path,
+        opt_stream_specifier: Option<&str>,
+    ) -> Self {
+        let kind = match status.code() {
+            None | Some(FFMPEG_INTERRUPTED_EXIT_CODE) => FFmpegErrorKind::Interrupted,
+            _ => Self::classify_input(input, opt_stream_specifier),
+        };
+
+        let details = match (kind, stderr.is_empty()) {
+            (FFmpegErrorKind::Interrupted, true) => "ffmpeg was interrupted".to_owned(),
+            (_, true) => format!("ffmpeg exited with an error, but no message: {}", status),
+            (_, false) => stderr.to_owned(),
+        };
+
+        Self { kind, details }
+    }
+
+    fn classify_input(input: &Path, opt_stream_specifier: Option<&str>) -> FFmpegErrorKind {
+        // if ffprobe is unavailable, the failure cannot be classified
+        let Ok(Some(code)) = FFProbe::error_code(input, opt_stream_specifier) else {
+            return FFmpegErrorKind::Other;
+        };
+
+        if UNSUPPORTED_ERROR_CODES.contains(&code) {
+            return FFmpegErrorKind::Unsupported;
+        }
+
+        // other error codes are negated OS error numbers
+        let Ok(os_error) = i32::try_from(-code) else {
+            return FFmpegErrorKind::Other;
+        };
+
+        match std::io::Error::from_raw_os_error(os_error).kind() {
+            ErrorKind::NotFound => FFmpegErrorKind::NotFound,
+            ErrorKind::PermissionDenied => FFmpegErrorKind::PermissionDenied,
+            ErrorKind::Unsupported => FFmpegErrorKind::Unsupported,
+            _ => FFmpegErrorKind::Other,
+        }
+    }
+
+    pub fn kind_of(err: &(dyn Error + Send + Sync + 'static)) -> Option<FFmpegErrorKind> {
+        err.downcast_ref::<FFmpegError>().map(|err| err.kind)
+    }
+}
+
+impl fmt::Display for FFmpegError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.details)
+    }
+}
+
+impl Error for FFmpegError {}
+
+pub struct FFmpegCapabilities;
+
+impl FFmpegCapabilities {
+    pub fn is_ffmpeg_hash_algo(hash_algo: &str) -> bool {
+        FFMPEG_HASH_ALGOS
+            .iter()
+            .any(|ffmpeg_hash_algo| ffmpeg_hash_algo.eq_ignore_ascii_case(hash_algo))
+    }
+
+    fn supports_hash_algo(ffmpeg_command: &Path, hash_algo: &str) -> bool {
+        ExecProcess::new(ffmpeg_command)
+            .envs(FFMPEG_LOCALE)
+            .args(FFMPEG_QUIET_ARGS)
+            .args(FFMPEG_SAMPLE_ARGS)
+            .args(["-f", "hash", "-hash", hash_algo, "-"])
+            .output()
+            .is_ok_and(|output| {
+                output.status.success()
+                    && parse_hash_line(&String::from_utf8_lossy(&output.stdout)).is_some()
+            })
+    }
+
+    // an ffmpeg built without lavfi cannot generate the sample, and so cannot be said to reject any hash algorithm
+    pub fn rejects_hash_algo(ffmpeg_command: &Path, hash_algo: &str) -> bool {
+        !Self::supports_hash_algo(ffmpeg_command, hash_algo)
+            && Self::supports_hash_algo(ffmpeg_command, "MD5")
+    }
+
+    // each requested hash algorithm is tried once, on a generated sample, before any file is hashed
+    pub fn check_hash_algos<'a>(
+        ffmpeg_command: &Path,
+        hash_algos: impl Iterator<Item = &'a str>,
+    ) -> DanoResult<()> {
+        let requested: BTreeSet<&str> = hash_algos.collect();
+
+        let unsupported: Vec<&str> = requested
+            .into_par_iter()
+            .filter(|hash_algo| !Self::supports_hash_algo(ffmpeg_command, hash_algo))
+            .collect();
+
+        if unsupported.is_empty() {
+            return Ok(());
+        }
+
+        // an ffmpeg built without lavfi cannot generate the sample, and so the check is inconclusive
+        if !Self::supports_hash_algo(ffmpeg_command, "MD5") {
+            return Ok(());
+        }
+
+        let supported: Vec<&str> = FFMPEG_HASH_ALGOS
+            .into_par_iter()
+            .filter(|hash_algo| Self::supports_hash_algo(ffmpeg_command, hash_algo))
+            .collect();
+
+        let msg = format!(
+            "The installed ffmpeg, {:?}, does not support the requested hash algorithm: {}.  Supported hash algorithms are: {}.",
+            ffmpeg_command,
+            unsupported.join(", "),
+            supported.join(", ")
+        );
+
+        Err(DanoError::new(&msg).into())
+    }
+}
+
+// the hash muxer prints a single "ALGO=hex" line, but stdout may contain other noise,
+// so the last line in that form is the hash
+pub fn parse_hash_line(stdout: &str) -> Option<(&str, &str)> {
+    stdout.lines().rev().find_map(|line| {
+        let (algo, value) = line.trim().split_once('=')?;
+
+        let is_algo = !algo.is_empty()
+            && algo
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "/-_".contains(c));
+        let is_value =
+            !value.is_empty() && value.len() <= 128 && value.chars().all(|c| c.is_ascii_hexdigit());
+
+        (is_algo && is_value).then_some((algo, value))
+    })
+}
@@ -15,131 +15,173 @@
 // For the full copyright and license information, please view the LICENSE file
 // that was distributed with this source code.
 
-use std::str::FromStr;
-use std::{path::Path, process::Command as ExecProcess, time::SystemTime};
+use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+    time::SystemTime,
+};
 
 use rayon::prelude::*;
-use which::which;
 
-use crate::config::SelectedStreams;
+use crate::config::{Config, SelectedStreams};
+use crate::import::Importer;
+use crate::ingest::RecordedFileInfo;
 use crate::lookup::HashValue;
-use crate::lookup::{FileInfo, FileMetadata};
+use crate::lookup::{FileInfo, FileInfoLookup, FileMetadata};
+use crate::requests::RequestBundle;
+use crate::utility::{prepare_thread_pool, print_err_buf, print_outcome};
 use crate::{
-    Config, DanoError, DanoResult, RecordedFileInfo, DANO_FILE_INFO_VERSION, HEXADECIMAL_RADIX,
+    DanoError, DanoResult, DANO_CLEAN_EXIT_CODE, DANO_DISORDER_EXIT_CODE, DANO_FILE_INFO_VERSION,
+    HEXADECIMAL_RADIX,
 };
 
 const FLAC_HASH_ALGO: &str = "MD5";
 const FLAC_DECODED: bool = true;
 const FLAC_SELECTED_STREAMS: SelectedStreams = SelectedStreams::AudioOnly;
 
-impl RecordedFileInfo {
-    pub fn from_flac(config: &Config) -> DanoResult<Vec<FileInfo>> {
-        config
-            .paths
-            .par_iter()
-            .flat_map(|path| match path.extension() {
-                Some(extension) if extension.to_ascii_lowercase() == "flac" => Some(path),
-                _ => {
-                    eprintln!("ERROR: {:?} does not have a valid FLAC extension", path);
-                    None
-                }
-            })
-            .map(|path| {
-                Self::generate_flac_file_info(
-                    path,
-                    Self::import_flac_hash_value(path)?,
-                    Self::import_flac_bps_value(path)?,
-                )
-            })
-            .collect()
+const FLAC_MARKER: &[u8; 4] = b"fLaC";
+const STREAMINFO_BLOCK_TYPE: u8 = 0;
+const STREAMINFO_LEN: usize = 34;
+const STREAMINFO_MD5_OFFSET: usize = 18;
+
+const ID3V2_MARKER: &[u8; 3] = b"ID3";
+const ID3V2_HEADER_LEN: usize = 10;
+const ID3V2_FOOTER_FLAG: u8 = 0x10;
+
+pub struct FlacImporter;
+
+pub struct FlacStreamInfo {
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub bits_per_sample: u32,
+    pub total_samples: u64,
+}
+
+impl Importer for FlacImporter {
+    fn name(&self) -> &'static str {
+        "flac"
+    }
+
+    fn is_valid_path(&self, path: &Path) -> bool {
+        matches!(path.extension(), Some(extension) if extension.eq_ignore_ascii_case("flac"))
+    }
+
+    fn import(&self, _config: &Config, path: &Path) -> DanoResult<Vec<FileInfo>> {
+        let (hash_value, bps_value) = Self::import_flac_values(path)?;
+        let file_info = Self::generate_flac_file_info(path, hash_value, bps_value)?;
+
+        Ok(vec![file_info])
     }
+}
+
+impl FlacImporter {
+    // the MD5 sum of the decoded audio, and the bits per sample, both from the STREAMINFO block
+    fn import_flac_values(path: &Path) -> DanoResult<(HashValue, u32)> {
+        let streaminfo = Self::read_streaminfo(path)?;
+
+        // an encoder which did not compute the MD5 sum leaves it all zeros, which trims to an empty value
+        let md5_string: String = streaminfo[STREAMINFO_MD5_OFFSET..STREAMINFO_LEN]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
 
-    fn import_flac_hash_value(path: &Path) -> DanoResult<HashValue> {
-        let metaflac_cmd = if let Ok(metaflac_cmd) = which("metaflac") {
-            metaflac_cmd
-        } else {
-            return Err(DanoError::new(
-                "'metaflac' command not found. Make sure the command 'metaflac' is in your path.",
-            )
-            .into());
+        let hash_value = HashValue {
+            radix: HEXADECIMAL_RADIX,
+            value: md5_string.trim_start_matches('0').into(),
         };
 
-        // all snapshots should have the same timestamp
-        let path_string = path.to_string_lossy();
+        Ok((hash_value, Self::parse_flac_bps_value(&streaminfo)))
+    }
 
-        let process_args = vec!["--show-md5sum", path_string.as_ref()];
+    pub fn import_flac_bps_value(path: &Path) -> DanoResult<u32> {
+        let streaminfo = Self::read_streaminfo(path)?;
 
-        let process_output = ExecProcess::new(metaflac_cmd)
-            .args(&process_args)
-            .output()?;
-        let stdout_string = std::str::from_utf8(&process_output.stdout)?.trim();
-        let stderr_string = std::str::from_utf8(&process_output.stderr)?.trim();
+        Ok(Self::parse_flac_bps_value(&streaminfo))
+    }
 
-        if stderr_string.contains("FLAC__METADATA_CHAIN_STATUS_NOT_A_FLAC_FILE") {
-            let msg = format!("Path is not a valid FLAC file: {}", path_string);
-            return Err(DanoError::new(&msg).into());
-        }
+    // the layout of the audio, as recorded in STREAMINFO, without decoding the file
+    pub fn import_stream_info(path: &Path) -> DanoResult<FlacStreamInfo> {
+        let streaminfo = Self::read_streaminfo(path)?;
 
-        let hash_value =
-            if stdout_string.chars().all(|c| c.is_ascii_hexdigit()) && stdout_string.len() <= 128 {
-                HashValue {
-                    radix: HEXADECIMAL_RADIX,
-                    value: stdout_string.trim_start_matches('0').into(),
-                }
-            } else {
-                return Err(DanoError::new("Could not parse integer from ffmpeg output.").into());
-            };
+        // the sample rate is 20 bits, the channel count, less one, is the next 3 bits,
+        // and the total count of samples, per channel, is the 36 bits which follow the bits per sample
+        let sample_rate = ((streaminfo[10] as u32) << 12)
+            | ((streaminfo[11] as u32) << 4)
+            | ((streaminfo[12] as u32) >> 4);
+        let channels = ((streaminfo[12] >> 1) & 0x07) as u32 + 1;
+        let total_samples = streaminfo[14..18]
+            .iter()
+            .fold((streaminfo[13] & 0x0f) as u64, |acc, byte| {
+                (acc << 8) | *byte as u64
+            });
 
-        if stdout_string.is_empty() {
-            // likely file DNE?, except we have already check when we parsed input files
-            // so this is a catch all, here we just bail if we have no explanation to give the user
-            let msg = format!("Could not generate hash from FLAC file: {}", path_string);
-            return Err(DanoError::new(&msg).into());
-        }
+        Ok(FlacStreamInfo {
+            sample_rate,
+            channels,
+            bits_per_sample: Self::parse_flac_bps_value(&streaminfo),
+            total_samples,
+        })
+    }
 
-        Ok(hash_value)
+    // bits per sample, less one, is 5 bits, from the last bit of byte 12 through the first 4 bits of byte 13
+    fn parse_flac_bps_value(streaminfo: &[u8; STREAMINFO_LEN]) -> u32 {
+        let bps_minus_one = ((streaminfo[12] & 0x01) << 4) | (streaminfo[13] >> 4);
+
+        bps_minus_one as u32 + 1
     }
 
-    pub fn import_flac_bps_value(path: &Path) -> DanoResult<u32> {
-        let metaflac_cmd = if let Ok(metaflac_cmd) = which("metaflac") {
-            metaflac_cmd
-        } else {
-            return Err(DanoError::new(
-                "'metaflac' command not found. Make sure the command 'metaflac' is in your path.",
-            )
-            .into());
+    // STREAMINFO is always the first metadata block, immediately after the "fLaC" marker,
+    // which itself may follow an ID3v2 tag, written by some taggers
+    fn read_streaminfo(path: &Path) -> DanoResult<[u8; STREAMINFO_LEN]> {
+        let not_flac = || {
+            let msg = format!("Path is not a valid FLAC file: {}", path.to_string_lossy());
+            DanoError::new(&msg)
         };
 
-        // all snapshots should have the same timestamp
-        let path_string = path.to_string_lossy();
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut marker = [0u8; 4];
+        reader.read_exact(&mut marker).map_err(|_| not_flac())?;
 
-        let process_args = vec!["--show-bps", path_string.as_ref()];
+        if marker[0..3] == *ID3V2_MARKER {
+            let mut id3_header = [0u8; ID3V2_HEADER_LEN - 4];
+            reader.read_exact(&mut id3_header).map_err(|_| not_flac())?;
 
-        let process_output = ExecProcess::new(metaflac_cmd)
-            .args(&process_args)
-            .output()?;
-        let stdout_string = std::str::from_utf8(&process_output.stdout)?.trim();
-        let stderr_string = std::str::from_utf8(&process_output.stderr)?.trim();
+            // the tag size is "syncsafe", 7 bits of each byte, and excludes the header, and the footer, if present
+            let tag_size = id3_header[2..6]
+                .iter()
+                .fold(0u64, |acc, byte| (acc << 7) | (*byte & 0x7f) as u64);
+            let footer_size = if id3_header[1] & ID3V2_FOOTER_FLAG != 0 {
+                ID3V2_HEADER_LEN as u64
+            } else {
+                0
+            };
 
-        if stderr_string.contains("FLAC__METADATA_CHAIN_STATUS_NOT_A_FLAC_FILE") {
-            let msg = format!("Path is not a valid FLAC file: {}", path_string);
-            return Err(DanoError::new(&msg).into());
+            reader.seek_relative((tag_size + footer_size) as i64)?;
+            reader.read_exact(&mut marker).map_err(|_| not_flac())?;
         }
 
-        let bps_value = if let Ok(bps) = std::primitive::u32::from_str(stdout_string) {
-            bps
-        } else {
-            return Err(DanoError::new("Could not parse integer from ffmpeg output.").into());
-        };
+        if marker != *FLAC_MARKER {
+            return Err(not_flac().into());
+        }
+
+        let mut block_header = [0u8; 4];
+        reader
+            .read_exact(&mut block_header)
+            .map_err(|_| not_flac())?;
 
-        if stdout_string.is_empty() {
-            // likely file DNE?, except we have already check when we parsed input files
-            // so this is a catch all, here we just bail if we have no explanation to give the user
-            let msg = format!("Could not generate hash from FLAC file: {}", path_string);
-            return Err(DanoError::new(&msg).into());
+        let block_type = block_header[0] & 0x7f;
+        let block_len = u32::from_be_bytes([0, block_header[1], block_header[2], block_header[3]]);
+
+        if block_type != STREAMINFO_BLOCK_TYPE || block_len as usize != STREAMINFO_LEN {
+            return Err(not_flac().into());
         }
 
-        Ok(bps_value)
+        let mut streaminfo = [0u8; STREAMINFO_LEN];
+        reader.read_exact(&mut streaminfo).map_err(|_| not_flac())?;
+
+        Ok(streaminfo)
     }
 
     fn generate_flac_file_info(
@@ -158,7 +200,179 @@ impl RecordedFileInfo {
                 selected_streams: FLAC_SELECTED_STREAMS,
                 decoded: FLAC_DECODED,
                 opt_bits_per_second: Some(bps_value),
+                opt_media_info: None,
+                hardlinked: false,
+                fallback: false,
+                opt_video_format: None,
+                native: false,
+                opt_frame_digests: None,
+                opt_stream_hashes: None,
+                opt_size: None,
+                opt_last_checked: None,
+                opt_tags_hash: None,
+                cover_art_excluded: false,
+                opt_decode_format: None,
+                opt_track_hashes: None,
             }),
         })
     }
 }
+
+// like "flac -t", but on many threads: each FLAC file is decoded by ffmpeg, at its own bit depth,
+// and the MD5 sum of the decoded audio is compared to the MD5 sum embedded in the file's STREAMINFO block
+pub struct FlacVerification;
+
+impl FlacVerification {
+    pub fn exec(config: &Config, recorded_file_info: RecordedFileInfo) -> DanoResult<i32> {
+        let (flac_paths, other_paths): (Vec<&Path>, Vec<&Path>) = config
+            .paths
+            .iter()
+            .map(|path| path.as_path())
+            .partition(|path| FlacImporter.is_valid_path(path));
+
+        if !config.opt_silent {
+            other_paths.iter().for_each(|path| {
+                eprintln!(
+                    "WARN: Path is not a FLAC file, and has been skipped: {:?}",
+                    path
+                )
+            });
+        }
+
+        let embedded_results: Vec<(&Path, DanoResult<Vec<FileInfo>>)> = flac_paths
+            .par_iter()
+            .map(|path| (*path, FlacImporter.import(config, path)))
+            .collect();
+
+        let mut num_failed = 0usize;
+        let mut embedded: Vec<FileInfo> = Vec::new();
+
+        embedded_results
+            .into_iter()
+            .for_each(|(path, res)| match res {
+                Ok(file_info) => embedded.extend(file_info),
+                Err(err) => {
+                    eprintln!("ERROR: {:?}: {}", path, err);
+                    num_failed += 1;
+                }
+            });
+
+        // an encoder may leave the MD5 sum unset, all zeros, and such a file cannot be verified
+        let (embedded, unset): (Vec<FileInfo>, Vec<FileInfo>) =
+            embedded.into_iter().partition(|file_info| {
+                file_info
+                    .metadata
+                    .as_ref()
+                    .is_some_and(|metadata| !metadata.hash_value.value.is_empty())
+            });
+
+        if !config.opt_silent {
+            unset.iter().for_each(|file_info| {
+                eprintln!(
+                    "WARN: {:?}: No MD5 sum is embedded in STREAMINFO, so the file cannot be verified.",
+                    file_info.path
+                )
+            });
+        }
+
+        let thread_pool = prepare_thread_pool(config)?;
+        let (rx_item, _rx_unsupported) = FileInfoLookup::exec(
+            config,
+            RequestBundle::from_recorded(config, &embedded),
+            thread_pool,
+        )?;
+
+        let hashed: BTreeMap<_, _> = rx_item
+            .iter()
+            .filter_map(|file_info| Some((file_info.path, file_info.metadata?)))
+            .collect();
+
+        let mut verified: BTreeMap<&Path, &HashValue> = BTreeMap::new();
+
+        embedded.iter().try_for_each(|file_info| -> DanoResult<()> {
+            let Some(metadata) = file_info.metadata.as_ref() else {
+                return Ok(());
+            };
+
+            match hashed.get(&file_info.path) {
+                Some(decoded) if decoded.hash_value == metadata.hash_value => {
+                    verified.insert(&file_info.path, &metadata.hash_value);
+
+                    if config.opt_silent {
+                        return Ok(());
+                    }
+
+                    print_outcome(
+                        config,
+                        &file_info.path,
+                        "ok",
+                        Some(metadata),
+                        &format!("{:?}: OK\n", file_info.path),
+                    )
+                }
+                Some(decoded) => {
+                    num_failed += 1;
+
+                    print_outcome(
+                        config,
+                        &file_info.path,
+                        "mismatch",
+                        Some(decoded),
+                        &format!(
+                            "WARN: {:?}: The decoded audio does not match the MD5 sum embedded in STREAMINFO.\n",
+                            file_info.path
+                        ),
+                    )
+                }
+                None => {
+                    num_failed += 1;
+
+                    print_outcome(
+                        config,
+                        &file_info.path,
+                        "error",
+                        None,
+                        &format!("WARN: {:?}: The file could not be decoded.\n", file_info.path),
+                    )
+                }
+            }
+        })?;
+
+        // a record of the same decoded audio stream, with the same MD5 sum, is checked as well
+        let checked: Vec<FileInfo> = recorded_file_info
+            .iter()
+            .filter(|file_info| {
+                matches!((verified.get(file_info.path.as_path()), &file_info.metadata), (Some(hash_value), Some(metadata))
+                    if metadata.hash_algo.eq_ignore_ascii_case(FLAC_HASH_ALGO)
+                        && metadata.decoded == FLAC_DECODED
+                        && metadata.selected_streams == FLAC_SELECTED_STREAMS
+                        && metadata.hash_value == **hash_value)
+            })
+            .cloned()
+            .collect();
+
+        let test_results = checked
+            .iter()
+            .map(|file_info| (file_info.path.clone(), DANO_CLEAN_EXIT_CODE))
+            .collect();
+
+        crate::record_checked(config, checked, &test_results, &BTreeSet::new());
+
+        if num_failed > 0 {
+            print_err_buf(&format!(
+                "FAILED: {} FLAC files were verified, but {} FLAC files did not match, or could not be decoded.\n",
+                verified.len(),
+                num_failed
+            ))?;
+
+            return Ok(DANO_DISORDER_EXIT_CODE);
+        }
+
+        print_err_buf(&format!(
+            "PASSED: {} FLAC files were verified against the MD5 sum embedded in STREAMINFO.\n",
+            verified.len()
+        ))?;
+
+        Ok(DANO_CLEAN_EXIT_CODE)
+    }
+}
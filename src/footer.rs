@@ -0,0 +1,150 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::path::Path;
+
+use crate::hasher::NativeHasher;
+use crate::{DanoError, DanoResult};
+
+// a hash file which has been rewritten is marked as sealed, so that a footer which is missing altogether,
+// as when the end of the hash file was lost, is also noticed
+const SEALED_LINE: &str =
+    "// DANO, Sealed: the lines preceding the footer are checked against the footer";
+const FOOTER_PREFIX: &str = "// DANO, Footer: ";
+pub const FOOTER_HASH_ALGO: &str = "sha256";
+
+// the count and checksum of the lines which precede the footer, which is written each time the hash file
+// is rewritten, records appended later follow the footer, and are not covered by it
+#[derive(Debug, PartialEq, Eq)]
+pub struct HashFileFooter {
+    num_lines: usize,
+    checksum: String,
+}
+
+impl HashFileFooter {
+    fn new(contents: &str) -> DanoResult<Self> {
+        let mut hasher = NativeHasher::new(FOOTER_HASH_ALGO)?;
+        hasher.update(contents.as_bytes());
+
+        Ok(Self {
+            num_lines: contents.lines().count(),
+            checksum: hasher.finalize_hex(),
+        })
+    }
+
+    fn as_line(&self) -> String {
+        format!(
+            "{}lines={}, {}={}",
+            FOOTER_PREFIX, self.num_lines, FOOTER_HASH_ALGO, self.checksum
+        )
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let (num_lines, checksum) = line.strip_prefix(FOOTER_PREFIX)?.split_once(", ")?;
+
+        Some(Self {
+            num_lines: num_lines.strip_prefix("lines=")?.parse().ok()?,
+            checksum: checksum
+                .strip_prefix(FOOTER_HASH_ALGO)?
+                .strip_prefix('=')?
+                .to_owned(),
+        })
+    }
+
+    pub fn is_footer_line(line: &str) -> bool {
+        line == SEALED_LINE || line.starts_with(FOOTER_PREFIX)
+    }
+
+    // the rewritten hash file, still a tmp file, is marked as sealed and given a footer, just before it replaces the hash file,
+    // and any footer carried over from the old hash file is dropped
+    pub fn seal(tmp_file: &Path) -> DanoResult<()> {
+        let contents = std::fs::read_to_string(tmp_file)?;
+
+        let lines: Vec<&str> = contents
+            .lines()
+            .filter(|line| !Self::is_footer_line(line))
+            .collect();
+
+        let num_header_lines = lines
+            .iter()
+            .take_while(|line| line.starts_with("//"))
+            .count();
+
+        let mut buffer = String::new();
+
+        lines[..num_header_lines]
+            .iter()
+            .chain(std::iter::once(&SEALED_LINE))
+            .chain(lines[num_header_lines..].iter())
+            .for_each(|line| {
+                buffer.push_str(line);
+                buffer.push('\n');
+            });
+
+        let footer = Self::new(&buffer)?;
+        buffer.push_str(&footer.as_line());
+        buffer.push('\n');
+
+        std::fs::write(tmp_file, buffer).map_err(|err| err.into())
+    }
+
+    // a hash file which does not match its footer, or which is sealed, but has lost its footer, was truncated,
+    // or was only partially written, and is not read, rather than silently yield fewer records
+    pub fn check(source: &Path, contents: &str) -> DanoResult<()> {
+        let mut offset = 0usize;
+
+        let opt_footer = contents.split_inclusive('\n').find_map(|line| {
+            if line.starts_with(FOOTER_PREFIX) {
+                return Some((offset, Self::parse(line.trim_end())));
+            }
+
+            offset += line.len();
+            None
+        });
+
+        let msg = match opt_footer {
+            Some((offset, Some(footer))) => {
+                let preceding = Self::new(&contents[..offset])?;
+
+                if preceding == footer {
+                    return Ok(());
+                }
+
+                format!(
+                    "{:?} does not match its footer, which records {} lines, but {} lines precede it, or the checksum of those lines differs, \
+                    so the hash file was likely damaged, or only partially written.",
+                    source, footer.num_lines, preceding.num_lines
+                )
+            }
+            Some((_, None)) => format!(
+                "{:?} has a footer which could not be parsed, so the hash file was likely truncated, or only partially written.",
+                source
+            ),
+            None if contents.lines().any(|line| line == SEALED_LINE) => format!(
+                "{:?} has lost its footer, so the hash file was likely truncated, or only partially written.",
+                source
+            ),
+            None => return Ok(()),
+        };
+
+        let msg = format!(
+            "{}  Use --compact, or --fsck --repair, to rewrite it from the records which remain.",
+            msg
+        );
+
+        Err(DanoError::new(&msg).into())
+    }
+}
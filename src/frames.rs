@@ -0,0 +1,188 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::hasher::NativeHasher;
+use crate::{DanoError, DanoResult};
+
+// each range digest covers this many consecutive frames of a stream, so a record stays small,
+// but a mismatch can still be localized
+const FRAMES_PER_RANGE: usize = 1000;
+
+// a digest of each range of frames, per stream, from ffmpeg's framehash muxer
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FrameDigests {
+    pub frames_per_range: usize,
+    pub streams: BTreeMap<u32, StreamFrameDigests>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StreamFrameDigests {
+    pub num_frames: usize,
+    pub ranges: Vec<Box<str>>,
+}
+
+impl FrameDigests {
+    // framehash output is a header of "#" comment lines, and then one line per frame, like:
+    // "0,          0,          0,     1152,      417, 7d3c..."
+    // the stream index is the first field, and the frame hash is the last.  returns the digest
+    // of every frame, in the form of ffmpeg's hash muxer output, "ALGO=hex", and the range digests
+    pub fn from_framehash_output(stdout: &str, hash_algo: &str) -> DanoResult<(String, Self)> {
+        let mut whole = NativeHasher::new(hash_algo).map_err(|_| {
+            let msg = format!(
+                "Frame hashing requires a hash algorithm dano implements (crc32, murmur3, md5, sha1, sha256, sha512), not: {}",
+                hash_algo
+            );
+            DanoError::new(&msg)
+        })?;
+        let algo_name = whole.name();
+
+        let mut frames: BTreeMap<u32, Vec<&str>> = BTreeMap::new();
+
+        stdout
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .try_for_each(|line| {
+                let mut fields = line.split(',').map(|field| field.trim());
+
+                let (Some(stream), Some(frame_hash)) = (fields.next(), fields.next_back()) else {
+                    let msg = format!("Could not parse ffmpeg framehash output: {:?}", line);
+                    return Err(DanoError::new(&msg));
+                };
+
+                let Ok(stream) = stream.parse::<u32>() else {
+                    let msg = format!("Could not parse ffmpeg framehash output: {:?}", line);
+                    return Err(DanoError::new(&msg));
+                };
+
+                whole.update(format!("{},{}\n", stream, frame_hash).as_bytes());
+                frames.entry(stream).or_default().push(frame_hash);
+
+                Ok(())
+            })?;
+
+        if frames.is_empty() {
+            return Err(DanoError::new("ffmpeg framehash output contained no frames.").into());
+        }
+
+        let streams = frames
+            .into_iter()
+            .map(|(stream, frame_hashes)| {
+                let ranges = frame_hashes
+                    .chunks(FRAMES_PER_RANGE)
+                    .map(|chunk| {
+                        // the algorithm is known to be supported, because the whole digest was created
+                        let mut hasher = NativeHasher::new(hash_algo)?;
+                        chunk.iter().for_each(|frame_hash| {
+                            hasher.update(frame_hash.as_bytes());
+                            hasher.update(b"\n");
+                        });
+                        Ok(hasher.finalize_hex().into_boxed_str())
+                    })
+                    .collect::<DanoResult<Vec<Box<str>>>>()?;
+
+                Ok((
+                    stream,
+                    StreamFrameDigests {
+                        num_frames: frame_hashes.len(),
+                        ranges,
+                    },
+                ))
+            })
+            .collect::<DanoResult<BTreeMap<u32, StreamFrameDigests>>>()?;
+
+        let hash_line = format!("{}={}", algo_name, whole.finalize_hex());
+
+        Ok((
+            hash_line,
+            Self {
+                frames_per_range: FRAMES_PER_RANGE,
+                streams,
+            },
+        ))
+    }
+
+    // where the frames of a mismatched file differ from the record, like "stream 0, frames 2000-2999"
+    pub fn describe_mismatch(&self, recorded: &FrameDigests) -> Vec<String> {
+        if self.frames_per_range != recorded.frames_per_range {
+            return Vec::new();
+        }
+
+        let mut streams: Vec<&u32> = self.streams.keys().chain(recorded.streams.keys()).collect();
+        streams.sort_unstable();
+        streams.dedup();
+
+        streams
+            .into_iter()
+            .flat_map(
+                |stream| match (self.streams.get(stream), recorded.streams.get(stream)) {
+                    (Some(current), Some(recorded)) => Self::describe_stream_mismatch(
+                        *stream,
+                        self.frames_per_range,
+                        current,
+                        recorded,
+                    ),
+                    (Some(_), None) => vec![format!("stream {}, is new", stream)],
+                    (None, _) => vec![format!("stream {}, is missing", stream)],
+                },
+            )
+            .collect()
+    }
+
+    fn describe_stream_mismatch(
+        stream: u32,
+        frames_per_range: usize,
+        current: &StreamFrameDigests,
+        recorded: &StreamFrameDigests,
+    ) -> Vec<String> {
+        let num_ranges = current.ranges.len().max(recorded.ranges.len());
+
+        // contiguous ranges which differ are reported as one
+        let mut differing: Vec<(usize, usize)> = Vec::new();
+
+        (0..num_ranges)
+            .filter(|idx| current.ranges.get(*idx) != recorded.ranges.get(*idx))
+            .for_each(|idx| match differing.last_mut() {
+                Some((_first, last)) if *last + 1 == idx => *last = idx,
+                _ => differing.push((idx, idx)),
+            });
+
+        let num_frames = current.num_frames.max(recorded.num_frames);
+
+        let mut res: Vec<String> = differing
+            .into_iter()
+            .map(|(first, last)| {
+                let first_frame = first * frames_per_range;
+                let last_frame = ((last + 1) * frames_per_range).min(num_frames) - 1;
+
+                format!("stream {}, frames {}-{}", stream, first_frame, last_frame)
+            })
+            .collect();
+
+        if current.num_frames != recorded.num_frames {
+            res.push(format!(
+                "stream {}, {} frames recorded, {} frames now",
+                stream, recorded.num_frames, current.num_frames
+            ));
+        }
+
+        res
+    }
+}
@@ -0,0 +1,363 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{collections::BTreeMap, path::PathBuf, time::SystemTime};
+
+use crate::config::FsckModeConfig;
+use crate::lookup::FileInfo;
+use crate::tree::TreeHashes;
+use crate::utility::{
+    check_free_space, check_hash_file_footer, deserialize, deserialize_version, get_tmp_file,
+    hash_file_exists, persist_hash_file, print_err_buf, print_out_buf, read_hash_file, serialize,
+};
+use crate::{
+    Config, DanoError, DanoResult, DANO_CLEAN_EXIT_CODE, DANO_DISORDER_EXIT_CODE,
+    DANO_FILE_INFO_VERSION,
+};
+
+const HEADER_PREFIX: &str = "//";
+
+// records of the same path, in the same form, are expected to have the same hash
+type FormKey = (PathBuf, Box<str>, bool, String);
+
+#[derive(Debug)]
+enum LineProblem {
+    Empty,
+    Unreadable(String),
+    NoMetadata,
+    NewerVersion(usize),
+    Legacy(usize),
+    NotNormalized,
+    Duplicate(usize),
+    Conflicting(usize),
+}
+
+impl LineProblem {
+    fn describe(&self) -> String {
+        match self {
+            LineProblem::Empty => "is empty".to_owned(),
+            LineProblem::Unreadable(err) => format!("could not be read: {}", err),
+            LineProblem::NoMetadata => "is a record without any hash".to_owned(),
+            LineProblem::NewerVersion(version) => format!(
+                "was written by a newer version of dano, in format version {}, and cannot be checked",
+                version
+            ),
+            LineProblem::Legacy(version) => format!(
+                "was written by an earlier version of dano, in format version {}",
+                version
+            ),
+            LineProblem::NotNormalized => {
+                "is not written as dano would write the same record".to_owned()
+            }
+            LineProblem::Duplicate(line_num) => {
+                format!("duplicates the record of the same path on line {}", line_num)
+            }
+            LineProblem::Conflicting(line_num) => format!(
+                "has a hash which conflicts with the more recently written record of the same path, in the same form, on line {}",
+                line_num
+            ),
+        }
+    }
+
+    // a line this version cannot read is never dropped, as it may be read by a newer version
+    fn repair(&self) -> LineRepair {
+        match self {
+            LineProblem::NewerVersion(_) => LineRepair::Keep,
+            LineProblem::Legacy(_) | LineProblem::NotNormalized => LineRepair::Normalize,
+            _ => LineRepair::Drop,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineRepair {
+    Keep,
+    Normalize,
+    Drop,
+}
+
+struct CheckedLine<'a> {
+    line: &'a str,
+    opt_file_info: Option<FileInfo>,
+    opt_problem: Option<LineProblem>,
+}
+
+pub struct HashFileCheck;
+
+impl HashFileCheck {
+    pub fn exec(config: &Config, fsck_config: &FsckModeConfig) -> DanoResult<i32> {
+        if !hash_file_exists(&config.hash_file) {
+            return Err(DanoError::new("No hash file exists to check.").into());
+        }
+
+        let contents = read_hash_file(&config.hash_file)?;
+
+        let opt_footer_problem = check_hash_file_footer(&config.hash_file, &contents).err();
+
+        let mut checked: Vec<CheckedLine> = contents.lines().map(Self::check_line).collect();
+
+        Self::check_duplicates(&mut checked);
+
+        let num_problems = checked
+            .iter()
+            .filter(|checked_line| checked_line.opt_problem.is_some())
+            .count()
+            + usize::from(opt_footer_problem.is_some());
+
+        if let Some(err) = &opt_footer_problem {
+            print_out_buf(&format!("{}\n", err))?;
+        }
+
+        checked
+            .iter()
+            .enumerate()
+            .try_for_each(|(idx, checked_line)| match &checked_line.opt_problem {
+                Some(problem) => print_out_buf(&format!(
+                    "{:?}, line {}: {}\n",
+                    config.hash_file,
+                    idx + 1,
+                    problem.describe()
+                )),
+                None => Ok(()),
+            })?;
+
+        if num_problems == 0 {
+            if !config.opt_silent {
+                print_err_buf(&format!(
+                    "PASSED: Checked {} lines of the hash file, and found no problems.\n",
+                    checked.len()
+                ))?;
+            }
+
+            return Ok(DANO_CLEAN_EXIT_CODE);
+        }
+
+        if !fsck_config.opt_repair {
+            let is_repairable = opt_footer_problem.is_some()
+                || checked.iter().any(|checked_line| {
+                    checked_line
+                        .opt_problem
+                        .as_ref()
+                        .is_some_and(|problem| problem.repair() != LineRepair::Keep)
+                });
+
+            if !config.opt_silent {
+                let hint = if is_repairable {
+                    "  Use --repair to drop, or to rewrite, the lines with problems."
+                } else {
+                    ""
+                };
+
+                print_err_buf(&format!(
+                    "WARN: Found {} problems in {} lines of the hash file.{}\n",
+                    num_problems,
+                    checked.len(),
+                    hint
+                ))?;
+            }
+
+            return Ok(DANO_DISORDER_EXIT_CODE);
+        }
+
+        Self::repair(config, &checked)?;
+
+        Ok(DANO_CLEAN_EXIT_CODE)
+    }
+
+    fn check_line(line: &str) -> CheckedLine<'_> {
+        let mut checked_line = CheckedLine {
+            line,
+            opt_file_info: None,
+            opt_problem: None,
+        };
+
+        if line.starts_with(HEADER_PREFIX) || TreeHashes::is_tree_hash_line(line) {
+            return checked_line;
+        }
+
+        if line.trim().is_empty() {
+            checked_line.opt_problem = Some(LineProblem::Empty);
+            return checked_line;
+        }
+
+        let opt_version = deserialize_version(line);
+
+        let file_info = match deserialize(line) {
+            Ok(file_info) => file_info,
+            Err(_) if opt_version.is_some_and(|version| version > DANO_FILE_INFO_VERSION) => {
+                checked_line.opt_problem = opt_version.map(LineProblem::NewerVersion);
+                return checked_line;
+            }
+            Err(err) => {
+                checked_line.opt_problem = Some(LineProblem::Unreadable(err.to_string()));
+                return checked_line;
+            }
+        };
+
+        checked_line.opt_problem = match opt_version {
+            _ if file_info.metadata.is_none() => Some(LineProblem::NoMetadata),
+            Some(version) if version != DANO_FILE_INFO_VERSION => {
+                Some(LineProblem::Legacy(version))
+            }
+            _ if !matches!(serialize(&file_info), Ok(serialized) if serialized.trim_end() == line) => {
+                Some(LineProblem::NotNormalized)
+            }
+            _ => None,
+        };
+
+        checked_line.opt_file_info = Some(file_info);
+        checked_line
+    }
+
+    // of the records of a path, in the same form, the most recently written is kept, and any other is either a duplicate,
+    // or conflicts with it
+    fn check_duplicates(checked: &mut [CheckedLine]) {
+        let mut latest: BTreeMap<FormKey, (usize, SystemTime)> = BTreeMap::new();
+
+        checked.iter().enumerate().for_each(|(idx, checked_line)| {
+            let Some((key, last_written)) = Self::form_key(checked_line) else {
+                return;
+            };
+
+            match latest.get(&key) {
+                Some((_, latest_written)) if *latest_written > last_written => (),
+                _ => {
+                    latest.insert(key, (idx, last_written));
+                }
+            }
+        });
+
+        let problems: Vec<(usize, LineProblem)> = checked
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, checked_line)| {
+                let (key, _) = Self::form_key(checked_line)?;
+                let (latest_idx, _) = latest.get(&key)?;
+
+                if *latest_idx == idx {
+                    return None;
+                }
+
+                let latest_hash = checked[*latest_idx]
+                    .opt_file_info
+                    .as_ref()?
+                    .metadata
+                    .as_ref()?
+                    .hash_value
+                    .clone();
+                let hash = checked_line
+                    .opt_file_info
+                    .as_ref()?
+                    .metadata
+                    .as_ref()?
+                    .hash_value
+                    .clone();
+
+                let problem = if hash == latest_hash {
+                    LineProblem::Duplicate(latest_idx + 1)
+                } else {
+                    LineProblem::Conflicting(latest_idx + 1)
+                };
+
+                Some((idx, problem))
+            })
+            .collect();
+
+        problems.into_iter().for_each(|(idx, problem)| {
+            checked[idx].opt_problem = Some(problem);
+        });
+    }
+
+    fn form_key(checked_line: &CheckedLine) -> Option<(FormKey, SystemTime)> {
+        let file_info = checked_line.opt_file_info.as_ref()?;
+        let metadata = file_info.metadata.as_ref()?;
+
+        let key = (
+            file_info.path.clone(),
+            metadata.hash_algo.clone(),
+            metadata.decoded,
+            format!("{:?}", metadata.selected_streams),
+        );
+
+        Some((key, metadata.last_written))
+    }
+
+    fn repair(config: &Config, checked: &[CheckedLine]) -> DanoResult<()> {
+        let mut num_dropped = 0usize;
+        let mut num_normalized = 0usize;
+        let mut num_kept = 0usize;
+
+        let mut buffer = String::new();
+
+        checked
+            .iter()
+            .try_for_each(|checked_line| -> DanoResult<()> {
+                let repair = checked_line
+                    .opt_problem
+                    .as_ref()
+                    .map(|problem| problem.repair())
+                    .unwrap_or(LineRepair::Keep);
+
+                match (repair, &checked_line.opt_file_info) {
+                    (LineRepair::Drop, _) => num_dropped += 1,
+                    (LineRepair::Normalize, Some(file_info)) => {
+                        num_normalized += 1;
+                        buffer.push_str(&serialize(file_info)?);
+                    }
+                    (_, _) => {
+                        if checked_line.opt_problem.is_some() {
+                            num_kept += 1;
+                        }
+
+                        buffer.push_str(checked_line.line);
+                        buffer.push('\n');
+                    }
+                }
+
+                Ok(())
+            })?;
+
+        if !config.opt_dry_run {
+            let tmp_file = get_tmp_file(config, &config.hash_file);
+            check_free_space(&tmp_file, buffer.len() as u64)?;
+
+            let res = std::fs::write(&tmp_file, &buffer)
+                .map_err(|err| err.into())
+                .and_then(|_| persist_hash_file(config, &tmp_file, &config.hash_file));
+
+            if res.is_err() {
+                let _ = std::fs::remove_file(&tmp_file);
+            }
+
+            res?;
+        }
+
+        if config.opt_silent {
+            return Ok(());
+        }
+
+        let verb = if config.opt_dry_run {
+            "Would repair"
+        } else {
+            "Repaired"
+        };
+
+        print_err_buf(&format!(
+            "{} the hash file: {} lines dropped, {} lines rewritten, and {} lines, which this version of dano cannot read, kept.\n",
+            verb, num_dropped, num_normalized, num_kept
+        ))
+    }
+}
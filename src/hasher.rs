@@ -0,0 +1,294 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::{DanoError, DanoResult};
+
+const READ_BUFFER_SIZE: usize = 1024 * 1024;
+
+// ffmpeg's murmur3 is the 128 bit x64 variant, with a 64 bit seed
+const MURMUR3_SEED: u64 = 0x725a_cc55_dadd_ca55;
+const MURMUR3_C1: u64 = 0x87c3_7b91_1142_53d5;
+const MURMUR3_C2: u64 = 0x4cf5_ad43_2745_937f;
+const MURMUR3_BLOCK_SIZE: usize = 16;
+
+// a streaming murmur3, which produces the same digest as ffmpeg's, so records may be compared
+pub struct Murmur3 {
+    h1: u64,
+    h2: u64,
+    len: u64,
+    tail: Vec<u8>,
+}
+
+impl Murmur3 {
+    fn new() -> Self {
+        Self {
+            h1: MURMUR3_SEED,
+            h2: MURMUR3_SEED,
+            len: 0,
+            tail: Vec::with_capacity(MURMUR3_BLOCK_SIZE),
+        }
+    }
+
+    fn get_k1(block: &[u8]) -> u64 {
+        let k = u64::from_le_bytes(block[..8].try_into().unwrap_or_default());
+        k.wrapping_mul(MURMUR3_C1)
+            .rotate_left(31)
+            .wrapping_mul(MURMUR3_C2)
+    }
+
+    fn get_k2(block: &[u8]) -> u64 {
+        let k = u64::from_le_bytes(block[8..16].try_into().unwrap_or_default());
+        k.wrapping_mul(MURMUR3_C2)
+            .rotate_left(33)
+            .wrapping_mul(MURMUR3_C1)
+    }
+
+    fn fmix(k: u64) -> u64 {
+        let k = (k ^ (k >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+        let k = (k ^ (k >> 33)).wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        k ^ (k >> 33)
+    }
+
+    fn update_block(&mut self, block: &[u8]) {
+        self.h1 = (self.h1 ^ Self::get_k1(block))
+            .rotate_left(27)
+            .wrapping_add(self.h2)
+            .wrapping_mul(5)
+            .wrapping_add(0x52dc_e729);
+        self.h2 = (self.h2 ^ Self::get_k2(block))
+            .rotate_left(31)
+            .wrapping_add(self.h1)
+            .wrapping_mul(5)
+            .wrapping_add(0x3849_5ab5);
+    }
+
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.len += bytes.len() as u64;
+
+        if !self.tail.is_empty() {
+            let needed = MURMUR3_BLOCK_SIZE - self.tail.len();
+            let (head, rest) = bytes.split_at(needed.min(bytes.len()));
+            self.tail.extend_from_slice(head);
+            bytes = rest;
+
+            if self.tail.len() < MURMUR3_BLOCK_SIZE {
+                return;
+            }
+
+            let block = std::mem::take(&mut self.tail);
+            self.update_block(&block);
+        }
+
+        let mut blocks = bytes.chunks_exact(MURMUR3_BLOCK_SIZE);
+        blocks.by_ref().for_each(|block| self.update_block(block));
+        self.tail.extend_from_slice(blocks.remainder());
+    }
+
+    fn finalize(mut self) -> Vec<u8> {
+        // the tail is zero padded, zero bytes contribute nothing to either half
+        self.tail.resize(MURMUR3_BLOCK_SIZE, 0);
+        self.h1 ^= Self::get_k1(&self.tail);
+        self.h2 ^= Self::get_k2(&self.tail);
+
+        self.h1 ^= self.len;
+        self.h2 ^= self.len;
+        self.h1 = self.h1.wrapping_add(self.h2);
+        self.h2 = self.h2.wrapping_add(self.h1);
+        self.h1 = Self::fmix(self.h1);
+        self.h2 = Self::fmix(self.h2);
+        self.h1 = self.h1.wrapping_add(self.h2);
+        self.h2 = self.h2.wrapping_add(self.h1);
+
+        let mut digest = self.h1.to_le_bytes().to_vec();
+        digest.extend_from_slice(&self.h2.to_le_bytes());
+        digest
+    }
+}
+
+// the reflected polynomial of CRC-32, as used by sfv files, zip, and ffmpeg's CRC32
+const CRC32_POLYNOMIAL: u32 = 0xedb8_8320;
+const CRC32_TABLE: [u32; 256] = Crc32::make_table();
+
+pub struct Crc32 {
+    crc: u32,
+}
+
+impl Crc32 {
+    const fn make_table() -> [u32; 256] {
+        let mut table = [0u32; 256];
+        let mut idx = 0;
+
+        while idx < 256 {
+            let mut crc = idx as u32;
+            let mut bit = 0;
+
+            while bit < 8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ CRC32_POLYNOMIAL
+                } else {
+                    crc >> 1
+                };
+                bit += 1;
+            }
+
+            table[idx] = crc;
+            idx += 1;
+        }
+
+        table
+    }
+
+    pub fn new() -> Self {
+        Self { crc: u32::MAX }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.crc = bytes.iter().fold(self.crc, |crc, byte| {
+            CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+        });
+    }
+
+    pub fn value(&self) -> u32 {
+        self.crc ^ u32::MAX
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        self.value().to_be_bytes().to_vec()
+    }
+}
+
+// for when we need to hash bytes ourselves, rather than asking ffmpeg,
+// algorithm names are the names ffmpeg would use, so records may be compared
+pub enum NativeHasher {
+    Crc32(Crc32),
+    Murmur3(Murmur3),
+    Md5(Md5),
+    Sha160(Sha1),
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl NativeHasher {
+    pub fn new(hash_algo: &str) -> DanoResult<Self> {
+        let res = match hash_algo.to_ascii_lowercase().as_str() {
+            "crc32" => NativeHasher::Crc32(Crc32::new()),
+            "murmur3" => NativeHasher::Murmur3(Murmur3::new()),
+            "md5" => NativeHasher::Md5(Md5::new()),
+            "sha1" | "sha160" => NativeHasher::Sha160(Sha1::new()),
+            "sha256" => NativeHasher::Sha256(Sha256::new()),
+            "sha512" => NativeHasher::Sha512(Sha512::new()),
+            "blake3" => NativeHasher::Blake3(Box::new(blake3::Hasher::new())),
+            _ => {
+                let msg = format!(
+                    "Hash algorithm is not supported for whole file hashing: {}",
+                    hash_algo
+                );
+                return Err(DanoError::new(&msg).into());
+            }
+        };
+
+        Ok(res)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NativeHasher::Crc32(_) => "CRC32",
+            NativeHasher::Murmur3(_) => "murmur3",
+            NativeHasher::Md5(_) => "MD5",
+            NativeHasher::Sha160(_) => "SHA160",
+            NativeHasher::Sha256(_) => "SHA256",
+            NativeHasher::Sha512(_) => "SHA512",
+            NativeHasher::Blake3(_) => "BLAKE3",
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        match self {
+            NativeHasher::Crc32(hasher) => hasher.update(bytes),
+            NativeHasher::Murmur3(hasher) => hasher.update(bytes),
+            NativeHasher::Md5(hasher) => hasher.update(bytes),
+            NativeHasher::Sha160(hasher) => hasher.update(bytes),
+            NativeHasher::Sha256(hasher) => hasher.update(bytes),
+            NativeHasher::Sha512(hasher) => hasher.update(bytes),
+            // large chunks are hashed on many threads at once
+            NativeHasher::Blake3(hasher) => {
+                hasher.update_rayon(bytes);
+            }
+        }
+    }
+
+    pub fn finalize_hex(self) -> String {
+        let bytes: Vec<u8> = match self {
+            NativeHasher::Crc32(hasher) => hasher.finalize(),
+            NativeHasher::Murmur3(hasher) => hasher.finalize(),
+            NativeHasher::Md5(hasher) => hasher.finalize().to_vec(),
+            NativeHasher::Sha160(hasher) => hasher.finalize().to_vec(),
+            NativeHasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            NativeHasher::Sha512(hasher) => hasher.finalize().to_vec(),
+            NativeHasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        };
+
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    // output mimics ffmpeg's hash muxer output, "ALGO=hex", so it can be parsed the same way
+    pub fn hash_file(path: &Path, hash_algo: &str) -> DanoResult<Box<str>> {
+        let len = path.metadata()?.len();
+
+        Self::hash_file_range(path, hash_algo, 0, len)
+    }
+
+    // hash only the given range of the file, such as the sample data of an uncompressed audio file
+    pub fn hash_file_range(
+        path: &Path,
+        hash_algo: &str,
+        offset: u64,
+        len: u64,
+    ) -> DanoResult<Box<str>> {
+        let mut hasher = Self::new(hash_algo)?;
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+
+        let mut range = file.take(len);
+        let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+
+        loop {
+            let bytes_read = range.read(&mut buffer)?;
+
+            if bytes_read == 0 {
+                break;
+            }
+
+            hasher.update(&buffer[..bytes_read]);
+        }
+
+        let name = hasher.name();
+
+        Ok(format!("{}={}", name, hasher.finalize_hex()).into())
+    }
+}
@@ -0,0 +1,237 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    ffi::OsString,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::lookup::{FileInfo, FileMetadata};
+use crate::undo::RecordChanges;
+use crate::utility::{format_time, print_out_buf};
+use crate::{Config, DanoError, DanoResult, ExecMode, DANO_CLEAN_EXIT_CODE};
+
+pub const HISTORY_EXTENSION: &str = "history";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryAction {
+    Accepted,
+    Migrated,
+    Added,
+    Overwritten,
+    Removed,
+    Pruned,
+    Compacted,
+    Cleaned,
+    Repaired,
+    Undone,
+}
+
+// one line of the history log, which records the old record, as well as the new, so a changed hash
+// is never simply forgotten
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    pub time: SystemTime,
+    pub user: Option<String>,
+    pub action: HistoryAction,
+    #[serde(with = "crate::serde_path")]
+    pub path: PathBuf,
+    pub old: Option<FileMetadata>,
+    pub new: Option<FileMetadata>,
+}
+
+impl HistoryEntry {
+    pub fn new(
+        action: HistoryAction,
+        path: &Path,
+        old: Option<FileMetadata>,
+        new: Option<FileMetadata>,
+    ) -> Self {
+        Self {
+            time: SystemTime::now(),
+            user: std::env::var("USER")
+                .or_else(|_| std::env::var("LOGNAME"))
+                .ok(),
+            action,
+            path: path.to_owned(),
+            old,
+            new,
+        }
+    }
+
+    fn describe(&self) -> String {
+        let describe_record = |opt_metadata: &Option<FileMetadata>| match opt_metadata {
+            Some(metadata) => format!("{}={}", metadata.hash_algo, metadata.hash_value.value),
+            None => "none".to_owned(),
+        };
+
+        format!(
+            "{} UTC: {:?} by {}: {} -> {} : {:?}\n",
+            format_time(self.time),
+            self.action,
+            self.user.as_deref().unwrap_or("unknown user"),
+            describe_record(&self.old),
+            describe_record(&self.new),
+            self.path
+        )
+    }
+}
+
+// an append only log, of newline delimited JSON, written next to the hash file
+pub struct HistoryLog {
+    path: PathBuf,
+}
+
+impl HistoryLog {
+    pub fn new(config: &Config) -> Self {
+        let mut path: OsString = config.output_file.as_os_str().to_owned();
+        path.push(".");
+        path.push(HISTORY_EXTENSION);
+
+        Self {
+            path: PathBuf::from(path),
+        }
+    }
+
+    pub fn append(&self, entries: &[HistoryEntry]) -> DanoResult<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let buffer = entries.iter().try_fold(String::new(), |mut acc, entry| {
+            acc += &serde_json::to_string(entry)?;
+            acc.push('\n');
+            DanoResult::Ok(acc)
+        })?;
+
+        let mut history_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        history_file.write_all(buffer.as_bytes())?;
+
+        Ok(())
+    }
+
+    // a new record which replaces an old record, of the same path, in the same form, overwrites it,
+    // and any other record was simply added, or removed
+    pub fn append_changes(&self, config: &Config, changes: &RecordChanges) -> DanoResult<()> {
+        let removed_action = match &config.exec_mode {
+            // accepted and migrated records are logged as such, by each mode
+            ExecMode::Accept | ExecMode::MigrateAlgo(_) => return Ok(()),
+            ExecMode::Prune => HistoryAction::Pruned,
+            ExecMode::Compact(_) => HistoryAction::Compacted,
+            ExecMode::Clean => HistoryAction::Cleaned,
+            ExecMode::Fsck(_) => HistoryAction::Repaired,
+            ExecMode::Undo => HistoryAction::Undone,
+            _ => HistoryAction::Removed,
+        };
+
+        let is_undo = matches!(config.exec_mode, ExecMode::Undo);
+
+        let is_same_form = |old: &FileInfo, new: &FileInfo| match (&old.metadata, &new.metadata) {
+            (Some(old_metadata), Some(new_metadata)) => {
+                old.path == new.path
+                    && old_metadata.hash_algo == new_metadata.hash_algo
+                    && old_metadata.decoded == new_metadata.decoded
+                    && old_metadata.selected_streams == new_metadata.selected_streams
+            }
+            _ => false,
+        };
+
+        let mut removed: Vec<&FileInfo> = changes.removed.iter().collect();
+
+        let mut entries: Vec<HistoryEntry> = changes
+            .added
+            .iter()
+            .map(|new| {
+                let opt_old = removed
+                    .iter()
+                    .position(|old| is_same_form(old, new))
+                    .map(|idx| removed.remove(idx));
+
+                let action = match opt_old {
+                    _ if is_undo => HistoryAction::Undone,
+                    Some(_) => HistoryAction::Overwritten,
+                    None => HistoryAction::Added,
+                };
+
+                HistoryEntry::new(
+                    action,
+                    &new.path,
+                    opt_old.and_then(|old| old.metadata.clone()),
+                    new.metadata.clone(),
+                )
+            })
+            .collect();
+
+        entries.extend(
+            removed.into_iter().map(|old| {
+                HistoryEntry::new(removed_action, &old.path, old.metadata.clone(), None)
+            }),
+        );
+
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        self.append(&entries)
+    }
+
+    fn read(&self) -> DanoResult<Vec<HistoryEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let contents = std::fs::read_to_string(&self.path)?;
+
+        // a line which was only partially appended is skipped
+        Ok(contents
+            .lines()
+            .flat_map(serde_json::from_str::<HistoryEntry>)
+            .collect())
+    }
+
+    // paths are compared as recorded, relative to the working directory, unless absolute
+    pub fn exec(&self, config: &Config, opt_path: Option<&Path>) -> DanoResult<i32> {
+        let entries: Vec<HistoryEntry> = self
+            .read()?
+            .into_iter()
+            .filter(|entry| match opt_path {
+                Some(path) => config.pwd.join(&entry.path) == config.pwd.join(path),
+                None => true,
+            })
+            .collect();
+
+        if entries.is_empty() {
+            let msg = match opt_path {
+                Some(path) => format!("No history is recorded for path: {:?}", path),
+                None => format!("No history is recorded in the history log: {:?}", self.path),
+            };
+            return Err(DanoError::new(&msg).into());
+        }
+
+        entries
+            .iter()
+            .try_for_each(|entry| print_out_buf(&entry.describe()))?;
+
+        Ok(DANO_CLEAN_EXIT_CODE)
+    }
+}
@@ -0,0 +1,121 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
+use crate::checksums::ChecksumFileImporter;
+use crate::cshatag::CshatagImporter;
+use crate::ffp::FfpImporter;
+use crate::flac::FlacImporter;
+use crate::lookup::FileInfo;
+use crate::sfv::SfvImporter;
+use crate::utility::prepare_thread_pool;
+use crate::wavpack::WavPackImporter;
+use crate::{Config, DanoResult, RecordedFileInfo};
+
+// an importer converts checksums recorded by some other tool into dano file info,
+// to add a new source, implement this trait and register the importer below
+pub trait Importer: Sync {
+    fn name(&self) -> &'static str;
+    fn is_valid_path(&self, path: &Path) -> bool;
+    fn import(&self, config: &Config, path: &Path) -> DanoResult<Vec<FileInfo>>;
+}
+
+const IMPORT_PROGRESS_INTERVAL: usize = 100;
+
+pub static IMPORTERS: &[&dyn Importer] = &[
+    &FlacImporter,
+    &WavPackImporter,
+    &CshatagImporter,
+    &ChecksumFileImporter,
+    &SfvImporter,
+    &FfpImporter,
+];
+
+// importers which read a checksum file, rather than the media files themselves
+static CHECKSUM_FILE_IMPORTERS: &[&dyn Importer] =
+    &[&SfvImporter, &FfpImporter, &ChecksumFileImporter];
+
+pub fn importer_names() -> impl Iterator<Item = &'static str> {
+    IMPORTERS.iter().map(|importer| importer.name())
+}
+
+// the importer for a checksum file, by its extension or conventional name
+pub fn detect_checksum_importer(path: &Path) -> Option<&'static dyn Importer> {
+    CHECKSUM_FILE_IMPORTERS
+        .iter()
+        .find(|importer| importer.is_valid_path(path))
+        .copied()
+}
+
+pub fn get_importer(name: &str) -> Option<&'static dyn Importer> {
+    IMPORTERS
+        .iter()
+        .find(|importer| importer.name() == name)
+        .copied()
+}
+
+impl RecordedFileInfo {
+    // imports run on the same thread pool as hashing, so --threads also limits the number of
+    // concurrent external processes, or files read
+    pub fn from_import(config: &Config, importer: &dyn Importer) -> DanoResult<Vec<FileInfo>> {
+        let thread_pool = prepare_thread_pool(config)?;
+
+        let valid_paths: Vec<&PathBuf> = config
+            .paths
+            .iter()
+            .filter(|path| {
+                if importer.is_valid_path(path) {
+                    return true;
+                }
+
+                eprintln!(
+                    "ERROR: {:?} is not a valid path for the {} importer",
+                    path,
+                    importer.name()
+                );
+                false
+            })
+            .collect();
+
+        let num_paths = valid_paths.len();
+        let num_done = AtomicUsize::new(0);
+
+        let imported: Vec<Vec<FileInfo>> = thread_pool.install(|| {
+            valid_paths
+                .par_iter()
+                .map(|path| {
+                    let res = importer.import(config, path);
+
+                    let done = num_done.fetch_add(1, Ordering::Relaxed) + 1;
+                    if !config.opt_silent
+                        && (done.is_multiple_of(IMPORT_PROGRESS_INTERVAL) || done == num_paths)
+                    {
+                        eprintln!("Progress: {}/{} files imported", done, num_paths);
+                    }
+
+                    res
+                })
+                .collect::<DanoResult<Vec<Vec<FileInfo>>>>()
+        })?;
+
+        Ok(imported.into_iter().flatten().collect())
+    }
+}
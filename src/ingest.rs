@@ -15,14 +15,54 @@
 // For the full copyright and license information, please view the LICENSE file
 // that was distributed with this source code.
 
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::io::ErrorKind;
 use std::ops::Deref;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use rayon::prelude::*;
 
+use crate::config::{TestModeConfig, WriteModeConfig};
+use crate::import::get_importer;
 use crate::lookup::FileInfo;
-use crate::utility::{deserialize, read_file_info_from_file};
-use crate::{Config, DanoError, DanoResult, ExecMode, DANO_XATTR_KEY_NAME};
+use crate::schedule::Schedule;
+use crate::sidecar::Sidecar;
+use crate::signature::HashFileSignature;
+use crate::utility::{
+    deserialize, deserialize_version, get_dano_xattr, hash_file_exists, invoked_from,
+    read_file_info_from_file, read_hash_file, resolve_path, write_non_file,
+};
+use crate::{Config, DanoError, DanoResult, ExecMode, DANO_FILE_INFO_VERSION};
+
+enum XattrRecord {
+    Current(FileInfo),
+    Legacy(FileInfo, usize),
+    Newer(usize),
+    Unparseable(String),
+    PermissionDenied,
+}
+
+#[derive(Default)]
+struct XattrSummary {
+    legacy: usize,
+    upgraded: usize,
+    newer: usize,
+    unparseable: usize,
+}
+
+impl XattrSummary {
+    fn print(&self) {
+        if self.legacy + self.newer + self.unparseable == 0 {
+            return;
+        }
+
+        eprintln!(
+            "Extended attributes: {} in a legacy format version, of which {} were upgraded, {} in a newer format version, which were skipped, and {} could not be parsed.",
+            self.legacy, self.upgraded, self.newer, self.unparseable
+        );
+    }
+}
 
 pub struct RecordedFileInfo {
     inner: Vec<FileInfo>,
@@ -47,64 +87,245 @@ impl RecordedFileInfo {
         self.inner
     }
 
+    // paths for which every recorded form is plainly unchanged on disk
+    pub fn unchanged_paths(&self) -> BTreeSet<PathBuf> {
+        let mut changed: BTreeSet<&Path> = BTreeSet::new();
+        let mut unchanged: BTreeSet<&Path> = BTreeSet::new();
+
+        self.iter().for_each(|file_info| {
+            let is_unchanged = file_info
+                .metadata
+                .as_ref()
+                .is_some_and(|metadata| metadata.is_unchanged(&file_info.path));
+
+            if is_unchanged {
+                unchanged.insert(&file_info.path);
+            } else {
+                changed.insert(&file_info.path);
+            }
+        });
+
+        unchanged
+            .difference(&changed)
+            .map(|path| path.to_path_buf())
+            .collect()
+    }
+
     pub fn new(config: &Config) -> DanoResult<Self> {
+        // a hash file which has been tampered with may not vouch for anything
+        HashFileSignature::verify(config, &config.hash_file)?;
+
         let mut recorded_file_info: Vec<FileInfo> = match &config.exec_mode {
-            ExecMode::Write(write_config) if write_config.opt_import_flac => {
-                Self::from_flac(config)?
-            }
+            ExecMode::Write(WriteModeConfig {
+                opt_import: Some(name),
+                ..
+            }) => match get_importer(name) {
+                Some(importer) => Self::from_import(config, importer)?,
+                None => {
+                    let msg = format!("No importer is registered with the name: {}", name);
+                    return Err(DanoError::new(&msg).into());
+                }
+            },
+            // copy verification reads only those records it may reuse
+            ExecMode::VerifyCopy(_) => Vec::new(),
+            // a status reads the records itself, without warning for each file which has none
+            ExecMode::Status(_) | ExecMode::Coverage(_) => Vec::new(),
+            // a prune, a diff, or a compaction, reads the hash files itself, and a compaction may rewrite
+            // a hash file which does not match its footer
+            ExecMode::Prune | ExecMode::Diff(_) | ExecMode::Compact(_) => Vec::new(),
+            // AccurateRip verification requires no records at all, and an undo reads only its own record
+            ExecMode::AccurateRip | ExecMode::Undo => Vec::new(),
+            // the history reads only the history log
+            ExecMode::History(_) => Vec::new(),
+            // a check reads the hash file itself, line by line, and may repair a hash file which does not match its footer
+            ExecMode::Fsck(_) => Vec::new(),
             _ => Self::from_recorded(config)?,
         };
 
-        // if empty, no valid hashes to test in test mode, and we should quit
+        // only test recorded file info whose paths match
+        if let ExecMode::Test(TestModeConfig {
+            opt_match: Some(pattern),
+            ..
+        }) = &config.exec_mode
+        {
+            recorded_file_info.retain(|file_info| pattern.matches_path(&file_info.path));
+        }
+
+        // only test the stalest recorded file info which fits within the time budget
+        if let ExecMode::Test(TestModeConfig {
+            opt_budget: Some(budget),
+            ..
+        }) = &config.exec_mode
+        {
+            recorded_file_info = Schedule::new(config)?.select(recorded_file_info, *budget);
+        }
+
+        // if empty, no valid hashes to test in test mode, and we should quit, except a daemon, which waits
+        // for entries to be added
         if let ExecMode::Test(test_mode_config) = &config.exec_mode {
             if recorded_file_info.is_empty()
                 && !test_mode_config.opt_overwrite_old
                 && !test_mode_config.opt_write_new
+                && test_mode_config.opt_reference.is_none()
+                && test_mode_config.opt_daemon.is_none()
             {
                 return Err(DanoError::new("No valid hashes to test.  Quitting.").into());
             }
         }
 
-        // sort and dedup in case we have paths in both hash file and xattrs
-        recorded_file_info.par_sort_unstable_by_key(|file_info| file_info.path.clone());
-        recorded_file_info.dedup_by_key(|file_info| file_info.path.clone());
+        Ok(Self::sorted_and_deduped(recorded_file_info))
+    }
+
+    // the records of a drive local catalog, and of the xattrs of the input files, for the input files only.
+    // relative paths in the catalog are resolved against the directory the catalog was written from
+    pub fn from_catalog(config: &Config) -> DanoResult<Self> {
+        HashFileSignature::verify(config, &config.hash_file)?;
+
+        let input_paths: BTreeSet<&Path> = config.paths.iter().map(|path| path.as_path()).collect();
 
-        Ok(Self {
+        let base_dir = read_hash_file(&config.hash_file)
+            .ok()
+            .and_then(|contents| {
+                let header: Vec<&str> = contents
+                    .lines()
+                    .take_while(|line| line.starts_with("//"))
+                    .collect();
+                invoked_from(&header)
+            })
+            .unwrap_or_else(|| config.pwd.clone());
+
+        let recorded_file_info: Vec<FileInfo> = Self::from_recorded(config)?
+            .into_iter()
+            .map(|file_info| FileInfo {
+                path: resolve_path(&base_dir, &file_info.path),
+                ..file_info
+            })
+            .filter(|file_info| input_paths.contains(file_info.path.as_path()))
+            .collect();
+
+        Ok(Self::sorted_and_deduped(recorded_file_info))
+    }
+
+    // sort and dedup in case we have paths in both hash file and xattrs, a path may be recorded
+    // both decoded and copied, so we keep the most recently written record of each form
+    fn sorted_and_deduped(mut recorded_file_info: Vec<FileInfo>) -> Self {
+        recorded_file_info.par_sort_unstable_by_key(|file_info| {
+            let opt_metadata = file_info.metadata.as_ref();
+
+            (
+                file_info.path.clone(),
+                opt_metadata.map(|metadata| metadata.decoded),
+                Reverse(opt_metadata.map(|metadata| metadata.last_written)),
+            )
+        });
+        recorded_file_info.dedup_by_key(|file_info| {
+            (
+                file_info.path.clone(),
+                file_info.metadata.as_ref().map(|metadata| metadata.decoded),
+            )
+        });
+
+        Self {
             inner: recorded_file_info,
-        })
+        }
     }
 
     fn from_recorded(config: &Config) -> DanoResult<Vec<FileInfo>> {
-        let mut file_info_from_xattrs: Vec<FileInfo> = {
-            config
-                .paths
-                .par_iter()
-                .filter_map(|path| match Self::read_file_info_from_xattr(path) {
-                    Some(file_info) => Some((path, file_info)),
+        // remote media has neither extended attributes, nor sidecars, and is recorded only in the hash file
+        if config.opt_remote_media.is_some() {
+            if !hash_file_exists(&config.hash_file) {
+                return Ok(Vec::new());
+            }
+
+            return read_file_info_from_file(config);
+        }
+
+        let xattr_records: Vec<(&PathBuf, Option<XattrRecord>)> = config
+            .paths
+            .par_iter()
+            .map(|path| (path, Self::read_xattr_record(path)))
+            .collect();
+
+        let mut summary = XattrSummary::default();
+
+        let mut file_info_from_xattrs: Vec<FileInfo> = xattr_records
+            .into_iter()
+            .filter_map(|(path, opt_record)| {
+                let file_info = match opt_record {
+                    Some(XattrRecord::Current(file_info)) => file_info,
+                    Some(XattrRecord::Legacy(file_info, version)) => {
+                        summary.legacy += 1;
+
+                        if Self::upgrade_xattr(config, path, &file_info, version) {
+                            summary.upgraded += 1;
+                        }
+
+                        file_info
+                    }
+                    Some(XattrRecord::Newer(version)) => {
+                        summary.newer += 1;
+                        eprintln!(
+                            "WARN: {:?}: Extended attribute was written by a newer version of dano, in format version {}, and is skipped.",
+                            path, version
+                        );
+                        return None;
+                    }
+                    Some(XattrRecord::Unparseable(err)) => {
+                        summary.unparseable += 1;
+                        eprintln!(
+                            "WARN: {:?}: Extended attribute could not be parsed: {}",
+                            path, err
+                        );
+                        return None;
+                    }
+                    Some(XattrRecord::PermissionDenied) => {
+                        eprintln!(
+                            "WARN: {:?}: Permission denied, extended attribute cannot be read.",
+                            path
+                        );
+                        return None;
+                    }
+                    // a sidecar, which is read below, is recorded in place of an extended attribute
+                    None if Sidecar::exists(path) => return None,
                     None => {
                         eprintln!(
                             "WARN: No dano extended attribute exists for path: {:?}",
                             path
                         );
-                        None
-                    }
-                })
-                .map(|(path, file_info)| {
-                    // use the actual path name always
-                    if path != &file_info.path {
-                        return FileInfo {
-                            version: file_info.version,
-                            path: path.to_owned(),
-                            metadata: file_info.metadata,
-                        };
+                        return None;
                     }
+                };
 
-                    file_info
+                // use the actual path name always
+                Some(FileInfo {
+                    path: path.to_owned(),
+                    ..file_info
                 })
-                .collect()
-        };
+            })
+            .collect();
+
+        if !config.opt_silent {
+            summary.print();
+        }
+
+        if config.opt_strict_schema && summary.newer > 0 {
+            let msg = format!(
+                "{} extended attributes were written by a newer version of dano, which this version cannot read.  \
+                Upgrade dano, or remove --strict-schema to skip such records.",
+                summary.newer
+            );
+            return Err(DanoError::new(&msg).into());
+        }
 
-        if config.hash_file.exists() {
+        let file_info_from_sidecars: Vec<FileInfo> = config
+            .paths
+            .par_iter()
+            .filter_map(|path| Sidecar::read(path))
+            .collect();
+
+        file_info_from_xattrs.extend(file_info_from_sidecars);
+
+        if hash_file_exists(&config.hash_file) {
             let file_info_from_file = read_file_info_from_file(config)?;
             file_info_from_xattrs.extend(file_info_from_file);
         }
@@ -113,25 +334,88 @@ impl RecordedFileInfo {
         Ok(file_info_from_xattrs)
     }
 
-    fn read_file_info_from_xattr(path: &Path) -> Option<FileInfo> {
-        fn inner(path: &Path) -> DanoResult<Option<FileInfo>> {
-            if let Some(bytes) = xattr::get(path, DANO_XATTR_KEY_NAME)? {
-                let line = std::str::from_utf8(&bytes)?;
-                let res = deserialize(line)?;
+    pub fn read_file_info_from_xattr(path: &Path) -> Option<FileInfo> {
+        // key idea is to let errors be printed but also let the files, which have errors,
+        // to have those errors be flattened
+        match Self::read_xattr_record(path)? {
+            XattrRecord::Current(file_info) | XattrRecord::Legacy(file_info, _) => Some(file_info),
+            XattrRecord::Unparseable(err) => {
+                eprintln!("ERROR: {:?}", err);
+                None
+            }
+            XattrRecord::Newer(_) | XattrRecord::PermissionDenied => None,
+        }
+    }
 
-                Ok(Some(res))
-            } else {
-                Ok(None)
+    // legacy records are converted to the latest version as they are read, but are reported,
+    // because they are only converted in memory, unless upgraded
+    fn read_xattr_record(path: &Path) -> Option<XattrRecord> {
+        let bytes = match get_dano_xattr(path) {
+            Ok(opt_bytes) => opt_bytes?,
+            Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+                return Some(XattrRecord::PermissionDenied)
             }
+            Err(err) => return Some(XattrRecord::Unparseable(err.to_string())),
+        };
+
+        let line = match std::str::from_utf8(&bytes) {
+            Ok(line) => line,
+            Err(err) => return Some(XattrRecord::Unparseable(err.to_string())),
+        };
+
+        let record = match (deserialize_version(line), deserialize(line)) {
+            (Some(version), Err(_)) if version > DANO_FILE_INFO_VERSION => {
+                XattrRecord::Newer(version)
+            }
+            (_, Err(err)) => XattrRecord::Unparseable(err.to_string()),
+            (Some(version), Ok(file_info)) if version != DANO_FILE_INFO_VERSION => {
+                XattrRecord::Legacy(file_info, version)
+            }
+            (_, Ok(file_info)) => XattrRecord::Current(file_info),
+        };
+
+        Some(record)
+    }
+
+    // returns whether the xattr was upgraded
+    fn upgrade_xattr(config: &Config, path: &Path, file_info: &FileInfo, version: usize) -> bool {
+        if !config.opt_upgrade_xattrs {
+            eprintln!(
+                "WARN: {:?}: Extended attribute is stored in legacy format version {}.  Use --upgrade-xattrs to upgrade it in place.",
+                path, version
+            );
+            return false;
         }
 
-        // key idea is to let errors be printed but also let the files, which have errors,
-        // to have those errors be flattened
-        match inner(path) {
-            Ok(res) => res,
+        if config.opt_dry_run {
+            eprintln!(
+                "WARN: Not upgrading extended attribute (because dry run was specified) for: {:?}",
+                path
+            );
+            return false;
+        }
+
+        let upgraded = FileInfo {
+            path: path.to_owned(),
+            ..file_info.clone()
+        };
+
+        match write_non_file(&upgraded) {
+            Ok(_) => {
+                if !config.opt_silent {
+                    eprintln!(
+                        "Upgraded extended attribute from legacy format version {} for: {:?}",
+                        version, path
+                    );
+                }
+                true
+            }
             Err(err) => {
-                eprintln!("ERROR: {:?}", err);
-                None
+                eprintln!(
+                    "WARN: {:?}: Could not upgrade extended attribute: {}",
+                    path, err
+                );
+                false
             }
         }
     }
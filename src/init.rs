@@ -0,0 +1,204 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::BTreeSet,
+    io::{BufRead, IsTerminal, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::backup::HashFileBackup;
+use crate::config::InitConfig;
+use crate::ingest::RecordedFileInfo;
+use crate::lookup::FileInfoLookup;
+use crate::output::WriteableFileInfo;
+use crate::process::{FileMap, ProcessedFiles};
+use crate::requests::{FileInfoRequest, RequestBundle};
+use crate::utility::{prepare_thread_pool, print_err_buf};
+use crate::{Config, DanoError, DanoResult, DANO_CLEAN_EXIT_CODE};
+
+// each batch is appended to disk when complete, so an interrupted run loses at most one batch
+const INIT_BATCH_SIZE: usize = 256;
+// used to estimate how long the initial run will take
+const ESTIMATED_BYTES_PER_SECOND: f64 = 50_000_000.0;
+const BYTES_PER_MEGABYTE: f64 = 1_000_000.0;
+const BYTES_PER_GIGABYTE: f64 = 1_000_000_000.0;
+
+pub struct InitWizard;
+
+impl InitWizard {
+    pub fn exec(
+        config: &Config,
+        init_config: &InitConfig,
+        recorded_file_info: RecordedFileInfo,
+    ) -> DanoResult<i32> {
+        // paths already recorded, in a hash file or an extended attribute, are complete,
+        // which is what makes an interrupted run resumable
+        let recorded_paths: BTreeSet<&Path> = recorded_file_info
+            .iter()
+            .filter(|file_info| file_info.metadata.is_some())
+            .map(|file_info| file_info.path.as_path())
+            .collect();
+
+        let pending: Vec<(PathBuf, u64)> = config
+            .paths
+            .iter()
+            .filter(|path| !recorded_paths.contains(path.as_path()))
+            .map(|path| {
+                let len = path.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+                (path.clone(), len)
+            })
+            .collect();
+
+        let total_bytes: u64 = pending.iter().map(|(_path, len)| len).sum();
+
+        let estimate = Duration::from_secs_f64(total_bytes as f64 / ESTIMATED_BYTES_PER_SECOND);
+
+        print_err_buf(&format!(
+            "Found {} media files in {:?}, {} of which are already recorded.  {} files ({:.1} GB) remain to be hashed.  \
+            Estimated time: {}.\n",
+            config.paths.len(),
+            init_config.dir,
+            config.paths.len() - pending.len(),
+            pending.len(),
+            total_bytes as f64 / BYTES_PER_GIGABYTE,
+            Self::format_duration(estimate)
+        ))?;
+
+        if pending.is_empty() {
+            print_err_buf("Nothing to do.\n")?;
+            return Ok(DANO_CLEAN_EXIT_CODE);
+        }
+
+        if !init_config.opt_yes && !config.opt_dry_run && !Self::confirm()? {
+            return Err(DanoError::new("Initial run was cancelled.").into());
+        }
+
+        let file_map = FileMap::new(recorded_file_info.into_inner());
+        let started = Instant::now();
+        let mut bytes_done: u64 = 0;
+        let mut files_done: usize = 0;
+        let mut exit_code = DANO_CLEAN_EXIT_CODE;
+
+        // each batch is appended to the hash file, which is backed up once, before the first batch,
+        // and rewritten once, after the last
+        let is_appended = WriteableFileInfo::is_appended(config);
+
+        if is_appended {
+            HashFileBackup::exec(config, &config.output_file)?;
+        }
+
+        let mut batch_config = Config {
+            paths: Vec::new(),
+            num_backups: 0,
+            ..config.clone()
+        };
+
+        for batch in pending.chunks(INIT_BATCH_SIZE) {
+            batch_config.paths = batch.iter().map(|(path, _len)| path.clone()).collect();
+
+            let batch_exit_code = Self::exec_batch(&batch_config, &file_map)?;
+
+            if batch_exit_code != DANO_CLEAN_EXIT_CODE {
+                exit_code = batch_exit_code;
+            }
+
+            files_done += batch.len();
+            bytes_done += batch.iter().map(|(_path, len)| len).sum::<u64>();
+
+            if let Some(max_rate) = init_config.opt_max_rate {
+                Self::throttle(started, bytes_done, max_rate);
+            }
+
+            let elapsed = started.elapsed().as_secs_f64();
+            let rate = if elapsed > 0.0 {
+                bytes_done as f64 / elapsed
+            } else {
+                0.0
+            };
+
+            print_err_buf(&format!(
+                "Progress: {}/{} files, {:.1}/{:.1} GB ({:.0}%), {:.1} MB/s\n",
+                files_done,
+                pending.len(),
+                bytes_done as f64 / BYTES_PER_GIGABYTE,
+                total_bytes as f64 / BYTES_PER_GIGABYTE,
+                100.0 * bytes_done as f64 / total_bytes.max(1) as f64,
+                rate / BYTES_PER_MEGABYTE
+            ))?;
+        }
+
+        if is_appended && batch_config.output_file.exists() {
+            WriteableFileInfo::dedup_and_rewrite(&batch_config)?;
+        }
+
+        Ok(exit_code)
+    }
+
+    fn exec_batch(config: &Config, file_map: &FileMap) -> DanoResult<i32> {
+        let thread_pool = prepare_thread_pool(config)?;
+
+        let file_info_requests: Vec<FileInfoRequest> =
+            RequestBundle::new(config, &[])?.into_inner();
+
+        let (rx_item, _rx_unsupported) =
+            FileInfoLookup::exec(config, file_info_requests.into(), thread_pool)?;
+        let processed_files = ProcessedFiles::with_file_map(config, file_map, rx_item, None)?;
+
+        processed_files.write_out(config)
+    }
+
+    // sleep until the average rate since the run started is no more than the maximum rate
+    fn throttle(started: Instant, bytes_done: u64, max_rate: u64) {
+        let target =
+            Duration::from_secs_f64(bytes_done as f64 / (max_rate as f64 * BYTES_PER_MEGABYTE));
+
+        if let Some(remaining) = target.checked_sub(started.elapsed()) {
+            std::thread::sleep(remaining);
+        }
+    }
+
+    fn confirm() -> DanoResult<bool> {
+        let stdin = std::io::stdin();
+
+        if !stdin.is_terminal() {
+            return Err(DanoError::new(
+                "Cannot ask for confirmation, because stdin is not a terminal.  Specify --yes to proceed.",
+            )
+            .into());
+        }
+
+        eprint!("Proceed? [y/N] ");
+        std::io::stderr().flush()?;
+
+        let mut answer = String::new();
+        stdin.lock().read_line(&mut answer)?;
+
+        Ok(matches!(answer.trim(), "y" | "Y" | "yes" | "Yes" | "YES"))
+    }
+
+    fn format_duration(duration: Duration) -> String {
+        let secs = duration.as_secs();
+
+        match secs {
+            0..=59 => "less than a minute".to_owned(),
+            60..=3599 => format!("{} minutes", secs / 60),
+            _ => format!("{} hours, {} minutes", secs / 3600, (secs % 3600) / 60),
+        }
+    }
+}
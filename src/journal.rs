@@ -0,0 +1,140 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    ffi::OsString,
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+};
+
+use crate::lookup::FileInfo;
+use crate::utility::{make_tmp_file, print_err_buf, serialize, write_non_file};
+use crate::{Config, DanoResult};
+
+const JOURNAL_EXTENSION: &str = "journal";
+
+// before a batch of extended attributes is written, every intended write is recorded in a journal,
+// next to the hash file, and the journal is removed once the batch is complete.  should a run die
+// midway, the next run rolls the journal forward, so either every file in the batch is stamped, or,
+// where a file has since changed, the user is told which file was not
+pub struct XattrJournal {
+    path: PathBuf,
+}
+
+impl XattrJournal {
+    pub fn new(config: &Config) -> Self {
+        let mut path: OsString = config.output_file.as_os_str().to_owned();
+        path.push(".");
+        path.push(JOURNAL_EXTENSION);
+
+        Self {
+            path: PathBuf::from(path),
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    // paths are recorded as absolute paths, because the next run may have another working directory
+    pub fn begin(&self, config: &Config, file_info: &[FileInfo]) -> DanoResult<()> {
+        let tmp_file = make_tmp_file(&self.path);
+
+        let mut buffer = String::new();
+        for item in file_info {
+            let absolute = FileInfo {
+                path: config.pwd.join(&item.path),
+                ..item.clone()
+            };
+            buffer.push_str(&serialize(&absolute)?);
+        }
+
+        // the journal must be on disk, in full, before the first extended attribute is written
+        let mut file = std::fs::File::create(&tmp_file)?;
+        file.write_all(buffer.as_bytes())?;
+        file.sync_all()?;
+
+        std::fs::rename(&tmp_file, &self.path).map_err(|err| err.into())
+    }
+
+    pub fn commit(&self) -> DanoResult<()> {
+        std::fs::remove_file(&self.path).map_err(|err| err.into())
+    }
+
+    pub fn replay(&self, config: &Config) -> DanoResult<()> {
+        if !self.exists() {
+            return Ok(());
+        }
+
+        if config.opt_dry_run || config.opt_read_only {
+            return print_err_buf(&format!(
+                "WARN: Not replaying interrupted extended attribute writes (because dry run or read only was specified): {:?}\n",
+                self.path
+            ));
+        }
+
+        let reader = BufReader::new(std::fs::File::open(&self.path)?);
+
+        for line in reader.lines() {
+            let line = line?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // a journal line is only incomplete when the run died while writing the journal itself,
+            // and then no extended attribute in the batch was written
+            let file_info: FileInfo = match serde_json::from_str(&line) {
+                Ok(file_info) => file_info,
+                Err(_) => continue,
+            };
+
+            Self::roll_forward(&file_info)?;
+        }
+
+        self.commit()
+    }
+
+    // a file which was modified after it was hashed is not stamped with the stale hash
+    fn roll_forward(file_info: &FileInfo) -> DanoResult<()> {
+        let Some(metadata) = &file_info.metadata else {
+            return Ok(());
+        };
+
+        let is_unchanged = matches!(
+            file_info.path.metadata().and_then(|current| current.modified()),
+            Ok(modify_time) if modify_time == metadata.modify_time
+        );
+
+        if !is_unchanged {
+            return print_err_buf(&format!(
+                "WARN: Not completing interrupted extended attribute write, because the file is missing or has changed: {:?}\n",
+                file_info.path
+            ));
+        }
+
+        match write_non_file(file_info) {
+            Ok(_) => print_err_buf(&format!(
+                "Completed interrupted extended attribute write for: {:?}\n",
+                file_info.path
+            )),
+            Err(err) => print_err_buf(&format!(
+                "ERROR: Could not complete interrupted extended attribute write for {:?}: {}\n",
+                file_info.path, err
+            )),
+        }
+    }
+}
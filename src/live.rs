@@ -0,0 +1,144 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::{Duration, SystemTime},
+};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use serde::Serialize;
+
+use crate::utility::{format_time, make_tmp_file, persist_tmp_file};
+use crate::{Config, DanoResult};
+
+const LIVE_STATUS_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Debug, Clone, Default)]
+struct LiveStatusSnapshot {
+    started: String,
+    updated: String,
+    finished: bool,
+    done: usize,
+    total: usize,
+    // the file most recently tested
+    current: Option<PathBuf>,
+    failures: usize,
+    failed_paths: Vec<PathBuf>,
+}
+
+// a small JSON file, rewritten in place, every few seconds, while a test runs, so
+// dashboards and scripts may follow along.  each rewrite is atomic, a reader never sees a partial file.
+pub struct LiveStatus {
+    path: PathBuf,
+    snapshot: Arc<Mutex<LiveStatusSnapshot>>,
+    tx_stop: Option<Sender<()>>,
+    opt_writer: Option<JoinHandle<()>>,
+}
+
+impl LiveStatus {
+    pub fn new(config: &Config, total: usize) -> Option<Self> {
+        let path = config.opt_status_file.clone()?;
+
+        let now = format_time(SystemTime::now());
+        let snapshot = Arc::new(Mutex::new(LiveStatusSnapshot {
+            started: now.clone(),
+            updated: now,
+            total,
+            ..Default::default()
+        }));
+
+        let (tx_stop, rx_stop): (Sender<()>, Receiver<()>) = crossbeam_channel::bounded(0);
+
+        let writer_path = path.clone();
+        let writer_snapshot = snapshot.clone();
+
+        // the status is written on a timer, rather than as results arrive, so a single long file
+        // does not leave the status file stale
+        let opt_writer = std::thread::spawn(move || loop {
+            Self::write_snapshot(&writer_path, &writer_snapshot);
+
+            match rx_stop.recv_timeout(LIVE_STATUS_INTERVAL) {
+                Err(RecvTimeoutError::Timeout) => continue,
+                _ => break,
+            }
+        });
+
+        Some(Self {
+            path,
+            snapshot,
+            tx_stop: Some(tx_stop),
+            opt_writer: Some(opt_writer),
+        })
+    }
+
+    pub fn update(&self, path: &Path, is_failure: bool) {
+        let Ok(mut snapshot) = self.snapshot.lock() else {
+            return;
+        };
+
+        snapshot.done += 1;
+        snapshot.current = Some(path.to_owned());
+
+        if is_failure {
+            snapshot.failures += 1;
+            snapshot.failed_paths.push(path.to_owned());
+        }
+    }
+
+    fn write_snapshot(path: &Path, snapshot: &Mutex<LiveStatusSnapshot>) {
+        let res = snapshot
+            .lock()
+            .map_err(|_| "status file snapshot lock was poisoned".into())
+            .and_then(|mut snapshot| {
+                snapshot.updated = format_time(SystemTime::now());
+                Self::write_atomic(path, &snapshot)
+            });
+
+        // the status file is a convenience, and no reason to fail a test
+        if let Err(err) = res {
+            eprintln!("WARN: Could not write status file {:?}: {}", path, err);
+        }
+    }
+
+    fn write_atomic(path: &Path, snapshot: &LiveStatusSnapshot) -> DanoResult<()> {
+        let tmp_file = make_tmp_file(path);
+        let mut buffer = serde_json::to_string_pretty(snapshot)?;
+        buffer.push('\n');
+
+        std::fs::write(&tmp_file, buffer)?;
+        persist_tmp_file(&tmp_file, path)
+    }
+}
+
+impl Drop for LiveStatus {
+    fn drop(&mut self) {
+        // stop the timer, and write the final status
+        drop(self.tx_stop.take());
+
+        if let Some(writer) = self.opt_writer.take() {
+            let _ = writer.join();
+        }
+
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            snapshot.finished = true;
+        }
+
+        Self::write_snapshot(&self.path, &self.snapshot);
+    }
+}
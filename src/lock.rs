@@ -0,0 +1,187 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    ffi::OsString,
+    fs::{File, OpenOptions, TryLockError},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::utility::{is_streamed_hash_file, print_err_buf};
+use crate::{Config, DanoError, DanoResult};
+
+pub const LOCK_EXTENSION: &str = "lock";
+// how often a lock held by another dano is tried again, while waiting for a limited time
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockWait {
+    Indefinitely,
+    AtMost(Duration),
+    NoWait,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockKind {
+    Shared,
+    Exclusive,
+}
+
+// an advisory lock, which is released when dropped, on a lock file next to the hash file, rather than on
+// the hash file itself, because a rewrite replaces the hash file, and any lock held upon it
+pub struct HashFileLock {
+    _lock_files: Vec<File>,
+}
+
+impl HashFileLock {
+    // a run which may write the output file locks it exclusively, and any other hash file it reads is shared,
+    // and the lock files are always locked in the same order, so two runs cannot each wait on the other
+    pub fn acquire(config: &Config, kind: LockKind) -> DanoResult<Self> {
+        // records streamed to stdout require no lock at all, except upon the hash file read
+        let mut locks: Vec<(PathBuf, LockKind)> = match kind {
+            _ if config.opt_output_stdout => Vec::new(),
+            LockKind::Exclusive => vec![(Self::lock_path(&config.output_file), kind)],
+            LockKind::Shared => Vec::new(),
+        };
+
+        // a hash file piped in, or fetched, cannot be rewritten while it is read, and requires no lock
+        if (kind == LockKind::Shared
+            || config.opt_output_stdout
+            || config.hash_file != config.output_file)
+            && !is_streamed_hash_file(&config.hash_file)
+        {
+            locks.push((Self::lock_path(&config.hash_file), LockKind::Shared));
+        }
+
+        locks.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let lock_files = locks
+            .iter()
+            .filter_map(|(lock_path, kind)| Self::open(config, lock_path, *kind).transpose())
+            .map(|res| {
+                res.and_then(|(lock_file, lock_path, kind)| {
+                    Self::lock(config, &lock_file, &lock_path, kind)?;
+                    Ok(lock_file)
+                })
+            })
+            .collect::<DanoResult<Vec<File>>>()?;
+
+        Ok(Self {
+            _lock_files: lock_files,
+        })
+    }
+
+    fn lock_path(path: &Path) -> PathBuf {
+        let mut lock_path: OsString = path.as_os_str().to_owned();
+        lock_path.push(".");
+        lock_path.push(LOCK_EXTENSION);
+
+        PathBuf::from(lock_path)
+    }
+
+    // only a run which may write the hash file creates a lock file, so a run which only reads it, or a read only run,
+    // locks only a lock file which already exists
+    fn open(
+        config: &Config,
+        lock_path: &Path,
+        kind: LockKind,
+    ) -> DanoResult<Option<(File, PathBuf, LockKind)>> {
+        let res = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(kind == LockKind::Exclusive && !config.opt_read_only)
+            .truncate(false)
+            .open(lock_path);
+
+        match res {
+            Ok(lock_file) => Ok(Some((lock_file, lock_path.to_owned(), kind))),
+            Err(_) if kind == LockKind::Shared => Ok(None),
+            Err(err) => {
+                let msg = format!(
+                    "Could not open the lock file of the hash file: {:?}: {}",
+                    lock_path, err
+                );
+                Err(DanoError::new(&msg).into())
+            }
+        }
+    }
+
+    fn try_lock(lock_file: &File, kind: LockKind) -> Result<(), TryLockError> {
+        match kind {
+            LockKind::Shared => lock_file.try_lock_shared(),
+            LockKind::Exclusive => lock_file.try_lock(),
+        }
+    }
+
+    fn lock(config: &Config, lock_file: &File, lock_path: &Path, kind: LockKind) -> DanoResult<()> {
+        match Self::try_lock(lock_file, kind) {
+            Ok(_) => return Ok(()),
+            Err(TryLockError::WouldBlock) => (),
+            // locking is advisory, and a filesystem which does not support locks is no reason to fail
+            Err(TryLockError::Error(err)) => {
+                return print_err_buf(&format!(
+                    "WARN: Could not lock the hash file, and will continue without a lock: {:?}: {}\n",
+                    lock_path, err
+                ));
+            }
+        }
+
+        if config.opt_lock_wait == LockWait::NoWait {
+            let msg = format!(
+                "Another dano holds the lock on the hash file: {:?}.  Quitting, as --no-wait was specified.",
+                lock_path
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        if !config.opt_silent {
+            print_err_buf(&format!(
+                "Waiting for another dano to release its lock on the hash file: {:?}\n",
+                lock_path
+            ))?;
+        }
+
+        match config.opt_lock_wait {
+            LockWait::AtMost(duration) => {
+                let started = Instant::now();
+
+                loop {
+                    std::thread::sleep(LOCK_RETRY_INTERVAL);
+
+                    match Self::try_lock(lock_file, kind) {
+                        Ok(_) => return Ok(()),
+                        Err(TryLockError::WouldBlock) if started.elapsed() < duration => continue,
+                        Err(TryLockError::WouldBlock) => {
+                            let msg = format!(
+                                "Timed out after waiting {} seconds for another dano to release its lock on the hash file: {:?}",
+                                duration.as_secs(),
+                                lock_path
+                            );
+                            return Err(DanoError::new(&msg).into());
+                        }
+                        Err(TryLockError::Error(err)) => return Err(err.into()),
+                    }
+                }
+            }
+            _ => match kind {
+                LockKind::Shared => lock_file.lock_shared(),
+                LockKind::Exclusive => lock_file.lock(),
+            }
+            .map_err(|err| err.into()),
+        }
+    }
+}
@@ -17,21 +17,57 @@
 
 use std::{
     cmp::{Ord, Ordering, PartialOrd},
+    collections::BTreeMap,
+    ffi::OsStr,
+    io::Read,
     path::{Path, PathBuf},
-    process::Command as ExecProcess,
-    time::SystemTime,
+    process::{Command as ExecProcess, Stdio},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use crossbeam_channel::{Receiver, Sender};
 use rayon::ThreadPool;
 use serde::{Deserialize, Serialize};
-use which::which;
 
-use crate::config::{OptFlacBitsPerSecond, SelectedStreams};
+use crate::config::{is_permission_denied, DecodeFormat, OptFlacBitsPerSecond, SelectedStreams};
+use crate::cue::{CueSheet, TrackHash};
+use crate::ffmpeg::{
+    parse_hash_line, FFmpegCapabilities, FFmpegError, FFmpegErrorKind, FFMPEG_LOCALE,
+    FFMPEG_QUIET_ARGS,
+};
+use crate::frames::FrameDigests;
+use crate::hasher::NativeHasher;
+use crate::mediainfo::{MediaInfo, VideoFormat};
+use crate::pcm::PcmHasher;
+use crate::probe::{AudioDepth, FFProbe};
+use crate::progress::Progress;
 use crate::requests::{FileInfoRequest, RequestBundle};
-use crate::utility::DanoError;
+use crate::streamhash::{StreamHash, StreamHashes};
+use crate::tags::TagsHash;
+use crate::utility::{print_err_buf, shell_quote_command, DanoError};
 use crate::{Config, DanoResult, DANO_FILE_INFO_VERSION, HEXADECIMAL_RADIX};
 
+const CHANNEL_SLOTS_PER_THREAD: usize = 4;
+
+// ffmpeg's piped stream data is read in chunks of this size
+const PIPE_BUFFER_SIZE: usize = 1024 * 1024;
+
+// ffmpeg output options which resample or remix audio, with or without a stream specifier, like "-ar:a"
+const AUDIO_CONVERSION_ARGS: [&str; 9] = [
+    "-ar",
+    "-ac",
+    "-af",
+    "-filter",
+    "-filter_complex",
+    "-lavfi",
+    "-channel_layout",
+    "-ch_layout",
+    "-sample_rate",
+];
+
+// the index of a group of requests, in request order, and its results
+type GroupResults = (usize, Vec<FileInfo>);
+
 pub struct FileInfoLookup;
 
 impl FileInfoLookup {
@@ -39,42 +75,202 @@ impl FileInfoLookup {
         config: &Config,
         requested_paths: RequestBundle,
         thread_pool: ThreadPool,
-    ) -> DanoResult<Receiver<FileInfo>> {
+    ) -> DanoResult<(Receiver<FileInfo>, Receiver<PathBuf>)> {
+        // a bounded channel provides backpressure: when the consumer, which compares each item
+        // to the recorded file info, falls behind, hashing threads block on send, instead of
+        // queuing an unlimited number of results in memory.  a few slots per thread is enough
+        // to keep every thread busy, while the consumer catches up.
+        let channel_capacity = thread_pool.current_num_threads() * CHANNEL_SLOTS_PER_THREAD;
         let (tx_item, rx_item): (Sender<FileInfo>, Receiver<FileInfo>) =
-            crossbeam_channel::unbounded();
+            crossbeam_channel::bounded(channel_capacity);
 
-        let requested_paths_clone = requested_paths.into_inner();
+        let request_groups = Self::group_hardlinks(requested_paths.into_inner());
+
+        // in a stable order, the results of each group, or none, when the group failed, are sent together,
+        // with the index of the group, so that every group is known to be complete, whether or not it failed
+        let (opt_tx_ordered, opt_rx_ordered) = if config.opt_stable_order {
+            let (tx_ordered, rx_ordered): (Sender<GroupResults>, Receiver<GroupResults>) =
+                crossbeam_channel::bounded(channel_capacity);
+            (Some(tx_ordered), Some(rx_ordered))
+        } else {
+            (None, None)
+        };
+
+        // paths which ffmpeg could not hash, because the codec or container is unsupported
+        let (tx_unsupported, rx_unsupported): (Sender<PathBuf>, Receiver<PathBuf>) =
+            crossbeam_channel::unbounded();
 
         let config_clone = config.clone();
         let tx_item_clone = tx_item;
 
         std::thread::spawn(move || {
+            let opt_progress = Progress::new(&config_clone, &request_groups);
+
             // exec threads to hash files
             thread_pool.in_place_scope(|file_info_scope| {
-                requested_paths_clone.iter().for_each(|request| {
+                request_groups.iter().enumerate().for_each(|(idx, group)| {
                     let config = &config_clone;
                     let tx_item = &tx_item_clone;
+                    let opt_tx_ordered = opt_tx_ordered.as_ref();
+                    let tx_unsupported = &tx_unsupported;
+                    let opt_progress = opt_progress.as_ref();
 
                     file_info_scope.spawn(move |_| {
-                        if let Err(err) = FileInfo::generate(config, request, tx_item) {
+                        if let Some(progress) = opt_progress {
+                            progress.start(&group[0].path);
+                        }
+
+                        let res = match opt_tx_ordered {
+                            Some(tx_ordered) => {
+                                let (tx_group, rx_group): (Sender<FileInfo>, Receiver<FileInfo>) =
+                                    crossbeam_channel::unbounded();
+                                let res = Self::generate_group(config, group, &tx_group);
+                                drop(tx_group);
+
+                                let _ = tx_ordered.send((idx, rx_group.into_iter().collect()));
+                                res
+                            }
+                            None => Self::generate_group(config, group, tx_item),
+                        };
+
+                        if let Err(err) = res {
                             // probably want to see the error, but not exit the process
                             // when there is an error in a single request/thread
-                            eprintln!("ERROR: {:?} from issued request {:?}", err, request);
+                            eprintln!("ERROR: {:?} from issued request {:?}", err, group[0]);
+
+                            if Self::is_unsupported(err.as_ref()) {
+                                group.iter().for_each(|request| {
+                                    let _ = tx_unsupported.send(request.path.clone());
+                                });
+                            }
+                        }
+
+                        if let Some(progress) = opt_progress {
+                            progress.finish(group);
                         }
                     })
                 });
             });
         });
 
+        if let Some(rx_ordered) = opt_rx_ordered {
+            return Ok((Self::reorder(rx_ordered, channel_capacity), rx_unsupported));
+        }
+
         // implicitly drop tx_item at end of scope, otherwise we will hold onto the ref and loop forever
         // explicit drop is: drop(tx_item);
-        Ok(rx_item)
+        Ok((rx_item, rx_unsupported))
+    }
+
+    // ffmpeg errors which will recur on every attempt, until the file itself changes
+    fn is_unsupported(err: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+        matches!(
+            FFmpegError::kind_of(err),
+            Some(FFmpegErrorKind::Unsupported)
+        )
+    }
+
+    fn generate_group(
+        config: &Config,
+        group: &[FileInfoRequest],
+        tx_item: &Sender<FileInfo>,
+    ) -> DanoResult<()> {
+        match group {
+            [request] => FileInfo::generate(config, request, tx_item),
+            _ => FileInfo::generate_hardlinked(config, group, tx_item),
+        }
+    }
+
+    // re-emit results in request order, group by group: the results of a group are held back only until
+    // every earlier group is complete, and, as a failed group is complete too, never until the lookup is
+    fn reorder(
+        rx_unordered: Receiver<GroupResults>,
+        channel_capacity: usize,
+    ) -> Receiver<FileInfo> {
+        let (tx_item, rx_item): (Sender<FileInfo>, Receiver<FileInfo>) =
+            crossbeam_channel::bounded(channel_capacity);
+
+        std::thread::spawn(move || {
+            let mut held_back: BTreeMap<usize, Vec<FileInfo>> = BTreeMap::new();
+            let mut next_idx = 0usize;
+
+            for (idx, file_infos) in rx_unordered.iter() {
+                held_back.insert(idx, file_infos);
+
+                while let Some(file_infos) = held_back.remove(&next_idx) {
+                    next_idx += 1;
+
+                    for file_info in file_infos {
+                        if tx_item.send(file_info).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+
+            // only a group whose thread panicked is never complete, and what follows it is flushed in order
+            for file_info in held_back.into_values().flatten() {
+                if tx_item.send(file_info).is_err() {
+                    return;
+                }
+            }
+        });
+
+        rx_item
+    }
+
+    // paths which are hardlinks to the same inode have the same contents, so we
+    // group these requests together, and only read the contents once per group
+    fn group_hardlinks(requests: Vec<FileInfoRequest>) -> Vec<Vec<FileInfoRequest>> {
+        let mut groups: Vec<Vec<FileInfoRequest>> = Vec::new();
+        let mut inode_map: BTreeMap<(u64, u64), Vec<usize>> = BTreeMap::new();
+
+        requests.into_iter().for_each(|request| {
+            let Some(inode_key) = Self::get_inode_key(&request.path) else {
+                groups.push(vec![request]);
+                return;
+            };
+
+            let indices = inode_map.entry(inode_key).or_default();
+
+            match indices
+                .iter()
+                .find(|idx| groups[**idx][0].differs_only_by_path(&request))
+            {
+                Some(idx) => groups[*idx].push(request),
+                None => {
+                    indices.push(groups.len());
+                    groups.push(vec![request]);
+                }
+            }
+        });
+
+        groups
+    }
+
+    #[cfg(unix)]
+    fn get_inode_key(path: &Path) -> Option<(u64, u64)> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = path.metadata().ok()?;
+
+        if metadata.nlink() > 1 {
+            Some((metadata.dev(), metadata.ino()))
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn get_inode_key(_path: &Path) -> Option<(u64, u64)> {
+        None
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct FileInfo {
     pub version: usize,
+    #[serde(with = "crate::serde_path")]
     pub path: PathBuf,
     pub metadata: Option<FileMetadata>,
 }
@@ -82,7 +278,7 @@ pub struct FileInfo {
 impl PartialOrd for FileInfo {
     #[inline]
     fn partial_cmp(&self, other: &FileInfo) -> Option<Ordering> {
-        Some(self.path.cmp(&other.path))
+        Some(self.cmp(other))
     }
 }
 
@@ -108,6 +304,60 @@ pub struct FileMetadata {
     pub decoded: bool,
     pub selected_streams: SelectedStreams,
     pub opt_bits_per_second: OptFlacBitsPerSecond,
+    #[serde(default)]
+    pub opt_media_info: Option<MediaInfo>,
+    #[serde(default)]
+    pub hardlinked: bool,
+    #[serde(default)]
+    pub fallback: bool,
+    #[serde(default)]
+    pub opt_video_format: Option<VideoFormat>,
+    // hashed by dano itself, rather than ffmpeg, such as the sample data of an uncompressed audio file
+    #[serde(default)]
+    pub native: bool,
+    // per range digests of each stream's frames, so a mismatch can be localized
+    #[serde(default)]
+    pub opt_frame_digests: Option<FrameDigests>,
+    // the hash of each stream, so a mismatch can be attributed to a stream
+    #[serde(default)]
+    pub opt_stream_hashes: Option<Vec<StreamHash>>,
+    // the size of the file when hashed, so a quick test can skip a file which is plainly unchanged
+    #[serde(default)]
+    pub opt_size: Option<u64>,
+    // when the file was last tested and found to match, none if never tested since being recorded
+    #[serde(default)]
+    pub opt_last_checked: Option<SystemTime>,
+    // a hash of the tags, apart from the streams, so a tag only edit can be told from a change to the streams
+    #[serde(default)]
+    pub opt_tags_hash: Option<Box<str>>,
+    // attached pictures, such as cover art, were excluded from the streams hashed, older records included them
+    #[serde(default)]
+    pub cover_art_excluded: bool,
+    // the sample format of decoded audio, when given explicitly, rather than detected
+    #[serde(default)]
+    pub opt_decode_format: Option<DecodeFormat>,
+    // the hash of each track of a single file album, as described by a CUE sheet beside it
+    #[serde(default)]
+    pub opt_track_hashes: Option<Vec<TrackHash>>,
+}
+
+impl FileMetadata {
+    // a file is checked when it is first hashed
+    pub fn last_checked(&self) -> SystemTime {
+        self.opt_last_checked.unwrap_or(self.last_written)
+    }
+
+    // the file on disk has the same modify time, and the same size, if a size was recorded
+    pub fn is_unchanged(&self, path: &Path) -> bool {
+        let Ok(path_metadata) = path.metadata() else {
+            return false;
+        };
+
+        path_metadata
+            .modified()
+            .is_ok_and(|modify_time| modify_time == self.modify_time)
+            && self.opt_size.is_none_or(|size| size == path_metadata.len())
+    }
 }
 
 impl FileInfo {
@@ -116,25 +366,439 @@ impl FileInfo {
         request: &FileInfoRequest,
         tx_item: &Sender<FileInfo>,
     ) -> DanoResult<()> {
-        if let Ok(ffmpeg_command) = which("ffmpeg") {
-            let decoded = match request.decoded {
-                Some(decoded) => decoded,
-                None => config.opt_decode,
+        let (selected_streams, fallback) = FileInfo::get_selected_streams(config, request);
+        let selected_streams = &selected_streams;
+
+        let request = &FileInfoRequest {
+            tags_hash: Some(request.tags_hash.unwrap_or(config.opt_tags_hash)),
+            exclude_cover_art: Some(
+                request
+                    .exclude_cover_art
+                    .unwrap_or(!config.opt_include_cover_art),
+            ),
+            cue_tracks: Some(request.cue_tracks.unwrap_or(config.opt_cue_tracks)),
+            ..request.clone()
+        };
+
+        // whole file hashes don't require ffmpeg at all
+        if let SelectedStreams::WholeFile = selected_streams {
+            let hash_algo = match &request.hash_algo {
+                Some(hash_algo) => hash_algo,
+                None => config.new_hash_algo(&request.path),
             };
-            let stdout_string =
-                FileInfo::get_hash_value(config, request, &ffmpeg_command, decoded)?;
-            FileInfo::transmit_file_info(
+            let stdout_string = NativeHasher::hash_file(&request.path, hash_algo)?;
+
+            return FileInfo::transmit_file_info(
+                config,
                 request,
                 &stdout_string,
                 tx_item,
+                false,
+                selected_streams,
+                fallback,
+            );
+        }
+
+        let decoded = match request.decoded {
+            Some(decoded) => decoded,
+            None => config.new_decoded(&request.path),
+        };
+
+        // an image has only a single frame, and a single stream
+        let is_image = matches!(selected_streams, SelectedStreams::ImageOnly);
+        let frame_hash = request.frame_hash.unwrap_or(config.opt_frame_hash) && !is_image;
+        let stream_hash = request.stream_hash.unwrap_or(config.opt_stream_hash) && !is_image;
+
+        if !frame_hash
+            && !stream_hash
+            && FileInfo::is_native(config, request, decoded, selected_streams)
+        {
+            let hash_algo = match &request.hash_algo {
+                Some(hash_algo) => hash_algo,
+                None => config.new_hash_algo(&request.path),
+            };
+            let stdout_string = PcmHasher::hash_file(&request.path, hash_algo)?;
+
+            return FileInfo::transmit_file_info(
+                config,
+                &FileInfoRequest {
+                    native: Some(true),
+                    ..request.clone()
+                },
+                &stdout_string,
+                tx_item,
                 decoded,
-                &config.selected_streams,
+                selected_streams,
+                fallback,
+            );
+        }
+
+        let ffmpeg_command = config.ffmpeg_command()?;
+
+        let request = &FileInfoRequest {
+            decode_format: FileInfo::get_decode_format(config, request, decoded, selected_streams),
+            ..request.clone()
+        };
+        let (decode_format, bits_per_second) =
+            FileInfo::get_sample_format(config, request, decoded, selected_streams);
+        let request = &FileInfoRequest {
+            video_format: FileInfo::get_video_format(config, request, decoded, selected_streams),
+            decode_format,
+            bits_per_second,
+            frame_hash: Some(frame_hash),
+            stream_hash: Some(stream_hash),
+            ..request.clone()
+        };
+        let stdout_string =
+            FileInfo::get_hash_value(config, request, &ffmpeg_command, decoded, selected_streams)?;
+
+        let opt_track_hashes =
+            FileInfo::get_track_hashes(config, request, &ffmpeg_command, decoded, selected_streams);
+
+        if frame_hash || stream_hash || opt_track_hashes.is_some() {
+            let hash_algo = match &request.hash_algo {
+                Some(hash_algo) => hash_algo,
+                None => config.new_hash_algo(&request.path),
+            };
+
+            let (hash_line, opt_frame_digests, opt_stream_hashes) = if frame_hash {
+                let (hash_line, frame_digests) =
+                    FrameDigests::from_framehash_output(&stdout_string, hash_algo)?;
+                (hash_line, Some(frame_digests), None)
+            } else if stream_hash {
+                let (hash_line, stream_hashes) =
+                    StreamHashes::from_streamhash_output(&stdout_string, hash_algo)?;
+                (hash_line, None, Some(stream_hashes))
+            } else {
+                (stdout_string.to_string(), None, None)
+            };
+
+            let mut file_info = FileInfo::new_file_info(
+                config,
+                request,
+                &hash_line,
+                decoded,
+                selected_streams,
+                fallback,
+            )?;
+
+            if let Some(metadata) = file_info.metadata.as_mut() {
+                metadata.opt_frame_digests = opt_frame_digests;
+                metadata.opt_stream_hashes = opt_stream_hashes;
+                metadata.opt_track_hashes = opt_track_hashes;
+            }
+
+            return tx_item.send(file_info).map_err(|err| err.into());
+        }
+
+        FileInfo::transmit_file_info(
+            config,
+            request,
+            &stdout_string,
+            tx_item,
+            decoded,
+            selected_streams,
+            fallback,
+        )
+    }
+
+    // the decoded audio of each track of a single file album, hashed on its own, at the same bit depth as the album,
+    // so a track may later be matched to a split of the album, or to a re-rip.  a CUE sheet which cannot be read
+    // is no reason not to record the album itself
+    fn get_track_hashes(
+        config: &Config,
+        request: &FileInfoRequest,
+        ffmpeg_command: &Path,
+        decoded: bool,
+        selected_streams: &SelectedStreams,
+    ) -> Option<Vec<TrackHash>> {
+        if request.cue_tracks != Some(true) {
+            return None;
+        }
+
+        let cue_path = CueSheet::find(&request.path)?;
+
+        if !decoded
+            || !matches!(
+                selected_streams,
+                SelectedStreams::All | SelectedStreams::AudioOnly
             )
-        } else {
-            Err(DanoError::new(
-                "'ffmpeg' command not found. Make sure the command 'ffmpeg' is in your path.",
+        {
+            eprintln!(
+                "WARN: CUE sheet tracks are only hashed when the audio is decoded, skipping tracks of: {:?}",
+                request.path
+            );
+            return None;
+        }
+
+        let res = CueSheet::read(&cue_path).and_then(|cue_sheet| {
+            cue_sheet
+                .regions()
+                .map(|region| {
+                    let mut input_args = request.input_args.clone().unwrap_or_default();
+                    input_args.extend(region.as_input_args());
+
+                    let track_request = FileInfoRequest {
+                        input_args: Some(input_args),
+                        frame_hash: Some(false),
+                        stream_hash: Some(false),
+                        ..request.clone()
+                    };
+
+                    let stdout_string = FileInfo::get_hash_value(
+                        config,
+                        &track_request,
+                        ffmpeg_command,
+                        decoded,
+                        &SelectedStreams::AudioOnly,
+                    )?;
+
+                    let Some((_, hash_value)) = parse_hash_line(&stdout_string) else {
+                        let msg = format!(
+                            "Could not parse a hash value from ffmpeg output: {:?}",
+                            stdout_string
+                        );
+                        return Err(DanoError::new(&msg).into());
+                    };
+
+                    Ok(region.into_track_hash(hash_value))
+                })
+                .collect::<DanoResult<Vec<TrackHash>>>()
+        });
+
+        match res {
+            Ok(track_hashes) => Some(track_hashes),
+            Err(err) => {
+                eprintln!(
+                    "WARN: Could not hash the CUE sheet tracks of {:?}: {}",
+                    request.path, err
+                );
+                None
+            }
+        }
+    }
+
+    // recorded requests are hashed as recorded, new requests are hashed natively when the file is uncompressed audio,
+    // the stream data is copied, not decoded, and the hash algorithm is one dano implements
+    fn is_native(
+        config: &Config,
+        request: &FileInfoRequest,
+        decoded: bool,
+        selected_streams: &SelectedStreams,
+    ) -> bool {
+        // remote media is only ever read by ffmpeg
+        if config.opt_remote_media.is_some() {
+            return false;
+        }
+
+        if let Some(native) = request.native {
+            return native;
+        }
+
+        let hash_algo = match &request.hash_algo {
+            Some(hash_algo) => hash_algo,
+            None => config.new_hash_algo(&request.path),
+        };
+
+        !config.opt_no_native
+            && !decoded
+            && matches!(
+                selected_streams,
+                SelectedStreams::All | SelectedStreams::AudioOnly
             )
-            .into())
+            && request.input_args.is_none()
+            && config.opt_ffmpeg_args.is_none()
+            && PcmHasher::is_supported(&request.path)
+            && NativeHasher::new(hash_algo).is_ok()
+    }
+
+    // hash the first path of a group of hardlinks, and send the result for every path in the group
+    fn generate_hardlinked(
+        config: &Config,
+        group: &[FileInfoRequest],
+        tx_item: &Sender<FileInfo>,
+    ) -> DanoResult<()> {
+        let (tx_link, rx_link): (Sender<FileInfo>, Receiver<FileInfo>) =
+            crossbeam_channel::unbounded();
+
+        FileInfo::generate(config, &group[0], &tx_link)?;
+        drop(tx_link);
+
+        rx_link.iter().try_for_each(|file_info| {
+            group.iter().try_for_each(|request| {
+                let metadata = file_info.metadata.clone().map(|metadata| FileMetadata {
+                    hardlinked: true,
+                    ..metadata
+                });
+
+                tx_item
+                    .send(FileInfo {
+                        version: file_info.version,
+                        path: request.path.clone(),
+                        metadata,
+                    })
+                    .map_err(|err| err.into())
+            })
+        })
+    }
+
+    // recorded requests are hashed with the streams recorded, but when a new request asks for only
+    // the audio or video stream, we verify such a stream exists first.  if it does not, we hash
+    // all streams, and mark the record as a fallback, so the record represents what was actually hashed
+    fn get_selected_streams(config: &Config, request: &FileInfoRequest) -> (SelectedStreams, bool) {
+        if let Some(selected_streams) = &request.selected_streams {
+            return (selected_streams.to_owned(), false);
+        }
+
+        let new_selected_streams = config.new_selected_streams(&request.path);
+
+        let codec_type = match new_selected_streams {
+            SelectedStreams::AudioOnly => "audio",
+            SelectedStreams::VideoOnly => "video",
+            selected_streams => return (selected_streams.to_owned(), false),
+        };
+
+        match FFProbe::has_stream(&config.media_input(&request.path), codec_type) {
+            Ok(false) => {
+                eprintln!(
+                    "WARN: No {} stream is available in {:?}.  Falling back to hashing all streams.",
+                    codec_type, request.path
+                );
+                (SelectedStreams::All, true)
+            }
+            // if ffprobe is unavailable, or cannot read the file, let ffmpeg decide
+            _ => (new_selected_streams.to_owned(), false),
+        }
+    }
+
+    // recorded requests are decoded to the sample format recorded, if any, new requests for decoded audio
+    // are decoded to the sample format given on the command line, if any
+    fn get_decode_format(
+        config: &Config,
+        request: &FileInfoRequest,
+        decoded: bool,
+        selected_streams: &SelectedStreams,
+    ) -> Option<DecodeFormat> {
+        if request.hash_algo.is_some() {
+            return request.decode_format;
+        }
+
+        if !decoded
+            || !matches!(
+                selected_streams,
+                SelectedStreams::All | SelectedStreams::AudioOnly | SelectedStreams::Stream(_)
+            )
+        {
+            return None;
+        }
+
+        config.opt_decode_format
+    }
+
+    // decoded audio must never be resampled or remixed, so any user specified argument which would do so is refused
+    fn check_decoded_audio_args(config: &Config) -> DanoResult<()> {
+        let Some(ffmpeg_args) = &config.opt_ffmpeg_args else {
+            return Ok(());
+        };
+
+        let opt_conversion_arg = ffmpeg_args.iter().find(|arg| {
+            AUDIO_CONVERSION_ARGS.iter().any(|option| {
+                arg.as_str() == *option
+                    || arg
+                        .strip_prefix(option)
+                        .is_some_and(|stream_specifier| stream_specifier.starts_with(':'))
+            })
+        });
+
+        match opt_conversion_arg {
+            Some(arg) => {
+                let msg = format!(
+                    "ffmpeg argument would change the sample rate or channel layout of decoded audio, \
+                    which would make the hash depend upon the version of ffmpeg: {}",
+                    arg
+                );
+                Err(DanoError::new(&msg).into())
+            }
+            None => Ok(()),
+        }
+    }
+
+    // recorded requests are decoded at the bit depth recorded, if any, as are FLAC files, whose bit depth is read from STREAMINFO.
+    // otherwise, a new request for decoded audio is decoded at the bit depth of the first audio stream, rather than truncated to 16 bits,
+    // or, when the stream is floating point, at its floating point format, rather than converted to integer samples
+    fn get_sample_format(
+        config: &Config,
+        request: &FileInfoRequest,
+        decoded: bool,
+        selected_streams: &SelectedStreams,
+    ) -> (Option<DecodeFormat>, Option<u32>) {
+        if request.hash_algo.is_some() || request.bits_per_second.is_some() {
+            return (request.decode_format, request.bits_per_second);
+        }
+
+        // an explicit sample format need not be detected
+        if request.decode_format.is_some() {
+            return (request.decode_format, None);
+        }
+
+        if !decoded
+            || !matches!(
+                selected_streams,
+                SelectedStreams::All | SelectedStreams::AudioOnly
+            )
+        {
+            return (None, None);
+        }
+
+        // if ffprobe is unavailable, or cannot read the file, let ffmpeg decide
+        match FFProbe::audio_bit_depth(&config.media_input(&request.path)) {
+            Ok(Some(AudioDepth::Integer(bits))) => (None, Some(bits)),
+            Ok(Some(AudioDepth::Float(decode_format))) => (Some(decode_format), None),
+            Ok(None) | Err(_) => (None, None),
+        }
+    }
+
+    // recorded requests are decoded with the video format recorded, if any.  a new request for decoded
+    // video streams is normalized to the pixel format and frame rate of the first video stream
+    fn get_video_format(
+        config: &Config,
+        request: &FileInfoRequest,
+        decoded: bool,
+        selected_streams: &SelectedStreams,
+    ) -> Option<VideoFormat> {
+        if request.hash_algo.is_some() {
+            return request.video_format.clone();
+        }
+
+        if !decoded
+            || !matches!(
+                selected_streams,
+                SelectedStreams::All | SelectedStreams::VideoOnly
+            )
+        {
+            return None;
+        }
+
+        // if ffprobe is unavailable, or cannot read the file, let ffmpeg decide
+        VideoFormat::probe(&config.media_input(&request.path))
+            .ok()
+            .flatten()
+    }
+
+    fn get_media_info(config: &Config, request: &FileInfoRequest) -> Option<MediaInfo> {
+        if !config.opt_media_info {
+            return None;
+        }
+
+        match MediaInfo::probe(&config.media_input(&request.path)) {
+            Ok(media_info) => Some(media_info),
+            Err(err) => {
+                eprintln!(
+                    "WARN: Could not probe technical metadata for {:?}: {}",
+                    request.path, err
+                );
+                None
+            }
         }
     }
 
@@ -143,152 +807,395 @@ impl FileInfo {
         request: &FileInfoRequest,
         ffmpeg_command: &Path,
         decoded: bool,
+        selected_streams: &SelectedStreams,
     ) -> DanoResult<Box<str>> {
         // all snapshots should have the same timestamp
-        let path_string = request.path.to_string_lossy();
         let hash_algo = match &request.hash_algo {
             Some(hash_algo) => hash_algo,
-            None => &config.selected_hash_algo,
-        };
-
-        let selected_streams = match &request.selected_streams {
-            Some(selected_streams) => selected_streams,
-            None => &config.selected_streams,
+            None => config.new_hash_algo(&request.path),
         };
 
         let opt_selected_streams_str = match selected_streams {
-            SelectedStreams::All => None,
+            SelectedStreams::All | SelectedStreams::WholeFile => None,
             SelectedStreams::AudioOnly => Some("0:a?"),
             SelectedStreams::VideoOnly => Some("0:v?"),
+            SelectedStreams::ImageOnly => Some("0:v:0"),
+            // an explicit stream which is not available is an error, rather than a fallback
+            SelectedStreams::Stream(specifier) => Some(specifier.as_ref()),
         };
 
+        // image pixel data is always decoded, and never rotated according to any EXIF orientation
+        let is_image = matches!(selected_streams, SelectedStreams::ImageOnly);
+        let decoded = decoded || is_image;
+
         let opt_bits_per_second_str = request.bits_per_second.map(|bps| {
-            let bits = format!("pcm_s{}le", bps.to_string());
+            let bits = format!("pcm_s{}le", bps);
             bits
         });
 
+        // output options which only apply to decoded streams
+        let mut decoded_args: Vec<&str> = Vec::new();
+
+        // the PCM codec only applies to audio streams, so any video streams of the same file may be decoded as well
+        if let Some(decode_format) = &request.decode_format {
+            decoded_args.extend(["-c:a", decode_format.codec()]);
+        } else if let Some(bps_string) = &opt_bits_per_second_str {
+            decoded_args.extend(["-c:a", bps_string]);
+        }
+
+        if decoded
+            && !matches!(
+                selected_streams,
+                SelectedStreams::VideoOnly | SelectedStreams::ImageOnly
+            )
+        {
+            FileInfo::check_decoded_audio_args(config)?;
+        }
+
+        if let Some(video_format) = &request.video_format {
+            decoded_args.extend(video_format.as_output_args());
+        }
+
+        // the framehash and streamhash muxers print a hash per frame, or per stream, rather than a single hash
+        let muxer = if request.frame_hash == Some(true) {
+            "framehash"
+        } else if request.stream_hash == Some(true) {
+            "streamhash"
+        } else {
+            "hash"
+        };
+
+        // when piped, ffmpeg writes the very bytes its hash muxer would hash to stdout, and dano hashes them instead
+        let is_piped = muxer == "hash" && config.is_piped_hash_algo(hash_algo);
+
+        // user specified arguments precede the hash muxer options
+        let mut output_args: Vec<&str> = config
+            .opt_ffmpeg_args
+            .iter()
+            .flatten()
+            .map(|arg| arg.as_str())
+            .collect();
+
+        if is_piped {
+            output_args.extend(["-f", "data", "-"]);
+        } else {
+            output_args.extend(["-f", muxer, "-hash", hash_algo, "-"]);
+        }
+
+        // remote media, recorded by the local path which mirrors it, is read from its URL
+        let ffmpeg_request = FileInfoRequest {
+            path: config.media_input(&request.path).into_owned(),
+            ..request.clone()
+        };
+
         let process_args = FileInfo::build_process_args(
-            &path_string,
-            hash_algo,
+            &ffmpeg_request,
+            &output_args,
             decoded,
+            is_image,
             opt_selected_streams_str,
-            &opt_bits_per_second_str,
+            FileInfo::is_cover_art_excluded(request, selected_streams),
+            &decoded_args,
         );
 
-        let process_output = ExecProcess::new(ffmpeg_command)
-            .args(&process_args)
-            .output()?;
+        // a file which cannot be read may yet be hashed, by running ffmpeg with a helper, like "sudo -n"
+        let opt_helper = config
+            .opt_elevate_with
+            .as_ref()
+            .filter(|_| is_permission_denied(&request.path));
+
+        let mut command = match opt_helper {
+            Some(helper) => {
+                let mut command = ExecProcess::new(&helper[0]);
+                command.args(&helper[1..]).arg(ffmpeg_command);
+                command
+            }
+            None => ExecProcess::new(ffmpeg_command),
+        };
+
+        command.envs(FFMPEG_LOCALE);
+
+        if config.opt_show_commands {
+            let helper_prefix = opt_helper
+                .map(|helper| format!("{} ", helper.join(" ")))
+                .unwrap_or_default();
 
+            print_err_buf(&format!(
+                "Command: {}{}\n",
+                helper_prefix,
+                shell_quote_command(ffmpeg_command, &process_args)
+            ))?;
+        }
+
+        command.args(&process_args);
+
+        if is_piped {
+            return FileInfo::hash_piped_output(
+                command,
+                hash_algo,
+                &ffmpeg_request.path,
+                opt_selected_streams_str,
+            );
+        }
+
+        let process_output = command.output()?;
+
+        // stderr is only read as a message, and need not be valid UTF-8
         let stdout = std::str::from_utf8(&process_output.stdout)?.trim();
-        let stderr = std::str::from_utf8(&process_output.stderr)?.trim();
+        let stderr = String::from_utf8_lossy(&process_output.stderr);
 
         if !process_output.status.success() {
-            if stderr.contains("incorrect codec parameters") {
+            let mut err = FFmpegError::new(
+                process_output.status,
+                stderr.trim(),
+                &ffmpeg_request.path,
+                opt_selected_streams_str,
+            );
+
+            // an input ffmpeg could read may have failed only because of the hash algorithm
+            if err.kind == FFmpegErrorKind::Other
+                && FFmpegCapabilities::rejects_hash_algo(ffmpeg_command, hash_algo)
+            {
                 eprintln!(
-                    "WARN: ffmpeg 'incorrect codec parameters' error may indicate that invalid hash algorithm specified.  \
-                    Possible this version of ffmpeg does not support: {} .",
-                    config.selected_hash_algo
+                    "WARN: This version of ffmpeg does not support the hash algorithm specified: {} .",
+                    hash_algo
                 );
+                err.kind = FFmpegErrorKind::InvalidHashAlgo;
             }
 
-            return Err(DanoError::new(&stderr).into());
+            return Err(err.into());
         }
 
         Ok(stdout.into())
     }
 
+    // the stream data is hashed as ffmpeg writes it, and is never held in memory
+    fn hash_piped_output(
+        mut command: ExecProcess,
+        hash_algo: &str,
+        input: &Path,
+        opt_stream_specifier: Option<&str>,
+    ) -> DanoResult<Box<str>> {
+        let mut hasher = NativeHasher::new(hash_algo)?;
+
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // stderr is drained on its own thread, so a chatty ffmpeg cannot block on a full pipe
+        let opt_stderr_handle = child.stderr.take().map(|mut stderr| {
+            std::thread::spawn(move || {
+                let mut buffer = Vec::new();
+                let _ = stderr.read_to_end(&mut buffer);
+                buffer
+            })
+        });
+
+        if let Some(mut stdout) = child.stdout.take() {
+            let mut buffer = vec![0u8; PIPE_BUFFER_SIZE];
+
+            loop {
+                let bytes_read = stdout.read(&mut buffer)?;
+
+                if bytes_read == 0 {
+                    break;
+                }
+
+                hasher.update(&buffer[..bytes_read]);
+            }
+        }
+
+        let status = child.wait()?;
+
+        let stderr = opt_stderr_handle
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default();
+
+        if !status.success() {
+            let stderr = String::from_utf8_lossy(&stderr);
+            return Err(
+                FFmpegError::new(status, stderr.trim(), input, opt_stream_specifier).into(),
+            );
+        }
+
+        // mimics the hash muxer's output, so it is parsed the same way
+        Ok(format!("{}={}", hasher.name(), hasher.finalize_hex()).into())
+    }
+
     fn transmit_file_info(
+        config: &Config,
         request: &FileInfoRequest,
         stdout_string: &str,
         tx_item: &Sender<FileInfo>,
         decoded: bool,
         selected_streams: &SelectedStreams,
+        fallback: bool,
     ) -> DanoResult<()> {
-        let timestamp = SystemTime::now();
+        let file_info = FileInfo::new_file_info(
+            config,
+            request,
+            stdout_string,
+            decoded,
+            selected_streams,
+            fallback,
+        )?;
 
-        if request.path.to_str().is_none() {
-            let msg = format!("Requested path failed UTF validation: {:?}", request.path);
-            return Err(DanoError::new(&msg).into());
-        }
+        tx_item.send(file_info).map_err(|err| err.into())
+    }
 
-        let phantom_file_info = FileInfo {
-            path: request.path.to_owned(),
-            version: DANO_FILE_INFO_VERSION,
-            metadata: None,
-        };
+    fn new_file_info(
+        config: &Config,
+        request: &FileInfoRequest,
+        stdout_string: &str,
+        decoded: bool,
+        selected_streams: &SelectedStreams,
+        fallback: bool,
+    ) -> DanoResult<FileInfo> {
+        let timestamp = SystemTime::now();
 
         if stdout_string.is_empty() {
             // if stdout string is empty, then file DNE
             // we want to print the request instead of an error
             // or just continuing so we send the path + dummy value
-            tx_item.send(phantom_file_info)?;
+            return Ok(FileInfo {
+                path: request.path.to_owned(),
+                version: DANO_FILE_INFO_VERSION,
+                metadata: None,
+            });
+        }
 
-            Ok(())
-        } else {
-            let res = match stdout_string.split_once('=') {
-                Some((first, last)) => {
-                    let hash_value = if last.chars().all(|c| c.is_ascii_hexdigit())
-                        && last.len() <= 128
-                    {
-                        HashValue {
-                            radix: HEXADECIMAL_RADIX,
-                            value: last.trim_start_matches('0').into(),
-                        }
-                    } else {
-                        return Err(
-                            DanoError::new("Could not parse integer from ffmpeg output.").into(),
-                        );
-                    };
+        let Some((first, last)) = parse_hash_line(stdout_string) else {
+            let msg = format!(
+                "Could not parse a hash value from ffmpeg output: {:?}",
+                stdout_string
+            );
+            return Err(DanoError::new(&msg).into());
+        };
 
-                    FileInfo {
-                        path: request.path.to_owned(),
-                        version: DANO_FILE_INFO_VERSION,
-                        metadata: Some(FileMetadata {
-                            last_written: timestamp,
-                            hash_algo: first.into(),
-                            hash_value,
-                            modify_time: request.path.metadata()?.modified()?,
-                            selected_streams: selected_streams.to_owned(),
-                            decoded,
-                            opt_bits_per_second: request.bits_per_second,
-                        }),
-                    }
-                }
-                None => phantom_file_info,
-            };
+        let hash_value = HashValue {
+            radix: HEXADECIMAL_RADIX,
+            value: last.trim_start_matches('0').into(),
+        };
+
+        // remote media has no local metadata, and so is recorded without a modify time, or a size
+        let (modify_time, opt_size) = match request.path.metadata() {
+            Ok(path_metadata) => (path_metadata.modified()?, Some(path_metadata.len())),
+            Err(_) if config.opt_remote_media.is_some() => (UNIX_EPOCH, None),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(FileInfo {
+            path: request.path.to_owned(),
+            version: DANO_FILE_INFO_VERSION,
+            metadata: Some(FileMetadata {
+                last_written: timestamp,
+                hash_algo: first.into(),
+                hash_value,
+                modify_time,
+                selected_streams: selected_streams.to_owned(),
+                decoded,
+                opt_bits_per_second: request.bits_per_second,
+                opt_media_info: FileInfo::get_media_info(config, request),
+                hardlinked: false,
+                fallback,
+                opt_video_format: request.video_format.clone(),
+                native: request.native.unwrap_or(false),
+                opt_frame_digests: None,
+                opt_stream_hashes: None,
+                opt_size,
+                opt_last_checked: None,
+                opt_tags_hash: FileInfo::get_tags_hash(config, request, selected_streams),
+                cover_art_excluded: FileInfo::is_cover_art_excluded(request, selected_streams),
+                opt_decode_format: request.decode_format,
+                opt_track_hashes: None,
+            }),
+        })
+    }
+
+    // cover art is only ever among the streams selected when all streams, or the video streams, are hashed
+    fn is_cover_art_excluded(
+        request: &FileInfoRequest,
+        selected_streams: &SelectedStreams,
+    ) -> bool {
+        request.exclude_cover_art == Some(true)
+            && matches!(
+                selected_streams,
+                SelectedStreams::All | SelectedStreams::VideoOnly
+            )
+    }
+
+    // a whole file hash already covers the tags
+    fn get_tags_hash(
+        config: &Config,
+        request: &FileInfoRequest,
+        selected_streams: &SelectedStreams,
+    ) -> Option<Box<str>> {
+        if request.tags_hash != Some(true) || selected_streams == &SelectedStreams::WholeFile {
+            return None;
+        }
 
-            tx_item.send(res)?;
-            Ok(())
+        match TagsHash::generate(&config.media_input(&request.path)) {
+            Ok(tags_hash) => Some(tags_hash),
+            Err(err) => {
+                eprintln!(
+                    "WARN: Could not hash the tags of {:?}: {}",
+                    request.path, err
+                );
+                None
+            }
         }
     }
 
     fn build_process_args<'a>(
-        path_string: &'a str,
-        hash_algo: &'a str,
+        request: &'a FileInfoRequest,
+        output_args: &[&'a str],
         decoded: bool,
+        is_image: bool,
         opt_selected_streams_str: Option<&'a str>,
-        opt_bits_per_second: &'a Option<String>,
-    ) -> Vec<&'a str> {
-        let mut process_args = vec!["-i", path_string];
+        exclude_cover_art: bool,
+        decoded_args: &[&'a str],
+    ) -> Vec<&'a OsStr> {
+        // global options first
+        let mut process_args: Vec<&str> = FFMPEG_QUIET_ARGS.to_vec();
 
-        let end_opts = vec!["-f", "hash", "-hash", hash_algo, "-"];
+        if is_image {
+            process_args.push("-noautorotate");
+        }
 
-        if let Some(selected_streams_str) = opt_selected_streams_str {
-            process_args.push("-map");
-            process_args.push(selected_streams_str);
+        // input options must precede the input file
+        if let Some(input_args) = &request.input_args {
+            process_args.extend(input_args.iter().map(|arg| arg.as_str()));
         }
 
-        if decoded {
-            if let Some(bps_string) = opt_bits_per_second {
-                let codec_copy: Vec<&str> = vec!["-c", &bps_string];
-                process_args.extend(codec_copy);
+        process_args.push("-i");
+
+        // the path is passed to ffmpeg as is, and need not be valid UTF-8
+        let mut process_args: Vec<&OsStr> = process_args.into_iter().map(OsStr::new).collect();
+        process_args.push(request.path.as_os_str());
+
+        let mut output_options: Vec<&str> = Vec::new();
+
+        // an attached picture, such as cover art, is a video stream, but the "V" stream specifier
+        // only matches video streams which are not attached pictures
+        match opt_selected_streams_str {
+            Some("0:v?") if exclude_cover_art => output_options.extend(["-map", "0:V?"]),
+            Some(selected_streams_str) => output_options.extend(["-map", selected_streams_str]),
+            None if exclude_cover_art => {
+                output_options.extend(["-map", "0", "-map", "-0:v", "-map", "0:V?"])
             }
+            None => (),
+        }
+
+        if decoded {
+            output_options.extend(decoded_args);
         } else {
-            let codec_copy: Vec<&str> = vec!["-codec", "copy"];
-            process_args.extend(codec_copy);
+            output_options.extend(["-codec", "copy"]);
         }
 
-        process_args.extend(end_opts);
+        output_options.extend(output_args);
+
+        process_args.extend(output_options.into_iter().map(OsStr::new));
 
         process_args
     }
@@ -15,40 +15,133 @@
 // For the full copyright and license information, please view the LICENSE file
 // that was distributed with this source code.
 
+mod accept;
+mod accuraterip;
+mod backup;
+mod binary;
+mod catalog;
+mod checksums;
+mod compact;
+mod compress;
 mod config;
+mod copy;
+mod coverage;
+mod cshatag;
+mod cue;
+mod daemon;
+mod dedup;
+mod diff;
+mod ffmpeg;
+mod ffp;
 mod flac;
+mod footer;
+mod frames;
+mod fsck;
+mod hasher;
+mod history;
+mod import;
 mod ingest;
+mod init;
+mod journal;
+mod live;
+mod lock;
 mod lookup;
+mod mediainfo;
+mod migrate;
 mod output;
+mod pcm;
+mod policy;
+mod probe;
 mod process;
+mod progress;
+mod prune;
+mod receipt;
+mod reference;
+mod remote;
+mod report;
 mod requests;
+mod rules;
+mod sanity;
+mod schedule;
+mod schema;
+mod serde_path;
+mod sfv;
+mod sidecar;
+mod signature;
+mod sniff;
+mod stable;
+mod state;
+mod status;
+mod streamhash;
+mod tags;
+mod tree;
+mod undo;
 mod utility;
 mod versions;
+mod watch;
+mod wavpack;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::PathBuf;
+use std::time::{Instant, SystemTime};
 
 use itertools::Itertools;
 
-use crate::lookup::FileInfo;
-use config::{Config, ExecMode};
+use crate::cue::TrackHash;
+use crate::lookup::{FileInfo, FileMetadata};
+use accept::AcceptChanges;
+use accuraterip::AccurateRipVerification;
+use catalog::MultiCatalog;
+use compact::HashFileCompaction;
+use config::{Config, ExecMode, TestModeConfig, WriteModeConfig};
+use copy::CopyVerification;
+use coverage::DirCoverage;
+use daemon::ScrubDaemon;
+use dedup::CrossAlgoDuplicates;
+use diff::HashFileDiff;
+use flac::FlacVerification;
+use fsck::HashFileCheck;
+use history::HistoryLog;
 use ingest::RecordedFileInfo;
+use init::InitWizard;
+use journal::XattrJournal;
+use live::LiveStatus;
+use lock::{HashFileLock, LockKind};
 use lookup::FileInfoLookup;
+use migrate::AlgoMigration;
 use output::WriteableFileInfo;
 use process::{ProcessedFiles, RemainderBundle};
+use prune::HashFilePrune;
+use receipt::VerificationReceipt;
+use reference::ReferenceCatalog;
+use report::HtmlReport;
 use requests::{FileInfoRequest, RequestBundle};
+use schedule::{Schedule, VerifyOutcome};
+use schema::SchemaReport;
+use sidecar::Sidecar;
+use signature::HashFileSignature;
+use state::StateFile;
+use status::DirStatus;
+use tree::TreeHashes;
+use undo::UndoJournal;
 use utility::{
-    prepare_thread_pool, print_err_buf, print_file_info, remove_dano_xattr, DanoError, DanoResult,
+    prepare_thread_pool, print_commentary, print_err_buf, print_file_info, print_out_buf,
+    remove_dano_xattr, remove_orphaned_tmp_files, DanoError, DanoResult,
 };
+use watch::WatchDir;
 
-const DANO_FILE_INFO_VERSION: usize = 5;
+const DANO_FILE_INFO_VERSION: usize = 6;
 const HEXADECIMAL_RADIX: u32 = 16;
+#[cfg(not(windows))]
 const DANO_XATTR_KEY_NAME: &str = "user.dano.checksum";
+#[cfg(windows)]
+const DANO_ADS_STREAM_NAME: &str = "dano.checksum";
 const DANO_DEFAULT_HASH_FILE_NAME: &str = "dano_hashes.txt";
 
 const DANO_CLEAN_EXIT_CODE: i32 = 0i32;
 const DANO_ERROR_EXIT_CODE: i32 = 1i32;
 const DANO_DISORDER_EXIT_CODE: i32 = 2i32;
+const DANO_PERMISSION_DENIED_EXIT_CODE: i32 = 4i32;
 
 fn main() {
     let exit_code = match exec() {
@@ -65,6 +158,62 @@ fn main() {
 fn exec() -> DanoResult<i32> {
     let config = Config::new()?;
 
+    // only modes which may rewrite the hash file concern themselves with its tmp files
+    let is_rewrite = match &config.exec_mode {
+        ExecMode::Write(_)
+        | ExecMode::Dump
+        | ExecMode::Compact(_)
+        | ExecMode::Prune
+        | ExecMode::Accept
+        | ExecMode::MigrateAlgo(_)
+        | ExecMode::TestFlac
+        | ExecMode::TreeHash(_)
+        | ExecMode::Undo => true,
+        ExecMode::Test(test_mode_config) => {
+            test_mode_config.opt_write_new || test_mode_config.opt_overwrite_old
+        }
+        ExecMode::Fsck(fsck_config) => fsck_config.opt_repair,
+        _ => false,
+    };
+
+    // a test given --record-checked records the time of each file's last check, and a clean removes the hash file,
+    // so each may write
+    let lock_kind = match &config.exec_mode {
+        _ if is_rewrite => LockKind::Exclusive,
+        ExecMode::Test(_) if config.opt_record_checked && !config.opt_read_only => {
+            LockKind::Exclusive
+        }
+        ExecMode::Clean if !config.opt_read_only => LockKind::Exclusive,
+        _ => LockKind::Shared,
+    };
+
+    // the lock is taken before any orphaned tmp file is removed, as it may be the tmp file of another run
+    let hash_file_lock = HashFileLock::acquire(&config, lock_kind)?;
+
+    if is_rewrite {
+        remove_orphaned_tmp_files(&config)?;
+    }
+
+    // complete any batch of extended attribute writes which an earlier run left unfinished
+    XattrJournal::new(&config).replay(&config)?;
+
+    // input files which span drives, each with a drive local catalog, are tested against each catalog in turn
+    if let Some(multi_catalog) = MultiCatalog::discover(&config) {
+        let exit_code = multi_catalog.exec(&config)?;
+        print_test_summary(&config, exit_code)?;
+        print_skipped_summary(&config)?;
+        return Ok(with_permission_denied(&config, exit_code));
+    }
+
+    // the state of the hash file before it is written, so that a signature left stale is known
+    let hash_file_signature = HashFileSignature::new(&config, is_rewrite)?;
+
+    // a run which may write records notes their state, so the changes it makes may be undone
+    let undo_journal = UndoJournal::new(
+        &config,
+        (is_rewrite || matches!(config.exec_mode, ExecMode::Clean)) && !config.opt_output_stdout,
+    )?;
+
     let recorded_file_info = RecordedFileInfo::new(&config)?;
 
     let exit_code = match &config.exec_mode {
@@ -73,35 +222,79 @@ fn exec() -> DanoResult<i32> {
             let errors: Vec<&PathBuf> = config
                 .paths
                 .iter()
-                .filter(|path| match remove_dano_xattr(path) {
-                    Ok(_) => {
-                        println!(
-                            "dano successfully removed extended attribute from: {:?}",
-                            path
+                .filter(|path| {
+                    // both the sidecar and the xattr are removed, so neither may short circuit the other
+                    if config.opt_dry_run {
+                        let _ = print_commentary(
+                            &config,
+                            &format!(
+                                "WARN: Not removing sidecar or extended attribute from (because dry run was specified): {:?}\n",
+                                path
+                            ),
                         );
-                        false
-                    }
-                    Err(err) if err.to_string().contains("No data available") => false,
-                    Err(err) => {
-                        eprintln!("ERROR: {}", err);
-                        true
+                        return false;
                     }
+
+                    let sidecar_failed = match Sidecar::remove(path) {
+                        Ok(true) => {
+                            let _ = print_commentary(
+                                &config,
+                                &format!("dano successfully removed sidecar for: {:?}\n", path),
+                            );
+                            false
+                        }
+                        Ok(false) => false,
+                        Err(err) => {
+                            eprintln!("ERROR: {}", err);
+                            true
+                        }
+                    };
+
+                    let xattr_failed = match remove_dano_xattr(path) {
+                        Ok(_) => {
+                            // an error printing commentary is no reason to stop cleaning
+                            let _ = print_commentary(
+                                &config,
+                                &format!(
+                                    "dano successfully removed extended attribute from: {:?}\n",
+                                    path
+                                ),
+                            );
+                            false
+                        }
+                        Err(err) if err.to_string().contains("No data available") => false,
+                        Err(err) => {
+                            eprintln!("ERROR: {}", err);
+                            true
+                        }
+                    };
+
+                    sidecar_failed || xattr_failed
                 })
                 .collect();
 
             if errors.is_empty() {
-                println!("All dano extended attributes successfully cleaned.");
+                if !config.opt_dry_run {
+                    print_commentary(
+                        &config,
+                        "All dano extended attributes successfully cleaned.\n",
+                    )?;
+                }
                 DANO_CLEAN_EXIT_CODE
             } else {
-                println!(
+                eprintln!(
                     "ERROR: Could not clean extended attributes form the following paths: {:?}",
                     errors
                 );
                 DANO_ERROR_EXIT_CODE
             }
         }
+        ExecMode::Write(WriteModeConfig {
+            opt_init: Some(init_config),
+            ..
+        }) => InitWizard::exec(&config, init_config, recorded_file_info)?,
         ExecMode::Write(write_config)
-            if write_config.opt_rewrite || write_config.opt_import_flac =>
+            if write_config.opt_rewrite || write_config.opt_import.is_some() =>
         {
             // here we print_file_info because we don't run these opts through verify_file_info,
             // which would ordinary print this information
@@ -115,12 +308,14 @@ fn exec() -> DanoResult<i32> {
                     modified_file_names: RemainderBundle::ModifiedFilename(
                         recorded_file_info.into_inner(),
                     ),
+                    test_results: BTreeMap::new(),
                     exit_code: DANO_CLEAN_EXIT_CODE,
                 }
-            } else if write_config.opt_import_flac {
+            } else if write_config.opt_import.is_some() {
                 ProcessedFiles {
                     new_files: RemainderBundle::NewFile(recorded_file_info.into_inner()),
                     modified_file_names: RemainderBundle::ModifiedFilename(Vec::new()),
+                    test_results: BTreeMap::new(),
                     exit_code: DANO_CLEAN_EXIT_CODE,
                 }
             } else {
@@ -129,45 +324,51 @@ fn exec() -> DanoResult<i32> {
 
             processed_files.write_out(&config)?
         }
-        ExecMode::Write(_) => {
-            let thread_pool = prepare_thread_pool(&config)?;
-
-            let raw_file_info_requests = RequestBundle::new(&config, &recorded_file_info)?;
-            // filter out files for which we already have a hash, only do requests on new files
-            let file_info_requests: Vec<FileInfoRequest> = raw_file_info_requests
-                .into_inner()
-                .into_iter()
-                .filter(|request| request.hash_algo.is_none())
-                .collect();
-
-            let rx_item = FileInfoLookup::exec(&config, file_info_requests.into(), thread_pool)?;
-            let processed_files = ProcessedFiles::new(&config, recorded_file_info, rx_item)?;
-
-            processed_files.write_out(&config)?
+        ExecMode::Write(WriteModeConfig {
+            opt_watch: Some(dir),
+            ..
+        }) => {
+            // a watch runs indefinitely, and so, after its first batch, locks the hash file for each batch
+            WatchDir::exec(&config, dir, recorded_file_info, hash_file_lock, exec_write)?
         }
-        ExecMode::Test(_) => {
+        ExecMode::Write(_) => exec_write(&config, recorded_file_info)?,
+        ExecMode::Test(TestModeConfig {
+            opt_reference: Some(reference),
+            ..
+        }) => {
             let thread_pool = prepare_thread_pool(&config)?;
 
-            let file_info_requests = RequestBundle::new(&config, &recorded_file_info)?;
-            let rx_item = FileInfoLookup::exec(&config, file_info_requests, thread_pool)?;
-            let processed_files = ProcessedFiles::new(&config, recorded_file_info, rx_item)?;
+            let reference_catalog = ReferenceCatalog::new(&config, reference)?;
+            let file_info_requests = RequestBundle::new(&config, reference_catalog.as_slice())?;
+            let (rx_item, _rx_unsupported) =
+                FileInfoLookup::exec(&config, file_info_requests, thread_pool)?;
 
-            let exit_code = processed_files.write_out(&config)?;
+            let exit_code = reference_catalog.verify(&config, &recorded_file_info, rx_item)?;
 
-            if !config.is_single_path {
-                match exit_code {
-                    i if i == DANO_CLEAN_EXIT_CODE => {
-                        print_err_buf("PASSED: File paths are consistent.  Paths contain no hash or filename mismatches.\n")?
-                    }
-                    i if i == DANO_DISORDER_EXIT_CODE => {
-                        print_err_buf("FAILED: File paths are inconsistent.  Some hash or filename mismatch was detected.\n")?
-                    }
-                    _ => {}
-                }
+            match exit_code {
+                i if i == DANO_CLEAN_EXIT_CODE => print_err_buf(
+                    "PASSED: File paths are consistent with the reference.  Paths contain no hash mismatches.\n",
+                )?,
+                _ => print_err_buf(
+                    "FAILED: File paths are inconsistent with the reference.  Some hash mismatch was detected.\n",
+                )?,
             }
 
             exit_code
         }
+        ExecMode::Test(TestModeConfig {
+            opt_daemon: Some(interval),
+            ..
+        }) => {
+            // a daemon runs indefinitely, and so instead locks the hash file for each batch
+            drop(hash_file_lock);
+            ScrubDaemon::exec(&config, *interval, exec_test)?
+        }
+        ExecMode::Test(_) => exec_test(&config, recorded_file_info)?,
+        ExecMode::Print if config.opt_schema_report => {
+            SchemaReport::exec(&config)?;
+            DANO_CLEAN_EXIT_CODE
+        }
         ExecMode::Print => {
             if recorded_file_info.is_empty() {
                 return Err(DanoError::new("No recorded file info is available to print.").into());
@@ -175,10 +376,22 @@ fn exec() -> DanoResult<i32> {
 
             recorded_file_info
                 .iter()
+                .filter(|file_info| match &config.opt_query {
+                    Some(query) => query.is_match(
+                        file_info
+                            .metadata
+                            .as_ref()
+                            .and_then(|metadata| metadata.opt_media_info.as_ref()),
+                    ),
+                    None => true,
+                })
                 .try_for_each(|file_info| print_file_info(&config, file_info))?;
 
             DANO_CLEAN_EXIT_CODE
         }
+        ExecMode::Duplicates if config.opt_cross_algo => {
+            CrossAlgoDuplicates::exec(&config, recorded_file_info)?
+        }
         ExecMode::Duplicates => {
             if recorded_file_info.is_empty() {
                 return Err(DanoError::new(
@@ -194,6 +407,9 @@ fn exec() -> DanoResult<i32> {
                 .into());
             }
 
+            // a file which matches a track of a recorded single file album is a duplicate of that track
+            let track_matches = TrackHash::find_matches(&recorded_file_info);
+
             let sorted_group_map: BTreeMap<Box<str>, Vec<FileInfo>> = recorded_file_info
                 .into_inner()
                 .into_iter()
@@ -210,7 +426,7 @@ fn exec() -> DanoResult<i32> {
                 .flatten()
                 .collect();
 
-            if duplicates.is_empty() {
+            if duplicates.is_empty() && track_matches.is_empty() {
                 if !config.opt_silent {
                     eprintln!("No duplicates found.");
                 }
@@ -219,12 +435,44 @@ fn exec() -> DanoResult<i32> {
                 duplicates
                     .iter()
                     .try_for_each(|file_info| print_file_info(&config, file_info))?;
+                track_matches
+                    .iter()
+                    .try_for_each(|track_match| print_out_buf(&format!("{}\n", track_match)))?;
                 if !config.opt_silent {
                     eprintln!("WARN: Duplicates found.");
                 }
                 DANO_DISORDER_EXIT_CODE
             }
         }
+        ExecMode::VerifyCopy(verify_copy_config) => {
+            CopyVerification::exec(&config, verify_copy_config)?
+        }
+        ExecMode::Status(status_config) => DirStatus::exec(&config, status_config)?,
+        ExecMode::Coverage(coverage_config) => DirCoverage::exec(&config, coverage_config)?,
+        ExecMode::Accept => AcceptChanges::exec(&config, recorded_file_info)?,
+        ExecMode::MigrateAlgo(migrate_config) => {
+            AlgoMigration::exec(&config, recorded_file_info, &migrate_config.hash_algo)?
+        }
+        ExecMode::TestFlac => FlacVerification::exec(&config, recorded_file_info)?,
+        ExecMode::AccurateRip => AccurateRipVerification::exec(&config)?,
+        ExecMode::TreeHash(tree_hash_config) => {
+            TreeHashes::exec(&config, tree_hash_config, recorded_file_info)?
+        }
+        ExecMode::Undo => UndoJournal::undo(&config)?,
+        ExecMode::History(opt_path) => {
+            HistoryLog::new(&config).exec(&config, opt_path.as_deref())?
+        }
+        ExecMode::Export(format) => recorded_file_info.export(&config, format)?,
+        ExecMode::Compact(compact_config) => {
+            HashFileCompaction::exec(&config, compact_config)?;
+            DANO_CLEAN_EXIT_CODE
+        }
+        ExecMode::Fsck(fsck_config) => HashFileCheck::exec(&config, fsck_config)?,
+        ExecMode::Diff(diff_config) => HashFileDiff::exec(&config, diff_config)?,
+        ExecMode::Prune => {
+            HashFilePrune::exec(&config)?;
+            DANO_CLEAN_EXIT_CODE
+        }
         ExecMode::Dump => {
             if recorded_file_info.is_empty() {
                 return Err(
@@ -232,7 +480,7 @@ fn exec() -> DanoResult<i32> {
                 );
             }
 
-            if config.output_file.exists() {
+            if config.output_file.exists() && !config.opt_output_stdout {
                 return Err(DanoError::new(
                     "Output file already exists.  Quitting without dumping to file.",
                 )
@@ -265,5 +513,279 @@ fn exec() -> DanoResult<i32> {
         }
     };
 
+    let record_changes = undo_journal.exec(&config)?;
+    HistoryLog::new(&config).append_changes(&config, &record_changes)?;
+
+    hash_file_signature.exec(&config)?;
+
+    print_skipped_summary(&config)?;
+
+    Ok(with_permission_denied(&config, exit_code))
+}
+
+// verify the recorded file info
+fn exec_test(config: &Config, recorded_file_info: RecordedFileInfo) -> DanoResult<i32> {
+    let ExecMode::Test(test_mode_config) = &config.exec_mode else {
+        unreachable!()
+    };
+
+    let thread_pool = prepare_thread_pool(config)?;
+    let started = Instant::now();
+
+    if let Some(hash_policy) = &config.opt_hash_policy {
+        hash_policy.flag_weak_records(config, &recorded_file_info)?;
+    }
+
+    let selected: Vec<PathBuf> = recorded_file_info
+        .iter()
+        .map(|file_info| file_info.path.clone())
+        .collect();
+
+    // a quick test hashes only the recorded files whose metadata has changed
+    let unchanged = if test_mode_config.opt_quick {
+        recorded_file_info.unchanged_paths()
+    } else {
+        BTreeSet::new()
+    };
+
+    let file_info_requests: RequestBundle = RequestBundle::new(config, &recorded_file_info)?
+        .into_inner()
+        .into_iter()
+        .filter(|request| !unchanged.contains(&request.path))
+        .collect::<Vec<FileInfoRequest>>()
+        .into();
+    let opt_live_status = LiveStatus::new(config, file_info_requests.len());
+    let (rx_item, rx_unsupported) = FileInfoLookup::exec(config, file_info_requests, thread_pool)?;
+    // the report requires the recorded file info, which is otherwise consumed
+    let report_file_info = match &config.opt_report_html {
+        Some(_) => recorded_file_info.to_vec(),
+        None => Vec::new(),
+    };
+    let checked_file_info = recorded_file_info.to_vec();
+    let mut processed_files =
+        ProcessedFiles::with_live_status(config, recorded_file_info, rx_item, opt_live_status)?;
+    processed_files.insert_unchanged(config, &unchanged)?;
+
+    // a daemon hashes each batch without the lock, and holds the lock only to write back the results
+    let _opt_hash_file_lock = match test_mode_config.opt_daemon {
+        Some(_) => Some(HashFileLock::acquire(config, LockKind::Exclusive)?),
+        None => None,
+    };
+
+    // a time budgeted run, or a daemon's run, records each entry selected, so the stalest are selected next
+    let is_scheduled =
+        test_mode_config.opt_budget.is_some() || test_mode_config.opt_daemon.is_some();
+
+    if is_scheduled && !config.opt_read_only {
+        let outcomes = processed_files
+            .test_results
+            .iter()
+            .map(|(path, exit_code)| (path.clone(), VerifyOutcome::from_exit_code(*exit_code)))
+            .collect();
+
+        Schedule::new(config)?.record_run(&selected, &outcomes, started.elapsed())?;
+    } else if !config.opt_read_only {
+        // the time of the last verification is a convenience, and no reason to fail a test
+        if let Err(err) = Schedule::new(config).and_then(|schedule| schedule.record_verified()) {
+            eprintln!(
+                "WARN: Could not record the time of this verification: {}",
+                err
+            );
+        }
+    }
+
+    HtmlReport::exec(config, &report_file_info, &processed_files)?;
+
+    let receipt = VerificationReceipt::new(&processed_files);
+    let test_results = processed_files.test_results.clone();
+
+    let exit_code = with_unsupported(
+        config,
+        processed_files.write_out(config)?,
+        rx_unsupported.try_iter().count(),
+    );
+
+    // a quick test did not hash the unchanged files, so these were not checked.  the hash file is
+    // rewritten before the receipt is written, so that the receipt is of the hash file as left on disk
+    record_checked(config, checked_file_info, &test_results, &unchanged);
+
+    receipt.exec(config, exit_code)?;
+
+    print_test_summary(config, exit_code)?;
+
+    Ok(exit_code)
+}
+
+// hash the input files which have not been recorded, and write the new records
+fn exec_write(config: &Config, recorded_file_info: RecordedFileInfo) -> DanoResult<i32> {
+    let ExecMode::Write(write_config) = &config.exec_mode else {
+        unreachable!()
+    };
+
+    if write_config.opt_xattr_if_missing {
+        recorded_file_info.write_missing_xattrs(config)?;
+    }
+
+    let thread_pool = prepare_thread_pool(config)?;
+
+    let opt_state_file = if write_config.opt_cache_unsupported {
+        Some(StateFile::new()?)
+    } else {
+        None
+    };
+    let mut num_unsupported = 0usize;
+
+    let raw_file_info_requests = RequestBundle::new(config, &recorded_file_info)?;
+    // filter out files for which we already have a hash, only do requests on new files
+    let file_info_requests: Vec<FileInfoRequest> = raw_file_info_requests
+        .into_inner()
+        .into_iter()
+        .filter(|request| request.hash_algo.is_none())
+        // and skip files which ffmpeg has previously failed to hash, unless these should be retried
+        .filter(|request| match &opt_state_file {
+            Some(state_file)
+                if !write_config.opt_retry_unsupported
+                    && state_file.is_unsupported(&config.pwd.join(&request.path)) =>
+            {
+                num_unsupported += 1;
+                false
+            }
+            _ => true,
+        })
+        .collect();
+
+    let (rx_item, rx_unsupported) =
+        FileInfoLookup::exec(config, file_info_requests.into(), thread_pool)?;
+    // the report requires the recorded file info, which is otherwise consumed
+    let report_file_info = match &config.opt_report_html {
+        Some(_) => recorded_file_info.to_vec(),
+        None => Vec::new(),
+    };
+    let processed_files = ProcessedFiles::new(config, recorded_file_info, rx_item)?;
+
+    let unsupported: Vec<PathBuf> = rx_unsupported.try_iter().collect();
+
+    if let Some(mut state_file) = opt_state_file {
+        unsupported
+            .iter()
+            .for_each(|path| state_file.insert_unsupported(&config.pwd.join(path)));
+
+        // files which were hashed are no longer unsupported
+        processed_files
+            .test_results
+            .keys()
+            .for_each(|path| state_file.remove_unsupported(&config.pwd.join(path)));
+
+        state_file.write()?;
+    }
+
+    HtmlReport::exec(config, &report_file_info, &processed_files)?;
+
+    let exit_code = with_unsupported(
+        config,
+        processed_files.write_out(config)?,
+        unsupported.len() + num_unsupported,
+    );
+
+    if num_unsupported > 0 && !config.opt_silent {
+        print_err_buf(&format!(
+            "Skipped {} files which ffmpeg previously could not hash; use --retry-unsupported to retry.\n",
+            num_unsupported
+        ))?;
+    }
+
     Ok(exit_code)
 }
+
+// input files which could not be read were never tested, so a run which is otherwise clean is not,
+// unless lenient
+fn with_permission_denied(config: &Config, exit_code: i32) -> i32 {
+    if exit_code == DANO_CLEAN_EXIT_CODE
+        && config.skipped_paths.permission_denied > 0
+        && config.strictness.fails_on_permission_denied()
+    {
+        DANO_PERMISSION_DENIED_EXIT_CODE
+    } else {
+        exit_code
+    }
+}
+
+// the time of each file's last check is a convenience, only recorded when asked, and no reason to fail a test
+fn record_checked(
+    config: &Config,
+    recorded_file_info: Vec<FileInfo>,
+    test_results: &BTreeMap<PathBuf, i32>,
+    unchanged: &BTreeSet<PathBuf>,
+) {
+    if !config.opt_record_checked {
+        return;
+    }
+
+    let now = SystemTime::now();
+
+    let checked: Vec<FileInfo> = recorded_file_info
+        .into_iter()
+        .filter(|file_info| {
+            test_results.get(&file_info.path) == Some(&DANO_CLEAN_EXIT_CODE)
+                && !unchanged.contains(&file_info.path)
+        })
+        .filter_map(|file_info| {
+            let metadata = file_info.metadata?;
+
+            Some(FileInfo {
+                version: DANO_FILE_INFO_VERSION,
+                path: file_info.path,
+                metadata: Some(FileMetadata {
+                    opt_last_checked: Some(now),
+                    ..metadata
+                }),
+            })
+        })
+        .collect();
+
+    if let Err(err) = WriteableFileInfo::from(checked).record_checked(config) {
+        eprintln!(
+            "WARN: Could not record the time of each file's last check: {}",
+            err
+        );
+    }
+}
+
+// files which ffmpeg could not hash were never verified, which only fails a strict run
+fn with_unsupported(config: &Config, exit_code: i32, num_unsupported: usize) -> i32 {
+    if exit_code == DANO_CLEAN_EXIT_CODE
+        && num_unsupported > 0
+        && config.strictness.fails_on_unsupported()
+    {
+        DANO_DISORDER_EXIT_CODE
+    } else {
+        exit_code
+    }
+}
+
+fn print_test_summary(config: &Config, exit_code: i32) -> DanoResult<()> {
+    if config.is_single_path {
+        return Ok(());
+    }
+
+    match exit_code {
+        i if i == DANO_CLEAN_EXIT_CODE => print_err_buf(
+            "PASSED: File paths are consistent.  Paths contain no hash or filename mismatches.\n",
+        ),
+        i if i == DANO_DISORDER_EXIT_CODE => print_err_buf(
+            "FAILED: File paths are inconsistent.  Some hash or filename mismatch was detected.\n",
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn print_skipped_summary(config: &Config) -> DanoResult<()> {
+    if config.opt_silent {
+        return Ok(());
+    }
+
+    match config.skipped_paths.summary() {
+        Some(summary) => print_err_buf(&format!("{}\n", summary)),
+        None => Ok(()),
+    }
+}
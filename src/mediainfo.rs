@@ -0,0 +1,266 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{fmt, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::probe::FFProbe;
+use crate::{DanoError, DanoResult};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct MediaInfo {
+    pub width: Option<u64>,
+    pub height: Option<u64>,
+    pub bit_rate: Option<u64>,
+    pub channels: Option<u64>,
+    pub sample_rate: Option<u64>,
+}
+
+impl fmt::Display for MediaInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts: Vec<String> = Vec::new();
+
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            parts.push(format!("{}x{}", width, height));
+        }
+
+        if let Some(bit_rate) = self.bit_rate {
+            parts.push(format!("{} kb/s", bit_rate / 1000));
+        }
+
+        if let Some(channels) = self.channels {
+            parts.push(format!("{} ch", channels));
+        }
+
+        if let Some(sample_rate) = self.sample_rate {
+            parts.push(format!("{} Hz", sample_rate));
+        }
+
+        if parts.is_empty() {
+            return write!(f, "no technical metadata");
+        }
+
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+impl MediaInfo {
+    pub fn probe(path: &Path) -> DanoResult<Self> {
+        let root = FFProbe::show_entries(
+            path,
+            "format=bit_rate:stream=codec_type,width,height,channels,sample_rate",
+        )?;
+
+        Ok(Self::from_ffprobe_json(&root))
+    }
+
+    // ffprobe prints some numbers as strings, and some as numbers, so accept either
+    fn as_u64(value: &Value) -> Option<u64> {
+        match value {
+            Value::Number(number) => number.as_u64(),
+            Value::String(string) => string.parse::<u64>().ok(),
+            _ => None,
+        }
+    }
+
+    fn from_ffprobe_json(root: &Value) -> Self {
+        let streams = FFProbe::streams(root);
+
+        let first_of_type = |codec_type: &str| {
+            streams.iter().find(|stream| {
+                stream.get("codec_type").and_then(|value| value.as_str()) == Some(codec_type)
+            })
+        };
+
+        let opt_video = first_of_type("video");
+        let opt_audio = first_of_type("audio");
+
+        let video_field =
+            |key: &str| opt_video.and_then(|stream| stream.get(key).and_then(Self::as_u64));
+        let audio_field =
+            |key: &str| opt_audio.and_then(|stream| stream.get(key).and_then(Self::as_u64));
+
+        MediaInfo {
+            width: video_field("width"),
+            height: video_field("height"),
+            bit_rate: root
+                .get("format")
+                .and_then(|format| format.get("bit_rate"))
+                .and_then(Self::as_u64),
+            channels: audio_field("channels"),
+            sample_rate: audio_field("sample_rate"),
+        }
+    }
+
+    pub fn get_field(&self, name: &str) -> Option<u64> {
+        match name {
+            "width" => self.width,
+            "height" => self.height,
+            "bit_rate" => self.bit_rate,
+            "channels" => self.channels,
+            "sample_rate" => self.sample_rate,
+            _ => None,
+        }
+    }
+}
+
+// the pixel format and frame rate decoded video is normalized to, so a decoded video hash
+// does not depend upon whichever format ffmpeg would negotiate by default
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VideoFormat {
+    pub pix_fmt: String,
+    pub frame_rate: String,
+}
+
+impl VideoFormat {
+    // probe the first video stream, ignoring any stream ffprobe cannot describe, like attached pictures
+    pub fn probe(path: &Path) -> DanoResult<Option<Self>> {
+        let root = FFProbe::show_entries(path, "stream=codec_type,pix_fmt,r_frame_rate")?;
+
+        let opt_video_format = FFProbe::streams(&root)
+            .iter()
+            .filter(|stream| {
+                stream.get("codec_type").and_then(|value| value.as_str()) == Some("video")
+            })
+            .find_map(|stream| {
+                let pix_fmt = stream.get("pix_fmt").and_then(|value| value.as_str())?;
+                let frame_rate = stream
+                    .get("r_frame_rate")
+                    .and_then(|value| value.as_str())?;
+
+                // ffprobe reports an unknown frame rate as "0/0"
+                if pix_fmt.is_empty() || frame_rate.starts_with('0') {
+                    return None;
+                }
+
+                Some(Self {
+                    pix_fmt: pix_fmt.to_owned(),
+                    frame_rate: frame_rate.to_owned(),
+                })
+            });
+
+        Ok(opt_video_format)
+    }
+
+    pub fn as_output_args(&self) -> [&str; 4] {
+        ["-pix_fmt", &self.pix_fmt, "-r", &self.frame_rate]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QueryOp {
+    Eq,
+    NotEq,
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct QueryTerm {
+    field: Box<str>,
+    op: QueryOp,
+    value: u64,
+}
+
+// a query is a comma separated list of terms, like "height>=1080,channels=2",
+// all of which must match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaQuery {
+    terms: Vec<QueryTerm>,
+}
+
+impl MediaQuery {
+    const FIELDS: [&'static str; 5] = ["width", "height", "bit_rate", "channels", "sample_rate"];
+
+    pub fn new(query: &str) -> DanoResult<Self> {
+        let terms = query
+            .split(',')
+            .map(|term| term.trim())
+            .filter(|term| !term.is_empty())
+            .map(Self::parse_term)
+            .collect::<DanoResult<Vec<QueryTerm>>>()?;
+
+        if terms.is_empty() {
+            return Err(DanoError::new("Query is empty.").into());
+        }
+
+        Ok(Self { terms })
+    }
+
+    fn parse_term(term: &str) -> DanoResult<QueryTerm> {
+        // longest operators first, so "<=" isn't mistaken for "<"
+        let ops = [
+            ("!=", QueryOp::NotEq),
+            ("<=", QueryOp::LessEq),
+            (">=", QueryOp::GreaterEq),
+            ("=", QueryOp::Eq),
+            ("<", QueryOp::Less),
+            (">", QueryOp::Greater),
+        ];
+
+        let Some((field, op, value)) = ops.iter().find_map(|(token, op)| {
+            term.split_once(token)
+                .map(|(field, value)| (field, *op, value))
+        }) else {
+            let msg = format!("Query term has no comparison operator: {}", term);
+            return Err(DanoError::new(&msg).into());
+        };
+
+        let field = field.trim();
+
+        if !Self::FIELDS.contains(&field) {
+            let msg = format!(
+                "Query field is unknown: {}.  Valid fields are: {}",
+                field,
+                Self::FIELDS.join(", ")
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        let value = value.trim().parse::<u64>()?;
+
+        Ok(QueryTerm {
+            field: field.into(),
+            op,
+            value,
+        })
+    }
+
+    pub fn is_match(&self, opt_media_info: Option<&MediaInfo>) -> bool {
+        let Some(media_info) = opt_media_info else {
+            return false;
+        };
+
+        self.terms
+            .iter()
+            .all(|term| match media_info.get_field(&term.field) {
+                Some(field_value) => match term.op {
+                    QueryOp::Eq => field_value == term.value,
+                    QueryOp::NotEq => field_value != term.value,
+                    QueryOp::Less => field_value < term.value,
+                    QueryOp::LessEq => field_value <= term.value,
+                    QueryOp::Greater => field_value > term.value,
+                    QueryOp::GreaterEq => field_value >= term.value,
+                },
+                None => false,
+            })
+    }
+}
@@ -0,0 +1,222 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{collections::BTreeSet, path::Path};
+
+use crate::history::{HistoryAction, HistoryEntry, HistoryLog};
+use crate::ingest::RecordedFileInfo;
+use crate::lookup::{FileInfo, FileInfoLookup, FileMetadata};
+use crate::output::WriteableFileInfo;
+use crate::requests::RequestBundle;
+use crate::utility::{prepare_thread_pool, print_err_buf, print_outcome};
+use crate::{Config, DanoResult, DANO_CLEAN_EXIT_CODE, DANO_DISORDER_EXIT_CODE};
+
+const MIGRATE_PREFIX: &str = "Migrating dano hash for: ";
+const NOT_MIGRATE_PREFIX: &str =
+    "WARN: Not migrating dano hash for (because dry run was specified): ";
+
+// each recorded file is hashed twice, in a single pass, once with the recorded hash algorithm, to verify it,
+// and once with the new hash algorithm.  only a record which is verified is replaced, so no file's record
+// is ever trusted without being checked, and the old record is kept in the history log
+pub struct AlgoMigration;
+
+impl AlgoMigration {
+    pub fn exec(
+        config: &Config,
+        recorded_file_info: RecordedFileInfo,
+        hash_algo: &str,
+    ) -> DanoResult<i32> {
+        let input_paths: BTreeSet<&Path> = config.paths.iter().map(|path| path.as_path()).collect();
+
+        // records already in the new hash algorithm need not be migrated
+        let recorded: Vec<FileInfo> = recorded_file_info
+            .iter()
+            .filter(|file_info| {
+                input_paths.is_empty() || input_paths.contains(file_info.path.as_path())
+            })
+            .filter(|file_info| {
+                file_info
+                    .metadata
+                    .as_ref()
+                    .is_some_and(|metadata| !metadata.hash_algo.eq_ignore_ascii_case(hash_algo))
+            })
+            .cloned()
+            .collect();
+
+        if recorded.is_empty() {
+            print_err_buf("No recorded file info to migrate.\n")?;
+            return Ok(DANO_CLEAN_EXIT_CODE);
+        }
+
+        // a missing file cannot be verified, so is never requested
+        let present: Vec<FileInfo> = recorded
+            .iter()
+            .filter(|file_info| file_info.path.exists())
+            .cloned()
+            .collect();
+
+        let requests = RequestBundle::from_recorded(config, &present)
+            .into_inner()
+            .into_iter()
+            .chain(RequestBundle::with_hash_algo(config, &present, hash_algo).into_inner())
+            .collect::<Vec<_>>();
+
+        let thread_pool = prepare_thread_pool(config)?;
+        let (rx_item, _rx_unsupported) =
+            FileInfoLookup::exec(config, RequestBundle::from(requests), thread_pool)?;
+
+        let hashed: Vec<FileInfo> = rx_item
+            .iter()
+            .filter(|file_info| file_info.metadata.is_some())
+            .collect();
+
+        let mut old = Vec::new();
+        let mut migrated = Vec::new();
+        let mut history = Vec::new();
+        let mut num_failed = 0usize;
+
+        recorded
+            .iter()
+            .try_for_each(|file_info| -> DanoResult<()> {
+                let Some(recorded_metadata) = file_info.metadata.as_ref() else {
+                    return Ok(());
+                };
+
+                let opt_verified =
+                    Self::find_hashed(&hashed, file_info, &recorded_metadata.hash_algo);
+                let opt_new = Self::find_hashed(&hashed, file_info, hash_algo);
+
+                let (status, msg) = match (opt_verified, opt_new) {
+                    (Some(verified), Some(new))
+                        if verified
+                            .metadata
+                            .as_ref()
+                            .map(|metadata| &metadata.hash_value)
+                            == Some(&recorded_metadata.hash_value) =>
+                    {
+                        history.push(HistoryEntry::new(
+                            HistoryAction::Migrated,
+                            &file_info.path,
+                            Some(recorded_metadata.clone()),
+                            new.metadata.clone(),
+                        ));
+                        old.push(file_info.clone());
+                        migrated.push(new.clone());
+
+                        (
+                            "migrated",
+                            format!(
+                                "{:?}: OK, migrated from {} to {}\n",
+                                file_info.path, recorded_metadata.hash_algo, hash_algo
+                            ),
+                        )
+                    }
+                    (Some(_), Some(_)) => {
+                        num_failed += 1;
+                        (
+                            "mismatch",
+                            format!(
+                                "{:?}: WARNING, checksum mismatch, record was not migrated.\n",
+                                file_info.path
+                            ),
+                        )
+                    }
+                    _ if !file_info.path.exists() => {
+                        num_failed += 1;
+                        (
+                            "missing",
+                            format!(
+                                "{:?}: WARNING, path does not exist, record was not migrated.\n",
+                                file_info.path
+                            ),
+                        )
+                    }
+                    _ => {
+                        num_failed += 1;
+                        (
+                            "error",
+                            format!(
+                            "{:?}: WARNING, path could not be hashed, record was not migrated.\n",
+                            file_info.path
+                        ),
+                        )
+                    }
+                };
+
+                print_outcome(
+                    config,
+                    &file_info.path,
+                    status,
+                    Some(recorded_metadata),
+                    &msg,
+                )
+            })?;
+
+        let num_migrated = migrated.len();
+        let migrated_verb = if config.opt_dry_run {
+            "may be migrated"
+        } else {
+            "were migrated"
+        };
+
+        if !migrated.is_empty() {
+            WriteableFileInfo::from(migrated).migrate(
+                config,
+                &old,
+                NOT_MIGRATE_PREFIX,
+                MIGRATE_PREFIX,
+            )?;
+
+            if !config.opt_dry_run {
+                HistoryLog::new(config).append(&history)?;
+            }
+        }
+
+        if num_failed > 0 {
+            print_err_buf(&format!(
+                "FAILED: {} records {} to {}, but {} records could not be verified, and were not migrated.\n",
+                num_migrated, migrated_verb, hash_algo, num_failed
+            ))?;
+
+            return Ok(DANO_DISORDER_EXIT_CODE);
+        }
+
+        print_err_buf(&format!(
+            "PASSED: {} records were verified, and {} to {}.\n",
+            num_migrated, migrated_verb, hash_algo
+        ))?;
+
+        Ok(DANO_CLEAN_EXIT_CODE)
+    }
+
+    // the result of hashing the recorded file, in its recorded form, with the given hash algorithm
+    fn find_hashed<'a>(
+        hashed: &'a [FileInfo],
+        file_info: &FileInfo,
+        hash_algo: &str,
+    ) -> Option<&'a FileInfo> {
+        let recorded: &FileMetadata = file_info.metadata.as_ref()?;
+
+        hashed.iter().find(|hashed| {
+            hashed.path == file_info.path
+                && hashed.metadata.as_ref().is_some_and(|metadata| {
+                    metadata.hash_algo.eq_ignore_ascii_case(hash_algo)
+                        && metadata.decoded == recorded.decoded
+                        && metadata.selected_streams == recorded.selected_streams
+                })
+        })
+    }
+}
@@ -15,18 +15,38 @@
 // For the full copyright and license information, please view the LICENSE file
 // that was distributed with this source code.
 
-use std::collections::BTreeSet;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
 use itertools::Itertools;
+use rayon::prelude::*;
 
 use crate::ingest::RecordedFileInfo;
-use crate::{Config, ExecMode};
+use clap::crate_version;
+
+use crate::backup::HashFileBackup;
+use crate::binary::{BinaryHashFile, HashFileFormat};
+use crate::compress::HashFileCompression;
+use crate::config::{ExportFormat, SelectedStreams};
+use crate::hasher::NativeHasher;
+use crate::{
+    Config, ExecMode, DANO_CLEAN_EXIT_CODE, DANO_DISORDER_EXIT_CODE, DANO_ERROR_EXIT_CODE,
+};
 
-use crate::lookup::FileInfo;
+use crate::journal::XattrJournal;
+use crate::lookup::{FileInfo, FileMetadata};
 use crate::process::{ProcessedFiles, RemainderBundle};
+use crate::sanity::SuspectHash;
+use crate::sidecar::Sidecar;
+use crate::signature::HashFileSignature;
+use crate::tree::TreeHashes;
 use crate::utility::{
-    get_output_file, make_tmp_file, print_err_buf, read_file_info_from_file, write_file,
-    write_non_file, DanoError, DanoResult,
+    check_free_space, file_header, get_output_file, get_tmp_file, has_dano_xattr,
+    persist_hash_file, print_err_buf, print_out_buf, read_file_info_from_file,
+    read_newer_version_lines, serialize, write_file, write_line, write_non_file, DanoError,
+    DanoResult,
 };
 
 const WRITE_NEW_PREFIX: &str = "Writing dano hash for: ";
@@ -41,6 +61,10 @@ const NOT_OVERWRITE_OLD_PREFIX: &str =
     "WARN: Not overwriting dano hash for (as overwriting is not specified): ";
 const NOT_OVERWRITE_OLD_SUFFIX: &str = ", --overwrite was not specified.";
 
+const RESTORE_XATTR_PREFIX: &str = "Writing missing dano extended attribute for: ";
+const NOT_RESTORE_XATTR_PREFIX: &str =
+    "WARN: Not writing missing dano extended attribute for (because dry run was specified): ";
+
 const NEW_FILES_EMPTY: &str = "No new file paths to write";
 const MODIFIED_FILE_NAMES_EMPTY: &str = "No old file data to overwrite";
 
@@ -56,7 +80,11 @@ pub enum WriteType {
 }
 
 impl ProcessedFiles {
-    pub fn write_out(self, config: &Config) -> DanoResult<i32> {
+    pub fn write_out(mut self, config: &Config) -> DanoResult<i32> {
+        if self.refuse_suspect_hashes(config)? && self.exit_code == DANO_CLEAN_EXIT_CODE {
+            self.exit_code = DANO_DISORDER_EXIT_CODE;
+        }
+
         [self.new_files, self.modified_file_names]
             .into_iter()
             .try_for_each(|remainder_bundle| {
@@ -74,6 +102,76 @@ impl ProcessedFiles {
         Ok(self.exit_code)
     }
 
+    // returns whether any suspect hash was refused, a rewrite only converts what was already recorded,
+    // and so is not checked
+    fn refuse_suspect_hashes(&mut self, config: &Config) -> DanoResult<bool> {
+        let (is_rewrite, is_import) = match &config.exec_mode {
+            ExecMode::Write(write_config) => {
+                (write_config.opt_rewrite, write_config.opt_import.is_some())
+            }
+            _ => (false, false),
+        };
+
+        if is_rewrite {
+            return Ok(false);
+        }
+
+        let mut is_refused = false;
+
+        [&mut self.new_files, &mut self.modified_file_names]
+            .into_iter()
+            .try_for_each(|remainder_bundle| {
+                let files = match remainder_bundle {
+                    RemainderBundle::NewFile(files) | RemainderBundle::ModifiedFilename(files) => {
+                        files
+                    }
+                };
+
+                let suspects: Vec<Option<SuspectHash>> = files
+                    .par_iter()
+                    .map(|file_info| SuspectHash::check(file_info, !is_import))
+                    .collect();
+
+                files
+                    .iter()
+                    .zip(suspects.iter())
+                    .filter_map(|(file_info, opt_suspect)| Some((file_info, opt_suspect.as_ref()?)))
+                    .try_for_each(|(file_info, suspect)| {
+                        let msg = if config.opt_allow_empty_streams {
+                            format!(
+                                "WARN: {:?}: Recording a suspicious hash, because --allow-empty-streams was specified: {}.\n",
+                                file_info.path,
+                                suspect.as_str()
+                            )
+                        } else {
+                            format!(
+                                "WARN: {:?}: Refusing to record a suspicious hash: {}.  Use --allow-empty-streams to record it anyway.\n",
+                                file_info.path,
+                                suspect.as_str()
+                            )
+                        };
+
+                        print_err_buf(&msg)
+                    })?;
+
+                if !config.opt_allow_empty_streams && suspects.iter().any(Option::is_some) {
+                    is_refused = true;
+
+                    *files = std::mem::take(files)
+                        .into_iter()
+                        .zip(suspects)
+                        .filter_map(|(file_info, opt_suspect)| {
+                            opt_suspect.is_none().then_some(file_info)
+                        })
+                        .collect();
+                }
+
+                DanoResult::Ok(())
+            })?;
+
+        Ok(is_refused)
+    }
+
     fn print_bundle_empty(config: &Config, remainder_bundle: &RemainderBundle) {
         if !config.is_single_path {
             match &config.exec_mode {
@@ -142,6 +240,152 @@ impl RemainderBundle {
     }
 }
 
+impl RecordedFileInfo {
+    // write recorded file info to input files which have no dano xattr, because the record
+    // was found elsewhere, like the hash file, these files need not be hashed again
+    pub fn write_missing_xattrs(&self, config: &Config) -> DanoResult<()> {
+        let input_paths: BTreeSet<&Path> = config.paths.iter().map(|path| path.as_path()).collect();
+
+        let missing: Vec<FileInfo> = self
+            .iter()
+            .filter(|file_info| file_info.metadata.is_some())
+            .filter(|file_info| input_paths.contains(file_info.path.as_path()))
+            .filter(|file_info| !has_dano_xattr(&file_info.path))
+            .cloned()
+            .collect();
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        WriteableFileInfo::from(missing).exec(
+            config,
+            NOT_RESTORE_XATTR_PREFIX,
+            RESTORE_XATTR_PREFIX,
+        )
+    }
+}
+
+impl ExportFormat {
+    fn hash_algo(&self) -> &'static str {
+        match self {
+            ExportFormat::Md5sum | ExportFormat::Ffp => "md5",
+            ExportFormat::Sha256sum => "sha256",
+            ExportFormat::Sfv => "crc32",
+        }
+    }
+
+    // hash values are recorded without leading zeros, but checksum files require the full width
+    fn hash_width(&self) -> usize {
+        match self {
+            ExportFormat::Md5sum | ExportFormat::Ffp => 32,
+            ExportFormat::Sha256sum => 64,
+            ExportFormat::Sfv => 8,
+        }
+    }
+
+    // whether a record is already in the form this format requires
+    fn is_exportable(&self, metadata: &FileMetadata) -> bool {
+        if !metadata.hash_algo.eq_ignore_ascii_case(self.hash_algo()) {
+            return false;
+        }
+
+        match self {
+            ExportFormat::Ffp => {
+                metadata.decoded
+                    && metadata.selected_streams == SelectedStreams::AudioOnly
+                    && metadata.opt_bits_per_second.is_some()
+            }
+            _ => metadata.selected_streams == SelectedStreams::WholeFile,
+        }
+    }
+
+    fn format_line(&self, path_string: &str, hash_value: &str) -> String {
+        let hash_value = format!("{:0>width$}", hash_value, width = self.hash_width());
+
+        match self {
+            // coreutils escapes a path containing a backslash or newline, and marks the line with a leading backslash
+            ExportFormat::Md5sum | ExportFormat::Sha256sum
+                if path_string.contains(['\\', '\n']) =>
+            {
+                let escaped = path_string.replace('\\', "\\\\").replace('\n', "\\n");
+                format!("\\{}  {}\n", hash_value, escaped)
+            }
+            ExportFormat::Md5sum | ExportFormat::Sha256sum => {
+                format!("{}  {}\n", hash_value, path_string)
+            }
+            ExportFormat::Sfv => format!("{} {}\n", path_string, hash_value.to_ascii_uppercase()),
+            ExportFormat::Ffp => format!("{}:{}\n", path_string, hash_value),
+        }
+    }
+}
+
+impl RecordedFileInfo {
+    // export to stdout, one line per path, such that the files may be verified without dano
+    pub fn export(&self, config: &Config, format: &ExportFormat) -> DanoResult<i32> {
+        if self.is_empty() {
+            return Err(DanoError::new("No recorded file info is available to export.").into());
+        }
+
+        let mut exit_code = DANO_CLEAN_EXIT_CODE;
+
+        if let ExportFormat::Sfv = format {
+            print_out_buf(&format!("; Generated by dano v{}\n", crate_version!()))?;
+        }
+
+        // a path may be recorded in more than one form, but is exported once
+        let by_path = self
+            .iter()
+            .filter_map(|file_info| Some((&file_info.path, file_info.metadata.as_ref()?)))
+            .into_group_map();
+
+        for (path, recorded) in by_path.into_iter().sorted_by_key(|(path, _)| *path) {
+            // a checksum file is text, and a path which is not UTF-8 cannot be written so that it could be verified
+            let Some(path_string) = path.to_str() else {
+                if !config.opt_silent {
+                    print_err_buf(&format!(
+                        "WARN: Not exporting {:?}, whose path is not valid UTF-8.\n",
+                        path
+                    ))?;
+                }
+                continue;
+            };
+
+            let hash_value: Box<str> = match recorded
+                .iter()
+                .find(|metadata| format.is_exportable(metadata))
+            {
+                Some(metadata) => metadata.hash_value.value.clone(),
+                // a FLAC fingerprint can only come from a record of the decoded audio
+                None if matches!(format, ExportFormat::Ffp) => {
+                    if !config.opt_silent {
+                        print_err_buf(&format!(
+                            "WARN: Not exporting {:?}, which has no record of its decoded audio stream hashed with MD5.\n",
+                            path
+                        ))?;
+                    }
+                    continue;
+                }
+                None => match NativeHasher::hash_file(path, format.hash_algo()) {
+                    Ok(hash_line) => match hash_line.split_once('=') {
+                        Some((_, hex)) => hex.into(),
+                        None => continue,
+                    },
+                    Err(err) => {
+                        print_err_buf(&format!("ERROR: Could not export {:?}: {}\n", path, err))?;
+                        exit_code = DANO_ERROR_EXIT_CODE;
+                        continue;
+                    }
+                },
+            };
+
+            print_out_buf(&format.format_line(path_string, &hash_value))?;
+        }
+
+        Ok(exit_code)
+    }
+}
+
 pub struct WriteableFileInfo {
     inner: Vec<FileInfo>,
 }
@@ -160,15 +404,83 @@ impl From<RecordedFileInfo> for WriteableFileInfo {
     }
 }
 
+// a path may be recorded in more than one form, such as both decoded and copied, or with more than one hash algorithm,
+// and each form of a path is a record of its own
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RecordForm {
+    path: PathBuf,
+    opt_form: Option<(Box<str>, bool, SelectedStreams)>,
+}
+
+impl RecordForm {
+    pub fn of(file_info: &FileInfo) -> Self {
+        Self {
+            path: file_info.path.clone(),
+            opt_form: file_info.metadata.as_ref().map(|metadata| {
+                (
+                    metadata.hash_algo.clone(),
+                    metadata.decoded,
+                    metadata.selected_streams.clone(),
+                )
+            }),
+        }
+    }
+}
+
+// each form of each path is kept once, the last given of each, sorted by path, then form
+fn unique_forms(file_infos: impl IntoIterator<Item = FileInfo>) -> Vec<FileInfo> {
+    let unique: BTreeMap<RecordForm, FileInfo> = file_infos
+        .into_iter()
+        .map(|file_info| (RecordForm::of(&file_info), file_info))
+        .collect();
+
+    unique.into_values().collect()
+}
+
+// the same record, of the same path, hashed in the same form, with the same hash value
+pub fn is_same_record(checked: &FileInfo, recorded: &FileInfo) -> bool {
+    match (&checked.metadata, &recorded.metadata) {
+        (Some(checked), Some(recorded)) => {
+            checked.hash_algo == recorded.hash_algo
+                && checked.decoded == recorded.decoded
+                && checked.selected_streams == recorded.selected_streams
+                && checked.hash_value == recorded.hash_value
+        }
+        _ => false,
+    }
+}
+
 impl WriteableFileInfo {
     pub fn exec(self, config: &Config, dry_prefix: &str, wet_prefix: &str) -> DanoResult<()> {
+        if let Some(hash_policy) = &config.opt_hash_policy {
+            if !config.opt_dry_run {
+                hash_policy.check_writeable(&self.inner)?;
+            }
+        }
+
         match &config.exec_mode {
             _ if config.opt_dry_run => self.print_action(dry_prefix, EMPTY_STR),
+            _ if config.opt_output_stdout => {
+                self.print_action(wet_prefix, EMPTY_STR)?;
+                self.write_action_stdout()
+            }
+            _ if config.opt_sidecar && !matches!(config.exec_mode, ExecMode::Dump) => {
+                self.print_action(wet_prefix, EMPTY_STR)?;
+                self.write_action_sidecar(config)
+            }
             // XATTR can be enabled via env var, because of this we don't want it to conflict with any other option,
             // so need to guard against it be enabled in modes it which we must write to disk, such as DUMP
+            _ if config.opt_xattr
+                && config.opt_both_stores
+                && !matches!(config.exec_mode, ExecMode::Dump) =>
+            {
+                self.print_action(wet_prefix, EMPTY_STR)?;
+                self.write_action_xattr(config)?;
+                self.append_and_rewrite(config)
+            }
             _ if config.opt_xattr && !matches!(config.exec_mode, ExecMode::Dump) => {
                 self.print_action(wet_prefix, EMPTY_STR)?;
-                self.write_action_xattr()
+                self.write_action_xattr(config)
             }
             ExecMode::Dump | ExecMode::Write(_) => {
                 self.print_action(wet_prefix, EMPTY_STR)?;
@@ -185,16 +497,279 @@ impl WriteableFileInfo {
         }
     }
 
+    // replaces the records of the same path, hashed in the same form, rather than appending alongside them
+    pub fn replace(self, config: &Config, dry_prefix: &str, wet_prefix: &str) -> DanoResult<()> {
+        if let Some(hash_policy) = &config.opt_hash_policy {
+            if !config.opt_dry_run {
+                hash_policy.check_writeable(&self.inner)?;
+            }
+        }
+
+        if config.opt_dry_run {
+            return self.print_action(dry_prefix, EMPTY_STR);
+        }
+
+        self.print_action(wet_prefix, EMPTY_STR)?;
+
+        if config.opt_output_stdout {
+            return self.write_action_stdout();
+        }
+
+        self.replace_action(config)
+    }
+
+    // records which were tested and found to match are recorded as checked, in place, in each store
+    // which holds the same record, but are never added to a store which does not
+    pub fn record_checked(self, config: &Config) -> DanoResult<()> {
+        if config.opt_dry_run || config.opt_read_only || self.inner.is_empty() {
+            return Ok(());
+        }
+
+        self.inner.iter().try_for_each(|checked| -> DanoResult<()> {
+            if matches!(Sidecar::read(&checked.path), Some(recorded) if is_same_record(checked, &recorded))
+            {
+                Sidecar::write(config, checked)?;
+            }
+
+            if config.opt_xattr
+                && matches!(RecordedFileInfo::read_file_info_from_xattr(&checked.path), Some(recorded) if is_same_record(checked, &recorded))
+            {
+                write_non_file(checked)?;
+            }
+
+            Ok(())
+        })?;
+
+        // a signed hash file is not rewritten merely to record the time of each check, unless it is to be signed again
+        if !config.output_file.exists()
+            || (config.opt_sign.is_none() && HashFileSignature::is_signed(&config.output_file))
+        {
+            return Ok(());
+        }
+
+        let mut is_updated = false;
+
+        // a path may be recorded, and checked, in more than one form
+        let checked_by_path: HashMap<&Path, Vec<&FileInfo>> = self
+            .inner
+            .iter()
+            .map(|checked| (checked.path.as_path(), checked))
+            .into_group_map();
+
+        let updated: Vec<FileInfo> = read_file_info_from_file(config)?
+            .into_iter()
+            .map(|recorded| {
+                match checked_by_path
+                    .get(recorded.path.as_path())
+                    .and_then(|checked| {
+                        checked
+                            .iter()
+                            .find(|checked| is_same_record(checked, &recorded))
+                            .copied()
+                    }) {
+                    Some(checked) => {
+                        is_updated = true;
+                        checked.clone()
+                    }
+                    None => recorded,
+                }
+            })
+            .collect();
+
+        if !is_updated {
+            return Ok(());
+        }
+
+        // recording the time of each check is not worth a backup, which would rotate out a backup from before a bad rewrite
+        let config = Config {
+            num_backups: 0,
+            ..config.clone()
+        };
+
+        Self { inner: updated }.write_action_file(&config, WriteType::Overwrite)
+    }
+
+    // each old record is replaced by its new record, under another hash algorithm, in each store which
+    // holds the old record, so the hash file is rewritten once, and no record is lost in between
+    pub fn migrate(
+        self,
+        config: &Config,
+        old: &[FileInfo],
+        dry_prefix: &str,
+        wet_prefix: &str,
+    ) -> DanoResult<()> {
+        if let Some(hash_policy) = &config.opt_hash_policy {
+            if !config.opt_dry_run {
+                hash_policy.check_writeable(&self.inner)?;
+            }
+        }
+
+        if config.opt_dry_run {
+            return self.print_action(dry_prefix, EMPTY_STR);
+        }
+
+        self.print_action(wet_prefix, EMPTY_STR)?;
+
+        // each old record, by its form, and the new record which replaces it
+        let old_to_new: HashMap<RecordForm, (&FileInfo, &FileInfo)> = old
+            .iter()
+            .zip(self.inner.iter())
+            .map(|(old, new)| (RecordForm::of(old), (old, new)))
+            .collect();
+
+        let find_new = |recorded: &FileInfo| -> Option<&FileInfo> {
+            old_to_new
+                .get(&RecordForm::of(recorded))
+                .filter(|(old, _new)| is_same_record(old, recorded))
+                .map(|(_old, new)| *new)
+        };
+
+        self.inner
+            .iter()
+            .zip(old.iter())
+            .try_for_each(|(new, old)| -> DanoResult<()> {
+                if matches!(Sidecar::read(&new.path), Some(recorded) if is_same_record(old, &recorded))
+                {
+                    Sidecar::write(config, new)?;
+                }
+
+                if config.opt_xattr
+                    && matches!(RecordedFileInfo::read_file_info_from_xattr(&new.path), Some(recorded) if is_same_record(old, &recorded))
+                {
+                    write_non_file(new)?;
+                }
+
+                Ok(())
+            })?;
+
+        if !config.output_file.exists() {
+            return Ok(());
+        }
+
+        let mut is_updated = false;
+
+        // each form of a path is migrated on its own, and each record is kept, in place
+        let updated: Vec<FileInfo> = read_file_info_from_file(config)?
+            .into_iter()
+            .map(|recorded| match find_new(&recorded) {
+                Some(new) => {
+                    is_updated = true;
+                    new.clone()
+                }
+                None => recorded,
+            })
+            .collect();
+
+        if !is_updated {
+            return Ok(());
+        }
+
+        Self { inner: updated }.write_action_file(config, WriteType::Overwrite)
+    }
+
+    // the hash file is rewritten with exactly these records
+    pub fn rewrite(self, config: &Config) -> DanoResult<()> {
+        if !config.output_file.exists() {
+            return self.write_action_file(config, WriteType::Append);
+        }
+
+        self.write_action_file(config, WriteType::Overwrite)
+    }
+
+    fn replace_action(self, config: &Config) -> DanoResult<()> {
+        if config.opt_sidecar {
+            return self.write_action_sidecar(config);
+        }
+
+        if config.opt_xattr {
+            self.write_action_xattr(config)?;
+
+            if !config.opt_both_stores {
+                return Ok(());
+            }
+        }
+
+        if !config.output_file.exists() {
+            return self.write_action_file(config, WriteType::Append);
+        }
+
+        // a record is replaced by a record of the same path in the same form, and a record without metadata
+        // replaces, or is replaced by, any record of the same path
+        let paths: HashSet<&Path> = self
+            .inner
+            .iter()
+            .map(|file_info| file_info.path.as_path())
+            .collect();
+
+        let any_form_paths: HashSet<&Path> = self
+            .inner
+            .iter()
+            .filter(|file_info| file_info.metadata.is_none())
+            .map(|file_info| file_info.path.as_path())
+            .collect();
+
+        let forms: HashSet<RecordForm> = self.inner.iter().map(RecordForm::of).collect();
+
+        let is_replaced = |recorded: &FileInfo| {
+            paths.contains(recorded.path.as_path())
+                && (recorded.metadata.is_none()
+                    || any_form_paths.contains(recorded.path.as_path())
+                    || forms.contains(&RecordForm::of(recorded)))
+        };
+
+        let unique_forms = unique_forms(
+            read_file_info_from_file(config)?
+                .into_iter()
+                .filter(|recorded| !is_replaced(recorded))
+                .chain(self.inner.iter().cloned()),
+        );
+
+        let writeable_file_info: WriteableFileInfo = Self {
+            inner: unique_forms,
+        };
+
+        writeable_file_info.write_action_file(config, WriteType::Overwrite)
+    }
+
     fn print_action(&self, prefix: &str, suffix: &str) -> DanoResult<()> {
         self.inner.iter().try_for_each(|file_info| {
             print_err_buf(&format!("{}{:?}{}\n", prefix, file_info.path, suffix))
         })
     }
 
+    // whether records are appended to the hash file, and not only to extended attributes, or to sidecars
+    pub fn is_appended(config: &Config) -> bool {
+        !config.opt_dry_run
+            && !config.opt_output_stdout
+            && (!config.opt_sidecar || matches!(config.exec_mode, ExecMode::Dump))
+            && (!config.opt_xattr
+                || config.opt_both_stores
+                || matches!(config.exec_mode, ExecMode::Dump))
+    }
+
     fn append_and_rewrite(&self, config: &Config) -> DanoResult<()> {
+        // an initial run appends each batch, and rewrites the hash file only once, after the last batch
+        if matches!(&config.exec_mode, ExecMode::Write(write_config) if write_config.opt_init.is_some())
+        {
+            return self.write_action_file(config, WriteType::Append);
+        }
+
+        // the backup is of the hash file before the append, so the rewrite which follows requires no other
+        HashFileBackup::exec(config, &config.output_file)?;
+
+        let config = &Config {
+            num_backups: 0,
+            ..config.clone()
+        };
+
         // append new paths
         self.write_action_file(config, WriteType::Append)?;
 
+        Self::dedup_and_rewrite(config)
+    }
+
+    // the hash file is rewritten with the most recently written record of each form of each path
+    pub fn dedup_and_rewrite(config: &Config) -> DanoResult<()> {
         // read back
         let recorded_file_info_with_duplicates: Vec<FileInfo> = if config.output_file.exists() {
             read_file_info_from_file(config)?
@@ -203,32 +778,85 @@ impl WriteableFileInfo {
         };
 
         // then dedup and sort
-        let unique_paths: BTreeSet<FileInfo> = recorded_file_info_with_duplicates
+        let mut deduped: Vec<FileInfo> = recorded_file_info_with_duplicates
             .into_iter()
             .filter(|file_info| file_info.metadata.is_some())
-            .into_group_map_by(|file_info| file_info.metadata.as_ref().unwrap().hash_value.clone())
-            .into_iter()
-            .flat_map(|(_hash, group_file_info)| {
+            // hardlinks share a hash value, but each link is recorded under its own path
+            .into_group_map_by(|file_info| {
+                let metadata = file_info.metadata.as_ref().unwrap();
+                let opt_link_path = metadata.hardlinked.then(|| file_info.path.clone());
+                (metadata.hash_value.clone(), opt_link_path)
+            })
+            .into_values()
+            .flat_map(|group_file_info| {
                 group_file_info
                     .into_iter()
                     .max_by_key(|file_info| file_info.metadata.as_ref().unwrap().last_written)
             })
             .collect();
 
+        // of each form of each path, the most recently written record is kept
+        deduped.sort_by_key(|file_info| file_info.metadata.as_ref().unwrap().last_written);
+
         let writeable_file_info: WriteableFileInfo = Self {
-            inner: unique_paths.into_iter().collect(),
+            inner: unique_forms(deduped),
         };
 
         // and overwrite
         writeable_file_info.write_action_file(config, WriteType::Overwrite)
     }
 
-    fn write_action_xattr(&self) -> DanoResult<()> {
-        self.inner.iter().try_for_each(write_non_file)
+    // records streamed to stdout are the lines of a hash file, without its header
+    fn write_action_stdout(&self) -> DanoResult<()> {
+        self.inner
+            .iter()
+            .try_for_each(|file_info| print_out_buf(&serialize(file_info)?))
+    }
+
+    fn write_action_sidecar(&self, config: &Config) -> DanoResult<()> {
+        self.inner
+            .iter()
+            .try_for_each(|file_info| Sidecar::write(config, file_info))
+    }
+
+    // a batch is journaled, so, should the run die midway, the next run completes the batch
+    fn write_action_xattr(&self, config: &Config) -> DanoResult<()> {
+        if self.inner.len() <= 1 {
+            return self.inner.iter().try_for_each(write_non_file);
+        }
+
+        let journal = XattrJournal::new(config);
+
+        journal.begin(config, &self.inner)?;
+        self.inner.iter().try_for_each(write_non_file)?;
+        journal.commit()
     }
 
     fn write_action_file(&self, config: &Config, write_type: WriteType) -> DanoResult<()> {
         match write_type {
+            WriteType::Append
+                if HashFileFormat::for_append(config, &config.output_file)
+                    == HashFileFormat::Binary =>
+            {
+                BinaryHashFile::append(&config.output_file, &file_header(config), &self.inner)
+            }
+            // records are appended to a compressed hash file in a frame of their own
+            WriteType::Append if HashFileCompression::is_compressed(&config.output_file) => {
+                let mut buffer = String::new();
+
+                if !config.output_file.exists() {
+                    buffer.push_str(&file_header(config));
+                }
+
+                self.inner
+                    .iter()
+                    .try_for_each(|file_info| -> DanoResult<()> {
+                        buffer.push_str(&serialize(file_info)?);
+                        Ok(())
+                    })?;
+
+                HashFileCompression::append(&config.output_file, buffer.as_bytes())
+            }
             WriteType::Append => {
                 let mut output_file = get_output_file(config, WriteType::Append)?;
                 self.inner
@@ -236,17 +864,34 @@ impl WriteableFileInfo {
                     .try_for_each(|file_info| write_file(file_info, &mut output_file))
             }
             WriteType::Overwrite => {
-                let mut output_file = get_output_file(config, WriteType::Overwrite)?;
+                let tmp_file = get_tmp_file(config, &config.output_file);
 
-                self.inner
-                    .iter()
-                    .try_for_each(|file_info| write_file(file_info, &mut output_file))?;
+                check_free_space(&tmp_file, config.output_file.metadata()?.len())?;
 
-                std::fs::rename(
-                    make_tmp_file(config.output_file.as_path()),
-                    &config.output_file,
-                )
-                .map_err(|err| err.into())
+                // records written by a newer version of dano are carried over, as they cannot be read
+                let newer_version_lines = read_newer_version_lines(&config.output_file)?;
+                // as are tree hashes, which are not records of any file
+                let tree_hash_lines = TreeHashes::read_lines(&config.output_file)?;
+
+                let res = get_output_file(config, WriteType::Overwrite)
+                    .and_then(|mut output_file| {
+                        self.inner
+                            .iter()
+                            .try_for_each(|file_info| write_file(file_info, &mut output_file))?;
+
+                        newer_version_lines
+                            .iter()
+                            .chain(tree_hash_lines.iter())
+                            .try_for_each(|line| write_line(line, &mut output_file))
+                    })
+                    .and_then(|_| persist_hash_file(config, &tmp_file, &config.output_file));
+
+                // don't leave a partial tmp file behind, if, for instance, the disk is full
+                if res.is_err() {
+                    let _ = std::fs::remove_file(&tmp_file);
+                }
+
+                res
             }
         }
     }
@@ -0,0 +1,221 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::hasher::NativeHasher;
+use crate::{DanoError, DanoResult};
+
+// the sample data of uncompressed audio files may be hashed by dano itself, skipping the chunk headers,
+// which is faster than asking ffmpeg, and is the same data which ffmpeg would hash as copied packets
+pub struct PcmHasher;
+
+#[derive(Clone, Copy)]
+enum Container {
+    Wave,
+    Aiff,
+}
+
+impl Container {
+    fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+
+        match extension.as_str() {
+            "wav" | "wave" => Some(Container::Wave),
+            "aif" | "aiff" | "aifc" => Some(Container::Aiff),
+            _ => None,
+        }
+    }
+
+    // chunk sizes are little endian in RIFF, big endian in IFF
+    fn chunk_size(&self, bytes: [u8; 4]) -> u64 {
+        match self {
+            Container::Wave => u32::from_le_bytes(bytes) as u64,
+            Container::Aiff => u32::from_be_bytes(bytes) as u64,
+        }
+    }
+}
+
+// the WAVE_FORMAT_EXTENSIBLE subformat GUID of integer PCM, KSDATAFORMAT_SUBTYPE_PCM
+const WAVE_SUBTYPE_PCM: [u8; 16] = [
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+// RF64 files store the real size of the "data" chunk in the "ds64" chunk
+const RF64_PLACEHOLDER_SIZE: u64 = 0xFFFF_FFFF;
+
+impl PcmHasher {
+    // only integer PCM is hashed natively, anything else, like compressed or float sample data,
+    // must be hashed by ffmpeg
+    pub fn is_supported(path: &Path) -> bool {
+        Container::from_path(path).is_some() && Self::sample_data_range(path).is_ok()
+    }
+
+    pub fn hash_file(path: &Path, hash_algo: &str) -> DanoResult<Box<str>> {
+        let (offset, len) = Self::sample_data_range(path)?;
+
+        NativeHasher::hash_file_range(path, hash_algo, offset, len)
+    }
+
+    // the offset and length of the sample data, within the "data" chunk of a WAVE file,
+    // or the "SSND" chunk of an AIFF file
+    fn sample_data_range(path: &Path) -> DanoResult<(u64, u64)> {
+        let Some(container) = Container::from_path(path) else {
+            let msg = format!("Path is not an uncompressed audio file: {:?}", path);
+            return Err(DanoError::new(&msg).into());
+        };
+
+        let mut file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+
+        let mut header = [0u8; 12];
+        file.read_exact(&mut header)?;
+
+        let is_valid = match container {
+            Container::Wave => {
+                matches!(&header[..4], b"RIFF" | b"RF64") && &header[8..12] == b"WAVE"
+            }
+            Container::Aiff => {
+                &header[..4] == b"FORM" && matches!(&header[8..12], b"AIFF" | b"AIFC")
+            }
+        };
+
+        if !is_valid {
+            let msg = format!(
+                "Could not parse the header of uncompressed audio file: {:?}",
+                path
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        let is_rf64 = &header[..4] == b"RF64";
+        let is_aifc = &header[8..12] == b"AIFC";
+
+        let mut opt_is_pcm: Option<bool> = None;
+        let mut opt_ds64_data_size: Option<u64> = None;
+        let mut opt_range: Option<(u64, u64)> = None;
+        let mut position = header.len() as u64;
+
+        while position + 8 <= file_len {
+            if let (Some(is_pcm), Some(range)) = (opt_is_pcm, opt_range) {
+                return Self::pcm_only(path, is_pcm, range);
+            }
+
+            let mut chunk_header = [0u8; 8];
+            file.read_exact(&mut chunk_header)?;
+
+            let chunk_id = &chunk_header[..4];
+            let mut chunk_size = container.chunk_size(chunk_header[4..8].try_into()?);
+            let chunk_start = position + 8;
+            // a chunk may claim more than remains, as when a recording was interrupted,
+            // so only what remains is hashed
+            let remaining = file_len - chunk_start;
+
+            match (container, chunk_id) {
+                (Container::Wave, b"ds64") if is_rf64 => {
+                    // the RIFF size, and then the data size, as 64 bit integers
+                    let mut ds64 = [0u8; 16];
+                    file.read_exact(&mut ds64)?;
+                    opt_ds64_data_size = Some(u64::from_le_bytes(ds64[8..16].try_into()?));
+                }
+                (Container::Wave, b"fmt ") => {
+                    let mut fmt = [0u8; 40];
+                    let fmt_len = (chunk_size.min(remaining) as usize).min(fmt.len());
+                    file.read_exact(&mut fmt[..fmt_len])?;
+
+                    let format_tag = u16::from_le_bytes(fmt[..2].try_into()?);
+
+                    opt_is_pcm = Some(match format_tag {
+                        WAVE_FORMAT_PCM => true,
+                        WAVE_FORMAT_EXTENSIBLE => {
+                            fmt_len == fmt.len() && fmt[24..40] == WAVE_SUBTYPE_PCM
+                        }
+                        _ => false,
+                    });
+                }
+                (Container::Wave, b"data") => {
+                    // the format must precede the sample data in a WAVE file
+                    let is_pcm = opt_is_pcm.unwrap_or(false);
+
+                    if is_rf64 && chunk_size == RF64_PLACEHOLDER_SIZE {
+                        let Some(ds64_data_size) = opt_ds64_data_size else {
+                            let msg = format!(
+                                "Could not find the data size of RF64 audio file: {:?}",
+                                path
+                            );
+                            return Err(DanoError::new(&msg).into());
+                        };
+
+                        chunk_size = ds64_data_size;
+                    }
+
+                    return Self::pcm_only(path, is_pcm, (chunk_start, chunk_size.min(remaining)));
+                }
+                (Container::Aiff, b"COMM") => {
+                    // an AIFF file is always integer PCM, an AIFC file names its compression type
+                    // after the channels, frames, sample size and sample rate
+                    opt_is_pcm = Some(if is_aifc {
+                        let mut comm = [0u8; 22];
+                        let comm_len = (chunk_size.min(remaining) as usize).min(comm.len());
+                        file.read_exact(&mut comm[..comm_len])?;
+
+                        comm_len == comm.len() && matches!(&comm[18..22], b"NONE" | b"sowt")
+                    } else {
+                        true
+                    });
+                }
+                (Container::Aiff, b"SSND") => {
+                    // the sample data is preceded by an offset, and a block size, and then the offset itself
+                    let mut ssnd_header = [0u8; 4];
+                    file.read_exact(&mut ssnd_header)?;
+                    let data_offset = 8 + u32::from_be_bytes(ssnd_header) as u64;
+
+                    let data_len = chunk_size.min(remaining).saturating_sub(data_offset);
+                    opt_range = Some((chunk_start + data_offset, data_len));
+                }
+                _ => {}
+            }
+
+            // chunks are padded to an even size
+            position = chunk_start + chunk_size + (chunk_size % 2);
+            file.seek(SeekFrom::Start(position))?;
+        }
+
+        if let (Some(is_pcm), Some(range)) = (opt_is_pcm, opt_range) {
+            return Self::pcm_only(path, is_pcm, range);
+        }
+
+        let msg = format!(
+            "Could not find the sample data of uncompressed audio file: {:?}",
+            path
+        );
+        Err(DanoError::new(&msg).into())
+    }
+
+    fn pcm_only(path: &Path, is_pcm: bool, range: (u64, u64)) -> DanoResult<(u64, u64)> {
+        if !is_pcm {
+            let msg = format!("Sample data of audio file is not integer PCM: {:?}", path);
+            return Err(DanoError::new(&msg).into());
+        }
+
+        Ok(range)
+    }
+}
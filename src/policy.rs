@@ -0,0 +1,136 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::collections::BTreeSet;
+
+use crate::lookup::FileInfo;
+use crate::utility::print_err_buf;
+use crate::{Config, DanoError, DanoResult};
+
+// a minimum hash algorithm, set by an administrator or user in a config file, such that
+// dano refuses to write records which use a weaker algorithm, unless explicitly allowed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashPolicy {
+    pub min_hash_algo: Box<str>,
+    pub opt_allow_weak_hash: bool,
+}
+
+impl HashPolicy {
+    pub fn new(min_hash_algo: &str, opt_allow_weak_hash: bool) -> DanoResult<Self> {
+        if Self::strength(min_hash_algo).is_none() {
+            let msg = format!(
+                "Minimum hash algorithm in config file is not a known hash algorithm: {}",
+                min_hash_algo
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        Ok(Self {
+            min_hash_algo: min_hash_algo.into(),
+            opt_allow_weak_hash,
+        })
+    }
+
+    // algorithms are ranked from non-cryptographic checksums to the longest cryptographic digests,
+    // ffmpeg names and native hasher names are both accepted, in any case
+    pub fn strength(hash_algo: &str) -> Option<u8> {
+        let rank = match hash_algo.to_ascii_lowercase().as_str() {
+            "crc32" | "adler32" => 0,
+            "murmur3" => 1,
+            "md5" => 2,
+            "sha1" | "sha160" => 3,
+            "sha256" | "blake3" => 4,
+            "sha384" => 5,
+            "sha512" => 6,
+            _ => return None,
+        };
+
+        Some(rank)
+    }
+
+    // the stronger of two minimums, so a user config may raise, but never lower, the system minimum
+    pub fn stronger<'a>(first: &'a str, second: &'a str) -> &'a str {
+        if Self::strength(second) > Self::strength(first) {
+            second
+        } else {
+            first
+        }
+    }
+
+    // an unknown algorithm cannot be shown to meet the minimum, so it is considered weak
+    pub fn is_weak(&self, hash_algo: &str) -> bool {
+        Self::strength(hash_algo) < Self::strength(&self.min_hash_algo)
+    }
+
+    pub fn check_selected(&self, hash_algo: &str) -> DanoResult<()> {
+        if self.opt_allow_weak_hash || !self.is_weak(hash_algo) {
+            return Ok(());
+        }
+
+        let msg = format!(
+            "Hash algorithm '{}' is weaker than the minimum required by policy, '{}'.  \
+            Specify a stronger algorithm with --hash-algo, or use --allow-weak-hash to override.",
+            hash_algo, self.min_hash_algo
+        );
+        Err(DanoError::new(&msg).into())
+    }
+
+    pub fn check_writeable(&self, file_info: &[FileInfo]) -> DanoResult<()> {
+        if self.opt_allow_weak_hash {
+            return Ok(());
+        }
+
+        let weak_algos: BTreeSet<&str> =
+            self.weak_records(file_info).map(|(_, algo)| algo).collect();
+
+        if weak_algos.is_empty() {
+            return Ok(());
+        }
+
+        let msg = format!(
+            "Refusing to write records which use hash algorithms weaker than the minimum required by policy, '{}': {}.  \
+            Use --allow-weak-hash to override.",
+            self.min_hash_algo,
+            weak_algos.into_iter().collect::<Vec<&str>>().join(", ")
+        );
+        Err(DanoError::new(&msg).into())
+    }
+
+    // when testing, records which fall short of the policy are flagged, but still tested
+    pub fn flag_weak_records(&self, config: &Config, file_info: &[FileInfo]) -> DanoResult<()> {
+        if config.opt_silent {
+            return Ok(());
+        }
+
+        self.weak_records(file_info)
+            .try_for_each(|(file_info, hash_algo)| {
+                print_err_buf(&format!(
+                    "WARN: {:?}: Recorded hash algorithm '{}' is weaker than the minimum required by policy, '{}'.\n",
+                    file_info.path, hash_algo, self.min_hash_algo
+                ))
+            })
+    }
+
+    fn weak_records<'a>(
+        &'a self,
+        file_info: &'a [FileInfo],
+    ) -> impl Iterator<Item = (&'a FileInfo, &'a str)> {
+        file_info.iter().filter_map(|file_info| {
+            let hash_algo = file_info.metadata.as_ref()?.hash_algo.as_ref();
+            self.is_weak(hash_algo).then_some((file_info, hash_algo))
+        })
+    }
+}
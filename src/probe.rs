@@ -0,0 +1,185 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{path::Path, process::Command as ExecProcess};
+
+use serde_json::Value;
+use which::which;
+
+use crate::config::DecodeFormat;
+use crate::ffmpeg::AVERROR_STREAM_NOT_FOUND;
+use crate::{DanoError, DanoResult};
+
+pub struct FFProbe;
+
+// the sample format at which the first audio stream is decoded, without conversion
+pub enum AudioDepth {
+    Integer(u32),
+    Float(DecodeFormat),
+}
+
+impl FFProbe {
+    // returns ffprobe's JSON output for the requested entries, like "stream=codec_type"
+    pub fn show_entries(path: &Path, entries: &str) -> DanoResult<Value> {
+        let ffprobe_command = if let Ok(ffprobe_command) = which("ffprobe") {
+            ffprobe_command
+        } else {
+            return Err(DanoError::new(
+                "'ffprobe' command not found. Make sure the command 'ffprobe' is in your path.",
+            )
+            .into());
+        };
+
+        let process_output = ExecProcess::new(ffprobe_command)
+            .args(["-v", "error", "-show_entries", entries, "-of", "json"])
+            .arg(path)
+            .output()?;
+
+        if !process_output.status.success() {
+            let stderr = std::str::from_utf8(&process_output.stderr)?.trim();
+            return Err(DanoError::new(stderr).into());
+        }
+
+        serde_json::from_slice(&process_output.stdout).map_err(|err| err.into())
+    }
+
+    // the error code ffprobe reports when it opens the input, and selects the streams, as ffmpeg would,
+    // none when the input opens, and contains the selected streams
+    pub fn error_code(path: &Path, opt_stream_specifier: Option<&str>) -> DanoResult<Option<i64>> {
+        let ffprobe_command = if let Ok(ffprobe_command) = which("ffprobe") {
+            ffprobe_command
+        } else {
+            return Err(DanoError::new(
+                "'ffprobe' command not found. Make sure the command 'ffprobe' is in your path.",
+            )
+            .into());
+        };
+
+        let mut command = ExecProcess::new(ffprobe_command);
+        command.args([
+            "-v",
+            "quiet",
+            "-show_error",
+            "-show_entries",
+            "stream=index",
+            "-of",
+            "json",
+        ]);
+
+        // ffprobe selects streams of its only input, and an optional stream need not be marked so
+        if let Some(stream_specifier) = opt_stream_specifier {
+            let stream_specifier = stream_specifier
+                .strip_prefix("0:")
+                .unwrap_or(stream_specifier);
+            let stream_specifier = stream_specifier
+                .strip_suffix('?')
+                .unwrap_or(stream_specifier);
+
+            command.args(["-select_streams", stream_specifier]);
+        }
+
+        let process_output = command.arg(path).output()?;
+        let root: Value = serde_json::from_slice(&process_output.stdout)?;
+
+        if let Some(code) = root
+            .get("error")
+            .and_then(|error| error.get("code"))
+            .and_then(|code| code.as_i64())
+        {
+            return Ok(Some(code));
+        }
+
+        if Self::streams(&root).is_empty() {
+            return Ok(Some(AVERROR_STREAM_NOT_FOUND));
+        }
+
+        Ok(None)
+    }
+
+    pub fn streams(root: &Value) -> &[Value] {
+        root.get("streams")
+            .and_then(|streams| streams.as_array())
+            .map(|streams| streams.as_slice())
+            .unwrap_or_default()
+    }
+
+    // whether the file contains at least one stream of the codec type, like "audio"
+    pub fn has_stream(path: &Path, codec_type: &str) -> DanoResult<bool> {
+        let root = Self::show_entries(path, "stream=codec_type")?;
+
+        Ok(Self::streams(&root).iter().any(|stream| {
+            stream.get("codec_type").and_then(|value| value.as_str()) == Some(codec_type)
+        }))
+    }
+
+    // the bit depth of the first audio stream, rounded up to a depth ffmpeg's PCM encoders accept, 16, 24 or 32,
+    // or, for a floating point stream, its floating point format, none for lossy streams, which have no bit depth
+    pub fn audio_bit_depth(path: &Path) -> DanoResult<Option<AudioDepth>> {
+        let root = Self::show_entries(
+            path,
+            "stream=codec_type,sample_fmt,bits_per_raw_sample,bits_per_sample",
+        )?;
+
+        let as_u32 = |value: &Value| match value {
+            Value::Number(number) => number.as_u64().map(|number| number as u32),
+            Value::String(string) => string.parse::<u32>().ok(),
+            _ => None,
+        };
+
+        let Some(stream) = Self::streams(&root).iter().find(|stream| {
+            stream.get("codec_type").and_then(|value| value.as_str()) == Some("audio")
+        }) else {
+            return Ok(None);
+        };
+
+        // lossy codecs decode to floating point samples too, but have no bit depth to preserve
+        let opt_bits = ["bits_per_raw_sample", "bits_per_sample"]
+            .iter()
+            .filter_map(|key| stream.get(*key).and_then(as_u32))
+            .find(|bits| *bits > 0);
+
+        let Some(bits) = opt_bits else {
+            return Ok(None);
+        };
+
+        // a floating point stream must never be converted to integer samples
+        let depth = match stream.get("sample_fmt").and_then(|value| value.as_str()) {
+            Some("flt" | "fltp") => AudioDepth::Float(DecodeFormat::F32),
+            Some("dbl" | "dblp") => AudioDepth::Float(DecodeFormat::F64),
+            _ => AudioDepth::Integer(match bits {
+                0..=16 => 16,
+                17..=24 => 24,
+                _ => 32,
+            }),
+        };
+
+        Ok(Some(depth))
+    }
+
+    // media is anything ffmpeg can demux which contains at least one audio or video stream
+    pub fn is_media(path: &Path) -> bool {
+        match Self::show_entries(path, "stream=codec_type") {
+            Ok(root) => Self::streams(&root).iter().any(|stream| {
+                matches!(
+                    stream.get("codec_type").and_then(|value| value.as_str()),
+                    Some("audio") | Some("video")
+                )
+            }),
+            Err(_) => false,
+        }
+    }
+}
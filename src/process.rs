@@ -15,17 +15,29 @@
 // For the full copyright and license information, please view the LICENSE file
 // that was distributed with this source code.
 
-use std::{collections::BTreeMap, ops::Deref, path::PathBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Deref,
+    path::PathBuf,
+};
 
 use crossbeam_channel::Receiver;
 use itertools::Either;
 use rayon::prelude::*;
 
 use crate::ingest::RecordedFileInfo;
+use crate::live::LiveStatus;
 use crate::{Config, ExecMode};
 
+use crate::cue::TrackHash;
 use crate::lookup::{FileInfo, FileMetadata};
-use crate::utility::{print_file_info, print_out_buf, DanoResult};
+use crate::streamhash::StreamHashes;
+use crate::utility::{print_err_buf, print_file_info, print_outcome, write_non_file, DanoResult};
+use crate::{DANO_DISORDER_EXIT_CODE, DANO_FILE_INFO_VERSION};
+
+// per path test results, as recorded by the schedule
+const MISSING_EXIT_CODE: i32 = 2i32;
+const MISMATCH_EXIT_CODE: i32 = 3i32;
 
 #[derive(Debug, Clone)]
 pub enum RemainderBundle {
@@ -36,6 +48,7 @@ pub enum RemainderBundle {
 pub struct ProcessedFiles {
     pub new_files: RemainderBundle,
     pub modified_file_names: RemainderBundle,
+    pub test_results: BTreeMap<PathBuf, i32>,
     pub exit_code: i32,
 }
 
@@ -45,27 +58,55 @@ impl ProcessedFiles {
         recorded_file_info: RecordedFileInfo,
         rx_item: Receiver<FileInfo>,
     ) -> DanoResult<ProcessedFiles> {
-        // prepare for loop
+        Self::with_live_status(config, recorded_file_info, rx_item, None)
+    }
+
+    // each result is also reported to the live status file, when one is given
+    pub fn with_live_status(
+        config: &Config,
+        recorded_file_info: RecordedFileInfo,
+        rx_item: Receiver<FileInfo>,
+        opt_live_status: Option<LiveStatus>,
+    ) -> DanoResult<ProcessedFiles> {
         let file_map = FileMap::new(recorded_file_info.into_inner());
+
+        Self::with_file_map(config, &file_map, rx_item, opt_live_status)
+    }
+
+    // a run in batches shares a single map of the recorded file info, rather than build it again for each batch
+    pub fn with_file_map(
+        config: &Config,
+        file_map: &FileMap,
+        rx_item: Receiver<FileInfo>,
+        opt_live_status: Option<LiveStatus>,
+    ) -> DanoResult<ProcessedFiles> {
+        // prepare for loop
         let mut exit_code = 0;
         // L
         let mut modified_file_names = Vec::new();
         // R
         let mut new_files = Vec::new();
 
+        let mut test_results = BTreeMap::new();
+
         // loop while recv from channel
         while let Ok(file_info) = rx_item.recv() {
-            if let (Some(new_files_partitioned), test_exit_code) =
-                &file_map.verify(config, &file_info)?
-            {
+            let verified = file_map.verify(config, &file_info)?;
+            test_results.insert(file_info.path.clone(), verified.1);
+
+            if let Some(live_status) = &opt_live_status {
+                live_status.update(&file_info.path, verified.1 != 0);
+            }
+
+            if Self::is_failure(config, &verified) {
+                exit_code = DANO_DISORDER_EXIT_CODE;
+            }
+
+            if let (Some(new_files_partitioned), _) = &verified {
                 match new_files_partitioned {
                     Either::Left(_) => modified_file_names.push(file_info),
                     Either::Right(_) => new_files.push(file_info),
                 }
-
-                if test_exit_code != &0 {
-                    exit_code = *test_exit_code
-                }
             }
         }
 
@@ -76,17 +117,55 @@ impl ProcessedFiles {
         Ok(ProcessedFiles {
             new_files: RemainderBundle::NewFile(new_files),
             modified_file_names: RemainderBundle::ModifiedFilename(modified_file_names),
+            test_results,
             exit_code,
         })
     }
+
+    // paths which a quick test did not hash again, because their metadata is unchanged
+    pub fn insert_unchanged(
+        &mut self,
+        config: &Config,
+        unchanged: &BTreeSet<PathBuf>,
+    ) -> DanoResult<()> {
+        unchanged.iter().try_for_each(|path| {
+            self.test_results.insert(path.clone(), 0);
+
+            if config.opt_silent {
+                return Ok(());
+            }
+
+            print_outcome(
+                config,
+                path,
+                "ok",
+                None,
+                &format!("{:?}: OK, size and modify time are unchanged\n", path),
+            )
+        })
+    }
+
+    // whether a path's outcome fails the run, given the strictness policy
+    fn is_failure(config: &Config, verified: &(Option<Either<&FileInfo, &FileInfo>>, i32)) -> bool {
+        match verified {
+            (_, MISSING_EXIT_CODE) => config.strictness.fails_on_missing(),
+            (_, MISMATCH_EXIT_CODE) => true,
+            (Some(Either::Right(_)), _) => {
+                matches!(config.exec_mode, ExecMode::Test(_)) && config.strictness.fails_on_new()
+            }
+            _ => false,
+        }
+    }
 }
 
-struct FileMap {
-    inner: BTreeMap<PathBuf, Option<FileMetadata>>,
+// a path may be recorded in more than one form, such as both decoded and copied,
+// so each path maps to every recorded form of its metadata
+pub struct FileMap {
+    inner: BTreeMap<PathBuf, Vec<FileMetadata>>,
 }
 
 impl Deref for FileMap {
-    type Target = BTreeMap<PathBuf, Option<FileMetadata>>;
+    type Target = BTreeMap<PathBuf, Vec<FileMetadata>>;
 
     fn deref(&self) -> &Self::Target {
         &self.inner
@@ -95,10 +174,12 @@ impl Deref for FileMap {
 
 impl From<Vec<FileInfo>> for FileMap {
     fn from(value: Vec<FileInfo>) -> Self {
-        let recorded_file_info_map: BTreeMap<PathBuf, Option<FileMetadata>> = value
-            .into_iter()
-            .map(|file_info| (file_info.path, file_info.metadata))
-            .collect();
+        let mut recorded_file_info_map: BTreeMap<PathBuf, Vec<FileMetadata>> = BTreeMap::new();
+
+        value.into_iter().for_each(|file_info| {
+            let forms = recorded_file_info_map.entry(file_info.path).or_default();
+            forms.extend(file_info.metadata);
+        });
 
         Self {
             inner: recorded_file_info_map,
@@ -107,7 +188,7 @@ impl From<Vec<FileInfo>> for FileMap {
 }
 
 impl FileMap {
-    fn new(recorded_file_info: Vec<FileInfo>) -> Self {
+    pub fn new(recorded_file_info: Vec<FileInfo>) -> Self {
         recorded_file_info.into()
     }
 
@@ -125,23 +206,32 @@ impl FileMap {
             // always print, even in silent
             match config.exec_mode {
                 ExecMode::Test(_) => {
-                    print_out_buf(&format!(
-                        "WARN: {:?}: Path does not exist.\n",
-                        &file_info.path
-                    ))?;
+                    print_outcome(
+                        config,
+                        &file_info.path,
+                        "missing",
+                        None,
+                        &format!("WARN: {:?}: Path does not exist.\n", &file_info.path),
+                    )?;
                 }
                 ExecMode::Write(_) => {
                     print_file_info(config, file_info)?;
                 }
                 _ => unreachable!(),
             }
-            test_exit_code = 2;
+            test_exit_code = MISSING_EXIT_CODE;
             None
         } else if !is_same_filename && !is_same_hash {
             // always print, even in silent
             match config.exec_mode {
                 ExecMode::Test(_) => {
-                    print_out_buf(&format!("{:?}: Path is a new file.\n", file_info.path))?;
+                    print_outcome(
+                        config,
+                        &file_info.path,
+                        "new",
+                        file_info.metadata.as_ref(),
+                        &format!("{:?}: Path is a new file.\n", file_info.path),
+                    )?;
                 }
                 ExecMode::Write(_) => {
                     print_file_info(config, file_info)?;
@@ -150,10 +240,34 @@ impl FileMap {
             }
             Some(Either::Right(file_info))
         } else if is_same_filename && is_same_hash {
+            if let ExecMode::Test(test_mode_config) = &config.exec_mode {
+                if test_mode_config.opt_restore_xattrs {
+                    self.restore_xattr(config, file_info)?;
+                }
+            }
+
             if !config.opt_silent {
                 match config.exec_mode {
+                    ExecMode::Test(_) if self.is_tags_changed(file_info) => {
+                        print_outcome(
+                            config,
+                            &file_info.path,
+                            "tags_changed",
+                            file_info.metadata.as_ref(),
+                            &format!(
+                                "{:?}: OK, but tags have changed, the streams are intact\n",
+                                &file_info.path
+                            ),
+                        )?;
+                    }
                     ExecMode::Test(_) => {
-                        print_out_buf(&format!("{:?}: OK\n", &file_info.path))?;
+                        print_outcome(
+                            config,
+                            &file_info.path,
+                            "ok",
+                            file_info.metadata.as_ref(),
+                            &format!("{:?}: OK\n", &file_info.path),
+                        )?;
                     }
                     ExecMode::Write(_) => {
                         print_file_info(config, file_info)?;
@@ -166,20 +280,25 @@ impl FileMap {
             // always print, even in silent
             match &config.exec_mode {
                 ExecMode::Test(test_mode_config) => {
-                    if test_mode_config.opt_overwrite_old {
-                        print_out_buf(format!(
+                    let msg = if test_mode_config.opt_overwrite_old {
+                        format!(
                             "{:?}: OK, but path has same hash for new filename.  Old file info has been overwritten.\n",
                             file_info.path
-                        ).as_ref())?;
+                        )
                     } else {
-                        print_out_buf(
-                            format!(
-                                "{:?}: OK, but path has same hash for new filename.\n",
-                                file_info.path
-                            )
-                            .as_ref(),
-                        )?;
-                    }
+                        format!(
+                            "{:?}: OK, but path has same hash for new filename.\n",
+                            file_info.path
+                        )
+                    };
+
+                    print_outcome(
+                        config,
+                        &file_info.path,
+                        "renamed",
+                        file_info.metadata.as_ref(),
+                        &msg,
+                    )?;
                 }
                 ExecMode::Write(_) => {
                     print_file_info(config, file_info)?;
@@ -191,17 +310,29 @@ impl FileMap {
             // always print, even in silent
             match config.exec_mode {
                 ExecMode::Test(_) => {
-                    print_out_buf(&format!(
+                    let mut msg = format!(
                         "WARN: {:?}: Path has new hash for same filename.\n",
                         file_info.path
-                    ))?;
+                    );
+
+                    self.describe_mismatch(file_info).iter().for_each(|detail| {
+                        msg += &format!("WARN: {:?}: {}\n", file_info.path, detail)
+                    });
+
+                    print_outcome(
+                        config,
+                        &file_info.path,
+                        "mismatch",
+                        file_info.metadata.as_ref(),
+                        &msg,
+                    )?;
                 }
                 ExecMode::Write(_) => {
                     print_file_info(config, file_info)?;
                 }
                 _ => unreachable!(),
             }
-            test_exit_code = 3;
+            test_exit_code = MISMATCH_EXIT_CODE;
             None
         } else {
             unreachable!()
@@ -210,15 +341,128 @@ impl FileMap {
         Ok((opt_file_info, test_exit_code))
     }
 
+    // the file has been verified, so if its xattr is missing or stale, rewrite the xattr from the record
+    fn restore_xattr(&self, config: &Config, file_info: &FileInfo) -> DanoResult<()> {
+        let Some(recorded_metadata) = self.get_recorded_form(file_info) else {
+            return Ok(());
+        };
+
+        let is_current = matches!(
+            RecordedFileInfo::read_file_info_from_xattr(&file_info.path),
+            Some(FileInfo { metadata: Some(xattr_metadata), .. })
+                if xattr_metadata.hash_value == recorded_metadata.hash_value
+        );
+
+        if is_current {
+            return Ok(());
+        }
+
+        if config.opt_dry_run {
+            return print_err_buf(&format!(
+                "WARN: Not restoring dano extended attribute for (because dry run was specified): {:?}\n",
+                file_info.path
+            ));
+        }
+
+        let verified = FileInfo {
+            version: DANO_FILE_INFO_VERSION,
+            path: file_info.path.to_owned(),
+            metadata: Some(recorded_metadata.to_owned()),
+        };
+
+        write_non_file(&verified)?;
+
+        print_err_buf(&format!(
+            "Restoring dano extended attribute for: {:?}\n",
+            file_info.path
+        ))
+    }
+
+    // when both the record and the new file info were frame, stream, or track hashed, where they differ
+    fn describe_mismatch(&self, file_info: &FileInfo) -> Vec<String> {
+        let (Some(current), Some(recorded)) = (
+            file_info.metadata.as_ref(),
+            self.get_recorded_form(file_info),
+        ) else {
+            return Vec::new();
+        };
+
+        let frames = match (&current.opt_frame_digests, &recorded.opt_frame_digests) {
+            (Some(current), Some(recorded)) => current.describe_mismatch(recorded),
+            _ => Vec::new(),
+        };
+
+        let streams = match (&current.opt_stream_hashes, &recorded.opt_stream_hashes) {
+            (Some(current), Some(recorded)) => StreamHashes::describe_mismatch(current, recorded),
+            _ => Vec::new(),
+        };
+
+        let tracks = match (&current.opt_track_hashes, &recorded.opt_track_hashes) {
+            (Some(current), Some(recorded)) => TrackHash::describe_mismatch(current, recorded),
+            _ => Vec::new(),
+        };
+
+        frames
+            .into_iter()
+            .map(|range| format!("Frames differ: {}", range))
+            .chain(
+                streams
+                    .into_iter()
+                    .map(|stream| format!("Stream differs: {}", stream)),
+            )
+            .chain(
+                tracks
+                    .into_iter()
+                    .map(|track| format!("Track differs: {}", track)),
+            )
+            .collect()
+    }
+
+    // when both the record and the new file info hold a tags hash, whether the tags differ
+    fn is_tags_changed(&self, file_info: &FileInfo) -> bool {
+        let (Some(current), Some(recorded)) = (
+            file_info.metadata.as_ref(),
+            self.get_recorded_form(file_info),
+        ) else {
+            return false;
+        };
+
+        matches!(
+            (&current.opt_tags_hash, &recorded.opt_tags_hash),
+            (Some(current), Some(recorded)) if current != recorded
+        )
+    }
+
+    // the recorded form of a path is the one hashed the same way, decoded or copied, as the new file info
+    fn get_recorded_form(&self, file_info: &FileInfo) -> Option<&FileMetadata> {
+        let forms = self.get(&file_info.path)?;
+
+        match &file_info.metadata {
+            Some(metadata) => forms
+                .iter()
+                .find(|form| form.decoded == metadata.decoded)
+                .or_else(|| forms.first()),
+            None => forms.first(),
+        }
+    }
+
+    // a path recorded only in another form, such as copied, when this file info is decoded,
+    // is a new form of the path, and not a change of content
     fn is_same_filename(&self, file_info: &FileInfo) -> bool {
-        self.deref().contains_key(&file_info.path)
+        match (self.get(&file_info.path), &file_info.metadata) {
+            (Some(forms), Some(metadata)) if !forms.is_empty() => {
+                forms.iter().any(|form| form.decoded == metadata.decoded)
+            }
+            (Some(_), _) => true,
+            (None, _) => false,
+        }
     }
 
     fn is_same_hash(&self, file_info: &FileInfo) -> bool {
         match &file_info.metadata {
             Some(path_metadata) => {
                 // fast path
-                if let Some(Some(fast_path_metadata)) = self.get(&file_info.path) {
+                if let Some(fast_path_metadata) = self.get_recorded_form(file_info) {
                     if fast_path_metadata.hash_value == path_metadata.hash_value {
                         return true;
                     }
@@ -226,7 +470,7 @@ impl FileMap {
 
                 // slow path -- why? if we have hash match with a new path name
                 self.par_iter()
-                    .filter_map(|(_file_map_path, file_map_metadata)| file_map_metadata.as_ref())
+                    .flat_map(|(_file_map_path, file_map_forms)| file_map_forms)
                     .any(|file_map_metadata| {
                         path_metadata.hash_value == file_map_metadata.hash_value
                     })
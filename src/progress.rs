@@ -0,0 +1,177 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::requests::FileInfoRequest;
+use crate::Config;
+
+const BYTES_PER_MEGABYTE: f64 = 1_000_000.0;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+// erase the line, so a shorter line never leaves the tail of a longer line behind
+const CLEAR_LINE: &str = "\r\x1b[2K";
+
+// whether a status line is drawn, and must be erased before other output is printed
+static IS_LINE_DRAWN: Mutex<bool> = Mutex::new(false);
+
+// erases any status line, and holds it erased, until the returned guard is dropped,
+// so other output is never printed onto the end of a status line
+pub fn clear_progress_line() -> Option<MutexGuard<'static, bool>> {
+    let mut is_line_drawn = IS_LINE_DRAWN.lock().ok()?;
+
+    if *is_line_drawn {
+        eprint!("{}", CLEAR_LINE);
+        *is_line_drawn = false;
+    }
+
+    Some(is_line_drawn)
+}
+
+// a single status line, on stderr, redrawn as hashing threads complete each request group
+pub struct Progress {
+    total_files: usize,
+    total_bytes: u64,
+    files_done: AtomicUsize,
+    bytes_done: AtomicU64,
+    started: Instant,
+    last_printed: Mutex<Option<Instant>>,
+    current: Mutex<PathBuf>,
+}
+
+impl Progress {
+    pub fn new(config: &Config, request_groups: &[Vec<FileInfoRequest>]) -> Option<Self> {
+        if !config.opt_progress {
+            return None;
+        }
+
+        // the contents of a hardlink group are read once, and so are only counted once
+        let total_bytes = request_groups
+            .iter()
+            .filter_map(|group| group.first())
+            .filter_map(|request| request.path.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum();
+
+        Some(Self {
+            total_files: request_groups.iter().map(|group| group.len()).sum(),
+            total_bytes,
+            files_done: AtomicUsize::new(0),
+            bytes_done: AtomicU64::new(0),
+            started: Instant::now(),
+            last_printed: Mutex::new(None),
+            current: Mutex::new(PathBuf::new()),
+        })
+    }
+
+    pub fn start(&self, path: &Path) {
+        if let Ok(mut current) = self.current.lock() {
+            *current = path.to_owned();
+        }
+    }
+
+    pub fn finish(&self, group: &[FileInfoRequest]) {
+        let len = group
+            .first()
+            .and_then(|request| request.path.metadata().ok())
+            .map(|metadata| metadata.len())
+            .unwrap_or_default();
+
+        let files_done = self.files_done.fetch_add(group.len(), Ordering::Relaxed) + group.len();
+        let bytes_done = self.bytes_done.fetch_add(len, Ordering::Relaxed) + len;
+
+        // a redraw is skipped when another thread has redrawn recently, except for the last
+        let Ok(mut last_printed) = self.last_printed.lock() else {
+            return;
+        };
+
+        let now = Instant::now();
+
+        if files_done < self.total_files
+            && matches!(*last_printed, Some(last) if now.duration_since(last) < PROGRESS_INTERVAL)
+        {
+            return;
+        }
+
+        *last_printed = Some(now);
+
+        let current = self
+            .current
+            .lock()
+            .map(|current| current.clone())
+            .unwrap_or_default();
+
+        if let Some(mut is_line_drawn) = clear_progress_line() {
+            eprint!("{}", self.format_line(files_done, bytes_done, &current));
+            *is_line_drawn = true;
+        }
+    }
+
+    fn format_line(&self, files_done: usize, bytes_done: u64, current: &Path) -> String {
+        let elapsed = self.started.elapsed().as_secs_f64();
+
+        let rate = if elapsed > 0.0 {
+            bytes_done as f64 / elapsed
+        } else {
+            0.0
+        };
+
+        let eta = if rate > 0.0 && bytes_done < self.total_bytes {
+            Self::format_eta(Duration::from_secs_f64(
+                (self.total_bytes - bytes_done) as f64 / rate,
+            ))
+        } else {
+            "--:--".to_owned()
+        };
+
+        format!(
+            "Progress: {}/{} files ({:.0}%), {:.1} MB/s, ETA {}, current: {:?}",
+            files_done,
+            self.total_files,
+            100.0 * bytes_done as f64 / self.total_bytes.max(1) as f64,
+            rate / BYTES_PER_MEGABYTE,
+            eta,
+            current
+        )
+    }
+
+    fn format_eta(duration: Duration) -> String {
+        let secs = duration.as_secs();
+
+        match secs {
+            0..=3599 => format!("{}:{:02}", secs / 60, secs % 60),
+            _ => format!("{}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60),
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        // leave the last status line in place, and begin any further output on a new line
+        if let Ok(mut is_line_drawn) = IS_LINE_DRAWN.lock() {
+            if *is_line_drawn {
+                eprintln!();
+                *is_line_drawn = false;
+            }
+        }
+    }
+}
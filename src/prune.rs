@@ -0,0 +1,148 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::path::{Path, PathBuf};
+
+use crate::utility::{
+    check_free_space, deserialize, get_dano_xattr, get_tmp_file, hash_file_exists, invoked_from,
+    persist_hash_file, print_err_buf, read_hash_file, resolve_path,
+};
+use crate::{Config, DanoError, DanoResult};
+
+const HEADER_PREFIX: &str = "//";
+
+// unlike a compaction, a prune removes only the records of paths which no longer exist,
+// and leaves every other line of the hash file exactly as it was
+pub struct HashFilePrune;
+
+impl HashFilePrune {
+    pub fn exec(config: &Config) -> DanoResult<()> {
+        if !hash_file_exists(&config.hash_file) {
+            return Err(DanoError::new("No hash file exists to prune.").into());
+        }
+
+        let contents = read_hash_file(&config.hash_file)?;
+
+        let header: Vec<&str> = contents
+            .lines()
+            .take_while(|line| line.starts_with(HEADER_PREFIX))
+            .collect();
+
+        // recorded paths are relative to the directory from which dano was invoked, when written
+        let base_dir = invoked_from(&header).unwrap_or_else(|| config.pwd.clone());
+
+        let mut pruned: Vec<PathBuf> = Vec::new();
+        let mut buffer = String::new();
+
+        contents.lines().for_each(|line| {
+            // never discard a line we do not understand, such as a record of a newer version
+            match deserialize(line) {
+                Ok(file_info) if !resolve_path(&base_dir, &file_info.path).exists() => {
+                    pruned.push(file_info.path);
+                }
+                _ => {
+                    buffer.push_str(line);
+                    buffer.push('\n');
+                }
+            }
+        });
+
+        pruned.sort();
+        pruned.dedup();
+
+        let prefix = if config.opt_dry_run {
+            "Would prune records for missing path: "
+        } else {
+            "Pruning records for missing path: "
+        };
+
+        if !config.opt_silent {
+            pruned
+                .iter()
+                .try_for_each(|path| print_err_buf(&format!("{}{:?}\n", prefix, path)))?;
+        }
+
+        if !pruned.is_empty() && !config.opt_dry_run {
+            let tmp_file = get_tmp_file(config, &config.hash_file);
+            check_free_space(&tmp_file, buffer.len() as u64)?;
+
+            let res = std::fs::write(&tmp_file, &buffer)
+                .map_err(|err| err.into())
+                .and_then(|_| persist_hash_file(config, &tmp_file, &config.hash_file));
+
+            if res.is_err() {
+                let _ = std::fs::remove_file(&tmp_file);
+            }
+
+            res?;
+        }
+
+        if config.opt_silent {
+            return Ok(());
+        }
+
+        let verb = if config.opt_dry_run {
+            "Would prune"
+        } else {
+            "Pruned"
+        };
+
+        print_err_buf(&format!(
+            "{} the records of {} missing paths from the hash file.\n",
+            verb,
+            pruned.len()
+        ))?;
+
+        Self::print_moved_xattrs(config)
+    }
+
+    // an extended attribute lives with its file, and so is never a dead entry, but its recorded path
+    // may be stale, when the file has been moved or renamed, and such records are reported, not removed
+    fn print_moved_xattrs(config: &Config) -> DanoResult<()> {
+        let moved: Vec<&Path> = config
+            .paths
+            .iter()
+            .filter(|path| match Self::xattr_recorded_path(path) {
+                Some(recorded_path) => !config.pwd.join(recorded_path).exists(),
+                None => false,
+            })
+            .map(|path| path.as_path())
+            .collect();
+
+        if moved.is_empty() {
+            return Ok(());
+        }
+
+        moved.iter().try_for_each(|path| {
+            print_err_buf(&format!(
+                "WARN: {:?}: Extended attribute records a path which no longer exists.  The file has been moved or renamed.\n",
+                path
+            ))
+        })?;
+
+        print_err_buf(&format!(
+            "Kept {} extended attributes which record a moved or renamed path.\n",
+            moved.len()
+        ))
+    }
+
+    fn xattr_recorded_path(path: &Path) -> Option<PathBuf> {
+        let bytes = get_dano_xattr(path).ok()??;
+        let line = std::str::from_utf8(&bytes).ok()?;
+
+        deserialize(line).ok().map(|file_info| file_info.path)
+    }
+}
@@ -0,0 +1,328 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    borrow::Cow,
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+    process::Command as ExecProcess,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use which::which;
+
+use crate::config::ReceiptConfig;
+use crate::hasher::NativeHasher;
+use crate::process::{ProcessedFiles, RemainderBundle};
+use crate::schedule::VerifyOutcome;
+use crate::signature::SigningTool;
+use crate::utility::{
+    canonicalize, format_time, is_streamed_hash_file, print_err_buf, read_streamed_hash_file,
+    shell_quote_command,
+};
+use crate::{Config, DanoError, DanoResult, DANO_CLEAN_EXIT_CODE};
+
+const RECEIPT_VERSION: usize = 1;
+const SIGNATURE_EXTENSION: &str = "asc";
+const TIMESTAMP_QUERY_EXTENSION: &str = "tsq";
+const TIMESTAMP_REPLY_EXTENSION: &str = "tsr";
+
+impl ReceiptConfig {
+    pub fn check_commands(&self) -> DanoResult<()> {
+        let mut required = vec!["gpg"];
+
+        if self.opt_tsa.is_some() {
+            required.extend(["openssl", "curl"]);
+        }
+
+        match required.into_iter().find(|cmd| which(cmd).is_err()) {
+            Some(cmd) => {
+                let msg = format!(
+                    "'{}' command not found.  Make sure the command '{}' is in your path.",
+                    cmd, cmd
+                );
+                Err(DanoError::new(&msg).into())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
+struct ReceiptSummary {
+    tested: usize,
+    ok: usize,
+    new: usize,
+    renamed: usize,
+    missing: usize,
+    mismatch: usize,
+    error: usize,
+}
+
+#[derive(Serialize)]
+struct ReceiptCatalog {
+    path: PathBuf,
+    sha256: String,
+}
+
+// the receipt itself is plain JSON, and so may be read without dano, the signature,
+// and the optional timestamp, are over the exact bytes of the receipt file
+#[derive(Serialize)]
+struct Receipt {
+    receipt_version: usize,
+    dano_version: &'static str,
+    verified_at: String,
+    verified_at_secs: u64,
+    invoked_from: PathBuf,
+    // none only when no hash file was used, as when only xattrs were verified
+    catalog: Option<ReceiptCatalog>,
+    summary: ReceiptSummary,
+    exit_code: i32,
+}
+
+// a receipt of a verification run, the summary is taken before the processed files are written out,
+// but the catalog checksum is taken after, because the catalog may be updated during the run
+pub struct VerificationReceipt {
+    verified_at: SystemTime,
+    summary: ReceiptSummary,
+}
+
+impl VerificationReceipt {
+    pub fn new(processed_files: &ProcessedFiles) -> Self {
+        let mut summary = ReceiptSummary {
+            tested: processed_files.test_results.len(),
+            ..Default::default()
+        };
+
+        [
+            &processed_files.new_files,
+            &processed_files.modified_file_names,
+        ]
+        .into_iter()
+        .for_each(|remainder_bundle| match remainder_bundle {
+            RemainderBundle::NewFile(files) => summary.new += files.len(),
+            RemainderBundle::ModifiedFilename(files) => summary.renamed += files.len(),
+        });
+
+        processed_files.test_results.values().for_each(|exit_code| {
+            match VerifyOutcome::from_exit_code(*exit_code) {
+                _ if *exit_code == DANO_CLEAN_EXIT_CODE => summary.ok += 1,
+                VerifyOutcome::Missing => summary.missing += 1,
+                VerifyOutcome::Mismatch => summary.mismatch += 1,
+                VerifyOutcome::Ok | VerifyOutcome::Error => summary.error += 1,
+            }
+        });
+
+        // new and renamed files verified without error, but are not otherwise OK
+        summary.ok = summary.ok.saturating_sub(summary.new + summary.renamed);
+
+        Self {
+            verified_at: SystemTime::now(),
+            summary,
+        }
+    }
+
+    pub fn exec(self, config: &Config, exit_code: i32) -> DanoResult<()> {
+        let Some(receipt_config) = &config.opt_receipt else {
+            return Ok(());
+        };
+
+        if config.opt_dry_run {
+            return print_err_buf(&format!(
+                "WARN: Not writing receipt to (because dry run was specified): {:?}\n",
+                receipt_config.path
+            ));
+        }
+
+        let receipt = Receipt {
+            receipt_version: RECEIPT_VERSION,
+            dano_version: env!("CARGO_PKG_VERSION"),
+            verified_at: format!("{} UTC", format_time(self.verified_at)),
+            verified_at_secs: self
+                .verified_at
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            invoked_from: config.pwd.clone(),
+            catalog: Self::catalog(config)?,
+            summary: self.summary,
+            exit_code,
+        };
+
+        std::fs::write(
+            &receipt_config.path,
+            serde_json::to_string_pretty(&receipt)? + "\n",
+        )?;
+
+        // an unsigned receipt proves nothing, so don't leave one behind
+        let signature = Self::sign(config, receipt_config).inspect_err(|_| {
+            let _ = std::fs::remove_file(&receipt_config.path);
+        })?;
+
+        if !config.opt_silent {
+            print_err_buf(&format!(
+                "Wrote receipt to: {:?}, signed in: {:?}\n",
+                receipt_config.path, signature
+            ))?;
+        }
+
+        if let Some(tsa) = &receipt_config.opt_tsa {
+            let timestamp = Self::timestamp(config, &receipt_config.path, tsa)?;
+
+            if !config.opt_silent {
+                print_err_buf(&format!("Wrote receipt timestamp to: {:?}\n", timestamp))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // the checksum is of the very bytes of the hash file, as read from stdin, or from a remote URL, or as left on disk
+    fn catalog(config: &Config) -> DanoResult<Option<ReceiptCatalog>> {
+        let hash_file = &config.hash_file;
+
+        let bytes: Cow<[u8]> = if is_streamed_hash_file(hash_file) {
+            Cow::Borrowed(read_streamed_hash_file(hash_file)?)
+        } else if hash_file.exists() || !config.opt_xattr {
+            let bytes = std::fs::read(hash_file).map_err(|err| {
+                let msg = format!(
+                    "Could not read the hash file, to checksum it for the receipt: {:?}: {}",
+                    hash_file, err
+                );
+                DanoError::new(&msg)
+            })?;
+
+            Cow::Owned(bytes)
+        } else {
+            // a verification of xattrs only has no catalog to checksum
+            return Ok(None);
+        };
+
+        let mut hasher = NativeHasher::new("sha256")?;
+        hasher.update(&bytes);
+
+        Ok(Some(ReceiptCatalog {
+            path: canonicalize(hash_file).unwrap_or_else(|_| hash_file.clone()),
+            sha256: hasher.finalize_hex(),
+        }))
+    }
+
+    fn with_extension(path: &Path, extension: &str) -> PathBuf {
+        let mut file_name: OsString = path.as_os_str().to_owned();
+        file_name.push(".");
+        file_name.push(extension);
+        PathBuf::from(file_name)
+    }
+
+    fn run(config: &Config, command: &Path, args: &[&OsStr]) -> DanoResult<()> {
+        if config.opt_show_commands {
+            print_err_buf(&format!(
+                "Command: {}\n",
+                shell_quote_command(command, args)
+            ))?;
+        }
+
+        let process_output = ExecProcess::new(command).args(args).output()?;
+
+        if !process_output.status.success() {
+            let msg = format!(
+                "{:?} failed: {}",
+                command,
+                String::from_utf8_lossy(&process_output.stderr).trim()
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        Ok(())
+    }
+
+    fn sign(config: &Config, receipt_config: &ReceiptConfig) -> DanoResult<PathBuf> {
+        let signature = Self::with_extension(&receipt_config.path, SIGNATURE_EXTENSION);
+
+        let mut args: Vec<&OsStr> = vec![
+            OsStr::new("--batch"),
+            OsStr::new("--yes"),
+            OsStr::new("--armor"),
+            OsStr::new("--detach-sign"),
+        ];
+
+        if let Some(key) = &receipt_config.opt_key {
+            args.extend([OsStr::new("--local-user"), OsStr::new(key)]);
+        }
+
+        args.extend([
+            OsStr::new("--output"),
+            signature.as_os_str(),
+            receipt_config.path.as_os_str(),
+        ]);
+
+        Self::run(config, &config.signing_command(SigningTool::Gpg)?, &args)?;
+
+        Ok(signature)
+    }
+
+    // an RFC 3161 timestamp query is created with openssl, and sent to the timestamp authority with curl
+    fn timestamp(config: &Config, receipt: &Path, tsa: &str) -> DanoResult<PathBuf> {
+        let query = Self::with_extension(receipt, TIMESTAMP_QUERY_EXTENSION);
+        let reply = Self::with_extension(receipt, TIMESTAMP_REPLY_EXTENSION);
+
+        let openssl_command = which("openssl").map_err(|_| {
+            DanoError::new(
+                "'openssl' command not found.  Make sure the command 'openssl' is in your path.",
+            )
+        })?;
+
+        Self::run(
+            config,
+            &openssl_command,
+            &[
+                OsStr::new("ts"),
+                OsStr::new("-query"),
+                OsStr::new("-data"),
+                receipt.as_os_str(),
+                OsStr::new("-sha256"),
+                OsStr::new("-cert"),
+                OsStr::new("-out"),
+                query.as_os_str(),
+            ],
+        )?;
+
+        let mut data = OsString::from("@");
+        data.push(&query);
+
+        let res = Self::run(
+            config,
+            &config.curl_command()?,
+            &[
+                OsStr::new("--fail"),
+                OsStr::new("--silent"),
+                OsStr::new("--show-error"),
+                OsStr::new("--header"),
+                OsStr::new("Content-Type: application/timestamp-query"),
+                OsStr::new("--data-binary"),
+                data.as_os_str(),
+                OsStr::new("--output"),
+                reply.as_os_str(),
+                OsStr::new(tsa),
+            ],
+        );
+
+        let _ = std::fs::remove_file(&query);
+
+        res.map(|_| reply)
+    }
+}
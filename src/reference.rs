@@ -0,0 +1,200 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use crossbeam_channel::Receiver;
+
+use crate::lookup::{FileInfo, FileMetadata};
+use crate::utility::{print_err_buf, print_outcome, read_file_info_from_path};
+use crate::{Config, DanoError, DanoResult, DANO_CLEAN_EXIT_CODE, DANO_DISORDER_EXIT_CODE};
+
+// a reference catalog is a hash file produced elsewhere, like by a publisher, or on a source NAS,
+// which is only ever read, and never written, by dano
+pub struct ReferenceCatalog {
+    inner: Vec<FileInfo>,
+}
+
+impl ReferenceCatalog {
+    pub fn new(config: &Config, reference: &Path) -> DanoResult<Self> {
+        if !reference.exists() {
+            let msg = format!("Reference hash file does not exist: {:?}", reference);
+            return Err(DanoError::new(&msg).into());
+        }
+
+        let input_paths: BTreeSet<&Path> = config.paths.iter().map(|path| path.as_path()).collect();
+
+        // when input files are given, only those entries of the reference are verified
+        let inner: Vec<FileInfo> = read_file_info_from_path(reference)?
+            .into_iter()
+            .filter(|file_info| file_info.metadata.is_some())
+            .filter(|file_info| {
+                input_paths.is_empty() || input_paths.contains(file_info.path.as_path())
+            })
+            .collect();
+
+        if inner.is_empty() {
+            return Err(DanoError::new(
+                "No valid hashes in the reference hash file to test.  Quitting.",
+            )
+            .into());
+        }
+
+        Ok(Self { inner })
+    }
+
+    pub fn as_slice(&self) -> &[FileInfo] {
+        &self.inner
+    }
+
+    fn get_form<'a>(
+        file_info: &[&'a FileInfo],
+        metadata: &FileMetadata,
+    ) -> Option<&'a FileMetadata> {
+        file_info
+            .iter()
+            .filter_map(|file_info| file_info.metadata.as_ref())
+            .find(|form| form.decoded == metadata.decoded)
+    }
+
+    // compare each current file to the reference, and, if the two disagree, use the local record
+    // to tell which side has changed
+    pub fn verify(
+        &self,
+        config: &Config,
+        local_file_info: &[FileInfo],
+        rx_item: Receiver<FileInfo>,
+    ) -> DanoResult<i32> {
+        let mut reference_map: BTreeMap<&Path, Vec<&FileInfo>> = BTreeMap::new();
+        self.inner.iter().for_each(|file_info| {
+            reference_map
+                .entry(file_info.path.as_path())
+                .or_default()
+                .push(file_info)
+        });
+
+        let mut local_map: BTreeMap<&Path, Vec<&FileInfo>> = BTreeMap::new();
+        local_file_info.iter().for_each(|file_info| {
+            local_map
+                .entry(file_info.path.as_path())
+                .or_default()
+                .push(file_info)
+        });
+
+        let mut exit_code = DANO_CLEAN_EXIT_CODE;
+        let mut received: BTreeSet<PathBuf> = BTreeSet::new();
+
+        while let Ok(file_info) = rx_item.recv() {
+            received.insert(file_info.path.clone());
+
+            let Some(current) = &file_info.metadata else {
+                print_outcome(
+                    config,
+                    &file_info.path,
+                    "missing",
+                    None,
+                    &format!(
+                        "WARN: {:?}: Path in reference does not exist.\n",
+                        file_info.path
+                    ),
+                )?;
+                exit_code = DANO_DISORDER_EXIT_CODE;
+                continue;
+            };
+
+            let opt_reference = reference_map
+                .get(file_info.path.as_path())
+                .and_then(|forms| Self::get_form(forms, current));
+            let opt_local = local_map
+                .get(file_info.path.as_path())
+                .and_then(|forms| Self::get_form(forms, current));
+
+            let Some(reference) = opt_reference else {
+                print_outcome(
+                    config,
+                    &file_info.path,
+                    "unreferenced",
+                    Some(current),
+                    &format!(
+                        "WARN: {:?}: Path is not in the reference.\n",
+                        file_info.path
+                    ),
+                )?;
+                continue;
+            };
+
+            let status = if reference.hash_value == current.hash_value {
+                "ok"
+            } else {
+                "mismatch"
+            };
+
+            let msg = if reference.hash_value == current.hash_value {
+                match opt_local {
+                    Some(local) if local.hash_value != reference.hash_value => {
+                        format!(
+                            "{:?}: OK, but the local record disagrees with the reference.\n",
+                            file_info.path
+                        )
+                    }
+                    _ if config.opt_silent => continue,
+                    _ => format!("{:?}: OK\n", file_info.path),
+                }
+            } else {
+                exit_code = DANO_DISORDER_EXIT_CODE;
+
+                match opt_local {
+                    Some(local) if local.hash_value == reference.hash_value => format!(
+                        "WARN: {:?}: Hash differs from reference.  The local record agrees with the reference, so the file has changed.\n",
+                        file_info.path
+                    ),
+                    Some(local) if local.hash_value == current.hash_value => format!(
+                        "WARN: {:?}: Hash differs from reference.  The local record agrees with the file, so the reference disagrees.\n",
+                        file_info.path
+                    ),
+                    Some(_) => format!(
+                        "WARN: {:?}: Hash differs from both the reference and the local record.\n",
+                        file_info.path
+                    ),
+                    None => format!(
+                        "WARN: {:?}: Hash differs from reference.  No local record exists.\n",
+                        file_info.path
+                    ),
+                }
+            };
+
+            print_outcome(config, &file_info.path, status, Some(current), &msg)?;
+        }
+
+        // paths which could not be hashed have already had their errors printed
+        reference_map
+            .keys()
+            .filter(|path| !received.contains(**path))
+            .try_for_each(|path| {
+                exit_code = DANO_DISORDER_EXIT_CODE;
+                print_err_buf(&format!(
+                    "WARN: {:?}: Path in reference could not be verified.\n",
+                    path
+                ))
+            })?;
+
+        Ok(exit_code)
+    }
+}
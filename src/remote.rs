@@ -0,0 +1,267 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    borrow::Cow,
+    ffi::OsStr,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command as ExecProcess, Stdio},
+    sync::OnceLock,
+};
+
+use itertools::Itertools;
+
+use crate::utility::{print_err_buf, shell_quote_command};
+use crate::{DanoError, DanoResult};
+
+const HTTP_SCHEMES: [&str; 2] = ["http://", "https://"];
+const S3_SCHEME: &str = "s3://";
+// the timeout, in seconds, to connect to the server, as a large hash file may take some time to transfer
+const REMOTE_CONNECT_TIMEOUT: &str = "30";
+const S3_DEFAULT_REGION: &str = "us-east-1";
+
+// the hash file fetched, with the URL from which it was fetched
+static REMOTE_HASH_FILE: OnceLock<(PathBuf, Vec<u8>)> = OnceLock::new();
+
+// a remote hash file is fetched once, with curl, and thereafter read from memory, and so it is only ever read,
+// and an s3:// hash file is fetched with the credentials of the environment, signed by curl
+pub struct RemoteHashFile;
+
+impl RemoteHashFile {
+    pub fn is_remote(path: &Path) -> bool {
+        path.to_str().is_some_and(|url| {
+            HTTP_SCHEMES
+                .iter()
+                .chain(std::iter::once(&S3_SCHEME))
+                .any(|scheme| url.starts_with(scheme))
+        })
+    }
+
+    pub fn read(path: &Path) -> DanoResult<&'static [u8]> {
+        match REMOTE_HASH_FILE.get() {
+            Some((url, bytes)) if url == path => Ok(bytes),
+            _ => {
+                let msg = format!(
+                    "Only the hash file may be read from a remote URL, and it has not been fetched: {:?}",
+                    path
+                );
+                Err(DanoError::new(&msg).into())
+            }
+        }
+    }
+
+    pub fn fetch(curl_command: &Path, path: &Path, opt_show_commands: bool) -> DanoResult<()> {
+        let url = path.to_string_lossy();
+
+        let (http_url, opt_curl_config) = match url.strip_prefix(S3_SCHEME) {
+            Some(bucket_and_key) => Self::s3_request(bucket_and_key)?,
+            None => (url.to_string(), None),
+        };
+
+        let mut process_args: Vec<&OsStr> = [
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--location",
+            "--netrc-optional",
+            "--connect-timeout",
+            REMOTE_CONNECT_TIMEOUT,
+        ]
+        .into_iter()
+        .map(OsStr::new)
+        .collect();
+
+        // credentials are given to curl on stdin, rather than as arguments, which any user may see
+        if opt_curl_config.is_some() {
+            process_args.extend([OsStr::new("--config"), OsStr::new("-")]);
+        }
+
+        process_args.push(OsStr::new(&http_url));
+
+        if opt_show_commands {
+            print_err_buf(&format!(
+                "Command: {}\n",
+                shell_quote_command(curl_command, &process_args)
+            ))?;
+        }
+
+        let mut child = ExecProcess::new(curl_command)
+            .args(&process_args)
+            .stdin(if opt_curl_config.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        if let (Some(curl_config), Some(mut stdin)) = (opt_curl_config, child.stdin.take()) {
+            stdin.write_all(curl_config.as_bytes())?;
+        }
+
+        let process_output = child.wait_with_output()?;
+
+        if !process_output.status.success() {
+            let msg = format!(
+                "Could not fetch the hash file from {:?}: {}",
+                url,
+                String::from_utf8_lossy(&process_output.stderr).trim()
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        let _ = REMOTE_HASH_FILE.set((path.to_owned(), process_output.stdout));
+
+        Ok(())
+    }
+
+    // an s3:// URL is fetched from AWS, or, given AWS_ENDPOINT_URL, from another S3 compatible service,
+    // and the request is signed with AWS Signature Version 4
+    fn s3_request(bucket_and_key: &str) -> DanoResult<(String, Option<String>)> {
+        let Some((bucket, key)) = bucket_and_key.split_once('/') else {
+            let msg = format!(
+                "An s3:// hash file must name both a bucket and a key: \"{}{}\"",
+                S3_SCHEME, bucket_and_key
+            );
+            return Err(DanoError::new(&msg).into());
+        };
+
+        let env_var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        let (Some(access_key_id), Some(secret_access_key)) = (
+            env_var("AWS_ACCESS_KEY_ID"),
+            env_var("AWS_SECRET_ACCESS_KEY"),
+        ) else {
+            return Err(DanoError::new(
+                "An s3:// hash file requires the AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY environment variables.",
+            )
+            .into());
+        };
+
+        let region = env_var("AWS_REGION")
+            .or_else(|| env_var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| S3_DEFAULT_REGION.to_owned());
+
+        let http_url = match env_var("AWS_ENDPOINT_URL") {
+            Some(endpoint) => format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, key),
+            None => format!("https://{}.s3.{}.amazonaws.com/{}", bucket, region, key),
+        };
+
+        let quote = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+
+        let mut curl_config = format!(
+            "aws-sigv4 = \"aws:amz:{}:s3\"\nuser = \"{}:{}\"\n",
+            quote(&region),
+            quote(&access_key_id),
+            quote(&secret_access_key)
+        );
+
+        if let Some(session_token) = env_var("AWS_SESSION_TOKEN") {
+            curl_config.push_str(&format!(
+                "header = \"x-amz-security-token: {}\"\n",
+                quote(&session_token)
+            ));
+        }
+
+        Ok((http_url, Some(curl_config)))
+    }
+}
+
+// media given as a URL is read by ffmpeg, over any protocol it supports, rather than from the local filesystem,
+// and, given a map, is recorded by the local path which mirrors it
+#[derive(Debug, Clone, Default)]
+pub struct RemoteMedia {
+    opt_map: Option<(String, PathBuf)>,
+}
+
+impl RemoteMedia {
+    pub fn new(opt_map: Option<&str>) -> DanoResult<Self> {
+        let Some(map) = opt_map else {
+            return Ok(Self::default());
+        };
+
+        match map.split_once('=') {
+            Some((url_prefix, local_dir))
+                if Self::is_url(Path::new(url_prefix)) && !local_dir.is_empty() =>
+            {
+                Ok(Self {
+                    opt_map: Some((
+                        url_prefix.trim_end_matches('/').to_owned(),
+                        PathBuf::from(local_dir),
+                    )),
+                })
+            }
+            _ => {
+                let msg = format!(
+                    "A remote map must be a URL prefix and a local directory, such as 'sftp://nas/media=/srv/media': {:?}",
+                    map
+                );
+                Err(DanoError::new(&msg).into())
+            }
+        }
+    }
+
+    pub fn is_url(path: &Path) -> bool {
+        path.to_str()
+            .and_then(|url| url.split_once("://"))
+            .is_some_and(|(scheme, _)| {
+                !scheme.is_empty()
+                    && scheme
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+            })
+    }
+
+    // a URL beneath the URL prefix is recorded by the local path which mirrors it
+    pub fn local_path(&self, path: &Path) -> PathBuf {
+        let opt_local_path = self.opt_map.as_ref().and_then(|(url_prefix, local_dir)| {
+            let rest = path.to_str()?.strip_prefix(url_prefix.as_str())?;
+
+            if !rest.is_empty() && !rest.starts_with('/') {
+                return None;
+            }
+
+            Some(local_dir.join(rest.trim_start_matches('/')))
+        });
+
+        opt_local_path.unwrap_or_else(|| path.to_owned())
+    }
+
+    // and a local path beneath the local directory is read from the URL which it mirrors
+    pub fn media_input<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        let opt_url = self.opt_map.as_ref().and_then(|(url_prefix, local_dir)| {
+            let rest = path.strip_prefix(local_dir).ok()?;
+
+            let components: Vec<&str> = rest
+                .components()
+                .map(|component| component.as_os_str().to_str())
+                .collect::<Option<_>>()?;
+
+            Some(PathBuf::from(
+                std::iter::once(url_prefix.as_str())
+                    .chain(components)
+                    .join("/"),
+            ))
+        });
+
+        match opt_url {
+            Some(url) => Cow::Owned(url),
+            None => Cow::Borrowed(path),
+        }
+    }
+}
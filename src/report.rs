@@ -0,0 +1,340 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    collections::BTreeMap,
+    fmt::Write as FmtWrite,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use itertools::Itertools;
+
+use crate::lookup::FileInfo;
+use crate::process::{ProcessedFiles, RemainderBundle};
+use crate::schedule::VerifyOutcome;
+use crate::utility::{format_time, print_err_buf};
+use crate::{Config, DanoResult, DANO_CLEAN_EXIT_CODE};
+
+const REPORT_FILE_NAME: &str = "index.html";
+
+// clicking a table header sorts that table by the column, clicking again reverses the order
+const SORT_SCRIPT: &str = r#"
+document.querySelectorAll("table.sortable th").forEach(function (th, column) {
+  th.addEventListener("click", function () {
+    var table = th.closest("table");
+    var tbody = table.tBodies[0];
+    var ascending = th.dataset.order !== "asc";
+    th.dataset.order = ascending ? "asc" : "desc";
+    Array.from(tbody.rows)
+      .sort(function (a, b) {
+        var x = a.cells[column].textContent, y = b.cells[column].textContent;
+        var cmp = x.localeCompare(y, undefined, { numeric: true });
+        return ascending ? cmp : -cmp;
+      })
+      .forEach(function (row) { tbody.appendChild(row); });
+  });
+});
+"#;
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; width: 100%; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; font-size: 0.9em; }
+th { background: #eee; cursor: pointer; user-select: none; }
+td.hash { font-family: monospace; }
+tr.failed td { background: #fdd; }
+"#;
+
+struct ReportEntry {
+    path: PathBuf,
+    file_info: Option<FileInfo>,
+    outcome: &'static str,
+}
+
+// a self-contained html page, with no external resources, which summarizes a run
+pub struct HtmlReport {
+    generated: SystemTime,
+    entries: Vec<ReportEntry>,
+}
+
+impl HtmlReport {
+    fn new(recorded_file_info: &[FileInfo], processed_files: &ProcessedFiles) -> Self {
+        let mut file_info_map: BTreeMap<PathBuf, FileInfo> = recorded_file_info
+            .iter()
+            .map(|file_info| (file_info.path.clone(), file_info.clone()))
+            .collect();
+
+        // newly written, or rewritten file info supersedes the recorded file info
+        let mut outcomes: BTreeMap<&Path, &'static str> = BTreeMap::new();
+
+        [
+            &processed_files.new_files,
+            &processed_files.modified_file_names,
+        ]
+        .into_iter()
+        .for_each(|remainder_bundle| {
+            let (files, outcome) = match remainder_bundle {
+                RemainderBundle::NewFile(files) => (files, "New"),
+                RemainderBundle::ModifiedFilename(files) => (files, "Renamed"),
+            };
+
+            files.iter().for_each(|file_info| {
+                file_info_map.insert(file_info.path.clone(), file_info.clone());
+                outcomes.insert(file_info.path.as_path(), outcome);
+            });
+        });
+
+        let mut paths: Vec<PathBuf> = file_info_map.keys().cloned().collect();
+        paths.extend(processed_files.test_results.keys().cloned());
+        paths.sort();
+        paths.dedup();
+
+        let entries = paths
+            .into_iter()
+            .map(|path| {
+                let outcome = match processed_files.test_results.get(&path) {
+                    Some(exit_code) if *exit_code == DANO_CLEAN_EXIT_CODE => {
+                        outcomes.get(path.as_path()).copied().unwrap_or("OK")
+                    }
+                    Some(exit_code) => match VerifyOutcome::from_exit_code(*exit_code) {
+                        VerifyOutcome::Missing => "Missing",
+                        VerifyOutcome::Mismatch => "Mismatch",
+                        VerifyOutcome::Ok | VerifyOutcome::Error => "Error",
+                    },
+                    None => "Not tested",
+                };
+
+                ReportEntry {
+                    file_info: file_info_map.remove(&path),
+                    path,
+                    outcome,
+                }
+            })
+            .collect();
+
+        Self {
+            generated: SystemTime::now(),
+            entries,
+        }
+    }
+
+    pub fn exec(
+        config: &Config,
+        recorded_file_info: &[FileInfo],
+        processed_files: &ProcessedFiles,
+    ) -> DanoResult<()> {
+        let Some(report_dir) = &config.opt_report_html else {
+            return Ok(());
+        };
+
+        if config.opt_dry_run {
+            return print_err_buf(&format!(
+                "WARN: Not writing HTML report to (because dry run was specified): {:?}\n",
+                report_dir
+            ));
+        }
+
+        let report_path = Self::new(recorded_file_info, processed_files).write(report_dir)?;
+
+        if !config.opt_silent {
+            print_err_buf(&format!("Wrote HTML report to: {:?}\n", report_path))?;
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, dir: &Path) -> DanoResult<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+
+        let report_path = dir.join(REPORT_FILE_NAME);
+        std::fs::write(&report_path, self.to_html())?;
+
+        Ok(report_path)
+    }
+
+    fn is_failed(entry: &ReportEntry) -> bool {
+        matches!(entry.outcome, "Error" | "Missing" | "Mismatch")
+    }
+
+    fn to_html(&self) -> String {
+        let mut buffer = String::new();
+
+        let _ = write!(
+            buffer,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>dano report</title>\n<style>{}</style>\n</head>\n<body>\n",
+            STYLE
+        );
+        let _ = write!(
+            buffer,
+            "<h1>dano report</h1>\n<p>Generated {} by dano {}.</p>\n",
+            format_time(self.generated),
+            env!("CARGO_PKG_VERSION")
+        );
+
+        self.write_stats(&mut buffer);
+
+        let failures: Vec<&ReportEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| Self::is_failed(entry))
+            .collect();
+
+        buffer += "<h2>Failures</h2>\n";
+        if failures.is_empty() {
+            buffer += "<p>No failures.</p>\n";
+        } else {
+            Self::write_entries_table(&mut buffer, &failures);
+        }
+
+        self.write_duplicates(&mut buffer);
+
+        buffer += "<h2>Entries</h2>\n";
+        let entries: Vec<&ReportEntry> = self.entries.iter().collect();
+        Self::write_entries_table(&mut buffer, &entries);
+
+        let _ = write!(
+            buffer,
+            "<script>{}</script>\n</body>\n</html>\n",
+            SORT_SCRIPT
+        );
+
+        buffer
+    }
+
+    fn write_stats(&self, buffer: &mut String) {
+        let outcome_counts = self.entries.iter().counts_by(|entry| entry.outcome);
+        let num_failed = self
+            .entries
+            .iter()
+            .filter(|entry| Self::is_failed(entry))
+            .count();
+
+        *buffer += "<h2>Statistics</h2>\n<table>\n<tbody>\n";
+        let _ = writeln!(
+            buffer,
+            "<tr><th>Entries</th><td>{}</td></tr>",
+            self.entries.len()
+        );
+        [
+            "OK",
+            "New",
+            "Renamed",
+            "Mismatch",
+            "Missing",
+            "Error",
+            "Not tested",
+        ]
+        .iter()
+        .for_each(|outcome| {
+            let _ = writeln!(
+                buffer,
+                "<tr><th>{}</th><td>{}</td></tr>",
+                outcome,
+                outcome_counts.get(outcome).unwrap_or(&0)
+            );
+        });
+        let _ = writeln!(buffer, "<tr><th>Failed</th><td>{}</td></tr>", num_failed);
+        *buffer += "</tbody>\n</table>\n";
+    }
+
+    fn write_duplicates(&self, buffer: &mut String) {
+        let duplicates: BTreeMap<Box<str>, Vec<&Path>> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let metadata = entry.file_info.as_ref()?.metadata.as_ref()?;
+                Some((metadata.hash_value.value.clone(), entry.path.as_path()))
+            })
+            .into_group_map()
+            .into_iter()
+            .filter(|(_hash_value, paths)| paths.len() > 1)
+            .collect();
+
+        *buffer += "<h2>Duplicates</h2>\n";
+        if duplicates.is_empty() {
+            *buffer += "<p>No duplicates.</p>\n";
+            return;
+        }
+
+        *buffer += "<table class=\"sortable\">\n<thead><tr><th>Hash value</th><th>Path</th></tr></thead>\n<tbody>\n";
+        duplicates.iter().for_each(|(hash_value, paths)| {
+            paths.iter().for_each(|path| {
+                let _ = writeln!(
+                    buffer,
+                    "<tr><td class=\"hash\">{}</td><td>{}</td></tr>",
+                    escape_html(hash_value),
+                    escape_html(&path.to_string_lossy())
+                );
+            })
+        });
+        *buffer += "</tbody>\n</table>\n";
+    }
+
+    fn write_entries_table(buffer: &mut String, entries: &[&ReportEntry]) {
+        *buffer += "<table class=\"sortable\">\n<thead><tr><th>Path</th><th>Outcome</th><th>Hash algorithm</th>\
+            <th>Hash value</th><th>Streams</th><th>Decoded</th><th>Last written</th></tr></thead>\n<tbody>\n";
+
+        entries.iter().for_each(|entry| {
+            let opt_metadata = entry
+                .file_info
+                .as_ref()
+                .and_then(|file_info| file_info.metadata.as_ref());
+
+            let (hash_algo, hash_value, streams, decoded, last_written) = match opt_metadata {
+                Some(metadata) => (
+                    metadata.hash_algo.to_string(),
+                    metadata.hash_value.value.to_string(),
+                    format!("{:?}", metadata.selected_streams),
+                    metadata.decoded.to_string(),
+                    format_time(metadata.last_written),
+                ),
+                None => Default::default(),
+            };
+
+            let _ = writeln!(
+                buffer,
+                "<tr{}><td>{}</td><td>{}</td><td>{}</td><td class=\"hash\">{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                if Self::is_failed(entry) { " class=\"failed\"" } else { "" },
+                escape_html(&entry.path.to_string_lossy()),
+                entry.outcome,
+                escape_html(&hash_algo),
+                escape_html(&hash_value),
+                streams,
+                decoded,
+                last_written
+            );
+        });
+
+        *buffer += "</tbody>\n</table>\n";
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .chars()
+        .fold(String::with_capacity(value.len()), |mut acc, c| {
+            match c {
+                '&' => acc.push_str("&amp;"),
+                '<' => acc.push_str("&lt;"),
+                '>' => acc.push_str("&gt;"),
+                '"' => acc.push_str("&quot;"),
+                '\'' => acc.push_str("&#39;"),
+                c => acc.push(c),
+            }
+            acc
+        })
+}
@@ -23,10 +23,12 @@ use std::{
 
 use rayon::prelude::*;
 
+use crate::config::{is_image_path, DecodeFormat, SelectedStreams};
+use crate::flac::FlacImporter;
 use crate::lookup::{FileInfo, FileMetadata};
+use crate::mediainfo::VideoFormat;
 use crate::utility::DanoResult;
-use crate::Config;
-use crate::{config::SelectedStreams, ingest::RecordedFileInfo};
+use crate::{Config, ExecMode};
 
 #[derive(Debug, Clone)]
 pub struct FileInfoRequest {
@@ -35,6 +37,34 @@ pub struct FileInfoRequest {
     pub decoded: Option<bool>,
     pub selected_streams: Option<SelectedStreams>,
     pub bits_per_second: Option<u32>,
+    pub input_args: Option<Vec<String>>,
+    pub video_format: Option<VideoFormat>,
+    pub native: Option<bool>,
+    pub frame_hash: Option<bool>,
+    pub stream_hash: Option<bool>,
+    pub tags_hash: Option<bool>,
+    pub exclude_cover_art: Option<bool>,
+    pub decode_format: Option<DecodeFormat>,
+    pub cue_tracks: Option<bool>,
+}
+
+impl FileInfoRequest {
+    // requests which differ only by path will produce the same hash for the same content
+    pub fn differs_only_by_path(&self, other: &FileInfoRequest) -> bool {
+        self.hash_algo == other.hash_algo
+            && self.decoded == other.decoded
+            && self.selected_streams == other.selected_streams
+            && self.bits_per_second == other.bits_per_second
+            && self.input_args == other.input_args
+            && self.video_format == other.video_format
+            && self.native == other.native
+            && self.frame_hash == other.frame_hash
+            && self.stream_hash == other.stream_hash
+            && self.tags_hash == other.tags_hash
+            && self.exclude_cover_art == other.exclude_cover_art
+            && self.decode_format == other.decode_format
+            && self.cue_tracks == other.cue_tracks
+    }
 }
 
 pub struct RequestBundle {
@@ -74,6 +104,15 @@ impl RequestBundle {
             decoded: Some(metadata.decoded),
             selected_streams: Some(metadata.selected_streams.to_owned()),
             bits_per_second: metadata.opt_bits_per_second,
+            input_args: None,
+            video_format: metadata.opt_video_format.clone(),
+            native: Some(metadata.native),
+            frame_hash: Some(metadata.opt_frame_digests.is_some()),
+            stream_hash: Some(metadata.opt_stream_hashes.is_some()),
+            tags_hash: Some(metadata.opt_tags_hash.is_some()),
+            exclude_cover_art: Some(metadata.cover_art_excluded),
+            decode_format: metadata.opt_decode_format,
+            cue_tracks: Some(metadata.opt_track_hashes.is_some()),
         }
     }
 
@@ -85,12 +124,41 @@ impl RequestBundle {
             decoded: None,
             selected_streams: None,
             bits_per_second: None,
+            input_args: None,
+            video_format: None,
+            native: None,
+            frame_hash: None,
+            stream_hash: None,
+            tags_hash: None,
+            exclude_cover_art: None,
+            decode_format: None,
+            cue_tracks: None,
+        }
+    }
+
+    // new requests
+    fn as_image_request(path: &Path) -> FileInfoRequest {
+        FileInfoRequest {
+            path: path.to_owned(),
+            hash_algo: None,
+            decoded: Some(true),
+            selected_streams: Some(SelectedStreams::ImageOnly),
+            bits_per_second: None,
+            input_args: None,
+            video_format: None,
+            native: None,
+            frame_hash: None,
+            stream_hash: None,
+            tags_hash: None,
+            exclude_cover_art: None,
+            decode_format: None,
+            cue_tracks: None,
         }
     }
 
     // new requests
     fn as_flac_request(path: &Path) -> FileInfoRequest {
-        let opt_bps = RecordedFileInfo::import_flac_bps_value(path).ok();
+        let opt_bps = FlacImporter::import_flac_bps_value(path).ok();
 
         FileInfoRequest {
             path: path.to_owned(),
@@ -98,46 +166,149 @@ impl RequestBundle {
             decoded: None,
             selected_streams: None,
             bits_per_second: opt_bps,
+            input_args: None,
+            video_format: None,
+            native: None,
+            frame_hash: None,
+            stream_hash: None,
+            tags_hash: None,
+            exclude_cover_art: None,
+            decode_format: None,
+            cue_tracks: None,
         }
     }
 
-    pub fn new(config: &Config, recorded_file_info: &[FileInfo]) -> DanoResult<Self> {
-        let mut recorded_file_info_requests: BTreeMap<&Path, FileInfoRequest> = recorded_file_info
-            .par_iter()
-            .map(|file_info| match &file_info.metadata {
-                Some(metadata) => (
-                    file_info.path.as_path(),
-                    Self::from_recorded_request(&file_info.path, metadata),
-                ),
-                None => (
-                    file_info.path.as_path(),
-                    Self::as_new_request(&file_info.path),
-                ),
+    // per extension overrides from the config file, a decode override only applies to new requests,
+    // because recorded requests must be hashed the same way they were recorded
+    fn with_ext_override(config: &Config, mut request: FileInfoRequest) -> FileInfoRequest {
+        if let Some(ext_override) = config.get_ext_override(&request.path) {
+            if request.decoded.is_none() {
+                request.decoded = ext_override.decode;
+            }
+
+            request.input_args = ext_override.ffmpeg_args.clone();
+        }
+
+        request
+    }
+
+    fn as_path_request(config: &Config, path: &Path) -> FileInfoRequest {
+        // a whole file hash requires nothing more than the path
+        if config.selected_streams == SelectedStreams::WholeFile {
+            return Self::as_new_request(path);
+        }
+
+        if config.opt_image_pixels && is_image_path(path) {
+            return Self::as_image_request(path);
+        }
+
+        if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+            if &ext.to_lowercase() == "flac" {
+                return Self::as_flac_request(path);
+            }
+        }
+
+        Self::as_new_request(path)
+    }
+
+    // recorded file info, requested only in its recorded form
+    pub fn from_recorded(config: &Config, recorded_file_info: &[FileInfo]) -> Self {
+        let requests = recorded_file_info
+            .iter()
+            .filter_map(|file_info| {
+                let metadata = file_info.metadata.as_ref()?;
+                let request = Self::from_recorded_request(&file_info.path, metadata);
+
+                Some(Self::with_ext_override(config, request))
+            })
+            .collect();
+
+        Self { inner: requests }
+    }
+
+    // recorded file info, requested in its recorded form, but with another hash algorithm
+    pub fn with_hash_algo(
+        config: &Config,
+        recorded_file_info: &[FileInfo],
+        hash_algo: &str,
+    ) -> Self {
+        let requests = recorded_file_info
+            .iter()
+            .filter_map(|file_info| {
+                let metadata = file_info.metadata.as_ref()?;
+
+                let request = FileInfoRequest {
+                    hash_algo: Some(hash_algo.into()),
+                    ..Self::from_recorded_request(&file_info.path, metadata)
+                };
+
+                Some(Self::with_ext_override(config, request))
             })
             .collect();
 
-        let paths_requests: Vec<(&Path, FileInfoRequest)> = config
+        Self { inner: requests }
+    }
+
+    pub fn new(config: &Config, recorded_file_info: &[FileInfo]) -> DanoResult<Self> {
+        // a path may be recorded in more than one form, such as both decoded and copied,
+        // and we request each recorded form
+        let mut recorded_file_info_requests: BTreeMap<&Path, Vec<FileInfoRequest>> =
+            BTreeMap::new();
+
+        recorded_file_info.iter().for_each(|file_info| {
+            let request = match &file_info.metadata {
+                Some(metadata) => Self::from_recorded_request(&file_info.path, metadata),
+                None => Self::as_new_request(&file_info.path),
+            };
+
+            recorded_file_info_requests
+                .entry(file_info.path.as_path())
+                .or_default()
+                .push(request);
+        });
+
+        // with a time budget, only the selected recorded file info is tested
+        let is_budgeted = matches!(&config.exec_mode, ExecMode::Test(test_mode_config) if test_mode_config.opt_budget.is_some());
+
+        let paths_requests: Vec<(&Path, Vec<FileInfoRequest>)> = config
             .paths
             .par_iter()
+            .filter(|path| !is_budgeted || recorded_file_info_requests.contains_key(path.as_path()))
             .map(
                 |path| match recorded_file_info_requests.get(path.as_path()) {
-                    Some(value) => (path.as_path(), value.to_owned()),
-                    None => {
-                        if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
-                            if &ext.to_lowercase() == "flac" {
-                                return (path.as_path(), Self::as_flac_request(path));
-                            }
+                    // when writing, a path recorded only in another form, decoded or copied, is requested in the new form
+                    Some(value) if matches!(config.exec_mode, ExecMode::Write(_)) => {
+                        let new_request =
+                            Self::with_ext_override(config, Self::as_path_request(config, path));
+                        let new_decoded = new_request
+                            .decoded
+                            .unwrap_or_else(|| config.new_decoded(path));
+
+                        let mut requests = value.to_owned();
+
+                        if value.iter().all(|request| {
+                            request
+                                .decoded
+                                .is_some_and(|decoded| decoded != new_decoded)
+                        }) {
+                            requests.push(new_request);
                         }
 
-                        (path.as_path(), Self::as_new_request(path))
+                        (path.as_path(), requests)
                     }
+                    Some(value) => (path.as_path(), value.to_owned()),
+                    None => (path.as_path(), vec![Self::as_path_request(config, path)]),
                 },
             )
             .collect();
 
         recorded_file_info_requests.extend(paths_requests);
 
-        let requests = recorded_file_info_requests.into_values().collect();
+        let requests = recorded_file_info_requests
+            .into_values()
+            .flatten()
+            .map(|request| Self::with_ext_override(config, request))
+            .collect();
 
         Ok(Self { inner: requests })
     }
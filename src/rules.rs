@@ -0,0 +1,140 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::path::Path;
+
+use glob::Pattern;
+use serde::Deserialize;
+
+use crate::config::SelectedStreams;
+use crate::{DanoError, DanoResult};
+
+const VALID_HASH_ALGOS: [&str; 10] = [
+    "murmur3", "md5", "crc32", "adler32", "sha1", "sha160", "sha256", "sha384", "sha512", "blake3",
+];
+
+// a rules file maps path globs to the settings used to hash new files, such as:
+//
+// [[rule]]
+// glob = "/music/**"
+// hash_algo = "md5"
+// decode = true
+// only = "audio"
+//
+// [[rule]]
+// glob = "/video/**"
+// hash_algo = "murmur3"
+// decode = false
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<RawPathRule>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+struct RawPathRule {
+    glob: String,
+    hash_algo: Option<String>,
+    decode: Option<bool>,
+    only: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathRule {
+    pub pattern: Pattern,
+    pub opt_hash_algo: Option<Box<str>>,
+    pub opt_decode: Option<bool>,
+    pub opt_selected_streams: Option<SelectedStreams>,
+}
+
+impl PathRule {
+    fn new(raw: RawPathRule, rules_file: &Path) -> DanoResult<Self> {
+        let invalid = |what: &str, value: &str| {
+            let msg = format!(
+                "Rule for '{}' in rules file {:?} has an invalid {}: {}",
+                raw.glob, rules_file, what, value
+            );
+            DanoError::new(&msg)
+        };
+
+        let pattern = Pattern::new(&raw.glob).map_err(|_| invalid("glob", &raw.glob))?;
+
+        let opt_hash_algo: Option<Box<str>> = match raw.hash_algo.as_deref() {
+            Some("sha1") => Some("sha160".into()),
+            Some(hash_algo) if VALID_HASH_ALGOS.contains(&hash_algo) => Some(hash_algo.into()),
+            Some(hash_algo) => return Err(invalid("hash algorithm", hash_algo).into()),
+            None => None,
+        };
+
+        let opt_selected_streams = match raw.only.as_deref() {
+            Some(only) => match SelectedStreams::from_only(only) {
+                Some(selected_streams) => Some(selected_streams),
+                None => return Err(invalid("stream selection", only).into()),
+            },
+            None => None,
+        };
+
+        Ok(Self {
+            pattern,
+            opt_hash_algo,
+            opt_decode: raw.decode,
+            opt_selected_streams,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathRules {
+    rules: Vec<PathRule>,
+}
+
+impl PathRules {
+    pub fn read(rules_file: &Path) -> DanoResult<Self> {
+        let contents = std::fs::read_to_string(rules_file).map_err(|err| {
+            let msg = format!("Could not read rules file {:?}: {}", rules_file, err);
+            DanoError::new(&msg)
+        })?;
+
+        let raw: RulesFile = toml::from_str(&contents).map_err(|err| {
+            let msg = format!("Could not parse rules file {:?}: {}", rules_file, err);
+            DanoError::new(&msg)
+        })?;
+
+        let rules = raw
+            .rule
+            .into_iter()
+            .map(|raw_rule| PathRule::new(raw_rule, rules_file))
+            .collect::<DanoResult<Vec<PathRule>>>()?;
+
+        Ok(Self { rules })
+    }
+
+    // the first matching rule wins, so more specific globs should be listed first
+    pub fn get(&self, path: &Path, pwd: &Path) -> Option<&PathRule> {
+        let absolute = pwd.join(path);
+
+        self.rules
+            .iter()
+            .find(|rule| rule.pattern.matches_path(path) || rule.pattern.matches_path(&absolute))
+    }
+
+    pub fn hash_algos(&self) -> impl Iterator<Item = &str> {
+        self.rules
+            .iter()
+            .filter_map(|rule| rule.opt_hash_algo.as_deref())
+    }
+}
@@ -0,0 +1,84 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::path::Path;
+
+use crate::config::SelectedStreams;
+use crate::hasher::NativeHasher;
+use crate::lookup::FileInfo;
+use crate::probe::FFProbe;
+
+// hashes which are almost certainly meaningless, and which, if recorded, would verify nothing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuspectHash {
+    ZeroHash,
+    EmptyStream,
+    ZeroDuration,
+}
+
+impl SuspectHash {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuspectHash::ZeroHash => "the hash value is zero",
+            SuspectHash::EmptyStream => {
+                "the hash value is the hash of no data, the selected streams may be empty"
+            }
+            SuspectHash::ZeroDuration => "the file has a duration of zero",
+        }
+    }
+
+    // the duration is only probed for hashes which ffmpeg produced, imported hashes were never ffmpeg's to vouch for
+    pub fn check(file_info: &FileInfo, probe_duration: bool) -> Option<Self> {
+        let metadata = file_info.metadata.as_ref()?;
+
+        // hash values are recorded without leading zeros
+        let hash_value = metadata.hash_value.value.trim_start_matches('0');
+
+        if hash_value.is_empty() {
+            return Some(SuspectHash::ZeroHash);
+        }
+
+        if let Ok(hasher) = NativeHasher::new(&metadata.hash_algo) {
+            if hasher.finalize_hex().trim_start_matches('0') == hash_value {
+                return Some(SuspectHash::EmptyStream);
+            }
+        }
+
+        let has_duration = !matches!(
+            metadata.selected_streams,
+            SelectedStreams::WholeFile | SelectedStreams::ImageOnly
+        );
+
+        if probe_duration && has_duration && Self::is_zero_duration(&file_info.path) {
+            return Some(SuspectHash::ZeroDuration);
+        }
+
+        None
+    }
+
+    // a file without a known duration is given the benefit of the doubt
+    fn is_zero_duration(path: &Path) -> bool {
+        let Ok(root) = FFProbe::show_entries(path, "format=duration") else {
+            return false;
+        };
+
+        root.get("format")
+            .and_then(|format| format.get("duration"))
+            .and_then(|duration| duration.as_str())
+            .and_then(|duration| duration.parse::<f64>().ok())
+            .is_some_and(|duration| duration <= 0.0)
+    }
+}
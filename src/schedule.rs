@@ -0,0 +1,239 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::lookup::FileInfo;
+use crate::state::StateFile;
+use crate::{Config, DanoResult, DANO_DISORDER_EXIT_CODE};
+
+// used to estimate how long an entry will take to verify, until a run has been timed
+const DEFAULT_BYTES_PER_SECOND: f64 = 50_000_000.0;
+const NEW_HASH_EXIT_CODE: i32 = 3i32;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyOutcome {
+    Ok,
+    Missing,
+    Mismatch,
+    Error,
+}
+
+impl VerifyOutcome {
+    pub fn from_exit_code(exit_code: i32) -> Self {
+        match exit_code {
+            DANO_DISORDER_EXIT_CODE => VerifyOutcome::Missing,
+            NEW_HASH_EXIT_CODE => VerifyOutcome::Mismatch,
+            _ => VerifyOutcome::Ok,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EntryState {
+    last_selected: SystemTime,
+    last_outcome: VerifyOutcome,
+}
+
+// the schedule records, per hash file, when each entry was last selected for verification,
+// and the outcome of that verification, so a time budgeted run can select the stalest entries first
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CatalogState {
+    #[serde(default)]
+    opt_bytes_per_second: Option<f64>,
+    #[serde(default)]
+    entries: BTreeMap<PathBuf, EntryState>,
+    #[serde(default)]
+    opt_last_verified: Option<SystemTime>,
+}
+
+pub struct Schedule {
+    state_file: StateFile,
+    catalog: PathBuf,
+}
+
+impl Schedule {
+    pub fn new(config: &Config) -> DanoResult<Self> {
+        let state_file = StateFile::new()?;
+
+        // key each catalog by its absolute path, so runs from different working directories agree
+        let catalog = config
+            .hash_file
+            .canonicalize()
+            .unwrap_or_else(|_| config.pwd.join(&config.hash_file));
+
+        Ok(Self {
+            state_file,
+            catalog,
+        })
+    }
+
+    fn catalog_state(&self) -> Option<&CatalogState> {
+        self.state_file.state.catalogs.get(&self.catalog)
+    }
+
+    // when the catalog was last verified, whether in full or in part
+    pub fn last_verified(&self) -> Option<SystemTime> {
+        let catalog_state = self.catalog_state()?;
+
+        catalog_state
+            .entries
+            .values()
+            .map(|entry_state| entry_state.last_selected)
+            .chain(catalog_state.opt_last_verified)
+            .max()
+    }
+
+    fn estimate(&self, path: &Path) -> Duration {
+        let bytes_per_second = self
+            .catalog_state()
+            .and_then(|catalog_state| catalog_state.opt_bytes_per_second)
+            .unwrap_or(DEFAULT_BYTES_PER_SECOND);
+
+        let len = path.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+
+        Duration::from_secs_f64(len as f64 / bytes_per_second)
+    }
+
+    // select the stalest entries, by when last selected or checked, until the estimated time to
+    // verify the selection would exceed the budget.  at least one entry is always selected.
+    pub fn select(&self, mut recorded_file_info: Vec<FileInfo>, budget: Duration) -> Vec<FileInfo> {
+        recorded_file_info.sort_by_key(|file_info| self.last_checked(file_info));
+
+        let mut spent = Duration::ZERO;
+
+        recorded_file_info
+            .into_iter()
+            .enumerate()
+            .take_while(|(idx, file_info)| {
+                spent += self.estimate(&file_info.path);
+                *idx == 0 || spent <= budget
+            })
+            .map(|(_idx, file_info)| file_info)
+            .collect()
+    }
+
+    fn last_selected(&self, path: &Path) -> Option<SystemTime> {
+        self.catalog_state()
+            .and_then(|catalog_state| catalog_state.entries.get(path))
+            .map(|entry_state| entry_state.last_selected)
+    }
+
+    // the later of when the entry was last selected, and when its record was last checked, or written
+    fn last_checked(&self, file_info: &FileInfo) -> Option<SystemTime> {
+        let opt_recorded = file_info
+            .metadata
+            .as_ref()
+            .map(|metadata| metadata.last_checked());
+
+        self.last_selected(&file_info.path).max(opt_recorded)
+    }
+
+    // entries which were never checked, or not checked within the interval, stalest first
+    pub fn due(&self, mut recorded_file_info: Vec<FileInfo>, interval: Duration) -> Vec<FileInfo> {
+        let now = SystemTime::now();
+
+        recorded_file_info.retain(|file_info| match self.last_checked(file_info) {
+            Some(last_checked) => last_checked + interval <= now,
+            None => true,
+        });
+
+        recorded_file_info.sort_by_key(|file_info| self.last_checked(file_info));
+
+        recorded_file_info
+    }
+
+    // when the next entry falls due, if any entry is not already due
+    pub fn next_due(
+        &self,
+        recorded_file_info: &[FileInfo],
+        interval: Duration,
+    ) -> Option<SystemTime> {
+        recorded_file_info
+            .iter()
+            .map(|file_info| {
+                self.last_checked(file_info)
+                    .map(|last_checked| last_checked + interval)
+            })
+            .min()
+            .flatten()
+    }
+
+    // record when each of the selected entries was verified, and the outcome, as well as
+    // the observed throughput of the run, which is used to estimate the cost of the next selection
+    pub fn record_run(
+        mut self,
+        selected: &[PathBuf],
+        outcomes: &BTreeMap<PathBuf, VerifyOutcome>,
+        elapsed: Duration,
+    ) -> DanoResult<()> {
+        let now = SystemTime::now();
+        let catalog_state = self
+            .state_file
+            .state
+            .catalogs
+            .entry(self.catalog.clone())
+            .or_default();
+
+        catalog_state.opt_last_verified = Some(now);
+
+        selected.iter().for_each(|path| {
+            let last_outcome = outcomes.get(path).copied().unwrap_or(VerifyOutcome::Error);
+
+            catalog_state.entries.insert(
+                path.clone(),
+                EntryState {
+                    last_selected: now,
+                    last_outcome,
+                },
+            );
+        });
+
+        let bytes: u64 = outcomes
+            .iter()
+            .filter(|(_path, outcome)| {
+                matches!(outcome, VerifyOutcome::Ok | VerifyOutcome::Mismatch)
+            })
+            .filter_map(|(path, _outcome)| path.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum();
+
+        if bytes > 0 && elapsed.as_secs_f64() > 0.0 {
+            catalog_state.opt_bytes_per_second = Some(bytes as f64 / elapsed.as_secs_f64());
+        }
+
+        self.state_file.write()
+    }
+
+    // record when a run, which was not time budgeted, and so selected no entries, verified the catalog
+    pub fn record_verified(mut self) -> DanoResult<()> {
+        self.state_file
+            .state
+            .catalogs
+            .entry(self.catalog.clone())
+            .or_default()
+            .opt_last_verified = Some(SystemTime::now());
+
+        self.state_file.write()
+    }
+}
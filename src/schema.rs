@@ -0,0 +1,172 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use crate::utility::{
+    deserialize_version, get_dano_xattr, hash_file_exists, print_commentary, print_err_buf,
+    read_hash_file,
+};
+use crate::{Config, DanoResult, DANO_FILE_INFO_VERSION};
+
+const HEADER_PREFIX: &str = "//";
+
+enum RecordSource {
+    HashFile,
+    Xattr,
+}
+
+impl RecordSource {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RecordSource::HashFile => "hash file",
+            RecordSource::Xattr => "extended attribute",
+        }
+    }
+}
+
+struct LegacyRecord {
+    path: PathBuf,
+    version: usize,
+    source: RecordSource,
+}
+
+// legacy records are converted to the latest version as they are read, so the schema report
+// reads the raw records, from the hash file and from the extended attributes of any input files
+#[derive(Default)]
+pub struct SchemaReport {
+    versions: BTreeMap<usize, usize>,
+    legacy: Vec<LegacyRecord>,
+    unparseable: usize,
+}
+
+impl SchemaReport {
+    pub fn exec(config: &Config) -> DanoResult<()> {
+        let report = Self::new(config)?;
+
+        report.print(config)
+    }
+
+    fn new(config: &Config) -> DanoResult<Self> {
+        let mut report = Self::default();
+
+        let input_paths: BTreeSet<&Path> = config.paths.iter().map(|path| path.as_path()).collect();
+
+        if hash_file_exists(&config.hash_file) {
+            let contents = read_hash_file(&config.hash_file)?;
+
+            contents
+                .lines()
+                .filter(|line| !line.trim().is_empty() && !line.starts_with(HEADER_PREFIX))
+                .for_each(|line| {
+                    let opt_path = Self::line_path(line);
+
+                    // when input files are specified, only their records are reported
+                    if !input_paths.is_empty()
+                        && !matches!(&opt_path, Some(path) if input_paths.contains(path.as_path()))
+                    {
+                        return;
+                    }
+
+                    match (deserialize_version(line), opt_path) {
+                        (Some(version), Some(path)) => {
+                            report.insert(path, version, RecordSource::HashFile)
+                        }
+                        _ => report.unparseable += 1,
+                    }
+                });
+        }
+
+        config.paths.iter().for_each(|path| {
+            if let Ok(Some(bytes)) = get_dano_xattr(path) {
+                match std::str::from_utf8(&bytes)
+                    .ok()
+                    .and_then(deserialize_version)
+                {
+                    Some(version) => report.insert(path.to_owned(), version, RecordSource::Xattr),
+                    None => report.unparseable += 1,
+                }
+            }
+        });
+
+        report.legacy.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(report)
+    }
+
+    fn line_path(line: &str) -> Option<PathBuf> {
+        let root: Value = serde_json::from_str(line).ok()?;
+        crate::serde_path::deserialize(root.get("path")?).ok()
+    }
+
+    fn insert(&mut self, path: PathBuf, version: usize, source: RecordSource) {
+        *self.versions.entry(version).or_default() += 1;
+
+        if version < DANO_FILE_INFO_VERSION {
+            self.legacy.push(LegacyRecord {
+                path,
+                version,
+                source,
+            });
+        }
+    }
+
+    fn print(&self, config: &Config) -> DanoResult<()> {
+        if self.versions.is_empty() && self.unparseable == 0 {
+            return print_err_buf("No recorded file info is available for a schema report.\n");
+        }
+
+        let mut buffer = String::new();
+
+        self.versions.iter().for_each(|(version, count)| {
+            let suffix = if *version == DANO_FILE_INFO_VERSION {
+                " (current)"
+            } else {
+                ""
+            };
+            buffer += &format!("Version {}{}: {} records\n", version, suffix, count);
+        });
+
+        if self.unparseable > 0 {
+            buffer += &format!("Unparseable: {} records\n", self.unparseable);
+        }
+
+        self.legacy.iter().for_each(|record| {
+            buffer += &format!(
+                "{:?}: Legacy version {} record, in the {}\n",
+                record.path,
+                record.version,
+                record.source.as_str()
+            );
+        });
+
+        print_commentary(config, &buffer)?;
+
+        if !self.legacy.is_empty() {
+            print_err_buf(&format!(
+                "WARN: {} records are stored in a legacy format.  Run 'dano --write --rewrite' to rewrite all recorded hashes to the latest format version.\n",
+                self.legacy.len()
+            ))?;
+        }
+
+        Ok(())
+    }
+}
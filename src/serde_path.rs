@@ -0,0 +1,106 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// a path is serialized as a string, like always, but a path which is not valid UTF-8, and
+// so cannot be a JSON string, is serialized as its raw bytes (or, on Windows, its UTF-16 units)
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SerializedPath {
+    Utf8(String),
+    Bytes { bytes: Vec<u8> },
+    Wide { wide: Vec<u16> },
+}
+
+pub fn serialize<P, S>(path: &P, serializer: S) -> Result<S::Ok, S::Error>
+where
+    P: AsRef<Path>,
+    S: Serializer,
+{
+    let path = path.as_ref();
+
+    let serialized = match path.to_str() {
+        Some(path_str) => SerializedPath::Utf8(path_str.to_owned()),
+        None => from_os_str(path),
+    };
+
+    serialized.serialize(serializer)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<PathBuf, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match SerializedPath::deserialize(deserializer)? {
+        SerializedPath::Utf8(path_string) => Ok(PathBuf::from(path_string)),
+        SerializedPath::Bytes { bytes } => from_bytes(bytes).map_err(serde::de::Error::custom),
+        SerializedPath::Wide { wide } => from_wide(wide).map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(unix)]
+fn from_os_str(path: &Path) -> SerializedPath {
+    use std::os::unix::ffi::OsStrExt;
+
+    SerializedPath::Bytes {
+        bytes: path.as_os_str().as_bytes().to_vec(),
+    }
+}
+
+#[cfg(windows)]
+fn from_os_str(path: &Path) -> SerializedPath {
+    use std::os::windows::ffi::OsStrExt;
+
+    SerializedPath::Wide {
+        wide: path.as_os_str().encode_wide().collect(),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn from_os_str(path: &Path) -> SerializedPath {
+    SerializedPath::Utf8(path.to_string_lossy().into_owned())
+}
+
+#[cfg(unix)]
+fn from_bytes(bytes: Vec<u8>) -> Result<PathBuf, &'static str> {
+    use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+    Ok(PathBuf::from(OsString::from_vec(bytes)))
+}
+
+#[cfg(not(unix))]
+fn from_bytes(bytes: Vec<u8>) -> Result<PathBuf, &'static str> {
+    String::from_utf8(bytes)
+        .map(PathBuf::from)
+        .map_err(|_| "Path was recorded as bytes which are not valid UTF-8 on this platform.")
+}
+
+#[cfg(windows)]
+fn from_wide(wide: Vec<u16>) -> Result<PathBuf, &'static str> {
+    use std::{ffi::OsString, os::windows::ffi::OsStringExt};
+
+    Ok(PathBuf::from(OsString::from_wide(&wide)))
+}
+
+#[cfg(not(windows))]
+fn from_wide(wide: Vec<u16>) -> Result<PathBuf, &'static str> {
+    String::from_utf16(&wide).map(PathBuf::from).map_err(|_| {
+        "Path was recorded as UTF-16 units which are not valid Unicode on this platform."
+    })
+}
@@ -0,0 +1,137 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::config::{Config, SelectedStreams};
+use crate::import::Importer;
+use crate::lookup::{FileInfo, FileMetadata, HashValue};
+use crate::{DanoError, DanoResult, DANO_FILE_INFO_VERSION, HEXADECIMAL_RADIX};
+
+const SFV_HASH_ALGO: &str = "CRC32";
+const SFV_HASH_LEN: usize = 8;
+
+// sfv files record the CRC32 of the whole file, so these records can only be verified by a whole file hash
+pub struct SfvImporter;
+
+impl Importer for SfvImporter {
+    fn name(&self) -> &'static str {
+        "sfv"
+    }
+
+    fn is_valid_path(&self, path: &Path) -> bool {
+        matches!(path.extension(), Some(extension) if extension.eq_ignore_ascii_case("sfv"))
+    }
+
+    fn import(&self, _config: &Config, path: &Path) -> DanoResult<Vec<FileInfo>> {
+        let contents = std::fs::read_to_string(path)?;
+
+        // paths within an sfv file are relative to the sfv file's directory
+        let parent = path.parent().unwrap_or_else(|| Path::new(""));
+
+        let file_info = contents
+            .lines()
+            .map(|line| line.trim())
+            // comments begin with a semicolon
+            .filter(|line| !line.is_empty() && !line.starts_with(';'))
+            .filter_map(|line| match Self::parse_line(line) {
+                Some(parsed) => Some(parsed),
+                None => {
+                    eprintln!("ERROR: Could not parse line from {:?}: {}", path, line);
+                    None
+                }
+            })
+            .map(|(entry_path, hash_string)| {
+                let entry_path = if entry_path.is_absolute() {
+                    entry_path
+                } else {
+                    parent.join(entry_path)
+                };
+
+                Self::generate_file_info(&entry_path, hash_string)
+            })
+            .filter_map(|res| match res {
+                Ok(file_info) => Some(file_info),
+                Err(err) => {
+                    eprintln!("ERROR: {}", err);
+                    None
+                }
+            })
+            .collect();
+
+        Ok(file_info)
+    }
+}
+
+impl SfvImporter {
+    // "<path> <crc32>", the path may itself contain spaces, so the hash is the last field
+    fn parse_line(line: &str) -> Option<(PathBuf, &str)> {
+        let (path, hash_string) = line.rsplit_once(char::is_whitespace)?;
+        let path = path.trim_end();
+
+        if path.is_empty()
+            || hash_string.len() != SFV_HASH_LEN
+            || !hash_string.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return None;
+        }
+
+        Some((PathBuf::from(path), hash_string))
+    }
+
+    fn generate_file_info(path: &Path, hash_string: &str) -> DanoResult<FileInfo> {
+        if !path.exists() {
+            let msg = format!("Path listed in sfv file does not exist: {:?}", path);
+            return Err(DanoError::new(&msg).into());
+        }
+
+        Ok(FileInfo {
+            path: path.to_owned(),
+            version: DANO_FILE_INFO_VERSION,
+            metadata: Some(FileMetadata {
+                last_written: SystemTime::now(),
+                hash_algo: SFV_HASH_ALGO.into(),
+                hash_value: HashValue {
+                    radix: HEXADECIMAL_RADIX,
+                    value: hash_string
+                        .to_ascii_lowercase()
+                        .trim_start_matches('0')
+                        .into(),
+                },
+                modify_time: path.metadata()?.modified()?,
+                selected_streams: SelectedStreams::WholeFile,
+                decoded: false,
+                opt_bits_per_second: None,
+                opt_media_info: None,
+                hardlinked: false,
+                fallback: false,
+                opt_video_format: None,
+                native: false,
+                opt_frame_digests: None,
+                opt_stream_hashes: None,
+                opt_size: None,
+                opt_last_checked: None,
+                opt_tags_hash: None,
+                cover_art_excluded: false,
+                opt_decode_format: None,
+                opt_track_hashes: None,
+            }),
+        })
+    }
+}
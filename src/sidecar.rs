@@ -0,0 +1,110 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    ffi::OsStr,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use crate::lookup::FileInfo;
+use crate::utility::{deserialize, get_tmp_file, persist_tmp_file, serialize};
+use crate::{Config, DanoResult};
+
+pub const DANO_SIDECAR_EXTENSION: &str = "dano";
+
+// a sidecar sits beside its media file, as "filename.ext.dano", and so may move with the file,
+// and, unlike an extended attribute, survives any file system
+pub struct Sidecar;
+
+impl Sidecar {
+    pub fn path(path: &Path) -> PathBuf {
+        let mut sidecar_path = path.as_os_str().to_owned();
+        sidecar_path.push(".");
+        sidecar_path.push(DANO_SIDECAR_EXTENSION);
+        PathBuf::from(sidecar_path)
+    }
+
+    pub fn is_sidecar(path: &Path) -> bool {
+        path.extension() == Some(OsStr::new(DANO_SIDECAR_EXTENSION))
+    }
+
+    pub fn exists(path: &Path) -> bool {
+        Self::path(path).exists()
+    }
+
+    pub fn read(path: &Path) -> Option<FileInfo> {
+        let sidecar_path = Self::path(path);
+
+        let contents = match std::fs::read_to_string(&sidecar_path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return None,
+            Err(err) => {
+                eprintln!(
+                    "WARN: {:?}: Sidecar could not be read: {}",
+                    sidecar_path, err
+                );
+                return None;
+            }
+        };
+
+        match deserialize(contents.trim()) {
+            // use the actual path name always
+            Ok(file_info) => Some(FileInfo {
+                path: path.to_owned(),
+                ..file_info
+            }),
+            Err(err) => {
+                eprintln!(
+                    "WARN: {:?}: Sidecar could not be parsed: {}",
+                    sidecar_path, err
+                );
+                None
+            }
+        }
+    }
+
+    // like an extended attribute, the recorded path is empty, because the file may be renamed
+    pub fn write(config: &Config, file_info: &FileInfo) -> DanoResult<()> {
+        let rewrite = FileInfo {
+            version: file_info.version,
+            path: PathBuf::new(),
+            metadata: file_info.metadata.to_owned(),
+        };
+
+        let sidecar_path = Self::path(&file_info.path);
+        let tmp_file = get_tmp_file(config, &sidecar_path);
+
+        let res = std::fs::write(&tmp_file, serialize(&rewrite)?)
+            .map_err(|err| err.into())
+            .and_then(|_| persist_tmp_file(&tmp_file, &sidecar_path));
+
+        if res.is_err() {
+            let _ = std::fs::remove_file(&tmp_file);
+        }
+
+        res
+    }
+
+    // returns whether there was a sidecar to remove
+    pub fn remove(path: &Path) -> DanoResult<bool> {
+        match std::fs::remove_file(Self::path(path)) {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+    process::{Command as ExecProcess, Stdio},
+    time::SystemTime,
+};
+
+use crate::config::Config;
+use crate::state::StateFile;
+use crate::utility::{is_streamed_hash_file, print_err_buf, shell_quote_command};
+use crate::{DanoError, DanoResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningTool {
+    Minisign,
+    Gpg,
+}
+
+impl SigningTool {
+    const ALL: [SigningTool; 2] = [SigningTool::Minisign, SigningTool::Gpg];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SigningTool::Minisign => "minisign",
+            SigningTool::Gpg => "gpg",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            SigningTool::Minisign => "minisig",
+            SigningTool::Gpg => "asc",
+        }
+    }
+
+    // a detached signature is written next to the file it signs
+    fn signature_path(&self, path: &Path) -> PathBuf {
+        let mut signature_path: OsString = path.as_os_str().to_owned();
+        signature_path.push(".");
+        signature_path.push(self.extension());
+
+        PathBuf::from(signature_path)
+    }
+
+    fn sign_args(&self, config: &Config, path: &Path) -> Vec<OsString> {
+        let signature_path = self.signature_path(path);
+
+        let mut args: Vec<OsString> = match self {
+            SigningTool::Minisign => vec!["-S".into(), "-m".into(), path.into()],
+            SigningTool::Gpg => vec![
+                "--batch".into(),
+                "--yes".into(),
+                "--armor".into(),
+                "--detach-sign".into(),
+            ],
+        };
+
+        match (self, &config.opt_sign_key) {
+            (SigningTool::Minisign, Some(key)) => args.extend(["-s".into(), key.clone()]),
+            (SigningTool::Gpg, Some(key)) => args.extend(["--local-user".into(), key.clone()]),
+            (_, None) => (),
+        }
+
+        match self {
+            SigningTool::Minisign => args.extend(["-x".into(), signature_path.into()]),
+            SigningTool::Gpg => {
+                args.extend(["--output".into(), signature_path.into(), path.into()])
+            }
+        }
+
+        args
+    }
+
+    fn verify_args(&self, config: &Config, path: &Path) -> Vec<OsString> {
+        let signature_path = self.signature_path(path);
+
+        match self {
+            SigningTool::Minisign => {
+                let mut args: Vec<OsString> = vec![
+                    "-V".into(),
+                    "-q".into(),
+                    "-m".into(),
+                    path.into(),
+                    "-x".into(),
+                    signature_path.into(),
+                ];
+
+                if let Some(public_key) = &config.opt_verify_key {
+                    args.extend(["-p".into(), public_key.into()]);
+                }
+
+                args
+            }
+            SigningTool::Gpg => vec![
+                "--batch".into(),
+                "--verify".into(),
+                signature_path.into(),
+                path.into(),
+            ],
+        }
+    }
+
+    fn print_command(&self, config: &Config, command: &Path, args: &[OsString]) -> DanoResult<()> {
+        if !config.opt_show_commands {
+            return Ok(());
+        }
+
+        let args: Vec<&OsStr> = args.iter().map(|arg| arg.as_os_str()).collect();
+
+        print_err_buf(&format!(
+            "Command: {}\n",
+            shell_quote_command(command, &args)
+        ))
+    }
+
+    // signing may ask for a passphrase, so the tool is given the terminal
+    fn sign(&self, config: &Config, path: &Path) -> DanoResult<()> {
+        let command = config.signing_command(*self)?;
+        let args = self.sign_args(config, path);
+
+        self.print_command(config, &command, &args)?;
+
+        let status = ExecProcess::new(&command)
+            .args(&args)
+            .stdout(Stdio::null())
+            .status()?;
+
+        if !status.success() {
+            let msg = format!(
+                "Could not sign the hash file with {}, which exited with {}: {:?}",
+                self.name(),
+                status,
+                path
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        if !config.opt_silent {
+            print_err_buf(&format!(
+                "Signed hash file with {}: {:?}\n",
+                self.name(),
+                self.signature_path(path)
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    fn verify(&self, config: &Config, path: &Path) -> DanoResult<()> {
+        let command = config.signing_command(*self)?;
+        let args = self.verify_args(config, path);
+
+        self.print_command(config, &command, &args)?;
+
+        let process_output = ExecProcess::new(&command)
+            .args(&args)
+            .stdin(Stdio::null())
+            .output()?;
+
+        if !process_output.status.success() {
+            let msg = format!(
+                "The {} signature of the hash file does not verify, so the hash file may have been tampered with, and is not read: {:?}: {}",
+                self.name(),
+                path,
+                String::from_utf8_lossy(&process_output.stderr).trim()
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        Ok(())
+    }
+}
+
+// the hash file as it was before this run, so that a run which writes the hash file is known to leave
+// its signatures stale
+pub struct HashFileSignature {
+    opt_state: Option<(SystemTime, u64)>,
+}
+
+impl HashFileSignature {
+    // a signed hash file is not written without a signature, unless the user asks, as the write would leave
+    // the hash file unsigned, which is just what tampering with a signed hash file would do
+    pub fn new(config: &Config, is_rewrite: bool) -> DanoResult<Self> {
+        let hash_file = &config.output_file;
+
+        if is_rewrite
+            && config.opt_sign.is_none()
+            && !config.opt_remove_signature
+            && !config.opt_dry_run
+            && !config.opt_output_stdout
+            && hash_file.exists()
+            && Self::is_expected(config, hash_file)?
+        {
+            let msg = format!(
+                "The hash file is signed, and would no longer be signed once written.  \
+                Use --sign to sign it again, or --remove-signature to write it without a signature: {:?}",
+                hash_file
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        Ok(Self {
+            opt_state: Self::file_state(hash_file),
+        })
+    }
+
+    fn file_state(path: &Path) -> Option<(SystemTime, u64)> {
+        let metadata = path.metadata().ok()?;
+
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+
+    fn signatures(path: &Path) -> Vec<SigningTool> {
+        SigningTool::ALL
+            .into_iter()
+            .filter(|tool| tool.signature_path(path).exists())
+            .collect()
+    }
+
+    pub fn is_signed(path: &Path) -> bool {
+        !Self::signatures(path).is_empty()
+    }
+
+    // key each hash file by its absolute path, so runs from different working directories agree
+    fn state_key(config: &Config, hash_file: &Path) -> PathBuf {
+        hash_file
+            .canonicalize()
+            .unwrap_or_else(|_| config.pwd.join(hash_file))
+    }
+
+    // a hash file is expected to be signed when a signature exists beside it, or when dano has signed it before,
+    // so removing the signature along with tampering with the hash file does not suffice
+    fn is_expected(config: &Config, hash_file: &Path) -> DanoResult<bool> {
+        if Self::is_signed(hash_file) {
+            return Ok(true);
+        }
+
+        let state_file = StateFile::new()?;
+
+        Ok(state_file.is_signed(&Self::state_key(config, hash_file)))
+    }
+
+    // a signed hash file is only read when each of its signatures verifies
+    pub fn verify(config: &Config, hash_file: &Path) -> DanoResult<()> {
+        // a hash file piped in, or fetched, has no signature beside it
+        if is_streamed_hash_file(hash_file) && config.opt_require_signature {
+            let msg = "A hash file read from stdin, or from a remote URL, cannot be verified, and a signature is required.";
+            return Err(DanoError::new(msg).into());
+        }
+
+        if !hash_file.exists() || is_streamed_hash_file(hash_file) {
+            return Ok(());
+        }
+
+        let signatures = Self::signatures(hash_file);
+
+        if signatures.is_empty() && config.opt_require_signature {
+            let msg = format!(
+                "The hash file is not signed, and a signature is required: {:?}",
+                hash_file
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        if signatures.is_empty()
+            && !config.opt_remove_signature
+            && Self::is_expected(config, hash_file)?
+        {
+            let msg = format!(
+                "The hash file was signed, but its signature is missing, so the hash file may have been tampered with, and is not read.  \
+                Use --remove-signature, if the signature was removed deliberately: {:?}",
+                hash_file
+            );
+            return Err(DanoError::new(&msg).into());
+        }
+
+        signatures
+            .iter()
+            .try_for_each(|tool| tool.verify(config, hash_file))
+    }
+
+    // once written, the hash file is signed again, when the user asks, else, given --remove-signature,
+    // its stale signatures are removed, because a signature which no longer verifies would refuse every later read
+    pub fn exec(self, config: &Config) -> DanoResult<()> {
+        let hash_file = &config.output_file;
+
+        let opt_state = Self::file_state(hash_file);
+
+        if opt_state.is_none() {
+            return Ok(());
+        }
+
+        let is_written = opt_state != self.opt_state;
+
+        let stale: Vec<SigningTool> = if is_written {
+            Self::signatures(hash_file)
+        } else {
+            Vec::new()
+        };
+
+        match config.opt_sign {
+            Some(tool) if is_written || !tool.signature_path(hash_file).exists() => {
+                if config.opt_dry_run {
+                    return print_err_buf(&format!(
+                        "WARN: Not signing hash file (because dry run was specified): {:?}\n",
+                        hash_file
+                    ));
+                }
+
+                tool.sign(config, hash_file)?;
+
+                let mut state_file = StateFile::new()?;
+                state_file.insert_signed(&Self::state_key(config, hash_file));
+                state_file.write()?;
+
+                stale
+                    .into_iter()
+                    .filter(|other| *other != tool)
+                    .try_for_each(|other| Self::remove_stale(config, hash_file, other))
+            }
+            Some(_) => Ok(()),
+            None => {
+                stale
+                    .into_iter()
+                    .try_for_each(|tool| Self::remove_stale(config, hash_file, tool))?;
+
+                if !config.opt_remove_signature || config.opt_dry_run || Self::is_signed(hash_file)
+                {
+                    return Ok(());
+                }
+
+                let mut state_file = StateFile::new()?;
+                let state_key = Self::state_key(config, hash_file);
+
+                if state_file.is_signed(&state_key) {
+                    state_file.remove_signed(&state_key);
+                    state_file.write()?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn remove_stale(config: &Config, hash_file: &Path, tool: SigningTool) -> DanoResult<()> {
+        std::fs::remove_file(tool.signature_path(hash_file))?;
+
+        if config.opt_silent {
+            return Ok(());
+        }
+
+        print_err_buf(&format!(
+            "The hash file was written, so its {} signature no longer verifies, and was removed: {:?}\n",
+            tool.name(),
+            hash_file
+        ))
+    }
+}
@@ -0,0 +1,61 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{fs::File, io::Read, path::Path};
+
+use infer::MatcherType;
+
+// enough of the head of a file to recognize any of the formats infer knows
+const SNIFF_LEN: u64 = 8192;
+
+pub enum SniffedContent {
+    Media,
+    NotMedia(&'static str),
+    Unknown,
+}
+
+pub struct ContentSniffer;
+
+impl ContentSniffer {
+    // recognizes a file by its magic bytes, rather than by its extension
+    pub fn sniff(path: &Path) -> SniffedContent {
+        let mut buf = Vec::new();
+
+        let Ok(file) = File::open(path) else {
+            return SniffedContent::Unknown;
+        };
+
+        if file.take(SNIFF_LEN).read_to_end(&mut buf).is_err() {
+            return SniffedContent::Unknown;
+        }
+
+        match infer::get(&buf) {
+            Some(kind) => match kind.matcher_type() {
+                MatcherType::Audio | MatcherType::Video | MatcherType::Image => {
+                    SniffedContent::Media
+                }
+                MatcherType::App
+                | MatcherType::Archive
+                | MatcherType::Book
+                | MatcherType::Doc
+                | MatcherType::Font
+                | MatcherType::Text => SniffedContent::NotMedia(kind.mime_type()),
+                MatcherType::Custom => SniffedContent::Unknown,
+            },
+            None => SniffedContent::Unknown,
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use crate::lookup::FileMetadata;
+use crate::tree::TreeHashes;
+use crate::utility::{deserialize, serialize, INVOKED_FROM_PREFIX};
+use crate::DanoResult;
+
+// the hash file, as rewritten, made the same for the same records, whatever order they were written in,
+// and wherever, and whenever, they were written, so that, kept in git, it changes only where a record changes
+pub struct StableOutput;
+
+impl StableOutput {
+    pub fn exec(tmp_file: &Path) -> DanoResult<()> {
+        let contents = std::fs::read_to_string(tmp_file)?;
+
+        std::fs::write(tmp_file, Self::stabilize(&contents)?).map_err(|err| err.into())
+    }
+
+    // the header is kept, in the order first written, but each header line only once, and without the directory
+    // dano was invoked from.  the times a record was written, and checked, and the modify time of its file, are
+    // dropped.  records are sorted by path, then tree hashes, and lines this version cannot read are carried over,
+    // last, as they are
+    fn stabilize(contents: &str) -> DanoResult<String> {
+        let mut header: Vec<&str> = Vec::new();
+        let mut records: Vec<(PathBuf, String)> = Vec::new();
+        let mut tree_hashes: Vec<(PathBuf, String)> = Vec::new();
+        let mut unreadable: Vec<String> = Vec::new();
+
+        contents.lines().try_for_each(|line| -> DanoResult<()> {
+            if line.starts_with(INVOKED_FROM_PREFIX) {
+                // recorded paths are then relative to the directory of the hash file, wherever it is
+            } else if line.starts_with("//") {
+                if !header.contains(&line) {
+                    header.push(line);
+                }
+            } else if let Ok(mut file_info) = deserialize(line) {
+                if let Some(metadata) = file_info.metadata.as_mut() {
+                    *metadata = FileMetadata {
+                        last_written: UNIX_EPOCH,
+                        modify_time: UNIX_EPOCH,
+                        opt_last_checked: None,
+                        ..metadata.clone()
+                    };
+                }
+
+                records.push((file_info.path.clone(), serialize(&file_info)?));
+            } else if let Some(stable) = TreeHashes::stable_line(line) {
+                tree_hashes.push(stable);
+            } else if !line.is_empty() {
+                unreadable.push(format!("{}\n", line));
+            }
+
+            Ok(())
+        })?;
+
+        records.sort();
+        records.dedup();
+        tree_hashes.sort();
+        tree_hashes.dedup();
+
+        let mut buffer = String::new();
+
+        header.iter().for_each(|line| {
+            buffer.push_str(line);
+            buffer.push('\n');
+        });
+
+        records
+            .into_iter()
+            .chain(tree_hashes)
+            .map(|(_path, line)| line)
+            .chain(unreadable)
+            .for_each(|line| buffer.push_str(&line));
+
+        Ok(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(path: &str, hash_value: &str, secs: u64) -> String {
+        format!(
+            "{{\"version\":6,\"path\":\"{}\",\"metadata\":{{\"hash_algo\":\"murmur3\",\"hash_value\":{{\"radix\":16,\"value\":\"{}\"}},\
+            \"last_written\":{{\"secs_since_epoch\":{},\"nanos_since_epoch\":0}},\"modify_time\":{{\"secs_since_epoch\":{},\"nanos_since_epoch\":0}},\
+            \"decoded\":false,\"selected_streams\":\"All\",\"opt_bits_per_second\":null,\"opt_media_info\":null,\"hardlinked\":false,\
+            \"fallback\":false,\"opt_video_format\":null,\"native\":false,\"opt_last_checked\":{{\"secs_since_epoch\":{},\"nanos_since_epoch\":0}}}}}}\n",
+            path, hash_value, secs, secs, secs
+        )
+    }
+
+    // the same records, written from different directories, at different times, and in a different order
+    #[test]
+    fn same_records_from_different_dirs_are_byte_identical() {
+        let first = format!(
+            "{}\"/home/alice/music\"\n{}{}",
+            INVOKED_FROM_PREFIX,
+            record("album/01.flac", "ab12", 1_700_000_000),
+            record("album/02.flac", "cd34", 1_700_000_100),
+        );
+        let second = format!(
+            "{}\"/mnt/backup/music\"\n{}{}",
+            INVOKED_FROM_PREFIX,
+            record("album/02.flac", "cd34", 1_800_000_000),
+            record("album/01.flac", "ab12", 1_800_000_100),
+        );
+
+        let first = StableOutput::stabilize(&first).unwrap();
+        let second = StableOutput::stabilize(&second).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first.lines().count(), 2);
+        assert!(!first.contains(INVOKED_FROM_PREFIX));
+    }
+}
@@ -0,0 +1,141 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::schedule::CatalogState;
+use crate::utility::make_tmp_file;
+use crate::{DanoError, DanoResult};
+
+const STATE_FILE_NAME: &str = "state.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedEntry {
+    len: u64,
+    modify_time: SystemTime,
+}
+
+impl UnsupportedEntry {
+    fn new(path: &Path) -> Option<Self> {
+        let metadata = path.metadata().ok()?;
+
+        Some(Self {
+            len: metadata.len(),
+            modify_time: metadata.modified().ok()?,
+        })
+    }
+}
+
+// the state file records information which must persist between runs, but is not itself
+// file info, such as the verification schedule, which files ffmpeg could not hash, and which hash files were signed
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct State {
+    #[serde(default)]
+    pub catalogs: BTreeMap<PathBuf, CatalogState>,
+    #[serde(default)]
+    pub unsupported: BTreeMap<PathBuf, UnsupportedEntry>,
+    #[serde(default)]
+    pub signed: BTreeSet<PathBuf>,
+}
+
+pub struct StateFile {
+    path: PathBuf,
+    pub state: State,
+}
+
+impl StateFile {
+    fn default_path() -> Option<PathBuf> {
+        let state_home = match std::env::var_os("XDG_STATE_HOME") {
+            Some(state_home) if !state_home.is_empty() => PathBuf::from(state_home),
+            // Windows has no HOME, but has a per user application data directory
+            #[cfg(windows)]
+            _ => PathBuf::from(std::env::var_os("LOCALAPPDATA")?),
+            #[cfg(not(windows))]
+            _ => PathBuf::from(std::env::var_os("HOME")?)
+                .join(".local")
+                .join("state"),
+        };
+
+        Some(state_home.join("dano").join(STATE_FILE_NAME))
+    }
+
+    pub fn new() -> DanoResult<Self> {
+        let path = Self::default_path().ok_or_else(|| {
+            DanoError::new("Could not determine a location for the dano state file.")
+        })?;
+
+        let state: State = if path.exists() {
+            let contents = std::fs::read_to_string(&path)?;
+
+            serde_json::from_str(&contents).map_err(|err| {
+                let msg = format!("Could not parse state file {:?}: {}", path, err);
+                DanoError::new(&msg)
+            })?
+        } else {
+            State::default()
+        };
+
+        Ok(Self { path, state })
+    }
+
+    pub fn write(&self) -> DanoResult<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let tmp_file = make_tmp_file(&self.path);
+        std::fs::write(&tmp_file, serde_json::to_string_pretty(&self.state)?)?;
+        std::fs::rename(&tmp_file, &self.path).map_err(|err| err.into())
+    }
+
+    // a file is skipped as unsupported only until its size or modify time changes
+    pub fn is_unsupported(&self, path: &Path) -> bool {
+        match self.state.unsupported.get(path) {
+            Some(entry) => UnsupportedEntry::new(path).as_ref() == Some(entry),
+            None => false,
+        }
+    }
+
+    pub fn insert_unsupported(&mut self, path: &Path) {
+        if let Some(entry) = UnsupportedEntry::new(path) {
+            self.state.unsupported.insert(path.to_owned(), entry);
+        }
+    }
+
+    pub fn remove_unsupported(&mut self, path: &Path) {
+        self.state.unsupported.remove(path);
+    }
+
+    // a hash file, once signed, must remain signed, so a signature which has gone missing is noticed
+    pub fn is_signed(&self, hash_file: &Path) -> bool {
+        self.state.signed.contains(hash_file)
+    }
+
+    pub fn insert_signed(&mut self, hash_file: &Path) {
+        self.state.signed.insert(hash_file.to_owned());
+    }
+
+    pub fn remove_signed(&mut self, hash_file: &Path) {
+        self.state.signed.remove(hash_file);
+    }
+}
@@ -0,0 +1,224 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+use rayon::prelude::*;
+
+use crate::config::StatusConfig;
+use crate::lookup::FileMetadata;
+use crate::schedule::Schedule;
+use crate::sidecar::Sidecar;
+use crate::utility::{
+    canonicalize, deserialize, format_time, get_dano_xattr, hash_file_exists, invoked_from,
+    print_out_buf, read_hash_file, resolve_path,
+};
+use crate::{Config, DanoResult, DANO_CLEAN_EXIT_CODE, DANO_DISORDER_EXIT_CODE};
+
+// a quick summary of a directory, for a shell prompt or a pre-backup check, which is built
+// only from the records, in the hash file and extended attributes, and from file metadata
+pub struct DirStatus {
+    dir: PathBuf,
+    num_recorded: usize,
+    num_modified: usize,
+    num_new: usize,
+    num_missing: usize,
+    opt_last_verified: Option<SystemTime>,
+    opt_stalest_checked: Option<SystemTime>,
+}
+
+impl DirStatus {
+    pub fn exec(config: &Config, status_config: &StatusConfig) -> DanoResult<i32> {
+        let status = Self::new(config, status_config)?;
+
+        status.print(config)?;
+
+        if status.num_modified + status.num_new + status.num_missing == 0 {
+            Ok(DANO_CLEAN_EXIT_CODE)
+        } else {
+            Ok(DANO_DISORDER_EXIT_CODE)
+        }
+    }
+
+    fn new(config: &Config, status_config: &StatusConfig) -> DanoResult<Self> {
+        let dir = canonicalize(&status_config.dir)?;
+
+        // the records of each path, a path may be recorded more than once, such as both decoded and copied
+        let mut recorded = Self::from_hash_file(config, &dir)?;
+
+        let present: Vec<(PathBuf, Option<SystemTime>)> = config
+            .paths
+            .par_iter()
+            .map(|path| {
+                let relative = path.strip_prefix(&status_config.dir).unwrap_or(path);
+                let absolute = dir.join(relative);
+                let opt_modify_time = path
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok();
+
+                (absolute, opt_modify_time)
+            })
+            .collect();
+
+        present.iter().for_each(|(absolute, _opt_modify_time)| {
+            if let Some(metadata) = Self::from_non_file(absolute) {
+                recorded.entry(absolute.clone()).or_default().push(metadata);
+            }
+        });
+
+        let num_recorded = present
+            .iter()
+            .filter(|(absolute, _opt_modify_time)| recorded.contains_key(absolute))
+            .count();
+
+        let num_modified = present
+            .iter()
+            .filter(|(absolute, opt_modify_time)| match recorded.get(absolute) {
+                Some(forms) => !matches!(opt_modify_time, Some(modify_time)
+                    if forms.iter().any(|metadata| &metadata.modify_time == modify_time)),
+                None => false,
+            })
+            .count();
+
+        let present_paths: BTreeSet<&PathBuf> = present
+            .iter()
+            .map(|(absolute, _opt_modify_time)| absolute)
+            .collect();
+
+        // recorded paths need not be media files, so a path not walked may yet exist
+        let num_missing = recorded
+            .keys()
+            .filter(|path| !present_paths.contains(path) && !path.exists())
+            .count();
+
+        let opt_last_verified = Schedule::new(config)
+            .ok()
+            .and_then(|schedule| schedule.last_verified());
+
+        // how stale is the least recently checked of the present files
+        let opt_stalest_checked = present
+            .iter()
+            .filter_map(|(absolute, _opt_modify_time)| {
+                recorded
+                    .get(absolute)?
+                    .iter()
+                    .map(|metadata| metadata.last_checked())
+                    .max()
+            })
+            .min();
+
+        Ok(Self {
+            dir,
+            num_recorded,
+            num_modified,
+            num_new: present.len() - num_recorded,
+            num_missing,
+            opt_last_verified,
+            opt_stalest_checked,
+        })
+    }
+
+    // relative paths in the hash file are resolved against the directory the hash file was written from
+    pub fn from_hash_file(
+        config: &Config,
+        dir: &Path,
+    ) -> DanoResult<BTreeMap<PathBuf, Vec<FileMetadata>>> {
+        let mut recorded: BTreeMap<PathBuf, Vec<FileMetadata>> = BTreeMap::new();
+
+        if !hash_file_exists(&config.hash_file) {
+            return Ok(recorded);
+        }
+
+        let contents = read_hash_file(&config.hash_file)?;
+
+        let header: Vec<&str> = contents
+            .lines()
+            .take_while(|line| line.starts_with("//"))
+            .collect();
+
+        let base_dir = invoked_from(&header).unwrap_or_else(|| config.pwd.clone());
+
+        contents
+            .lines()
+            .flat_map(deserialize)
+            .filter_map(|file_info| {
+                let path = Self::normalize(&resolve_path(&base_dir, &file_info.path));
+                Some((path, file_info.metadata?))
+            })
+            .filter(|(path, _metadata)| path.starts_with(dir))
+            .for_each(|(path, metadata)| {
+                recorded.entry(path).or_default().push(metadata);
+            });
+
+        Ok(recorded)
+    }
+
+    // the record stored with the file, in an extended attribute, or else in a sidecar
+    pub fn from_non_file(path: &Path) -> Option<FileMetadata> {
+        let from_xattr = || {
+            let bytes = get_dano_xattr(path).ok()??;
+            let line = std::str::from_utf8(&bytes).ok()?;
+
+            deserialize(line).ok()?.metadata
+        };
+
+        from_xattr().or_else(|| Sidecar::read(path)?.metadata)
+    }
+
+    // without touching the disk, as a recorded path may no longer exist
+    pub fn normalize(path: &Path) -> PathBuf {
+        path.components()
+            .fold(PathBuf::new(), |mut acc, component| {
+                match component {
+                    Component::CurDir => {}
+                    Component::ParentDir => {
+                        acc.pop();
+                    }
+                    component => acc.push(component),
+                }
+                acc
+            })
+    }
+
+    fn print(&self, config: &Config) -> DanoResult<()> {
+        let last_verified = match self.opt_last_verified {
+            Some(last_verified) => format!("{} UTC", format_time(last_verified)),
+            None => "never".to_owned(),
+        };
+
+        let stalest_checked = match self.opt_stalest_checked {
+            Some(stalest_checked) => format!("{} UTC", format_time(stalest_checked)),
+            None => "none".to_owned(),
+        };
+
+        print_out_buf(&format!(
+            "Status of {:?}, against hash file {:?}:\nRecorded: {} files ({} modified since being recorded)\nNew: {} files\nMissing: {} files\nLast verified: {}\nStalest check: {}\n",
+            self.dir,
+            config.hash_file,
+            self.num_recorded,
+            self.num_modified,
+            self.num_new,
+            self.num_missing,
+            last_verified,
+            stalest_checked
+        ))
+    }
+}
@@ -0,0 +1,123 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use serde::{Deserialize, Serialize};
+
+use crate::hasher::NativeHasher;
+use crate::{DanoError, DanoResult};
+
+// the hash of a single stream of a container, from ffmpeg's streamhash muxer
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StreamHash {
+    pub index: u32,
+    pub codec_type: Box<str>,
+    pub hash_value: Box<str>,
+}
+
+pub struct StreamHashes;
+
+impl StreamHashes {
+    // streamhash output is one line per stream, like "0,v,MD5=7d3c...", the stream index, the codec type,
+    // and the hash.  returns a digest of every stream hash, in the form of ffmpeg's hash muxer output, "ALGO=hex",
+    // and the stream hashes
+    pub fn from_streamhash_output(
+        stdout: &str,
+        hash_algo: &str,
+    ) -> DanoResult<(String, Vec<StreamHash>)> {
+        let mut whole = NativeHasher::new(hash_algo).map_err(|_| {
+            let msg = format!(
+                "Stream hashing requires a hash algorithm dano implements (crc32, murmur3, md5, sha1, sha256, sha512), not: {}",
+                hash_algo
+            );
+            DanoError::new(&msg)
+        })?;
+        let algo_name = whole.name();
+
+        let stream_hashes = stdout
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let parse_err = || {
+                    let msg = format!("Could not parse ffmpeg streamhash output: {:?}", line);
+                    DanoError::new(&msg)
+                };
+
+                let mut fields = line.splitn(3, ',');
+
+                let (Some(index), Some(codec_type), Some(hash)) =
+                    (fields.next(), fields.next(), fields.next())
+                else {
+                    return Err(parse_err().into());
+                };
+
+                let index = index.trim().parse::<u32>().map_err(|_| parse_err())?;
+                let (_algo, hash_value) = hash.trim().split_once('=').ok_or_else(parse_err)?;
+
+                whole.update(format!("{},{}\n", index, hash_value).as_bytes());
+
+                Ok(StreamHash {
+                    index,
+                    codec_type: Self::codec_type_name(codec_type.trim()).into(),
+                    hash_value: hash_value.into(),
+                })
+            })
+            .collect::<DanoResult<Vec<StreamHash>>>()?;
+
+        if stream_hashes.is_empty() {
+            return Err(DanoError::new("ffmpeg streamhash output contained no streams.").into());
+        }
+
+        let hash_line = format!("{}={}", algo_name, whole.finalize_hex());
+
+        Ok((hash_line, stream_hashes))
+    }
+
+    fn codec_type_name(codec_type: &str) -> &str {
+        match codec_type {
+            "a" => "audio",
+            "v" => "video",
+            "s" => "subtitle",
+            "d" => "data",
+            "t" => "attachment",
+            other => other,
+        }
+    }
+
+    // which streams of a mismatched file differ from the record, like "stream 1 (audio)"
+    pub fn describe_mismatch(current: &[StreamHash], recorded: &[StreamHash]) -> Vec<String> {
+        let changed = current.iter().filter_map(|stream| {
+            match recorded.iter().find(|other| other.index == stream.index) {
+                Some(other) if other == stream => None,
+                Some(_) => Some(format!(
+                    "stream {} ({}) has changed",
+                    stream.index, stream.codec_type
+                )),
+                None => Some(format!(
+                    "stream {} ({}) is new",
+                    stream.index, stream.codec_type
+                )),
+            }
+        });
+
+        let missing = recorded
+            .iter()
+            .filter(|stream| !current.iter().any(|other| other.index == stream.index))
+            .map(|stream| format!("stream {} ({}) is missing", stream.index, stream.codec_type));
+
+        changed.chain(missing).collect()
+    }
+}
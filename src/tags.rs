@@ -0,0 +1,40 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::path::Path;
+
+use crate::hasher::NativeHasher;
+use crate::probe::FFProbe;
+use crate::DanoResult;
+
+const TAGS_HASH_ALGO: &str = "murmur3";
+const TAGS_ENTRIES: &str = "format_tags:stream_tags";
+
+// a hash of the container's and each stream's tags, apart from the hash of the streams,
+// so a test can tell a tag only edit from a change to the streams themselves
+pub struct TagsHash;
+
+impl TagsHash {
+    pub fn generate(path: &Path) -> DanoResult<Box<str>> {
+        let root = FFProbe::show_entries(path, TAGS_ENTRIES)?;
+
+        // JSON objects are ordered by key, so the same tags always serialize the same way
+        let mut hasher = NativeHasher::new(TAGS_HASH_ALGO)?;
+        hasher.update(serde_json::to_string(&root)?.as_bytes());
+
+        Ok(hasher.finalize_hex().into())
+    }
+}
@@ -0,0 +1,507 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::TreeHashModeConfig;
+use crate::hasher::NativeHasher;
+use crate::ingest::RecordedFileInfo;
+use crate::lookup::FileInfo;
+use crate::output::WriteType;
+use crate::utility::{
+    check_free_space, get_output_file, get_tmp_file, hash_file_exists, persist_hash_file,
+    print_err_buf, print_out_buf, print_outcome, read_hash_file, write_line,
+};
+use crate::{
+    Config, DanoError, DanoResult, DANO_CLEAN_EXIT_CODE, DANO_DISORDER_EXIT_CODE,
+    DANO_FILE_INFO_VERSION,
+};
+
+// the directory from which dano was invoked is recorded as "." rather than as an empty path
+const CURRENT_DIR: &str = ".";
+
+// the tree hash of each directory, and the count of files beneath it
+type Trees = BTreeMap<PathBuf, (Box<str>, usize)>;
+
+// the aggregate hash of a directory, recorded in the hash file, on a line of its own, beside the records of its files
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TreeHash {
+    #[serde(with = "crate::serde_path")]
+    pub path: PathBuf,
+    pub hash_algo: Box<str>,
+    pub hash_value: Box<str>,
+    pub num_files: usize,
+    pub last_written: SystemTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct TreeHashRecord {
+    version: usize,
+    tree: TreeHash,
+}
+
+// a directory's tree hash is the hash of the sorted hashes of its files, and of the tree hashes of its subdirectories,
+// so a change to any file beneath a directory changes the tree hash of the directory, and of each directory above it.
+// names are not hashed, so, like the hash of a file, a tree hash survives renaming, and two copies of an album,
+// with the same streams, have the same tree hash, however their files are named
+pub struct TreeHashes;
+
+impl TreeHashes {
+    pub fn exec(
+        config: &Config,
+        tree_hash_config: &TreeHashModeConfig,
+        recorded_file_info: RecordedFileInfo,
+    ) -> DanoResult<i32> {
+        let selected_algo = NativeHasher::new(&config.selected_hash_algo)
+            .map_err(|_| {
+                let msg = format!(
+                    "Tree hashing requires a hash algorithm dano implements (crc32, murmur3, md5, sha1, sha256, sha512, blake3), not: {}",
+                    config.selected_hash_algo
+                );
+                DanoError::new(&msg)
+            })?
+            .name();
+
+        // without input files, every record in the hash file is a member of some tree
+        let input_paths: BTreeSet<&Path> = config.paths.iter().map(|path| path.as_path()).collect();
+
+        let members: Vec<&FileInfo> = recorded_file_info
+            .iter()
+            .filter(|file_info| file_info.metadata.is_some())
+            .filter(|file_info| {
+                input_paths.is_empty() || input_paths.contains(file_info.path.as_path())
+            })
+            .collect();
+
+        if members.is_empty() {
+            return Err(DanoError::new(
+                "No recorded file info is available from which to compute tree hashes.",
+            )
+            .into());
+        }
+
+        let stored = Self::read_stored(&config.hash_file)?;
+
+        // a tree hash is compared with the hash algorithm it was recorded with
+        let mut computed: BTreeMap<Box<str>, Trees> = BTreeMap::new();
+
+        let algos: BTreeSet<Box<str>> = std::iter::once(selected_algo.into())
+            .chain(stored.values().map(|tree_hash| tree_hash.hash_algo.clone()))
+            .collect();
+
+        algos.into_iter().try_for_each(|algo| -> DanoResult<()> {
+            let trees = Self::compute(&members, &algo)?;
+            computed.insert(algo, trees);
+            Ok(())
+        })?;
+
+        let mut exit_code = DANO_CLEAN_EXIT_CODE;
+        let mut updated: Vec<TreeHash> = Vec::new();
+        let timestamp = SystemTime::now();
+
+        computed[selected_algo].keys().try_for_each(|dir| -> DanoResult<()> {
+            let display_dir = Self::display_dir(dir);
+
+            let (hash_algo, (hash_value, num_files)) = match stored.get(dir) {
+                Some(tree_hash) => (
+                    tree_hash.hash_algo.clone(),
+                    computed[&tree_hash.hash_algo][dir].clone(),
+                ),
+                None => (
+                    selected_algo.into(),
+                    computed[selected_algo][dir].clone(),
+                ),
+            };
+
+            match stored.get(dir) {
+                Some(tree_hash) if tree_hash.hash_value == hash_value => {
+                    if config.opt_silent {
+                        return Ok(());
+                    }
+
+                    return print_outcome(
+                        config,
+                        display_dir,
+                        "ok",
+                        None,
+                        &format!("{:?}: OK\n", display_dir),
+                    );
+                }
+                Some(_) => {
+                    exit_code = DANO_DISORDER_EXIT_CODE;
+
+                    print_outcome(
+                        config,
+                        display_dir,
+                        "mismatch",
+                        None,
+                        &format!(
+                            "WARN: {:?}: Tree hash has changed, the records of the {} files beneath it have changed since it was recorded.\n",
+                            display_dir, num_files
+                        ),
+                    )?;
+
+                    // like a file's record, a changed tree hash is kept, until it is deliberately overwritten
+                    if !tree_hash_config.opt_overwrite_old {
+                        return Ok(());
+                    }
+                }
+                None => {
+                    print_out_buf(&format!(
+                        "{}={} : {:?}\n",
+                        hash_algo, hash_value, display_dir
+                    ))?;
+                }
+            }
+
+            updated.push(TreeHash {
+                path: display_dir.to_owned(),
+                hash_algo,
+                hash_value,
+                num_files,
+                last_written: timestamp,
+            });
+
+            Ok(())
+        })?;
+
+        // without input files, a recorded tree with no records left beneath it is gone, and its tree hash is reported,
+        // and only removed when overwritten
+        let removed: Vec<&Path> = if input_paths.is_empty() {
+            stored
+                .iter()
+                .filter(|(dir, _)| !computed[selected_algo].contains_key(dir.as_path()))
+                .map(|(_, tree_hash)| tree_hash.path.as_path())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        removed.iter().try_for_each(|dir| {
+            exit_code = DANO_DISORDER_EXIT_CODE;
+
+            print_outcome(
+                config,
+                dir,
+                "missing",
+                None,
+                &format!(
+                    "WARN: {:?}: Tree hash was recorded, but no records of files beneath it remain.\n",
+                    dir
+                ),
+            )
+        })?;
+
+        if !config.opt_silent {
+            Self::print_identical(&computed[selected_algo])?;
+        }
+
+        let removed = if tree_hash_config.opt_overwrite_old {
+            removed
+        } else {
+            Vec::new()
+        };
+
+        Self::write(config, &updated, &removed)?;
+
+        Ok(exit_code)
+    }
+
+    fn compute(members: &[&FileInfo], hash_algo: &str) -> DanoResult<Trees> {
+        let mut entries: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+
+        members.iter().for_each(|file_info| {
+            let Some(metadata) = file_info.metadata.as_ref() else {
+                return;
+            };
+
+            let form = if metadata.decoded {
+                "decoded"
+            } else {
+                "copied"
+            };
+
+            entries
+                .entry(Self::parent_dir(&file_info.path))
+                .or_default()
+                .push(format!(
+                    "file {}:{}:{}",
+                    metadata.hash_algo.to_ascii_lowercase(),
+                    form,
+                    metadata.hash_value.value
+                ));
+        });
+
+        // every directory between a member and the deepest directory common to all members is a tree
+        let common = entries.keys().skip(1).fold(
+            entries.keys().next().cloned().unwrap_or_default(),
+            |mut common, dir| {
+                while !dir.starts_with(&common) && common.pop() {}
+                common
+            },
+        );
+
+        let mut dirs: BTreeSet<PathBuf> = BTreeSet::new();
+
+        entries.keys().for_each(|dir| {
+            dir.ancestors()
+                .take_while(|ancestor| ancestor.starts_with(&common))
+                .for_each(|ancestor| {
+                    dirs.insert(ancestor.to_owned());
+                });
+        });
+
+        // the deepest directories are hashed first, so each subdirectory is hashed before its parent
+        let mut dirs: Vec<PathBuf> = dirs.into_iter().collect();
+        dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+
+        let mut trees: Trees = BTreeMap::new();
+
+        dirs.into_iter().try_for_each(|dir| -> DanoResult<()> {
+            let files = entries
+                .get(&dir)
+                .map(|lines| lines.as_slice())
+                .unwrap_or_default();
+
+            let subdirs = trees
+                .iter()
+                .filter(|(subdir, _)| subdir.parent() == Some(dir.as_path()));
+
+            let num_files = files.len() + subdirs.clone().map(|(_, (_, num))| num).sum::<usize>();
+
+            let mut lines: Vec<String> = files
+                .iter()
+                .cloned()
+                .chain(subdirs.map(|(_, (hash_value, _))| format!("dir {}", hash_value)))
+                .collect();
+            lines.sort_unstable();
+
+            let mut hasher = NativeHasher::new(hash_algo)?;
+            lines.iter().for_each(|line| {
+                hasher.update(line.as_bytes());
+                hasher.update(b"\n");
+            });
+
+            trees.insert(dir, (hasher.finalize_hex().into(), num_files));
+
+            Ok(())
+        })?;
+
+        Ok(trees)
+    }
+
+    // a relative path in the directory from which dano was invoked has an empty parent
+    fn parent_dir(path: &Path) -> PathBuf {
+        path.parent()
+            .map(|parent| parent.to_owned())
+            .unwrap_or_default()
+    }
+
+    fn display_dir(dir: &Path) -> &Path {
+        if dir.as_os_str().is_empty() {
+            Path::new(CURRENT_DIR)
+        } else {
+            dir
+        }
+    }
+
+    fn from_display_dir(dir: &Path) -> PathBuf {
+        if dir == Path::new(CURRENT_DIR) {
+            PathBuf::new()
+        } else {
+            dir.to_owned()
+        }
+    }
+
+    pub fn is_tree_hash_line(line: &str) -> bool {
+        Self::parse_line(line).is_some()
+    }
+
+    // a tree hash, written again as this version writes it, as with --stable-output
+    pub fn stable_line(line: &str) -> Option<(PathBuf, String)> {
+        let tree = Self::parse_line(line)?;
+
+        let stable = TreeHashRecord {
+            version: DANO_FILE_INFO_VERSION,
+            tree: TreeHash {
+                last_written: UNIX_EPOCH,
+                ..tree
+            },
+        };
+
+        let line = serde_json::to_string(&stable).ok()?;
+
+        Some((stable.tree.path, format!("{}\n", line)))
+    }
+
+    fn parse_line(line: &str) -> Option<TreeHash> {
+        let record: TreeHashRecord = serde_json::from_str(line).ok()?;
+
+        Some(record.tree)
+    }
+
+    // the most recently written tree hash of each directory
+    fn read_stored(hash_file: &Path) -> DanoResult<BTreeMap<PathBuf, TreeHash>> {
+        if !hash_file_exists(hash_file) {
+            return Ok(BTreeMap::new());
+        }
+
+        let contents = read_hash_file(hash_file)?;
+        let mut stored: BTreeMap<PathBuf, TreeHash> = BTreeMap::new();
+
+        contents
+            .lines()
+            .filter_map(Self::parse_line)
+            .for_each(|tree_hash| {
+                let dir = Self::from_display_dir(&tree_hash.path);
+
+                match stored.get(&dir) {
+                    Some(existing) if existing.last_written > tree_hash.last_written => (),
+                    _ => {
+                        stored.insert(dir, tree_hash);
+                    }
+                }
+            });
+
+        Ok(stored)
+    }
+
+    // the lines of a hash file which record tree hashes, which a rewrite of the file's records must carry over
+    pub fn read_lines(hash_file: &Path) -> DanoResult<Vec<String>> {
+        if !hash_file_exists(hash_file) {
+            return Ok(Vec::new());
+        }
+
+        let contents = read_hash_file(hash_file)?;
+
+        Ok(contents
+            .lines()
+            .filter(|line| Self::is_tree_hash_line(line))
+            .map(|line| line.to_owned())
+            .collect())
+    }
+
+    // directories whose files have the same streams, however they are named
+    fn print_identical(trees: &Trees) -> DanoResult<()> {
+        let mut groups: BTreeMap<&str, Vec<&Path>> = BTreeMap::new();
+
+        trees.iter().for_each(|(dir, (hash_value, _))| {
+            groups
+                .entry(hash_value.as_ref())
+                .or_default()
+                .push(Self::display_dir(dir))
+        });
+
+        groups
+            .values()
+            .filter(|dirs| dirs.len() > 1)
+            .try_for_each(|dirs| {
+                print_err_buf(&format!(
+                    "WARN: Directories have identical contents: {:?}\n",
+                    dirs
+                ))
+            })
+    }
+
+    // new and changed tree hashes replace any earlier tree hash of the same directory
+    fn write(config: &Config, updated: &[TreeHash], removed: &[&Path]) -> DanoResult<()> {
+        if updated.is_empty() && removed.is_empty() {
+            return Ok(());
+        }
+
+        if config.opt_dry_run || config.opt_read_only {
+            if !config.opt_silent {
+                updated
+                    .iter()
+                    .map(|tree_hash| tree_hash.path.as_path())
+                    .chain(removed.iter().copied())
+                    .try_for_each(|dir| {
+                        print_err_buf(&format!(
+                            "WARN: Not writing tree hash for (as writing is not permitted): {:?}\n",
+                            dir
+                        ))
+                    })?;
+            }
+
+            return Ok(());
+        }
+
+        let lines = updated
+            .iter()
+            .map(|tree_hash| {
+                serde_json::to_string(&TreeHashRecord {
+                    version: DANO_FILE_INFO_VERSION,
+                    tree: tree_hash.clone(),
+                })
+            })
+            .collect::<Result<Vec<String>, serde_json::Error>>()?;
+
+        updated.iter().try_for_each(|tree_hash| {
+            print_err_buf(&format!("Writing tree hash for: {:?}\n", tree_hash.path))
+        })?;
+
+        removed
+            .iter()
+            .try_for_each(|dir| print_err_buf(&format!("Removing tree hash for: {:?}\n", dir)))?;
+
+        if !config.output_file.exists() {
+            let mut output_file = get_output_file(config, WriteType::Append)?;
+
+            return lines
+                .iter()
+                .try_for_each(|line| write_line(line, &mut output_file));
+        }
+
+        let replaced: BTreeSet<&Path> = updated
+            .iter()
+            .map(|tree_hash| tree_hash.path.as_path())
+            .chain(removed.iter().copied())
+            .collect();
+
+        let contents = read_hash_file(&config.output_file)?;
+        let mut buffer = String::new();
+
+        contents
+            .lines()
+            .filter(|line| {
+                !matches!(Self::parse_line(line), Some(tree_hash) if replaced.contains(tree_hash.path.as_path()))
+            })
+            .chain(lines.iter().map(|line| line.as_str()))
+            .for_each(|line| {
+                buffer.push_str(line);
+                buffer.push('\n');
+            });
+
+        let tmp_file = get_tmp_file(config, &config.output_file);
+        check_free_space(&tmp_file, buffer.len() as u64)?;
+
+        let res = std::fs::write(&tmp_file, &buffer)
+            .map_err(|err| err.into())
+            .and_then(|_| persist_hash_file(config, &tmp_file, &config.output_file));
+
+        if res.is_err() {
+            let _ = std::fs::remove_file(&tmp_file);
+        }
+
+        res
+    }
+}
@@ -0,0 +1,403 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    collections::{BTreeMap, HashSet},
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::compress::HashFileCompression;
+use crate::lookup::{FileInfo, FileMetadata, HashValue};
+use crate::output::{RecordForm, WriteableFileInfo};
+use crate::utility::{
+    deserialize, format_time, get_dano_xattr, hash_file_text, make_tmp_file, print_err_buf,
+    read_file_info_from_file, remove_dano_xattr, serialize, write_non_file,
+};
+use crate::{
+    Config, DanoError, DanoResult, ExecMode, DANO_CLEAN_EXIT_CODE, DANO_DISORDER_EXIT_CODE,
+};
+
+pub const UNDO_EXTENSION: &str = "undo";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct XattrChange {
+    #[serde(with = "crate::serde_path")]
+    path: PathBuf,
+    old: Option<FileInfo>,
+    new: Option<FileInfo>,
+}
+
+// the last write operation, as the records it removed from, and added to, the hash file,
+// and the extended attributes it changed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct UndoRecord {
+    time: SystemTime,
+    removed: Vec<FileInfo>,
+    added: Vec<FileInfo>,
+    xattrs: Vec<XattrChange>,
+}
+
+// the contents of the hash file, as they were read, and the extended attributes of the input files, as absolute paths,
+// without the time of each file's last check, which is only bookkeeping
+struct Snapshot {
+    opt_contents: Option<Vec<u8>>,
+    xattrs: BTreeMap<PathBuf, Option<FileInfo>>,
+}
+
+impl Snapshot {
+    fn new(config: &Config, paths: &[PathBuf]) -> DanoResult<Self> {
+        let opt_contents = if config.output_file.exists() {
+            Some(HashFileCompression::read(&config.output_file)?)
+        } else {
+            None
+        };
+
+        let xattrs = paths
+            .par_iter()
+            .map(|path| {
+                let path = config.pwd.join(path);
+                let opt_file_info = Self::read_xattr(&path);
+
+                (path, opt_file_info)
+            })
+            .collect();
+
+        Ok(Self {
+            opt_contents,
+            xattrs,
+        })
+    }
+
+    fn text(&self, config: &Config) -> DanoResult<String> {
+        match &self.opt_contents {
+            Some(contents) => hash_file_text(&config.output_file, contents.clone()),
+            None => Ok(String::new()),
+        }
+    }
+
+    // only the lines which differ are parsed, and a record whose only change is the time of its last check is unchanged
+    fn record_changes(
+        config: &Config,
+        before: &Snapshot,
+        after: &Snapshot,
+    ) -> DanoResult<(Vec<FileInfo>, Vec<FileInfo>)> {
+        if before.opt_contents == after.opt_contents {
+            return Ok((Vec::new(), Vec::new()));
+        }
+
+        let before_text = before.text(config)?;
+        let after_text = after.text(config)?;
+
+        let before_lines: HashSet<&str> = before_text.lines().collect();
+        let after_lines: HashSet<&str> = after_text.lines().collect();
+
+        let records = |text: &str, other_lines: &HashSet<&str>| {
+            text.lines()
+                .filter(|line| !other_lines.contains(line))
+                .flat_map(deserialize)
+                .map(|file_info| {
+                    let file_info = Self::without_last_checked(file_info);
+                    Ok((serialize(&file_info)?, file_info))
+                })
+                .collect::<DanoResult<BTreeMap<String, FileInfo>>>()
+        };
+
+        let before_records = records(&before_text, &after_lines)?;
+        let after_records = records(&after_text, &before_lines)?;
+
+        let removed: Vec<FileInfo> = before_records
+            .iter()
+            .filter(|(key, _)| !after_records.contains_key(*key))
+            .map(|(_, file_info)| file_info.clone())
+            .collect();
+
+        let added: Vec<FileInfo> = after_records
+            .into_iter()
+            .filter(|(key, _)| !before_records.contains_key(key))
+            .map(|(_, file_info)| file_info)
+            .collect();
+
+        Ok((removed, added))
+    }
+
+    // errors are reported when the extended attributes are read as records, and not again here
+    fn read_xattr(path: &Path) -> Option<FileInfo> {
+        let bytes = get_dano_xattr(path).ok()??;
+        let file_info = deserialize(std::str::from_utf8(&bytes).ok()?).ok()?;
+
+        Some(Self::without_last_checked(FileInfo {
+            path: path.to_owned(),
+            ..file_info
+        }))
+    }
+
+    fn without_last_checked(file_info: FileInfo) -> FileInfo {
+        FileInfo {
+            metadata: file_info.metadata.map(|metadata| FileMetadata {
+                opt_last_checked: None,
+                ..metadata
+            }),
+            ..file_info
+        }
+    }
+}
+
+// records match when of the same path, hashed in the same form, with the same hash value, as compared by is_same_record
+type RecordKey = (RecordForm, HashValue);
+
+fn record_key(file_info: &FileInfo) -> Option<RecordKey> {
+    let hash_value = file_info.metadata.as_ref()?.hash_value.clone();
+
+    Some((RecordForm::of(file_info), hash_value))
+}
+
+// the records a run removed from, and added to, the hash file
+#[derive(Default)]
+pub struct RecordChanges {
+    pub removed: Vec<FileInfo>,
+    pub added: Vec<FileInfo>,
+}
+
+// before a run which may write the hash file, or extended attributes, its state is noted, and, once the run
+// has changed either, the changes are recorded next to the hash file, replacing the changes of the last such run
+pub struct UndoJournal {
+    path: PathBuf,
+    opt_before: Option<Snapshot>,
+}
+
+impl UndoJournal {
+    pub fn new(config: &Config, may_write: bool) -> DanoResult<Self> {
+        let path = Self::journal_path(config);
+
+        if !may_write || config.opt_dry_run {
+            return Ok(Self {
+                path,
+                opt_before: None,
+            });
+        }
+
+        // an undo changes the extended attributes of the paths of the last write, and is itself recorded, so a second undo redoes the write
+        let mut paths = config.paths.clone();
+
+        if matches!(config.exec_mode, ExecMode::Undo) {
+            if let Ok(undo_record) = Self::read(&path) {
+                paths.extend(undo_record.xattrs.into_iter().map(|change| change.path));
+            }
+        }
+
+        Ok(Self {
+            path,
+            opt_before: Some(Snapshot::new(config, &paths)?),
+        })
+    }
+
+    fn journal_path(config: &Config) -> PathBuf {
+        let mut path: OsString = config.output_file.as_os_str().to_owned();
+        path.push(".");
+        path.push(UNDO_EXTENSION);
+
+        PathBuf::from(path)
+    }
+
+    fn read(path: &Path) -> DanoResult<UndoRecord> {
+        let contents = std::fs::read_to_string(path)?;
+
+        serde_json::from_str(&contents).map_err(|err| err.into())
+    }
+
+    // a run which changed nothing leaves the record of the last write which did
+    pub fn exec(self, config: &Config) -> DanoResult<RecordChanges> {
+        let Some(before) = self.opt_before else {
+            return Ok(RecordChanges::default());
+        };
+
+        let paths: Vec<PathBuf> = before.xattrs.keys().cloned().collect();
+        let after = Snapshot::new(config, &paths)?;
+
+        let (removed, added) = Snapshot::record_changes(config, &before, &after)?;
+
+        let xattrs: Vec<XattrChange> = before
+            .xattrs
+            .into_iter()
+            .filter_map(|(path, old)| {
+                let new = after.xattrs.get(&path)?.clone();
+                (old != new).then_some(XattrChange { path, old, new })
+            })
+            .collect();
+
+        if removed.is_empty() && added.is_empty() && xattrs.is_empty() {
+            return Ok(RecordChanges::default());
+        }
+
+        let undo_record = UndoRecord {
+            time: SystemTime::now(),
+            removed,
+            added,
+            xattrs,
+        };
+
+        let tmp_file = make_tmp_file(&self.path);
+
+        std::fs::write(&tmp_file, serde_json::to_string(&undo_record)?)?;
+        std::fs::rename(&tmp_file, &self.path)?;
+
+        Ok(RecordChanges {
+            removed: undo_record.removed,
+            added: undo_record.added,
+        })
+    }
+
+    // only the changes of the last write are undone, and a record, or an extended attribute, which has changed since
+    // is left as it is
+    pub fn undo(config: &Config) -> DanoResult<i32> {
+        let path = Self::journal_path(config);
+
+        if !path.exists() {
+            let msg = format!("No record of a write operation to undo exists: {:?}", path);
+            return Err(DanoError::new(&msg).into());
+        }
+
+        let undo_record = Self::read(&path)?;
+
+        if !config.opt_silent {
+            print_err_buf(&format!(
+                "Undoing the write operation of {} UTC.\n",
+                format_time(undo_record.time)
+            ))?;
+        }
+
+        let (num_removed, num_restored) = Self::undo_records(config, &undo_record)?;
+        let (num_xattrs, num_changed) = Self::undo_xattrs(config, &undo_record)?;
+
+        if !config.opt_silent {
+            print_err_buf(&format!(
+                "{} {} records from, and {} {} records to, the hash file, and {} {} extended attributes.\n",
+                if config.opt_dry_run { "Would remove" } else { "Removed" },
+                num_removed,
+                if config.opt_dry_run { "would restore" } else { "restored" },
+                num_restored,
+                if config.opt_dry_run { "would restore" } else { "restored" },
+                num_xattrs
+            ))?;
+        }
+
+        if num_changed > 0 {
+            return Ok(DANO_DISORDER_EXIT_CODE);
+        }
+
+        Ok(DANO_CLEAN_EXIT_CODE)
+    }
+
+    fn undo_records(config: &Config, undo_record: &UndoRecord) -> DanoResult<(usize, usize)> {
+        if undo_record.removed.is_empty() && undo_record.added.is_empty() {
+            return Ok((0, 0));
+        }
+
+        let current: Vec<FileInfo> = if config.output_file.exists() {
+            read_file_info_from_file(config)?
+        } else {
+            Vec::new()
+        };
+
+        let added: HashSet<RecordKey> = undo_record.added.iter().flat_map(record_key).collect();
+
+        let (kept, dropped): (Vec<FileInfo>, Vec<FileInfo>) = current.into_iter().partition(
+            |recorded| !matches!(record_key(recorded), Some(key) if added.contains(&key)),
+        );
+
+        let kept_keys: HashSet<RecordKey> = kept.iter().flat_map(record_key).collect();
+
+        let restored: Vec<FileInfo> = undo_record
+            .removed
+            .iter()
+            .filter(|removed| !matches!(record_key(removed), Some(key) if kept_keys.contains(&key)))
+            .cloned()
+            .collect();
+
+        let (remove_prefix, restore_prefix) = if config.opt_dry_run {
+            ("Would remove record for: ", "Would restore record for: ")
+        } else {
+            ("Removing record for: ", "Restoring record for: ")
+        };
+
+        if !config.opt_silent {
+            dropped.iter().try_for_each(|file_info| {
+                print_err_buf(&format!("{}{:?}\n", remove_prefix, file_info.path))
+            })?;
+
+            restored.iter().try_for_each(|file_info| {
+                print_err_buf(&format!("{}{:?}\n", restore_prefix, file_info.path))
+            })?;
+        }
+
+        let num_removed = dropped.len();
+        let num_restored = restored.len();
+
+        if !config.opt_dry_run && num_removed + num_restored > 0 {
+            let records: Vec<FileInfo> = kept.into_iter().chain(restored).collect();
+            WriteableFileInfo::from(records).rewrite(config)?;
+        }
+
+        Ok((num_removed, num_restored))
+    }
+
+    // returns the number of extended attributes restored, and the number left as they are, because each has changed since
+    fn undo_xattrs(config: &Config, undo_record: &UndoRecord) -> DanoResult<(usize, usize)> {
+        let mut num_restored = 0usize;
+        let mut num_changed = 0usize;
+
+        undo_record.xattrs.iter().try_for_each(|change| {
+            if Snapshot::read_xattr(&change.path) != change.new {
+                num_changed += 1;
+
+                return print_err_buf(&format!(
+                    "WARN: Not restoring extended attribute, which has changed since the write operation: {:?}\n",
+                    change.path
+                ));
+            }
+
+            num_restored += 1;
+
+            if config.opt_dry_run {
+                return print_err_buf(&format!(
+                    "Would restore extended attribute for: {:?}\n",
+                    change.path
+                ));
+            }
+
+            match &change.old {
+                Some(old) => write_non_file(old)?,
+                None => remove_dano_xattr(&change.path)?,
+            }
+
+            if config.opt_silent {
+                return Ok(());
+            }
+
+            print_err_buf(&format!(
+                "Restored extended attribute for: {:?}\n",
+                change.path
+            ))
+        })?;
+
+        Ok((num_restored, num_changed))
+    }
+}
@@ -17,19 +17,38 @@
 
 use std::{
     error::Error,
+    ffi::OsStr,
     fmt,
     fs::{File, OpenOptions},
-    io::{Read, Write},
+    io::{ErrorKind, Read, Write},
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use rayon::{prelude::*, ThreadPool};
+use serde::Serialize;
 use serde_json::Value;
 
-use crate::lookup::FileInfo;
+use crate::backup::HashFileBackup;
+use crate::binary::{BinaryHashFile, HashFileFormat};
+use crate::compress::HashFileCompression;
+use crate::config::PorcelainFormat;
+use crate::footer::HashFileFooter;
+use crate::lookup::{FileInfo, FileMetadata};
 use crate::output::WriteType;
+use crate::progress::clear_progress_line;
+use crate::remote::RemoteHashFile;
+use crate::stable::StableOutput;
 use crate::versions::LegacyVersion;
-use crate::{Config, ExecMode, DANO_FILE_INFO_VERSION, DANO_XATTR_KEY_NAME};
+#[cfg(windows)]
+use crate::DANO_ADS_STREAM_NAME;
+#[cfg(not(windows))]
+use crate::DANO_XATTR_KEY_NAME;
+use crate::{Config, ExecMode, DANO_FILE_INFO_VERSION};
 
 pub type DanoResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
@@ -37,6 +56,7 @@ pub type DanoResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
 // this is one of those things one can't make a const function
 const HASH_VALUE_MIN_WIDTH: usize = 32;
 const TMP_SUFFIX: &str = ".tmp";
+pub const INVOKED_FROM_PREFIX: &str = "// DANO, Invoked from: ";
 
 pub fn prepare_thread_pool(config: &Config) -> DanoResult<ThreadPool> {
     let num_threads = if let Some(num_threads) = config.opt_num_threads {
@@ -91,6 +111,119 @@ pub fn make_tmp_file(path: &Path) -> PathBuf {
     PathBuf::from(res)
 }
 
+// the rewrite copy of a file is placed next to the file, unless a tmp dir is specified
+pub fn get_tmp_file(config: &Config, path: &Path) -> PathBuf {
+    match (&config.opt_tmp_dir, path.file_name()) {
+        (Some(tmp_dir), Some(file_name)) => make_tmp_file(&tmp_dir.join(file_name)),
+        _ => make_tmp_file(path),
+    }
+}
+
+// a rename across filesystems is not possible, so then the tmp file is copied over the file instead
+pub fn persist_tmp_file(tmp_file: &Path, path: &Path) -> DanoResult<()> {
+    match std::fs::rename(tmp_file, path) {
+        Ok(_) => Ok(()),
+        Err(err) if err.kind() == ErrorKind::CrossesDevices => {
+            std::fs::copy(tmp_file, path)?;
+            std::fs::remove_file(tmp_file).map_err(|err| err.into())
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+// a rewrite of the hash file is sealed with a footer, and the hash file it replaces is kept as a backup
+pub fn persist_hash_file(config: &Config, tmp_file: &Path, path: &Path) -> DanoResult<()> {
+    if config.opt_stable_output {
+        StableOutput::exec(tmp_file)?;
+    }
+
+    // a binary hash file is sealed with a footer of its encoded entries, rather than of the text it was encoded from
+    if HashFileFormat::for_rewrite(config, path) == HashFileFormat::Binary {
+        BinaryHashFile::encode(tmp_file)?;
+    } else {
+        HashFileFooter::seal(tmp_file)?;
+    }
+
+    if HashFileCompression::is_compressed(path) {
+        HashFileCompression::compress(tmp_file)?;
+    }
+
+    HashFileBackup::exec(config, path)?;
+    persist_tmp_file(tmp_file, path)
+}
+
+// bail before writing a tmp file which cannot fit, rather than fail mid-way through the write
+pub fn check_free_space(tmp_file: &Path, required: u64) -> DanoResult<()> {
+    let dir = match tmp_file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    match available_space(dir) {
+        Some(available) if available < required => {
+            let msg = format!(
+                "Not enough free space to write the temporary file {:?}: {} bytes are required, but only {} bytes are available.  \
+                Use --tmp-dir to place temporary files on another filesystem.",
+                tmp_file, required, available
+            );
+            Err(DanoError::new(&msg).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn available_space(dir: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir_string = CString::new(dir.as_os_str().as_bytes()).ok()?;
+
+    // SAFETY: statvfs only writes to the zeroed struct we own, and the path is a valid C string
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(dir_string.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+
+    // the widths of these fields vary by platform
+    #[allow(clippy::useless_conversion)]
+    u64::from(stat.f_bavail).checked_mul(u64::from(stat.f_frsize))
+}
+
+#[cfg(not(unix))]
+fn available_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+// a tmp file which exists on startup was left behind by an interrupted rewrite,
+// and a later rewrite should never build upon it
+pub fn remove_orphaned_tmp_files(config: &Config) -> DanoResult<()> {
+    let mut tmp_files: Vec<PathBuf> = [&config.output_file, &config.hash_file]
+        .into_iter()
+        .flat_map(|path| [make_tmp_file(path), get_tmp_file(config, path)])
+        .filter(|tmp_file| tmp_file.exists())
+        .collect();
+
+    tmp_files.sort();
+    tmp_files.dedup();
+
+    tmp_files.iter().try_for_each(|tmp_file| {
+        if config.opt_dry_run {
+            return print_err_buf(&format!(
+                "WARN: Not removing orphaned temporary file (because dry run was specified): {:?}\n",
+                tmp_file
+            ));
+        }
+
+        std::fs::remove_file(tmp_file)?;
+
+        print_err_buf(&format!(
+            "WARN: Removed orphaned temporary file: {:?}\n",
+            tmp_file
+        ))
+    })
+}
+
 pub fn write_file(file_info: &FileInfo, output_file: &mut File) -> DanoResult<()> {
     let serialized = serialize(file_info)?;
     write_out_file(&serialized, output_file)
@@ -109,17 +242,100 @@ pub fn write_non_file(file_info: &FileInfo) -> DanoResult<()> {
     write_out_xattr(&serialized, file_info)
 }
 
+// on Windows, a canonical path is a verbatim path, such as "\\?\C:\Music", which is neither how users write paths,
+// nor comparable to a path which is not verbatim, so the prefix is removed
+pub fn canonicalize(path: &Path) -> std::io::Result<PathBuf> {
+    let canonical = path.canonicalize()?;
+
+    #[cfg(windows)]
+    if let Some(canonical_string) = canonical.to_str() {
+        if let Some(unc) = canonical_string.strip_prefix(r"\\?\UNC\") {
+            return Ok(PathBuf::from(format!(r"\\{}", unc)));
+        }
+
+        if let Some(disk) = canonical_string.strip_prefix(r"\\?\") {
+            return Ok(PathBuf::from(disk));
+        }
+    }
+
+    Ok(canonical)
+}
+
+pub fn has_dano_xattr(path: &Path) -> bool {
+    matches!(get_dano_xattr(path), Ok(Some(_)))
+}
+
+#[cfg(not(windows))]
+pub fn get_dano_xattr(path: &Path) -> std::io::Result<Option<Vec<u8>>> {
+    xattr::get(path, DANO_XATTR_KEY_NAME)
+}
+
+#[cfg(not(windows))]
 pub fn remove_dano_xattr(path: &Path) -> DanoResult<()> {
     xattr::remove(path, DANO_XATTR_KEY_NAME).map_err(|err| err.into())
 }
 
+#[cfg(not(windows))]
 fn write_out_xattr(out_string: &str, file_info: &FileInfo) -> DanoResult<()> {
     let _ = xattr::remove(&file_info.path, DANO_XATTR_KEY_NAME);
     xattr::set(&file_info.path, DANO_XATTR_KEY_NAME, out_string.as_bytes())
         .map_err(|err| err.into())
 }
 
+// on Windows, the record is attached to the file in an NTFS alternate data stream, such as "file.mkv:dano.checksum"
+#[cfg(windows)]
+fn get_ads_path(path: &Path) -> PathBuf {
+    let mut ads_path = path.as_os_str().to_owned();
+    ads_path.push(":");
+    ads_path.push(DANO_ADS_STREAM_NAME);
+    PathBuf::from(ads_path)
+}
+
+#[cfg(windows)]
+pub fn get_dano_xattr(path: &Path) -> std::io::Result<Option<Vec<u8>>> {
+    match std::fs::read(get_ads_path(path)) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(windows)]
+pub fn remove_dano_xattr(path: &Path) -> DanoResult<()> {
+    if !has_dano_xattr(path) {
+        return Err(std::io::Error::new(ErrorKind::NotFound, "No data available").into());
+    }
+
+    with_modify_time_preserved(path, || std::fs::remove_file(get_ads_path(path)))
+}
+
+#[cfg(windows)]
+fn write_out_xattr(out_string: &str, file_info: &FileInfo) -> DanoResult<()> {
+    with_modify_time_preserved(&file_info.path, || {
+        std::fs::write(get_ads_path(&file_info.path), out_string.as_bytes())
+    })
+}
+
+// writing to a stream updates the modify time of the file, which is recorded, and so must be restored
+#[cfg(windows)]
+fn with_modify_time_preserved<F>(path: &Path, write: F) -> DanoResult<()>
+where
+    F: FnOnce() -> std::io::Result<()>,
+{
+    let modify_time = path.metadata()?.modified()?;
+
+    write()?;
+
+    OpenOptions::new()
+        .write(true)
+        .open(path)?
+        .set_modified(modify_time)
+        .map_err(|err| err.into())
+}
+
 pub fn print_err_buf(err_buf: &str) -> DanoResult<()> {
+    let _progress_line = clear_progress_line();
+
     // mutex keeps threads from writing over each other
     let err = std::io::stderr();
     let mut err_locked = err.lock();
@@ -128,6 +344,8 @@ pub fn print_err_buf(err_buf: &str) -> DanoResult<()> {
 }
 
 pub fn print_out_buf(output_buf: &str) -> DanoResult<()> {
+    let _progress_line = clear_progress_line();
+
     // mutex keeps threads from writing over each other
     let out = std::io::stdout();
     let mut out_locked = out.lock();
@@ -135,18 +353,142 @@ pub fn print_out_buf(output_buf: &str) -> DanoResult<()> {
     out_locked.flush().map_err(|err| err.into())
 }
 
+// with --porcelain, stdout carries only results, one line per path, and all commentary goes to stderr
+#[derive(Serialize)]
+struct PorcelainRecord<'a> {
+    status: &'a str,
+    #[serde(serialize_with = "crate::serde_path::serialize")]
+    path: &'a Path,
+    hash_algo: Option<&'a str>,
+    hash_value: Option<&'a str>,
+}
+
+impl PorcelainRecord<'_> {
+    fn format(&self, format: &PorcelainFormat) -> DanoResult<String> {
+        match format {
+            PorcelainFormat::Ndjson => Ok(serde_json::to_string(self)? + "\n"),
+            PorcelainFormat::Tsv => {
+                // tabs and newlines within a path would otherwise break the line
+                let escape = |value: &str| {
+                    value
+                        .replace('\\', "\\\\")
+                        .replace('\t', "\\t")
+                        .replace('\n', "\\n")
+                };
+
+                Ok(format!(
+                    "{}\t{}\t{}\t{}\n",
+                    self.status,
+                    escape(&self.path.to_string_lossy()),
+                    self.hash_algo.unwrap_or_default(),
+                    self.hash_value.unwrap_or_default()
+                ))
+            }
+        }
+    }
+}
+
+// print the outcome for a path, as a human readable message, or, with --porcelain, as a result
+// to stdout, along with the message to stderr
+pub fn print_outcome(
+    config: &Config,
+    path: &Path,
+    status: &str,
+    opt_metadata: Option<&FileMetadata>,
+    msg: &str,
+) -> DanoResult<()> {
+    // stdout is reserved for the records streamed to it
+    if config.opt_output_stdout {
+        return print_err_buf(msg);
+    }
+
+    let Some(format) = &config.opt_porcelain else {
+        return print_out_buf(msg);
+    };
+
+    print_err_buf(msg)?;
+
+    let record = PorcelainRecord {
+        status,
+        path,
+        hash_algo: opt_metadata.map(|metadata| metadata.hash_algo.as_ref()),
+        hash_value: opt_metadata.map(|metadata| metadata.hash_value.value.as_ref()),
+    };
+
+    print_out_buf(&record.format(format)?)
+}
+
+// commentary which is printed to stdout, except with --porcelain
+pub fn print_commentary(config: &Config, msg: &str) -> DanoResult<()> {
+    match &config.opt_porcelain {
+        _ if config.opt_output_stdout => print_err_buf(msg),
+        Some(_) => print_err_buf(msg),
+        None => print_out_buf(msg),
+    }
+}
+
+// quote a command, so it may be copied and pasted into a POSIX shell
+pub fn shell_quote_command(command: &Path, args: &[&OsStr]) -> String {
+    std::iter::once(command.as_os_str())
+        .chain(args.iter().copied())
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+
+            if !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_./:=?@%+,".contains(c))
+            {
+                return arg.into_owned();
+            }
+
+            format!("'{}'", arg.replace('\'', "'\\''"))
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
 pub fn print_file_info(config: &Config, file_info: &FileInfo) -> DanoResult<()> {
+    // with --porcelain, file info is a result, and is always printed to stdout, unless records are streamed to stdout
+    if let (Some(format), Some(metadata), false) = (
+        &config.opt_porcelain,
+        &file_info.metadata,
+        config.opt_output_stdout,
+    ) {
+        let status = match config.exec_mode {
+            ExecMode::Write(_) => "hashed",
+            ExecMode::Duplicates => "duplicate",
+            ExecMode::Accept => "accepted",
+            ExecMode::MigrateAlgo(_) => "migrated",
+            _ => "recorded",
+        };
+
+        let record = PorcelainRecord {
+            status,
+            path: &file_info.path,
+            hash_algo: Some(&metadata.hash_algo),
+            hash_value: Some(&metadata.hash_value.value),
+        };
+
+        return print_out_buf(&record.format(format)?);
+    }
+
     let buffer = match &file_info.metadata {
         Some(metadata) => {
             let hash_value_as_hex = format!("{}", metadata.hash_value.value);
 
-            format!(
+            let line = format!(
                 "{}={:<width$} : {:?}\n",
                 metadata.hash_algo,
                 hash_value_as_hex,
                 file_info.path,
                 width = HASH_VALUE_MIN_WIDTH
-            )
+            );
+
+            match &metadata.opt_media_info {
+                Some(media_info) if config.opt_long => format!("{}  ({})\n", line, media_info),
+                _ => line,
+            }
         }
         None => {
             let msg = format!("Could not find file metadata for: {:?}\n", file_info.path);
@@ -157,42 +499,118 @@ pub fn print_file_info(config: &Config, file_info: &FileInfo) -> DanoResult<()>
     // why?  b/c the writing of the file is the thing in write and dump mode and
     // this fn used then is just to print info about the hash.  we may wish to send to dev null
     match config.exec_mode {
-        ExecMode::Print | ExecMode::Duplicates | ExecMode::Test(_) => print_out_buf(&buffer),
-        ExecMode::Write(_) | ExecMode::Dump | ExecMode::Clean => print_err_buf(&buffer),
+        ExecMode::Print
+        | ExecMode::Duplicates
+        | ExecMode::Test(_)
+        | ExecMode::VerifyCopy(_)
+        | ExecMode::Status(_)
+        | ExecMode::Coverage(_)
+        | ExecMode::Diff(_)
+        | ExecMode::TestFlac
+        | ExecMode::AccurateRip
+        | ExecMode::TreeHash(_)
+        | ExecMode::History(_)
+        | ExecMode::Fsck(_) => print_out_buf(&buffer),
+        ExecMode::Write(_)
+        | ExecMode::Dump
+        | ExecMode::Clean
+        | ExecMode::Compact(_)
+        | ExecMode::Prune
+        | ExecMode::Accept
+        | ExecMode::Export(_)
+        | ExecMode::MigrateAlgo(_)
+        | ExecMode::Undo => print_err_buf(&buffer),
     }
 }
 
-pub fn get_hash_file(config: &Config) -> DanoResult<File> {
-    if let Ok(input_file) = OpenOptions::new().read(true).open(&config.hash_file) {
+pub fn get_hash_file(config: &Config) -> DanoResult<Box<dyn Read>> {
+    if let Ok(input_file) = HashFileCompression::open(&config.hash_file) {
         Ok(input_file)
     } else {
         Err(DanoError::new("dano could not open a file to write to").into())
     }
 }
 
+pub fn file_header(config: &Config) -> String {
+    format!("{}{:?}\n", INVOKED_FROM_PREFIX, config.pwd)
+}
+
 fn print_file_header(config: &Config, output_file: &mut File) -> DanoResult<()> {
-    write_out_file(
-        format!("// DANO, Invoked from: {:?}\n", config.pwd).as_str(),
-        output_file,
+    write_out_file(&file_header(config), output_file)
+}
+
+// recorded paths are relative to the directory from which dano was invoked, as noted in the header of the hash file
+pub fn invoked_from(header: &[&str]) -> Option<PathBuf> {
+    header.iter().find_map(|line| {
+        let quoted = line.strip_prefix(INVOKED_FROM_PREFIX)?;
+        let unquoted = quoted.strip_prefix('"')?.strip_suffix('"')?;
+        Some(PathBuf::from(unquoted))
+    })
+}
+
+// formats as UTC, "YYYY-MM-DD HH:MM:SS", which also sorts correctly as text
+pub fn format_time(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    let days = (secs / 86_400) as i64;
+    let secs_of_day = secs % 86_400;
+
+    // days since the epoch to a proleptic gregorian calendar date
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
     )
 }
 
+pub fn resolve_path(base_dir: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_owned()
+    } else {
+        base_dir.join(path)
+    }
+}
+
 pub fn get_output_file(config: &Config, write_type: WriteType) -> DanoResult<File> {
     let output_file = match write_type {
         WriteType::Append => config.output_file.clone(),
-        WriteType::Overwrite => make_tmp_file(&config.output_file),
+        WriteType::Overwrite => get_tmp_file(config, &config.output_file),
     };
 
-    let is_first_run = !output_file.exists();
-
-    let mut output_file = OpenOptions::new()
-        // should overwrite the file always
-        // FYI append() is for adding to the file
-        .create(true)
-        .append(true)
-        // create_new() will only create if DNE
-        // create on a file that exists just opens
-        .open(&output_file)?;
+    let is_first_run = !output_file.exists() || matches!(write_type, WriteType::Overwrite);
+
+    let mut output_file = match write_type {
+        WriteType::Append => OpenOptions::new()
+            // FYI append() is for adding to the file
+            // create on a file that exists just opens
+            .create(true)
+            .append(true)
+            .open(&output_file)?,
+        // the tmp file should be overwritten always, never appended to
+        WriteType::Overwrite => OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&output_file)?,
+    };
 
     if is_first_run {
         print_file_header(config, &mut output_file)?
@@ -214,6 +632,17 @@ pub fn serialize(file_info: &FileInfo) -> DanoResult<String> {
     }
 }
 
+// the version of a serialized record, before any conversion to the latest version
+pub fn deserialize_version(line: &str) -> Option<usize> {
+    let root: Value = serde_json::from_str(line).ok()?;
+    serde_json::from_value(root.get("version")?.to_owned()).ok()
+}
+
+// a record written by a newer version of dano, in a format this version cannot read
+pub fn is_newer_version(line: &str) -> bool {
+    matches!(deserialize_version(line), Some(version) if version > DANO_FILE_INFO_VERSION)
+}
+
 pub fn deserialize(line: &str) -> DanoResult<FileInfo> {
     let root: Value = serde_json::from_str(line)?;
     let value = root
@@ -223,6 +652,14 @@ pub fn deserialize(line: &str) -> DanoResult<FileInfo> {
 
     let version: usize = serde_json::from_value(value)?;
 
+    if version > DANO_FILE_INFO_VERSION {
+        let msg = format!(
+            "Record was written by a newer version of dano, in format version {}, but this version of dano reads format versions up to {}.",
+            version, DANO_FILE_INFO_VERSION
+        );
+        return Err(DanoError::new(&msg).into());
+    }
+
     if version == DANO_FILE_INFO_VERSION {
         serde_json::from_str(line).map_err(|err| err.into())
     } else {
@@ -232,11 +669,194 @@ pub fn deserialize(line: &str) -> DanoResult<FileInfo> {
 
 pub fn read_file_info_from_file(config: &Config) -> DanoResult<Vec<FileInfo>> {
     let mut input_file = get_hash_file(config)?;
-    let mut buffer = String::new();
-    input_file.read_to_string(&mut buffer)?;
+    let mut bytes = Vec::new();
+    input_file.read_to_end(&mut bytes)?;
+
+    if BinaryHashFile::is_binary(&bytes) {
+        let (records, num_newer) = BinaryHashFile::read_records(&config.hash_file, &bytes)?;
+        check_newer_versions(config, &config.hash_file, num_newer)?;
+        return Ok(records);
+    }
+
+    let buffer = text_from_bytes(bytes)?;
+
+    HashFileFooter::check(&config.hash_file, &buffer)?;
+
+    let num_newer = buffer
+        .par_lines()
+        .filter(|line| is_newer_version(line))
+        .count();
+    check_newer_versions(config, &config.hash_file, num_newer)?;
+
+    Ok(buffer.par_lines().flat_map(deserialize).collect())
+}
+
+// records of a newer version are skipped, with a single warning, because the hash file may be read more
+// than once each run, unless the user would rather fail
+pub fn check_newer_versions(config: &Config, source: &Path, num_newer: usize) -> DanoResult<()> {
+    static IS_WARNED: AtomicBool = AtomicBool::new(false);
+
+    if num_newer == 0 {
+        return Ok(());
+    }
+
+    if config.opt_strict_schema {
+        let msg = format!(
+            "{:?} contains {} records written by a newer version of dano, which this version cannot read.  \
+            Upgrade dano, or remove --strict-schema to skip such records.",
+            source, num_newer
+        );
+        return Err(DanoError::new(&msg).into());
+    }
+
+    if !config.opt_silent && !IS_WARNED.swap(true, Ordering::Relaxed) {
+        eprintln!(
+            "WARN: {:?} contains {} records written by a newer version of dano, which this version cannot read.  \
+            These records are skipped, but are preserved when the hash file is rewritten.  Use --strict-schema to fail instead.",
+            source, num_newer
+        );
+    }
+
+    Ok(())
+}
+
+// the lines of a hash file written by a newer version of dano, which a rewrite must carry over unchanged
+pub fn read_newer_version_lines(path: &Path) -> DanoResult<Vec<String>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = read_hash_file(path)?;
+
+    Ok(contents
+        .lines()
+        .filter(|line| is_newer_version(line))
+        .map(|line| line.to_owned())
+        .collect())
+}
+
+pub fn write_line(line: &str, output_file: &mut File) -> DanoResult<()> {
+    write_out_file(line, output_file)?;
+    write_out_file("\n", output_file)
+}
+
+pub fn read_file_info_from_path(path: &Path) -> DanoResult<Vec<FileInfo>> {
+    let bytes = HashFileCompression::read(path)?;
+
+    if BinaryHashFile::is_binary(&bytes) {
+        let (records, _num_newer) = BinaryHashFile::read_records(path, &bytes)?;
+        return Ok(records);
+    }
+
+    let buffer = text_from_bytes(bytes)?;
+    HashFileFooter::check(path, &buffer)?;
     Ok(buffer.par_lines().flat_map(deserialize).collect())
 }
 
+// a binary hash file is checked against the footer of its encoded entries, which its text does not include
+pub fn check_hash_file_footer(path: &Path, contents: &str) -> DanoResult<()> {
+    let bytes = HashFileCompression::read(path)?;
+
+    if BinaryHashFile::is_binary(&bytes) {
+        return BinaryHashFile::check(path, &bytes);
+    }
+
+    HashFileFooter::check(path, contents)
+}
+
+// the text of a hash file, whether written in the text format, or in the binary format
+pub fn read_hash_file(path: &Path) -> DanoResult<String> {
+    hash_file_text(path, HashFileCompression::read(path)?)
+}
+
+// the text of the bytes of a hash file, once decompressed, whether written in the text format, or in the binary format
+pub fn hash_file_text(path: &Path, bytes: Vec<u8>) -> DanoResult<String> {
+    if BinaryHashFile::is_binary(&bytes) {
+        return BinaryHashFile::read_text(path, &bytes);
+    }
+
+    text_from_bytes(bytes)
+}
+
+fn text_from_bytes(bytes: Vec<u8>) -> DanoResult<String> {
+    String::from_utf8(bytes).map_err(|err| std::io::Error::new(ErrorKind::InvalidData, err).into())
+}
+
+// a list of paths, one per line, or NUL delimited, read from a file, or from stdin, given "-"
+pub fn read_files_from(list: &Path, opt_null: bool) -> DanoResult<Vec<PathBuf>> {
+    let buffer = if list == Path::new("-") {
+        let mut buffer = Vec::new();
+        std::io::stdin().lock().read_to_end(&mut buffer)?;
+        buffer
+    } else {
+        std::fs::read(list).map_err(|err| {
+            let msg = format!("Could not read list of input files {:?}: {}", list, err);
+            DanoError::new(&msg)
+        })?
+    };
+
+    let delimiter = if opt_null { b'\0' } else { b'\n' };
+
+    buffer
+        .split(|byte| *byte == delimiter)
+        // a list written on Windows may end each line with a carriage return
+        .map(|line| match line {
+            [rest @ .., b'\r'] if !opt_null => rest,
+            line => line,
+        })
+        .filter(|line| !line.is_empty())
+        .map(path_from_bytes)
+        .collect()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> DanoResult<PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
+
+    Ok(PathBuf::from(OsStr::from_bytes(bytes)))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> DanoResult<PathBuf> {
+    Ok(PathBuf::from(std::str::from_utf8(bytes)?))
+}
+
+// a hash file may be piped in, given "-", in place of a path
+pub fn is_stdin_path(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+// a hash file piped in, or fetched from a remote URL, is read from memory, and is never rewritten
+pub fn is_streamed_hash_file(path: &Path) -> bool {
+    is_stdin_path(path) || RemoteHashFile::is_remote(path)
+}
+
+pub fn read_streamed_hash_file(path: &Path) -> DanoResult<&'static [u8]> {
+    if RemoteHashFile::is_remote(path) {
+        return RemoteHashFile::read(path);
+    }
+
+    read_stdin_hash_file()
+}
+
+pub fn hash_file_exists(path: &Path) -> bool {
+    is_streamed_hash_file(path) || path.exists()
+}
+
+// a hash file piped in is read just once, as a hash file may be read more than once each run
+fn read_stdin_hash_file() -> DanoResult<&'static [u8]> {
+    static STDIN_HASH_FILE: OnceLock<Vec<u8>> = OnceLock::new();
+
+    if let Some(bytes) = STDIN_HASH_FILE.get() {
+        return Ok(bytes);
+    }
+
+    let mut buffer = Vec::new();
+    std::io::stdin().lock().read_to_end(&mut buffer)?;
+
+    Ok(STDIN_HASH_FILE.get_or_init(|| buffer))
+}
+
 pub fn read_stdin() -> DanoResult<Vec<PathBuf>> {
     let stdin = std::io::stdin();
     let mut stdin = stdin.lock();
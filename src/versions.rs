@@ -30,6 +30,7 @@ pub enum LegacyVersion {
     Version2,
     Version3,
     Version4,
+    Version5,
 }
 
 impl LegacyVersion {
@@ -53,6 +54,7 @@ impl LegacyVersion {
             2 => LegacyVersion::Version2,
             3 => LegacyVersion::Version3,
             4 => LegacyVersion::Version4,
+            5 => LegacyVersion::Version5,
             _ => return Err(DanoError::new("Legacy version number is invalid").into()),
         };
 
@@ -65,6 +67,7 @@ impl LegacyVersion {
             LegacyVersion::Version2 => FileInfoV2::try_from(line)?.convert(),
             LegacyVersion::Version3 => FileInfoV3::try_from(line)?.convert(),
             LegacyVersion::Version4 => FileInfoV4::try_from(line)?.convert(),
+            LegacyVersion::Version5 => FileInfoV5::try_from(line)?.convert(),
         }
     }
 }
@@ -128,6 +131,19 @@ impl FileInfoV1 {
             decoded: false,
             selected_streams: SelectedStreams::All,
             opt_bits_per_second: None,
+            opt_media_info: None,
+            hardlinked: false,
+            fallback: false,
+            opt_video_format: None,
+            native: false,
+            opt_frame_digests: None,
+            opt_stream_hashes: None,
+            opt_size: None,
+            opt_last_checked: None,
+            opt_tags_hash: None,
+            cover_art_excluded: false,
+            opt_decode_format: None,
+            opt_track_hashes: None,
         });
 
         Ok(FileInfo {
@@ -175,6 +191,19 @@ impl FileInfoV2 {
             decoded: metadata.decoded,
             selected_streams: SelectedStreams::All,
             opt_bits_per_second: None,
+            opt_media_info: None,
+            hardlinked: false,
+            fallback: false,
+            opt_video_format: None,
+            native: false,
+            opt_frame_digests: None,
+            opt_stream_hashes: None,
+            opt_size: None,
+            opt_last_checked: None,
+            opt_tags_hash: None,
+            cover_art_excluded: false,
+            opt_decode_format: None,
+            opt_track_hashes: None,
         });
 
         Ok(FileInfo {
@@ -192,6 +221,7 @@ pub struct FileInfoV3 {
     pub metadata: Option<FileMetadata>,
 }
 
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct FileMetadataV3 {
     pub hash_algo: Box<str>,
@@ -223,6 +253,19 @@ impl FileInfoV3 {
             decoded: metadata.decoded,
             selected_streams: metadata.selected_streams.to_owned(),
             opt_bits_per_second: None,
+            opt_media_info: None,
+            hardlinked: false,
+            fallback: false,
+            opt_video_format: None,
+            native: false,
+            opt_frame_digests: None,
+            opt_stream_hashes: None,
+            opt_size: None,
+            opt_last_checked: None,
+            opt_tags_hash: None,
+            cover_art_excluded: false,
+            opt_decode_format: None,
+            opt_track_hashes: None,
         });
 
         Ok(FileInfo {
@@ -240,6 +283,7 @@ pub struct FileInfoV4 {
     pub metadata: Option<FileMetadata>,
 }
 
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct FileMetadataV4 {
     pub hash_algo: Box<str>,
@@ -271,6 +315,57 @@ impl FileInfoV4 {
             decoded: metadata.decoded,
             selected_streams: metadata.selected_streams.to_owned(),
             opt_bits_per_second: None,
+            opt_media_info: None,
+            hardlinked: false,
+            fallback: false,
+            opt_video_format: None,
+            native: false,
+            opt_frame_digests: None,
+            opt_stream_hashes: None,
+            opt_size: None,
+            opt_last_checked: None,
+            opt_tags_hash: None,
+            cover_art_excluded: false,
+            opt_decode_format: None,
+            opt_track_hashes: None,
+        });
+
+        Ok(FileInfo {
+            version: DANO_FILE_INFO_VERSION,
+            path: self.path.to_owned(),
+            metadata: new_metadata,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileInfoV5 {
+    pub version: usize,
+    pub path: PathBuf,
+    pub metadata: Option<FileMetadata>,
+}
+
+impl TryFrom<&str> for FileInfoV5 {
+    type Error = serde_json::Error;
+
+    fn try_from(line: &str) -> Result<Self, Self::Error> {
+        let rewrite = line.replace("FileInfo", "FileInfoV5");
+        let legacy_file_info: FileInfoV5 = serde_json::from_str(&rewrite)?;
+
+        Ok(legacy_file_info)
+    }
+}
+
+impl FileInfoV5 {
+    // version 5 records have no record of when they were last checked
+    fn convert(&self) -> DanoResult<FileInfo> {
+        let new_metadata = self.metadata.as_ref().map(|metadata| FileMetadata {
+            opt_last_checked: None,
+            opt_tags_hash: None,
+            cover_art_excluded: false,
+            opt_decode_format: None,
+            opt_track_hashes: None,
+            ..metadata.to_owned()
         });
 
         Ok(FileInfo {
@@ -0,0 +1,194 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, RecvTimeoutError},
+    time::{Duration, Instant, SystemTime},
+};
+
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::ingest::RecordedFileInfo;
+use crate::lock::{HashFileLock, LockKind};
+use crate::utility::{print_commentary, print_err_buf};
+use crate::{Config, DanoError, DanoResult, DANO_CLEAN_EXIT_CODE};
+
+// a file has settled, and may be hashed, once its size and modify time are unchanged for this long
+const SETTLE_DURATION: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// the size and modify time of a file, when last observed, and when either last changed
+struct PendingFile {
+    len: u64,
+    modify_time: SystemTime,
+    last_changed: Instant,
+}
+
+impl PendingFile {
+    fn new(path: &Path) -> Option<Self> {
+        let metadata = path.metadata().ok()?;
+
+        Some(Self {
+            len: metadata.len(),
+            modify_time: metadata.modified().ok()?,
+            last_changed: Instant::now(),
+        })
+    }
+
+    // a file which has since vanished is never settled, and is dropped
+    fn is_settled(&mut self, path: &Path) -> Option<bool> {
+        let current = Self::new(path)?;
+
+        if current.len != self.len || current.modify_time != self.modify_time {
+            *self = current;
+            return Some(false);
+        }
+
+        Some(self.last_changed.elapsed() >= SETTLE_DURATION)
+    }
+}
+
+pub struct WatchDir;
+
+impl WatchDir {
+    // each batch of settled files is written by the normal write pipeline, so each batch is appended
+    // to the hash file, and xattrs, as it arrives
+    pub fn exec<F>(
+        config: &Config,
+        dir: &Path,
+        recorded_file_info: RecordedFileInfo,
+        hash_file_lock: HashFileLock,
+        write_batch: F,
+    ) -> DanoResult<i32>
+    where
+        F: Fn(&Config, RecordedFileInfo) -> DanoResult<i32>,
+    {
+        let (tx, rx) = channel::<notify::Result<Event>>();
+
+        let mut watcher = notify::recommended_watcher(tx).map_err(|err| {
+            let msg = format!("Could not watch directory {:?}: {}", dir, err);
+            DanoError::new(&msg)
+        })?;
+
+        watcher
+            .watch(dir, RecursiveMode::Recursive)
+            .map_err(|err| {
+                let msg = format!("Could not watch directory {:?}: {}", dir, err);
+                DanoError::new(&msg)
+            })?;
+
+        // files already in the directory are hashed first, under the lock the records were read with,
+        // and files which arrive meanwhile are queued by the watcher
+        if !config.paths.is_empty() {
+            write_batch(config, recorded_file_info)?;
+        }
+
+        // afterwards, the hash file is locked only for each batch, as a watch runs indefinitely
+        drop(hash_file_lock);
+
+        // the watcher reports canonical paths, but paths are recorded in the form given, as when walking the directory
+        let canonical_dir = dir.canonicalize()?;
+
+        print_commentary(
+            config,
+            &format!("Watching {:?} for new files.  Press Ctrl-C to stop.\n", dir),
+        )?;
+
+        let mut pending: BTreeMap<PathBuf, PendingFile> = BTreeMap::new();
+
+        // pending files are checked on a timer, so a stream of events, as when a large file is written, never delays the check
+        let mut next_check = Instant::now() + POLL_INTERVAL;
+
+        loop {
+            match rx.recv_timeout(next_check.saturating_duration_since(Instant::now())) {
+                Ok(Ok(event)) => {
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                        event
+                            .paths
+                            .into_iter()
+                            .map(|path| match path.strip_prefix(&canonical_dir) {
+                                Ok(relative) => dir.join(relative),
+                                Err(_) => path,
+                            })
+                            .filter(|path| path.is_file())
+                            .for_each(|path| {
+                                // a file already pending is checked against its last observed size and modify time
+                                if let Entry::Vacant(entry) = pending.entry(path) {
+                                    if let Some(pending_file) = PendingFile::new(entry.key()) {
+                                        entry.insert(pending_file);
+                                    }
+                                }
+                            });
+                    }
+                }
+                Ok(Err(err)) => {
+                    print_err_buf(&format!("WARN: Error while watching {:?}: {}\n", dir, err))?;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            if Instant::now() < next_check {
+                continue;
+            }
+
+            next_check = Instant::now() + POLL_INTERVAL;
+
+            let mut settled: Vec<PathBuf> = Vec::new();
+
+            pending.retain(|path, pending_file| match pending_file.is_settled(path) {
+                Some(true) => {
+                    settled.push(path.clone());
+                    false
+                }
+                Some(false) => true,
+                None => false,
+            });
+
+            if settled.is_empty() {
+                continue;
+            }
+
+            let paths = config.filter_paths(&settled);
+
+            if !paths.is_empty() {
+                Self::exec_batch(config, paths, &write_batch)?;
+            }
+        }
+
+        Ok(DANO_CLEAN_EXIT_CODE)
+    }
+
+    fn exec_batch<F>(config: &Config, paths: Vec<PathBuf>, write_batch: &F) -> DanoResult<i32>
+    where
+        F: Fn(&Config, RecordedFileInfo) -> DanoResult<i32>,
+    {
+        let batch_config = Config {
+            paths,
+            ..config.clone()
+        };
+
+        let _hash_file_lock = HashFileLock::acquire(&batch_config, LockKind::Exclusive)?;
+
+        // a file may already be recorded, for instance when it was moved into the directory,
+        // or by another run, since the records were last read
+        let recorded_file_info = RecordedFileInfo::new(&batch_config)?;
+
+        write_batch(&batch_config, recorded_file_info)
+    }
+}
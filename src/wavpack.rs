@@ -0,0 +1,203 @@
+//       ___           ___           ___           ___
+//      /\  \         /\  \         /\__\         /\  \
+//     /::\  \       /::\  \       /::|  |       /::\  \
+//    /:/\:\  \     /:/\:\  \     /:|:|  |      /:/\:\  \
+//   /:/  \:\__\   /::\~\:\  \   /:/|:|  |__   /:/  \:\  \
+//  /:/__/ \:|__| /:/\:\ \:\__\ /:/ |:| /\__\ /:/__/ \:\__\
+//  \:\  \ /:/  / \/__\:\/:/  / \/__|:|/:/  / \:\  \ /:/  /
+//   \:\  /:/  /       \::/  /      |:/:/  /   \:\  /:/  /
+//    \:\/:/  /        /:/  /       |::/  /     \:\/:/  /
+//     \::/__/        /:/  /        /:/  /       \::/  /
+//      ~~            \/__/         \/__/         \/__/
+//
+// (c) Robert Swinford <robert.swinford<...at...>gmail.com>
+//
+// For the full copyright and license information, please view the LICENSE file
+// that was distributed with this source code.
+
+use std::{
+    fs::File,
+    io::{BufReader, ErrorKind, Read},
+    path::Path,
+    time::SystemTime,
+};
+
+use crate::config::{Config, SelectedStreams};
+use crate::import::Importer;
+use crate::lookup::{FileInfo, FileMetadata, HashValue};
+use crate::{DanoError, DanoResult, DANO_FILE_INFO_VERSION, HEXADECIMAL_RADIX};
+
+const WAVPACK_HASH_ALGO: &str = "MD5";
+const WAVPACK_DECODED: bool = true;
+const WAVPACK_SELECTED_STREAMS: SelectedStreams = SelectedStreams::AudioOnly;
+
+const WAVPACK_BLOCK_ID: &[u8; 4] = b"wvpk";
+const WAVPACK_HEADER_LEN: usize = 32;
+const WAVPACK_FLAGS_BYTES_STORED: u32 = 0x3;
+const WAVPACK_FLAGS_FLOAT_DATA: u32 = 0x80;
+// the WavPack format limits a block to 1 MiB, so a larger block size is not trusted, rather than allocated
+const WAVPACK_MAX_BLOCK_SIZE: u32 = 1024 * 1024;
+
+const ID_UNIQUE: u8 = 0x3f;
+const ID_ODD_SIZE: u8 = 0x40;
+const ID_LARGE: u8 = 0x80;
+const ID_MD5_CHECKSUM: u8 = 0x26;
+
+pub struct WavPackImporter;
+
+impl Importer for WavPackImporter {
+    fn name(&self) -> &'static str {
+        "wavpack"
+    }
+
+    fn is_valid_path(&self, path: &Path) -> bool {
+        matches!(path.extension(), Some(extension) if extension.eq_ignore_ascii_case("wv"))
+    }
+
+    fn import(&self, _config: &Config, path: &Path) -> DanoResult<Vec<FileInfo>> {
+        let (hash_value, bps_value) = Self::read_md5_and_bps(path)?;
+
+        let file_info = FileInfo {
+            path: path.to_owned(),
+            version: DANO_FILE_INFO_VERSION,
+            metadata: Some(FileMetadata {
+                last_written: SystemTime::now(),
+                hash_algo: WAVPACK_HASH_ALGO.into(),
+                hash_value,
+                modify_time: path.metadata()?.modified()?,
+                selected_streams: WAVPACK_SELECTED_STREAMS,
+                decoded: WAVPACK_DECODED,
+                opt_bits_per_second: Some(bps_value),
+                opt_media_info: None,
+                hardlinked: false,
+                fallback: false,
+                opt_video_format: None,
+                native: false,
+                opt_frame_digests: None,
+                opt_stream_hashes: None,
+                opt_size: None,
+                opt_last_checked: None,
+                opt_tags_hash: None,
+                cover_art_excluded: false,
+                opt_decode_format: None,
+                opt_track_hashes: None,
+            }),
+        };
+
+        Ok(vec![file_info])
+    }
+}
+
+impl WavPackImporter {
+    // walk each WavPack block, reading the sample format from the block header, and
+    // search the metadata sub-blocks for the MD5 sum, which is usually found in the final block
+    fn read_md5_and_bps(path: &Path) -> DanoResult<(HashValue, u32)> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut header = [0u8; WAVPACK_HEADER_LEN];
+        let mut opt_bps = None;
+        let mut opt_md5 = None;
+
+        loop {
+            match reader.read_exact(&mut header) {
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err.into()),
+            }
+
+            if &header[0..4] != WAVPACK_BLOCK_ID {
+                let msg = format!("Path is not a valid WavPack file: {:?}", path);
+                return Err(DanoError::new(&msg).into());
+            }
+
+            let block_size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+            let flags = u32::from_le_bytes([header[24], header[25], header[26], header[27]]);
+
+            if block_size > WAVPACK_MAX_BLOCK_SIZE {
+                let msg = format!(
+                    "WavPack file contains a block of {} bytes, larger than the WavPack maximum of {} bytes: {:?}",
+                    block_size, WAVPACK_MAX_BLOCK_SIZE, path
+                );
+                return Err(DanoError::new(&msg).into());
+            }
+
+            if flags & WAVPACK_FLAGS_FLOAT_DATA != 0 {
+                let msg = format!(
+                    "Floating point WavPack files are not supported for import: {:?}",
+                    path
+                );
+                return Err(DanoError::new(&msg).into());
+            }
+
+            if opt_bps.is_none() {
+                opt_bps = Some(((flags & WAVPACK_FLAGS_BYTES_STORED) + 1) * 8);
+            }
+
+            // block size does not include the first 8 bytes of the header
+            let body_len = (block_size as usize + 8).saturating_sub(WAVPACK_HEADER_LEN);
+            let mut body = vec![0u8; body_len];
+            reader.read_exact(&mut body)?;
+
+            if let Some(md5) = Self::find_md5_sub_block(&body) {
+                opt_md5 = Some(md5);
+            }
+        }
+
+        match (opt_md5, opt_bps) {
+            (Some(md5), Some(bps)) => {
+                let hex: String = md5.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+                let hash_value = HashValue {
+                    radix: HEXADECIMAL_RADIX,
+                    value: hex.trim_start_matches('0').into(),
+                };
+
+                Ok((hash_value, bps))
+            }
+            _ => {
+                let msg = format!("WavPack file does not contain an MD5 sum: {:?}", path);
+                Err(DanoError::new(&msg).into())
+            }
+        }
+    }
+
+    fn find_md5_sub_block(body: &[u8]) -> Option<[u8; 16]> {
+        let mut idx = 0usize;
+
+        while idx + 2 <= body.len() {
+            let id = body[idx];
+
+            let (word_count, data_start) = if id & ID_LARGE != 0 {
+                if idx + 4 > body.len() {
+                    return None;
+                }
+                let count = body[idx + 1] as usize
+                    | (body[idx + 2] as usize) << 8
+                    | (body[idx + 3] as usize) << 16;
+                (count, idx + 4)
+            } else {
+                (body[idx + 1] as usize, idx + 2)
+            };
+
+            let padded_len = word_count * 2;
+            let data_len = if id & ID_ODD_SIZE != 0 {
+                padded_len.saturating_sub(1)
+            } else {
+                padded_len
+            };
+
+            if data_start + padded_len > body.len() {
+                return None;
+            }
+
+            if id & ID_UNIQUE == ID_MD5_CHECKSUM && data_len == 16 {
+                let mut md5 = [0u8; 16];
+                md5.copy_from_slice(&body[data_start..data_start + 16]);
+                return Some(md5);
+            }
+
+            idx = data_start + padded_len;
+        }
+
+        None
+    }
+}